@@ -0,0 +1,262 @@
+use std::f32::consts::{PI, TAU};
+
+use bevy::{gizmos::GizmoPlugin, input::InputPlugin, prelude::*};
+
+use crate::{
+    buoyancy::FluidBuoyancy, collider::Collider, domain::Domain, neighbors::FluidNeighbors,
+    params::SimulationParams, sdf::to_world, shallow_water::ShallowWater, surface::ColorField,
+    SimulationSet, Velocity, SIMULATION_SCHEDULE,
+};
+
+const STATION_SIZE: f32 = 2.0;
+
+// Floating hull moved by the fluid, as an example of the coupling APIs. The displaced fluid
+// lifts it and rights it when tipped, through `FluidBuoyancy`, and stations spread around
+// its outline sample the flow, dragging each submerged part of the hull toward the velocity
+// of the fluid around it, harder in denser fluid. It floats on shallow water too, though
+// without making waves in it. Its collider shoves the particles out of the way as it moves.
+// I and K push it forward and back along its local x axis, and J and L turn it.
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct Boat {
+    pub linear_velocity: Vec2,
+    /// Counterclockwise, in radians per second
+    pub angular_velocity: f32,
+    /// Density relative to the fluid's target density, below 1 to float
+    pub relative_density: f32,
+    /// Forward acceleration at full throttle
+    pub thrust: f32,
+    /// Angular acceleration at full rudder, in radians per second squared
+    pub turn_rate: f32,
+    /// Rate at which submerged stations take on the fluid velocity at the target density,
+    /// in 1/s
+    pub drag: f32,
+    /// From -1 for full reverse to 1 for full ahead
+    pub throttle: f32,
+    /// From -1 for hard clockwise to 1 for hard counterclockwise
+    pub rudder: f32,
+    /// Points sampling the flow, in hull coordinates
+    stations: Vec<Vec2>,
+}
+
+impl Boat {
+    /// Boat with `stations` sampling points spread evenly around the outline of `hull`
+    pub fn new(hull: &Collider, stations: usize) -> Self {
+        Self {
+            linear_velocity: Vec2::ZERO,
+            angular_velocity: 0.0,
+            relative_density: 0.5,
+            thrust: 150.0,
+            turn_rate: 2.0,
+            drag: 3.0,
+            throttle: 0.0,
+            rudder: 0.0,
+            stations: stations_around(hull, stations.max(1)),
+        }
+    }
+}
+
+fn stations_around(hull: &Collider, count: usize) -> Vec<Vec2> {
+    let outline = match hull {
+        Collider::Circle { radius } => {
+            return (0..count)
+                .map(|index| Vec2::from_angle(TAU * index as f32 / count as f32) * *radius)
+                .collect();
+        }
+        Collider::Box { half_size } => vec![
+            Vec2::new(-half_size.x, -half_size.y),
+            Vec2::new(half_size.x, -half_size.y),
+            Vec2::new(half_size.x, half_size.y),
+            Vec2::new(-half_size.x, half_size.y),
+        ],
+        Collider::ConvexPolygon { points } => points.clone(),
+    };
+
+    let edges: Vec<(Vec2, Vec2)> = outline
+        .iter()
+        .copied()
+        .zip(outline.iter().copied().cycle().skip(1))
+        .collect();
+    let perimeter: f32 = edges.iter().map(|(a, b)| a.distance(*b)).sum();
+    if perimeter <= 0.0 {
+        return outline;
+    }
+
+    // Walk the outline, dropping a station every `step` along it.
+    let step = perimeter / count as f32;
+    let mut stations = Vec::with_capacity(count);
+    let mut next = step / 2.0;
+    let mut walked = 0.0;
+
+    for (a, b) in edges {
+        let length = a.distance(b);
+        while next < walked + length && stations.len() < count {
+            stations.push(a.lerp(b, (next - walked) / length));
+            next += step;
+        }
+        walked += length;
+    }
+
+    stations
+}
+
+fn hull_area(hull: &Collider) -> f32 {
+    match hull {
+        Collider::Circle { radius } => PI * radius * radius,
+        Collider::Box { half_size } => 4.0 * half_size.x * half_size.y,
+        Collider::ConvexPolygon { points } => {
+            points
+                .iter()
+                .zip(points.iter().cycle().skip(1))
+                .map(|(a, b)| a.perp_dot(*b))
+                .sum::<f32>()
+                / 2.0
+        }
+    }
+}
+
+pub struct BoatPlugin;
+
+impl Plugin for BoatPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Boat>().add_systems(
+            SIMULATION_SCHEDULE,
+            boat_system.in_set(SimulationSet::Integration),
+        );
+
+        if app.is_plugin_added::<InputPlugin>() {
+            app.add_systems(Update, steer_boats_system);
+        }
+
+        if app.is_plugin_added::<GizmoPlugin>() {
+            app.add_systems(Update, draw_boats_system);
+        }
+    }
+}
+
+fn steer_boats_system(input: Res<ButtonInput<KeyCode>>, mut boats: Query<&mut Boat>) {
+    let axis = |positive, negative| {
+        f32::from(input.pressed(positive)) - f32::from(input.pressed(negative))
+    };
+    let throttle = axis(KeyCode::KeyI, KeyCode::KeyK);
+    let rudder = axis(KeyCode::KeyJ, KeyCode::KeyL);
+
+    for mut boat in boats.iter_mut() {
+        if boat.throttle != throttle || boat.rudder != rudder {
+            boat.throttle = throttle;
+            boat.rudder = rudder;
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn boat_system(
+    time: Res<Time>,
+    params: Res<SimulationParams>,
+    domains: Query<&Domain>,
+    buoyancy: FluidBuoyancy,
+    field: Option<Res<ColorField>>,
+    neighbors: FluidNeighbors,
+    particles: Query<&Velocity>,
+    lakes: Query<&ShallowWater>,
+    mut boats: Query<(&mut Boat, &Collider, &mut Transform)>,
+) {
+    let delta_time = time.delta_secs();
+    if delta_time <= 0.0 {
+        return;
+    }
+
+    let bounds = domains
+        .get_single()
+        .map(|domain| Rect::from_center_half_size(Vec2::ZERO, domain.half_size()))
+        .ok();
+
+    for (mut boat, hull, mut transform) in boats.iter_mut() {
+        let size = hull.bounds().size();
+        let mass =
+            (hull_area(hull) * params.target_density * boat.relative_density).max(f32::EPSILON);
+        let inertia = (mass * size.length_squared() / 12.0).max(f32::EPSILON);
+        let center = transform.translation.truncate();
+        let forward = (transform.rotation * Vec3::X).truncate();
+
+        let displacement = buoyancy.displacement(hull, &transform);
+        let mut force = displacement.force + params.gravity_vector() * mass;
+        let mut torque = displacement.torque;
+
+        // Each station stands for an equal share of the hull.
+        let share = mass / boat.stations.len() as f32;
+
+        for &station in &boat.stations {
+            let point = to_world(&transform, station);
+            let (flow, density) = match field.as_deref().filter(|field| field.is_submerged(point)) {
+                Some(field) => {
+                    let (sum, total) = neighbors
+                        .within_radius(point.extend(0.0), params.smoothing_radius)
+                        .filter_map(|(entity, position)| {
+                            let velocity = particles.get(entity).ok()?.0.truncate();
+                            let weight =
+                                1.0 - position.truncate().distance(point) / params.smoothing_radius;
+                            Some((velocity * weight, weight))
+                        })
+                        .fold((Vec2::ZERO, 0.0), |(sum, total), (velocity, weight)| {
+                            (sum + velocity, total + weight)
+                        });
+                    if total <= 0.0 {
+                        continue;
+                    }
+
+                    (sum / total, field.density(point) / params.target_density)
+                }
+                // Shallow water sits at the target density.
+                None => match lakes.iter().find_map(|lake| lake.velocity_at(point)) {
+                    Some(flow) => (flow, 1.0),
+                    None => continue,
+                },
+            };
+
+            let arm = point - center;
+            let station_velocity = boat.linear_velocity + boat.angular_velocity * arm.perp();
+            let drag = (flow - station_velocity) * boat.drag * density * share;
+
+            force += drag;
+            torque += arm.perp_dot(drag);
+        }
+
+        force += forward * boat.thrust * boat.throttle * mass;
+        torque += boat.turn_rate * boat.rudder * inertia;
+
+        boat.linear_velocity += force / mass * delta_time;
+        boat.angular_velocity += torque / inertia * delta_time;
+
+        let mut position = center + boat.linear_velocity * delta_time;
+        if let Some(bounds) = bounds {
+            let clamped = position.clamp(bounds.min, bounds.max);
+            if clamped.x != position.x {
+                boat.linear_velocity.x = 0.0;
+            }
+            if clamped.y != position.y {
+                boat.linear_velocity.y = 0.0;
+            }
+            position = clamped;
+        }
+
+        transform.translation = position.extend(transform.translation.z);
+        transform.rotate_z(boat.angular_velocity * delta_time);
+    }
+}
+
+fn draw_boats_system(mut gizmos: Gizmos, boats: Query<(&Boat, &Collider, &Transform)>) {
+    let color = Color::srgb(0.95, 0.8, 0.3);
+
+    for (boat, hull, transform) in boats.iter() {
+        for &station in &boat.stations {
+            gizmos.circle_2d(to_world(transform, station), STATION_SIZE, color);
+        }
+
+        // Bow arrow, reaching past the hull under throttle.
+        let center = transform.translation.truncate();
+        let forward = (transform.rotation * Vec3::X).truncate();
+        let length = hull.bounds().half_size().x * (1.0 + boat.throttle.max(0.0));
+        gizmos.arrow_2d(center, center + forward * length, color);
+    }
+}
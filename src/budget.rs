@@ -0,0 +1,257 @@
+use std::collections::VecDeque;
+
+use bevy::{prelude::*, utils::HashSet};
+use clap::ValueEnum;
+
+use crate::{
+    flow::{DespawnCause, ParticleDespawned},
+    neighbors::FluidNeighbors,
+    params::SimulationParams,
+    DensityCache, SimulationSet, Velocity, SIMULATION_SCHEDULE,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, ValueEnum)]
+pub enum OverflowPolicy {
+    /// Refuse spawns once `--max-particles` are alive
+    #[default]
+    Reject,
+    /// Spawn anyway and delete the oldest particles
+    DespawnOldest,
+    /// Spawn anyway and merge pairs of slow particles in the densest regions
+    Merge,
+}
+
+/// Mass of a particle in multiples of `SimulationParams::mass`, more than one once merged
+#[derive(Component, Reflect, Clone, Copy, PartialEq, Debug)]
+#[reflect(Component)]
+pub struct ParticleMass(pub f32);
+
+impl Default for ParticleMass {
+    fn default() -> Self {
+        Self(1.0)
+    }
+}
+
+pub fn particle_mass(
+    masses: &Query<&ParticleMass>,
+    entity: Entity,
+    params: &SimulationParams,
+) -> f32 {
+    masses.get(entity).map_or(1.0, |mass| mass.0) * params.mass
+}
+
+// Most particles alive at once, starting out as `--max-particles` and changed at runtime by
+// the quality presets, and what happens to spawns past it.
+#[derive(Resource, Clone, Copy, Debug, Default)]
+pub struct ParticleBudget {
+    /// Unbounded when unset
+    pub max: Option<usize>,
+    pub overflow: OverflowPolicy,
+}
+
+impl ParticleBudget {
+    /// Particles a spawn may add next to the `current` ones
+    pub fn remaining(&self, current: usize) -> usize {
+        self.max.map_or(usize::MAX, |max| match self.overflow {
+            OverflowPolicy::Reject => max.saturating_sub(current),
+            // Room is made after the step, so only a single batch is held to the budget.
+            OverflowPolicy::DespawnOldest => max,
+            // Merging keeps all the fluid and may run out of pairs, so spawns wait until it
+            // has caught up.
+            OverflowPolicy::Merge if current > max => 0,
+            OverflowPolicy::Merge => max,
+        })
+    }
+}
+
+// Brings the particle count back down to the `ParticleBudget` after each step when the
+// overflow policy lets emitters, inflows and the brush spawn past it.
+pub struct BudgetPlugin;
+
+impl Plugin for BudgetPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<ParticleMass>()
+            .init_resource::<SpawnOrder>()
+            .add_systems(
+                SIMULATION_SCHEDULE,
+                (track_spawns_system, budget_system)
+                    .chain()
+                    .after(SimulationSet::Collision)
+                    .run_if(overflowing_allowed),
+            );
+    }
+}
+
+// Live particles from oldest to youngest. Entities despawned elsewhere linger until they
+// reach the front or the queue is compacted.
+#[derive(Resource, Default)]
+struct SpawnOrder(VecDeque<Entity>);
+
+fn overflowing_allowed(budget: Res<ParticleBudget>) -> bool {
+    budget.max.is_some() && budget.overflow != OverflowPolicy::Reject
+}
+
+fn track_spawns_system(
+    mut order: ResMut<SpawnOrder>,
+    added: Query<Entity, Added<Velocity>>,
+    particles: Query<(), With<Velocity>>,
+) {
+    order.0.extend(added.iter());
+
+    if order.0.len() > 2 * particles.iter().count() {
+        order.0.retain(|&entity| particles.contains(entity));
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn budget_system(
+    mut commands: Commands,
+    budget: Res<ParticleBudget>,
+    params: Res<SimulationParams>,
+    density_cache: Res<DensityCache>,
+    neighbors: FluidNeighbors,
+    mut order: ResMut<SpawnOrder>,
+    mut particles: Query<(&mut Transform, &mut Velocity, &mut ParticleMass)>,
+    mut despawned: EventWriter<ParticleDespawned>,
+) {
+    let Some(max) = budget.max else {
+        return;
+    };
+
+    let mut excess = particles.iter().count().saturating_sub(max);
+    if excess == 0 {
+        return;
+    }
+
+    if budget.overflow == OverflowPolicy::Merge {
+        merge(
+            &mut commands,
+            &params,
+            &density_cache,
+            &neighbors,
+            &order,
+            &mut particles,
+            &mut despawned,
+            excess,
+        );
+        return;
+    }
+
+    while excess > 0 {
+        let Some(entity) = order.0.pop_front() else {
+            break;
+        };
+
+        let Ok((transform, _, _)) = particles.get(entity) else {
+            continue;
+        };
+
+        despawn(&mut commands, &mut despawned, entity, transform.translation);
+        excess -= 1;
+    }
+}
+
+// Merges up to `excess` pairs, each of a particle no faster than the mean and its nearest
+// such neighbor within a particle spacing, densest first. The survivor moves to the pair's
+// center of mass and takes their summed mass and momentum.
+#[allow(clippy::too_many_arguments)]
+fn merge(
+    commands: &mut Commands,
+    params: &SimulationParams,
+    density_cache: &DensityCache,
+    neighbors: &FluidNeighbors,
+    order: &SpawnOrder,
+    particles: &mut Query<(&mut Transform, &mut Velocity, &mut ParticleMass)>,
+    despawned: &mut EventWriter<ParticleDespawned>,
+    mut excess: usize,
+) {
+    let speeds: Vec<_> = order
+        .0
+        .iter()
+        .filter_map(|&entity| {
+            let (_, velocity, _) = particles.get(entity).ok()?;
+            Some((entity, velocity.0.length()))
+        })
+        .collect();
+    let calm_speed =
+        speeds.iter().map(|&(_, speed)| speed).sum::<f32>() / speeds.len().max(1) as f32;
+
+    let mut candidates: Vec<_> = speeds
+        .into_iter()
+        .filter(|&(_, speed)| speed <= calm_speed)
+        .map(|(entity, _)| {
+            let density = density_cache.densities.get(&entity).copied();
+            (entity, density.unwrap_or_default())
+        })
+        .collect();
+    candidates.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    let mut merged = HashSet::new();
+
+    for (entity, _) in candidates {
+        if excess == 0 {
+            break;
+        }
+
+        if merged.contains(&entity) {
+            continue;
+        }
+
+        let Ok((transform, _, _)) = particles.get(entity) else {
+            continue;
+        };
+        let position = transform.translation;
+
+        // Pairs across a periodic edge are skipped, as their center lies outside the domain.
+        let partner = neighbors
+            .within_radius(position, params.particle_spacing)
+            .filter(|&(other, other_position)| {
+                other != entity
+                    && !merged.contains(&other)
+                    && other_position.distance(position) <= params.particle_spacing
+            })
+            .filter_map(|(other, other_position)| {
+                let (_, other_velocity, other_mass) = particles.get(other).ok()?;
+                (other_velocity.0.length() <= calm_speed).then_some((
+                    other,
+                    other_position,
+                    other_velocity.0,
+                    other_mass.0,
+                ))
+            })
+            .min_by(|a, b| {
+                a.1.distance_squared(position)
+                    .total_cmp(&b.1.distance_squared(position))
+            });
+
+        let Some((other, other_position, other_velocity, other_mass)) = partner else {
+            continue;
+        };
+
+        let Ok((mut transform, mut velocity, mut mass)) = particles.get_mut(entity) else {
+            continue;
+        };
+        let total = mass.0 + other_mass;
+        transform.translation = (position * mass.0 + other_position * other_mass) / total;
+        velocity.0 = (velocity.0 * mass.0 + other_velocity * other_mass) / total;
+        mass.0 = total;
+
+        despawn(commands, despawned, other, other_position);
+        merged.extend([entity, other]);
+        excess -= 1;
+    }
+}
+
+fn despawn(
+    commands: &mut Commands,
+    despawned: &mut EventWriter<ParticleDespawned>,
+    entity: Entity,
+    position: Vec3,
+) {
+    commands.entity(entity).despawn();
+    despawned.send(ParticleDespawned {
+        entity,
+        position: position.truncate(),
+        cause: DespawnCause::Budget,
+    });
+}
@@ -0,0 +1,149 @@
+use bevy::{ecs::system::SystemParam, gizmos::GizmoPlugin, prelude::*};
+
+use crate::{
+    collider::Collider,
+    params::SimulationParams,
+    sdf::{to_world, SignedDistance},
+    shallow_water::ShallowWater,
+    surface::{ColorField, ShowNormals},
+};
+
+// Samples per smoothing radius across a body, and the most along either axis.
+const SAMPLES_PER_RADIUS: f32 = 4.0;
+const MAX_SAMPLES: usize = 64;
+const CENTROID_SIZE: f32 = 2.0;
+const TURN_RADIUS: f32 = 8.0;
+
+/// Fluid displaced by a body, as of the last density stage.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Displacement {
+    /// Area of the body under the fluid surface
+    pub area: f32,
+    /// Center of the displaced fluid, in world space
+    pub centroid: Vec2,
+    /// Weight of the displaced fluid, pointing against gravity
+    pub force: Vec2,
+    /// Turning effect of `force` about the body's `Transform`, counterclockwise positive
+    pub torque: f32,
+}
+
+/// Buoyancy for bodies the solver does not move itself, so another physics plugin can float
+/// them by applying the returned force and torque.
+#[derive(SystemParam)]
+pub struct FluidBuoyancy<'w, 's> {
+    field: Option<Res<'w, ColorField>>,
+    lakes: Query<'w, 's, &'static ShallowWater>,
+    params: Res<'w, SimulationParams>,
+}
+
+impl FluidBuoyancy<'_, '_> {
+    /// Fluid displaced by `shape` placed at `transform`, sampled on a grid a fraction of a
+    /// smoothing radius apart. Shallow water counts at the target density. Polygons must be
+    /// convex, as for colliders.
+    pub fn displacement(&self, shape: &Collider, transform: &Transform) -> Displacement {
+        let field = self.field.as_deref();
+        if field.is_none() && self.lakes.is_empty() {
+            return Displacement::default();
+        }
+
+        let bounds = shape.bounds();
+        if bounds.is_empty() {
+            return Displacement::default();
+        }
+
+        let spacing = (self.params.smoothing_radius / SAMPLES_PER_RADIUS)
+            .max(bounds.size().max_element() / MAX_SAMPLES as f32);
+        let samples = (bounds.size() / spacing).ceil().as_uvec2().max(UVec2::ONE);
+        let cell = bounds.size() / samples.as_vec2();
+        let cell_area = cell.x * cell.y;
+
+        let mut area = 0.0;
+        let mut moment = Vec2::ZERO;
+        let mut weight = 0.0;
+
+        for y in 0..samples.y {
+            for x in 0..samples.x {
+                let local = bounds.min + (Vec2::new(x as f32, y as f32) + 0.5) * cell;
+                if shape.signed_distance(local) > 0.0 {
+                    continue;
+                }
+
+                let point = to_world(transform, local);
+                let density = match field.filter(|field| field.is_submerged(point)) {
+                    Some(field) => field.density(point),
+                    None if self.lakes.iter().any(|lake| lake.is_submerged(point)) => {
+                        self.params.target_density
+                    }
+                    None => continue,
+                };
+
+                area += cell_area;
+                moment += point * cell_area;
+                weight += density * cell_area;
+            }
+        }
+
+        if area <= 0.0 {
+            return Displacement::default();
+        }
+
+        let centroid = moment / area;
+        let force = -self.params.gravity_vector() * weight;
+        let arm = centroid - transform.translation.truncate();
+
+        Displacement {
+            area,
+            centroid,
+            force,
+            torque: arm.perp_dot(force),
+        }
+    }
+}
+
+pub struct BuoyancyPlugin;
+
+impl Plugin for BuoyancyPlugin {
+    fn build(&self, app: &mut App) {
+        if app.is_plugin_added::<GizmoPlugin>() {
+            app.add_systems(
+                Update,
+                draw_buoyancy_system
+                    .run_if(|show: Option<Res<ShowNormals>>| show.is_some_and(|show| show.0)),
+            );
+        }
+    }
+}
+
+// Shown with the surface overlay: the center of the fluid each collider displaces, an arrow
+// along its lift as long as the side of a square of the displaced area, and an arc turning
+// the way the lift would rotate it, a full half turn when it acts at the edge of the body.
+fn draw_buoyancy_system(
+    mut gizmos: Gizmos,
+    buoyancy: FluidBuoyancy,
+    colliders: Query<(&Collider, &Transform)>,
+) {
+    let color = Color::srgb(0.3, 0.9, 0.6);
+
+    for (collider, transform) in colliders.iter() {
+        let displacement = buoyancy.displacement(collider, transform);
+        let Some(direction) = displacement.force.try_normalize() else {
+            continue;
+        };
+
+        gizmos.circle_2d(displacement.centroid, CENTROID_SIZE, color);
+        gizmos.arrow_2d(
+            displacement.centroid,
+            displacement.centroid + direction * displacement.area.sqrt(),
+            color,
+        );
+
+        let reach = collider.bounds().half_size().length() * displacement.force.length();
+        let turn = (displacement.torque / reach.max(f32::EPSILON)).clamp(-1.0, 1.0);
+        gizmos.arc_2d(
+            Isometry2d::from_translation(transform.translation.truncate()),
+            turn * std::f32::consts::PI,
+            TURN_RADIUS,
+            color,
+        );
+    }
+}
@@ -0,0 +1,99 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::SpatialGrid;
+
+// Most cells a flat map allocates before the domain counts as too fine for one.
+const MAX_FLAT_CELLS: usize = 1 << 20;
+
+// Buckets of items keyed by grid cell. Grids over the bounded domain store their cells in a
+// flat array indexed by coordinates, without hashing; only items that strayed outside the
+// domain, and grids too fine to allocate, fall back to a hash map.
+pub enum CellMap<T> {
+    Flat {
+        size: IVec2,
+        slots: Vec<Vec<T>>,
+        outside: HashMap<(i32, i32), Vec<T>>,
+    },
+    Hashed(HashMap<(i32, i32), Vec<T>>),
+}
+
+impl<T> CellMap<T> {
+    pub fn new(grid: &SpatialGrid) -> Self {
+        let size = (grid.size / grid.cell_size)
+            .ceil()
+            .as_ivec2()
+            .max(IVec2::ONE);
+        let count = size.x as usize * size.y as usize;
+
+        if count > MAX_FLAT_CELLS {
+            return Self::Hashed(HashMap::new());
+        }
+
+        Self::Flat {
+            size,
+            slots: std::iter::repeat_with(Vec::new).take(count).collect(),
+            outside: HashMap::new(),
+        }
+    }
+
+    fn index(size: IVec2, (x, y): (i32, i32)) -> Option<usize> {
+        ((0..size.x).contains(&x) && (0..size.y).contains(&y)).then(|| (y * size.x + x) as usize)
+    }
+
+    pub fn push(&mut self, cell: (i32, i32), item: T) {
+        match self {
+            Self::Flat {
+                size,
+                slots,
+                outside,
+            } => match Self::index(*size, cell) {
+                Some(index) => slots[index].push(item),
+                None => outside.entry(cell).or_default().push(item),
+            },
+            Self::Hashed(cells) => cells.entry(cell).or_default().push(item),
+        }
+    }
+
+    pub fn get(&self, cell: &(i32, i32)) -> Option<&Vec<T>> {
+        match self {
+            Self::Flat {
+                size,
+                slots,
+                outside,
+            } => match Self::index(*size, *cell) {
+                Some(index) => slots.get(index).filter(|slot| !slot.is_empty()),
+                None => outside.get(cell),
+            },
+            Self::Hashed(cells) => cells.get(cell),
+        }
+    }
+
+    /// Occupied cells and their items, in no particular order
+    pub fn iter(&self) -> Box<dyn Iterator<Item = ((i32, i32), &Vec<T>)> + '_> {
+        match self {
+            Self::Flat {
+                size,
+                slots,
+                outside,
+            } => {
+                let width = size.x;
+                Box::new(
+                    slots
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, slot)| !slot.is_empty())
+                        .map(move |(index, slot)| {
+                            let index = index as i32;
+                            ((index % width, index / width), slot)
+                        })
+                        .chain(outside.iter().map(|(&cell, items)| (cell, items))),
+                )
+            }
+            Self::Hashed(cells) => Box::new(cells.iter().map(|(&cell, items)| (cell, items))),
+        }
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &Vec<T>> {
+        self.iter().map(|(_, items)| items)
+    }
+}
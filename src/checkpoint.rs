@@ -0,0 +1,330 @@
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use bevy::{input::InputPlugin, prelude::*, utils::HashMap};
+
+use crate::{
+    budget::ParticleMass, cli::Args, particle_bundle, rewind::RewindBuffer, FixedColor,
+    ParticleColor, Velocity,
+};
+
+// Fewest entries the entity remap is let grow to before it is pruned.
+const MIN_REMAP_PRUNE: usize = 4096;
+
+pub struct CheckpointPlugin;
+
+impl Plugin for CheckpointPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<RollbackRequest>()
+            .add_event::<SaveState>()
+            .init_resource::<EntityRemap>()
+            .add_systems(Startup, init_checkpoints)
+            .add_systems(PreUpdate, rollback_system)
+            .add_systems(
+                PostUpdate,
+                (checkpoint_system, save_state_system, prune_remap_system),
+            );
+
+        if app.is_plugin_added::<InputPlugin>() {
+            app.add_systems(Update, rollback_hotkey_system);
+        }
+    }
+}
+
+/// Restores the checkpoint `steps` entries before the newest one, discarding it and
+/// everything newer. `RollbackRequest(0)` undoes back to the most recent checkpoint.
+#[derive(Event)]
+pub struct RollbackRequest(pub usize);
+
+/// Writes every particle's position, velocity and color to the file at this path.
+#[derive(Event)]
+pub struct SaveState(pub PathBuf);
+
+#[derive(Resource)]
+pub struct Checkpoints {
+    timer: Timer,
+    capacity: usize,
+    snapshots: VecDeque<Checkpoint>,
+}
+
+struct Checkpoint {
+    elapsed: f32,
+    particles: Vec<ParticleState>,
+}
+
+/// Triggered for each particle `restore` brings back, as a new entity in place of the
+/// deleted `previous` one.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct ParticleRespawned {
+    pub previous: Entity,
+    pub entity: Entity,
+}
+
+/// Entities that rollbacks and rewinds respawned deleted particles as, shared between them
+/// so that states either one recorded still find a particle the other brought back.
+#[derive(Resource)]
+pub struct EntityRemap {
+    entities: HashMap<Entity, Entity>,
+    // Size the map may reach before entries no recorded state leads through are dropped.
+    prune_at: usize,
+}
+
+impl Default for EntityRemap {
+    fn default() -> Self {
+        Self {
+            entities: HashMap::new(),
+            prune_at: MIN_REMAP_PRUNE,
+        }
+    }
+}
+
+impl EntityRemap {
+    /// The entity standing in for `entity` now, following it through every respawn since
+    pub fn resolve(&self, mut entity: Entity) -> Entity {
+        while let Some(&next) = self.entities.get(&entity) {
+            entity = next;
+        }
+        entity
+    }
+
+    // Keeps only the entries for `recorded` entities, each pointing straight at the entity it
+    // resolves to.
+    fn prune(&mut self, recorded: impl Iterator<Item = Entity>) {
+        let entities = recorded
+            .filter(|entity| self.entities.contains_key(entity))
+            .map(|entity| (entity, self.resolve(entity)))
+            .collect();
+        self.entities = entities;
+        self.prune_at = (self.entities.len() * 2).max(MIN_REMAP_PRUNE);
+    }
+}
+
+#[derive(Clone)]
+pub struct ParticleState {
+    entity: Entity,
+    position: Vec3,
+    velocity: Vec3,
+    color: Color,
+    fixed_color: bool,
+    mass: ParticleMass,
+}
+
+pub type ParticleStateQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static Transform,
+        &'static Velocity,
+        &'static ParticleColor,
+        Has<FixedColor>,
+        &'static ParticleMass,
+    ),
+>;
+
+pub type RestoreQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static mut Transform,
+        &'static mut Velocity,
+        &'static mut ParticleMass,
+    ),
+    With<ParticleColor>,
+>;
+
+pub fn capture(particles: &ParticleStateQuery) -> Vec<ParticleState> {
+    particles
+        .iter()
+        .map(
+            |(entity, transform, velocity, color, fixed_color, mass)| ParticleState {
+                entity,
+                position: transform.translation,
+                velocity: velocity.0,
+                color: color.0,
+                fixed_color,
+                mass: *mass,
+            },
+        )
+        .collect()
+}
+
+// Moves the particles back to `states`, despawning those that didn't exist yet and
+// respawning those removed since. Respawned particles get new entities, recorded in `remap`
+// so that older states still find them.
+pub fn restore(
+    commands: &mut Commands,
+    states: &[ParticleState],
+    remap: &mut EntityRemap,
+    particles: &mut RestoreQuery,
+) {
+    let mut states: HashMap<Entity, &ParticleState> = states
+        .iter()
+        .map(|state| {
+            // Pointing the recorded entity straight at the current one keeps later lookups
+            // from walking the same respawns again.
+            let entity = remap.resolve(state.entity);
+            if entity != state.entity {
+                remap.entities.insert(state.entity, entity);
+            }
+            (entity, state)
+        })
+        .collect();
+
+    for (entity, mut transform, mut velocity, mut mass) in particles.iter_mut() {
+        match states.remove(&entity) {
+            Some(state) => {
+                transform.translation = state.position;
+                velocity.0 = state.velocity;
+                mass.set_if_neq(state.mass);
+            }
+            None => commands.entity(entity).despawn(),
+        }
+    }
+
+    for (previous, state) in states {
+        let mut particle = commands.spawn(particle_bundle(state.position, state.color));
+        particle.insert((Velocity(state.velocity), state.mass));
+
+        if state.fixed_color {
+            particle.insert(FixedColor);
+        }
+
+        let entity = particle.id();
+        remap.entities.insert(previous, entity);
+        commands.trigger(ParticleRespawned { previous, entity });
+    }
+}
+
+fn init_checkpoints(mut commands: Commands, args: Res<Args>) {
+    commands.insert_resource(Checkpoints {
+        timer: Timer::from_seconds(args.checkpoint_interval.max(0.0), TimerMode::Repeating),
+        capacity: if args.checkpoint_interval > 0.0 {
+            args.checkpoints
+        } else {
+            0
+        },
+        snapshots: VecDeque::new(),
+    });
+}
+
+fn checkpoint_system(
+    time: Res<Time>,
+    mut checkpoints: ResMut<Checkpoints>,
+    particles: ParticleStateQuery,
+) {
+    if checkpoints.capacity == 0 {
+        return;
+    }
+
+    if !checkpoints.timer.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    if checkpoints.snapshots.len() >= checkpoints.capacity {
+        checkpoints.snapshots.pop_front();
+    }
+
+    checkpoints.snapshots.push_back(Checkpoint {
+        elapsed: time.elapsed_secs(),
+        particles: capture(&particles),
+    });
+}
+
+fn rollback_system(
+    mut commands: Commands,
+    mut requests: EventReader<RollbackRequest>,
+    mut checkpoints: ResMut<Checkpoints>,
+    mut remap: ResMut<EntityRemap>,
+    mut particles: RestoreQuery,
+) {
+    let Some(steps) = requests.read().map(|request| request.0).max() else {
+        return;
+    };
+
+    let Some(index) = checkpoints.snapshots.len().checked_sub(steps + 1) else {
+        warn!("No checkpoint {steps} steps back to roll back to");
+        return;
+    };
+
+    let checkpoint = checkpoints.snapshots.drain(index..).next().unwrap();
+    checkpoints.timer.reset();
+
+    restore(
+        &mut commands,
+        &checkpoint.particles,
+        &mut remap,
+        &mut particles,
+    );
+
+    info!("Rolled back to checkpoint at {:.1}s", checkpoint.elapsed);
+}
+
+// Prunes the remap whenever it has doubled since last time, as the rewind buffer and the
+// checkpoints drop the states that led through most of its entries.
+fn prune_remap_system(
+    mut remap: ResMut<EntityRemap>,
+    checkpoints: Res<Checkpoints>,
+    rewind: Option<Res<RewindBuffer>>,
+) {
+    if remap.entities.len() <= remap.prune_at {
+        return;
+    }
+
+    let recorded = checkpoints
+        .snapshots
+        .iter()
+        .flat_map(|checkpoint| &checkpoint.particles)
+        .chain(rewind.as_deref().into_iter().flat_map(RewindBuffer::states))
+        .map(|state| state.entity);
+    remap.prune(recorded);
+}
+
+// Little-endian: the magic, the particle count as a u32, then per particle its position and
+// velocity as three f32 each, its sRGBA color as four f32 and a byte set for fixed colors.
+fn write_state(path: &Path, particles: &[ParticleState]) -> io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    file.write_all(b"LQSTATE1")?;
+    file.write_all(&(particles.len() as u32).to_le_bytes())?;
+
+    for particle in particles {
+        let color = particle.color.to_srgba().to_f32_array();
+        for value in particle
+            .position
+            .to_array()
+            .into_iter()
+            .chain(particle.velocity.to_array())
+            .chain(color)
+        {
+            file.write_all(&value.to_le_bytes())?;
+        }
+        file.write_all(&[particle.fixed_color as u8])?;
+    }
+
+    file.flush()
+}
+
+fn save_state_system(mut requests: EventReader<SaveState>, particles: ParticleStateQuery) {
+    for SaveState(path) in requests.read() {
+        let states = capture(&particles);
+
+        match write_state(path, &states) {
+            Ok(()) => info!("Saved {} particles to {}", states.len(), path.display()),
+            Err(error) => error!("Failed to save state to {}: {error}", path.display()),
+        }
+    }
+}
+
+fn rollback_hotkey_system(
+    input: Res<ButtonInput<KeyCode>>,
+    mut requests: EventWriter<RollbackRequest>,
+) {
+    if input.just_pressed(KeyCode::Backspace) {
+        requests.send(RollbackRequest(0));
+    }
+}
@@ -0,0 +1,43 @@
+use bevy::{
+    prelude::*,
+    tasks::{ComputeTaskPool, TaskPool},
+};
+
+use crate::{groups::ParticleGroup, precision::RealVec3, PositionHash, SpatialGrid};
+
+// Splits the grid into strips of whole cell columns, one per compute thread, and runs
+// `solve` on the particles of each strip in parallel. Strips only read the shared grid, so
+// the cells across their borders serve as halos without being copied, and the strips
+// synchronize at the end of each pass.
+pub fn solve_chunked<T: Send + 'static>(
+    grid: &SpatialGrid,
+    cells: &PositionHash,
+    solve: impl Fn(Entity, RealVec3, ParticleGroup) -> Option<T> + Sync,
+) -> Vec<T> {
+    let pool = ComputeTaskPool::get_or_init(TaskPool::default);
+    let columns = grid.cells.x.max(1);
+    let count = pool.thread_num().clamp(1, columns as usize);
+
+    let mut strips = vec![Vec::new(); count];
+    for ((x, _), particles) in cells.iter() {
+        // Cells past a closed edge belong to the strip at that edge.
+        let strip = (x.clamp(0, columns - 1) as usize * count) / columns as usize;
+        strips[strip].push(particles);
+    }
+
+    let solve = &solve;
+    pool.scope(|scope| {
+        for strip in &strips {
+            scope.spawn(async move {
+                strip
+                    .iter()
+                    .flat_map(|particles| particles.iter())
+                    .filter_map(|&(entity, position, group)| solve(entity, position, group))
+                    .collect::<Vec<_>>()
+            });
+        }
+    })
+    .into_iter()
+    .flatten()
+    .collect()
+}
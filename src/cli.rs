@@ -0,0 +1,216 @@
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+use clap::{Parser, ValueEnum};
+
+use crate::{budget::OverflowPolicy, presets::Preset, quality::Quality, spawn_mask::MaskChannel};
+
+#[derive(Resource, Reflect, Clone, Copy, PartialEq, Eq, Debug, ValueEnum)]
+#[reflect(Resource)]
+pub enum Solver {
+    /// Explicit weakly compressible SPH
+    Sph,
+    /// Position-based fluids, iterating on density constraints
+    Pbf,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, ValueEnum)]
+pub enum Sampling {
+    /// Regular square grid at the particle spacing
+    Lattice,
+    /// Random Poisson-disk points with about as many particles per area
+    Poisson,
+}
+
+#[derive(Parser, Resource)]
+#[command(version, about = "2D SPH fluid sandbox")]
+pub struct Args {
+    /// Scene file (`*.scene.ron`, relative to the assets folder) with particle blocks and obstacles
+    #[arg(long)]
+    pub scene: Option<String>,
+
+    /// Bundled scene to start with instead of a scene file
+    #[arg(long, value_enum, conflicts_with = "scene")]
+    pub preset: Option<Preset>,
+
+    /// Also respawn the scene's fluid when its file changes on disk, not just the rest of it
+    #[arg(long)]
+    pub rebuild_scene: bool,
+
+    /// Simulation parameters (`*.params.ron`, relative to the assets folder), re-applied on change
+    #[arg(long)]
+    pub config: Option<String>,
+
+    /// Number of particles in the default block, ignored when a scene is given
+    #[arg(long, default_value_t = 400)]
+    pub particles: usize,
+
+    /// Start with a seeded column of this many particles for profiling; F5 respawns it
+    #[arg(long, value_name = "PARTICLES", conflicts_with_all = ["scene", "preset", "spawn_mask"])]
+    pub stress: Option<usize>,
+
+    /// Upper bound on live particles across every spawning path
+    #[arg(long)]
+    pub max_particles: Option<usize>,
+
+    /// Bundled solver iterations, particle budget and rendering for the machine at hand,
+    /// overriding `--max-particles`
+    #[arg(long, value_enum)]
+    pub quality: Option<Quality>,
+
+    /// What happens to spawns past `--max-particles`
+    #[arg(long, value_enum, default_value_t = OverflowPolicy::Reject)]
+    pub overflow: OverflowPolicy,
+
+    /// Run without a window for the given number of steps, then exit
+    #[arg(long, value_name = "STEPS")]
+    pub headless: Option<u32>,
+
+    #[arg(long, value_enum, default_value_t = Solver::Sph)]
+    pub solver: Solver,
+
+    #[arg(long, default_value_t = 0)]
+    pub seed: u64,
+
+    /// Solver steps run out of sight before the first frame, letting pre-filled tanks settle
+    #[arg(long, value_name = "STEPS", default_value_t = 0)]
+    pub relax_steps: u32,
+
+    /// Relax without gravity, only evening out the initial spacing
+    #[arg(long)]
+    pub relax_without_gravity: bool,
+
+    /// How the default block and scene blocks are filled with particles
+    #[arg(long, value_enum, default_value_t = Sampling::Lattice)]
+    pub sampling: Sampling,
+
+    /// Offset bulk-spawned particles randomly by up to this fraction of the particle spacing,
+    /// so blocks don't collapse in perfectly symmetric patterns
+    #[arg(long, value_name = "FRACTION", default_value_t = 0.0)]
+    pub spawn_jitter: f32,
+
+    /// Check the smoothing kernel's normalization and derivative whenever parameters change,
+    /// always on in debug builds
+    #[arg(long)]
+    pub validate_kernels: bool,
+
+    /// Rhai script (`*.rhai`, relative to the assets folder) defining extra forces or spawn
+    /// rules, reloaded on change
+    #[cfg(feature = "scripting")]
+    #[arg(long)]
+    pub script: Option<String>,
+
+    /// SVG file (relative to the assets folder) whose shapes become static obstacles
+    #[arg(long)]
+    pub obstacles: Option<String>,
+
+    /// Image (relative to the assets folder) filled with particles where it exceeds the threshold
+    #[arg(long)]
+    pub spawn_mask: Option<String>,
+
+    #[arg(long, value_enum, default_value_t = MaskChannel::Alpha)]
+    pub mask_channel: MaskChannel,
+
+    #[arg(long, default_value_t = 0.5)]
+    pub mask_threshold: f32,
+
+    /// Keep the default particle color instead of sampling it from the mask
+    #[arg(long)]
+    pub mask_uncolored: bool,
+
+    /// Step the simulation here and stream its state to clients connecting on this address
+    #[cfg(not(target_arch = "wasm32"))]
+    #[arg(long, value_name = "ADDRESS")]
+    pub serve: Option<String>,
+
+    /// Render the simulation of the server at this address and forward input to it
+    #[cfg(not(target_arch = "wasm32"))]
+    #[arg(long, value_name = "ADDRESS", conflicts_with_all = ["serve", "headless"])]
+    pub connect: Option<String>,
+
+    /// Seconds of simulated time between automatic checkpoints, 0 to disable
+    #[arg(long, default_value_t = 5.0)]
+    pub checkpoint_interval: f32,
+
+    /// Number of checkpoints kept for rollback (Backspace)
+    #[arg(long, default_value_t = 12)]
+    pub checkpoints: usize,
+
+    /// Play a tone for each splash against walls or back into the fluid
+    #[arg(long)]
+    pub splash_audio: bool,
+
+    /// Seconds of frame-by-frame history kept for rewinding (R, Shift+R to scrub forward)
+    #[arg(long, default_value_t = 10.0)]
+    pub rewind_seconds: f32,
+
+    /// Memory the rewind history may use, in megabytes, 0 to disable
+    #[arg(long, default_value_t = 256)]
+    pub rewind_memory: usize,
+
+    /// Write per-frame particle positions to this PC2 point cache
+    #[cfg(not(target_arch = "wasm32"))]
+    #[arg(long)]
+    pub point_cache: Option<PathBuf>,
+
+    /// Write a JSON summary of stage timings, density error and particle counts on exit
+    #[cfg(not(target_arch = "wasm32"))]
+    #[arg(long)]
+    pub summary: Option<PathBuf>,
+
+    /// Write the surface heights recorded by the scene's wave gauges to this CSV file on exit
+    #[cfg(not(target_arch = "wasm32"))]
+    #[arg(long)]
+    pub wave_gauge_csv: Option<PathBuf>,
+}
+
+impl Args {
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn is_client(&self) -> bool {
+        self.connect.is_some()
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn is_client(&self) -> bool {
+        false
+    }
+
+    pub fn scene_path(&self) -> Option<String> {
+        self.scene
+            .clone()
+            .or_else(|| self.preset.map(|preset| preset.path().to_string()))
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn parse_args() -> Args {
+    Args::parse()
+}
+
+// Browsers have no command line, so options come from the page's query string instead:
+// `index.html?scene=scenes/basin.scene.ron&particles=300`.
+#[cfg(target_arch = "wasm32")]
+pub fn parse_args() -> Args {
+    let query = web_sys::window()
+        .and_then(|window| window.location().search().ok())
+        .unwrap_or_default();
+
+    let options = query
+        .trim_start_matches('?')
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .flat_map(|pair| match pair.split_once('=') {
+            Some((key, value)) => vec![format!("--{key}"), value.to_string()],
+            None => vec![format!("--{pair}")],
+        });
+
+    let mut args = Args::try_parse_from(std::iter::once("liquids_bevy".to_string()).chain(options))
+        .unwrap_or_else(|error| {
+            warn!("Ignoring invalid query options: {error}");
+            Args::parse_from(["liquids_bevy"])
+        });
+
+    args.max_particles.get_or_insert(crate::WASM_MAX_PARTICLES);
+    args
+}
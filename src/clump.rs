@@ -0,0 +1,153 @@
+use bevy::{gizmos::GizmoPlugin, prelude::*};
+
+use crate::{
+    boundary_collision_system, collision_system, params::SimulationParams, SimulationSet, Velocity,
+    SIMULATION_SCHEDULE,
+};
+
+// Particles moving as one rigid body, e.g. a chunk of ice floating in water. The members
+// keep taking part in the fluid solver, and after each step their motion is replaced by the
+// rigid motion that best matches it: the shared velocity of their center of mass, plus a
+// rotation carrying their angular momentum. Their positions are then snapped back into the
+// shape they had when frozen, turned by the best-fitting angle. Despawning the clump
+// dissolves it back into fluid.
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct RigidClump {
+    /// Members and their offsets from the center of mass when frozen
+    members: Vec<(Entity, Vec2)>,
+    /// Turn since the clump was frozen, in radians
+    pub angle: f32,
+}
+
+impl RigidClump {
+    pub fn new(particles: &[(Entity, Vec2)]) -> Self {
+        let center = particles
+            .iter()
+            .map(|&(_, position)| position)
+            .sum::<Vec2>()
+            / particles.len() as f32;
+
+        Self {
+            members: particles
+                .iter()
+                .map(|&(entity, position)| (entity, position - center))
+                .collect(),
+            angle: 0.0,
+        }
+    }
+
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.members.iter().any(|&(member, _)| member == entity)
+    }
+}
+
+pub struct ClumpPlugin;
+
+impl Plugin for ClumpPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<RigidClump>().add_systems(
+            SIMULATION_SCHEDULE,
+            rigid_clump_system
+                .in_set(SimulationSet::Collision)
+                .after(collision_system)
+                .before(boundary_collision_system),
+        );
+
+        if app.is_plugin_added::<GizmoPlugin>() {
+            app.add_systems(Update, draw_clumps_system);
+        }
+    }
+}
+
+fn rigid_clump_system(
+    mut commands: Commands,
+    mut clumps: Query<(Entity, &mut RigidClump)>,
+    mut particles: Query<(&mut Transform, &mut Velocity)>,
+) {
+    for (clump_entity, mut clump) in clumps.iter_mut() {
+        // Members deleted since freezing drop out, and the rest keep their shape.
+        let members: Vec<(Entity, Vec2, Vec2, Vec2)> = clump
+            .members
+            .iter()
+            .filter_map(|&(entity, rest)| {
+                let (transform, velocity) = particles.get(entity).ok()?;
+                Some((
+                    entity,
+                    rest,
+                    transform.translation.truncate(),
+                    velocity.0.truncate(),
+                ))
+            })
+            .collect();
+
+        if members.len() < 2 {
+            commands.entity(clump_entity).despawn();
+            continue;
+        }
+
+        if members.len() < clump.members.len() {
+            clump.members = members
+                .iter()
+                .map(|&(entity, rest, _, _)| (entity, rest))
+                .collect();
+        }
+
+        let count = members.len() as f32;
+        let center = members.iter().map(|member| member.2).sum::<Vec2>() / count;
+        let velocity = members.iter().map(|member| member.3).sum::<Vec2>() / count;
+        let rest_center = members.iter().map(|member| member.1).sum::<Vec2>() / count;
+
+        // Angular momentum and moment of inertia about the center, and the sums whose ratio
+        // is the tangent of the angle best turning the rest shape onto the current one.
+        let (mut momentum, mut inertia, mut cosine, mut sine) = (0.0, 0.0, 0.0, 0.0);
+
+        for &(_, rest, position, member_velocity) in &members {
+            let offset = position - center;
+            let rest = rest - rest_center;
+
+            momentum += offset.perp_dot(member_velocity - velocity);
+            inertia += offset.length_squared();
+            cosine += rest.dot(offset);
+            sine += rest.perp_dot(offset);
+        }
+
+        let angular_velocity = if inertia > 0.0 {
+            momentum / inertia
+        } else {
+            0.0
+        };
+        let rotation = Vec2::new(cosine, sine).normalize_or(Vec2::X);
+        clump.angle = rotation.to_angle();
+
+        for (entity, rest, _, _) in members {
+            let Ok((mut transform, mut member_velocity)) = particles.get_mut(entity) else {
+                continue;
+            };
+            let offset = rotation.rotate(rest - rest_center);
+
+            transform.translation = (center + offset).extend(transform.translation.z);
+            member_velocity.0 =
+                (velocity + angular_velocity * offset.perp()).extend(member_velocity.0.z);
+        }
+    }
+}
+
+fn draw_clumps_system(
+    mut gizmos: Gizmos,
+    params: Res<SimulationParams>,
+    clumps: Query<&RigidClump>,
+    particles: Query<&Transform, With<Velocity>>,
+) {
+    for clump in clumps.iter() {
+        for &(entity, _) in &clump.members {
+            if let Ok(transform) = particles.get(entity) {
+                gizmos.circle_2d(
+                    transform.translation.truncate(),
+                    params.radius * 1.5,
+                    Color::srgb(0.8, 0.95, 1.0),
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use bevy::{gizmos::GizmoPlugin, prelude::*};
+
+use crate::sdf::{to_world, SignedDistance};
+
+// Static shapes placed by their `Transform` (translation and rotation about z).
+// Shape coordinates are local to that transform.
+#[derive(Component, Reflect, Clone)]
+#[reflect(Component)]
+pub enum Collider {
+    Circle { radius: f32 },
+    Box { half_size: Vec2 },
+    ConvexPolygon { points: Vec<Vec2> },
+}
+
+impl Collider {
+    pub fn convex_polygon(mut points: Vec<Vec2>) -> Self {
+        let twice_area: f32 = points
+            .iter()
+            .zip(points.iter().cycle().skip(1))
+            .map(|(a, b)| a.perp_dot(*b))
+            .sum();
+
+        if twice_area < 0.0 {
+            points.reverse();
+        }
+
+        Self::ConvexPolygon { points }
+    }
+
+    /// Local bounding box of the shape
+    pub fn bounds(&self) -> Rect {
+        match self {
+            Self::Circle { radius } => {
+                Rect::from_center_half_size(Vec2::ZERO, Vec2::splat(*radius))
+            }
+            Self::Box { half_size } => Rect::from_center_half_size(Vec2::ZERO, *half_size),
+            Self::ConvexPolygon { points } => points
+                .iter()
+                .fold(Rect::EMPTY, |bounds, &point| bounds.union_point(point)),
+        }
+    }
+
+    // Signed distance to the surface (negative inside) and the outward normal there.
+    fn distance(&self, point: Vec2) -> Option<(f32, Vec2)> {
+        match self {
+            Self::Circle { radius } => {
+                let distance = point.length();
+                let normal = if distance > f32::EPSILON {
+                    point / distance
+                } else {
+                    Vec2::Y
+                };
+
+                Some((distance - radius, normal))
+            }
+            Self::Box { half_size } => {
+                let q = point.abs() - *half_size;
+
+                if q.x > 0.0 || q.y > 0.0 {
+                    let outside = q.max(Vec2::ZERO);
+                    let distance = outside.length();
+                    Some((distance, point.signum() * outside / distance))
+                } else if q.x > q.y {
+                    Some((q.x, Vec2::new(point.x.signum(), 0.0)))
+                } else {
+                    Some((q.y, Vec2::new(0.0, point.y.signum())))
+                }
+            }
+            Self::ConvexPolygon { points } => {
+                let mut inside = points.len() >= 3;
+                let mut closest: Option<(f32, Vec2)> = None;
+
+                for (&a, &b) in points.iter().zip(points.iter().cycle().skip(1)) {
+                    let edge = b - a;
+                    if edge.perp_dot(point - a) < 0.0 {
+                        inside = false;
+                    }
+
+                    let length_squared = edge.length_squared();
+                    if length_squared <= f32::EPSILON {
+                        continue;
+                    }
+
+                    let t = ((point - a).dot(edge) / length_squared).clamp(0.0, 1.0);
+                    let offset = point - (a + edge * t);
+                    let distance = offset.length();
+
+                    if closest.is_none_or(|(closest, _)| distance < closest) {
+                        let normal = if distance > f32::EPSILON {
+                            offset / distance
+                        } else {
+                            Vec2::new(edge.y, -edge.x).normalize()
+                        };
+                        closest = Some((distance, normal));
+                    }
+                }
+
+                closest.map(|(distance, normal)| {
+                    if inside {
+                        (-distance, -normal)
+                    } else {
+                        (distance, normal)
+                    }
+                })
+            }
+        }
+    }
+
+    fn outline(&self) -> Vec<Vec2> {
+        match self {
+            Self::Circle { .. } => Vec::new(),
+            Self::Box { half_size } => vec![
+                Vec2::new(-half_size.x, -half_size.y),
+                Vec2::new(half_size.x, -half_size.y),
+                Vec2::new(half_size.x, half_size.y),
+                Vec2::new(-half_size.x, half_size.y),
+            ],
+            Self::ConvexPolygon { points } => points.clone(),
+        }
+    }
+}
+
+impl SignedDistance for Collider {
+    fn signed_distance(&self, point: Vec2) -> f32 {
+        self.distance(point)
+            .map_or(f32::INFINITY, |(distance, _)| distance)
+    }
+
+    fn gradient(&self, point: Vec2) -> Vec2 {
+        self.distance(point)
+            .map_or(Vec2::ZERO, |(_, normal)| normal)
+    }
+}
+
+pub struct ColliderPlugin;
+
+impl Plugin for ColliderPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Collider>();
+
+        if app.is_plugin_added::<GizmoPlugin>() {
+            app.add_systems(Update, draw_colliders_system);
+        }
+    }
+}
+
+fn draw_colliders_system(mut gizmos: Gizmos, colliders: Query<(&Collider, &Transform)>) {
+    for (collider, transform) in colliders.iter() {
+        draw_collider(&mut gizmos, collider, transform, Color::srgb(0.8, 0.8, 0.8));
+    }
+}
+
+pub fn draw_collider(
+    gizmos: &mut Gizmos,
+    collider: &Collider,
+    transform: &Transform,
+    color: Color,
+) {
+    if let Collider::Circle { radius } = collider {
+        gizmos.circle_2d(transform.translation.truncate(), *radius, color);
+        return;
+    }
+
+    let outline: Vec<_> = collider
+        .outline()
+        .into_iter()
+        .map(|point| to_world(transform, point))
+        .collect();
+
+    gizmos.linestrip_2d(
+        outline.iter().copied().chain(outline.first().copied()),
+        color,
+    );
+}
@@ -0,0 +1,112 @@
+use bevy::{
+    diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic},
+    math::DVec2,
+    prelude::*,
+};
+use serde::Serialize;
+
+use crate::{
+    budget::ParticleMass, domain::Domain, params::SimulationParams, SimulationSet, Velocity,
+    SIMULATION_SCHEDULE,
+};
+
+pub const KINETIC_ENERGY: DiagnosticPath = DiagnosticPath::const_new("conservation/kinetic_energy");
+pub const POTENTIAL_ENERGY: DiagnosticPath =
+    DiagnosticPath::const_new("conservation/potential_energy");
+pub const ENERGY_DRIFT: DiagnosticPath = DiagnosticPath::const_new("conservation/energy_drift");
+pub const MOMENTUM_DRIFT: DiagnosticPath = DiagnosticPath::const_new("conservation/momentum_drift");
+
+// Totals over all particles after each step, with potential energy measured from the domain
+// corner lowest along gravity. Drift is relative to the first step since the particle count last changed, as
+// emitters and drains add and remove both quantities.
+#[derive(Resource, Reflect, Serialize, Default, Clone)]
+#[reflect(Resource)]
+pub struct Conservation {
+    pub momentum: [f64; 2],
+    pub kinetic_energy: f64,
+    pub potential_energy: f64,
+    /// Change in total energy relative to the baseline total
+    pub energy_drift: f64,
+    /// Magnitude of the change in total momentum
+    pub momentum_drift: f64,
+    #[serde(skip)]
+    #[reflect(ignore)]
+    baseline: Option<Baseline>,
+}
+
+#[derive(Clone, Copy)]
+struct Baseline {
+    count: usize,
+    momentum: DVec2,
+    energy: f64,
+}
+
+pub struct ConservationPlugin;
+
+impl Plugin for ConservationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Conservation>()
+            .register_type::<Conservation>()
+            .register_diagnostic(Diagnostic::new(KINETIC_ENERGY))
+            .register_diagnostic(Diagnostic::new(POTENTIAL_ENERGY))
+            .register_diagnostic(Diagnostic::new(ENERGY_DRIFT))
+            .register_diagnostic(Diagnostic::new(MOMENTUM_DRIFT))
+            .add_systems(
+                SIMULATION_SCHEDULE,
+                conservation_system.after(SimulationSet::Collision),
+            );
+    }
+}
+
+fn conservation_system(
+    params: Res<SimulationParams>,
+    domains: Query<&Domain>,
+    particles: Query<(&Transform, &Velocity, &ParticleMass)>,
+    mut conservation: ResMut<Conservation>,
+    mut diagnostics: Diagnostics,
+) {
+    let gravity = params.gravity_vector().as_dvec2();
+    let floor = domains.single().half_size().as_dvec2() * gravity.signum();
+
+    let mut count = 0;
+    let mut momentum = DVec2::ZERO;
+    let mut kinetic_energy = 0.0;
+    let mut potential_energy = 0.0;
+
+    for (transform, velocity, particle_mass) in particles.iter() {
+        let mass = f64::from(params.mass * particle_mass.0);
+        let velocity = velocity.0.truncate().as_dvec2();
+
+        count += 1;
+        momentum += mass * velocity;
+        kinetic_energy += 0.5 * mass * velocity.length_squared();
+        potential_energy += mass * gravity.dot(floor - transform.translation.truncate().as_dvec2());
+    }
+
+    let energy = kinetic_energy + potential_energy;
+    let baseline = match conservation.baseline {
+        Some(baseline) if baseline.count == count => baseline,
+        _ => Baseline {
+            count,
+            momentum,
+            energy,
+        },
+    };
+
+    let energy_drift = (energy - baseline.energy) / baseline.energy.abs().max(f64::EPSILON);
+    let momentum_drift = momentum.distance(baseline.momentum);
+
+    *conservation = Conservation {
+        momentum: momentum.to_array(),
+        kinetic_energy,
+        potential_energy,
+        energy_drift,
+        momentum_drift,
+        baseline: Some(baseline),
+    };
+
+    diagnostics.add_measurement(&KINETIC_ENERGY, || kinetic_energy);
+    diagnostics.add_measurement(&POTENTIAL_ENERGY, || potential_energy);
+    diagnostics.add_measurement(&ENERGY_DRIFT, || energy_drift);
+    diagnostics.add_measurement(&MOMENTUM_DRIFT, || momentum_drift);
+}
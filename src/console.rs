@@ -0,0 +1,243 @@
+use bevy::{
+    input::InputSystem,
+    prelude::*,
+    reflect::{GetPath, PartialReflect},
+};
+use bevy_inspector_egui::bevy_egui::{egui, EguiContexts};
+use clap::ValueEnum;
+
+use crate::{
+    checkpoint::SaveState,
+    cli::Solver,
+    fluid_commands::{FluidMaterial, SpawnParticles, SpawnShape},
+    params::SimulationParams,
+    presets::Preset,
+    quality::Quality,
+    scene::LoadScene,
+};
+
+// Lines of output kept in the console.
+const HISTORY: usize = 200;
+// Largest block side or disc radius, in particle spacings.
+const MAX_SIZE: f32 = 1000.0;
+
+const HELP: &str = "\
+spawn block <columns> <rows> [x y]   block of particles, centered on the origin by default
+spawn disc <radius> [x y]            disc of particles, the radius in particle spacings
+spawn particle <x> <y>               single particle
+set gravity <x> <y>                  gravity vector
+set <parameter> <value>              any number or flag of the simulation parameters
+solver <sph|pbf>
+quality <low|medium|high|ultra>
+preset <name>                        load a bundled scene
+save <path>                          write the particle state to a file
+clear";
+
+// Typed commands for quick experiments, toggled with the backtick key. Commands go through
+// the same events and resources the menus and hotkeys use.
+pub struct ConsolePlugin;
+
+impl Plugin for ConsolePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Console>()
+            .add_systems(
+                PreUpdate,
+                (toggle_console_system, absorb_keys_system)
+                    .chain()
+                    .after(InputSystem),
+            )
+            .add_systems(Update, (console_window_system, run_commands_system).chain());
+    }
+}
+
+#[derive(Resource, Default)]
+struct Console {
+    open: bool,
+    input: String,
+    output: Vec<String>,
+    pending: Vec<String>,
+}
+
+impl Console {
+    fn print(&mut self, line: impl Into<String>) {
+        self.output.push(line.into());
+
+        let excess = self.output.len().saturating_sub(HISTORY);
+        self.output.drain(..excess);
+    }
+}
+
+fn toggle_console_system(input: Res<ButtonInput<KeyCode>>, mut console: ResMut<Console>) {
+    if input.just_pressed(KeyCode::Backquote) {
+        console.open = !console.open;
+    }
+}
+
+// Keeps typed commands from also reaching the hotkeys.
+fn absorb_keys_system(console: Res<Console>, mut input: ResMut<ButtonInput<KeyCode>>) {
+    if console.open {
+        input.reset_all();
+    }
+}
+
+fn console_window_system(mut contexts: EguiContexts, mut console: ResMut<Console>) {
+    if !console.open {
+        return;
+    }
+
+    let console = &mut *console;
+
+    egui::Window::new("Console").show(contexts.ctx_mut(), |ui| {
+        egui::ScrollArea::vertical()
+            .max_height(200.0)
+            .stick_to_bottom(true)
+            .show(ui, |ui| {
+                for line in &console.output {
+                    ui.monospace(line);
+                }
+            });
+
+        let response = ui.text_edit_singleline(&mut console.input);
+        if response.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter)) {
+            let line = std::mem::take(&mut console.input);
+            if !line.trim().is_empty() {
+                console.pending.push(line);
+            }
+            response.request_focus();
+        }
+    });
+}
+
+fn run_commands_system(world: &mut World) {
+    let pending = std::mem::take(&mut world.resource_mut::<Console>().pending);
+
+    for line in pending {
+        let result = run_command(world, &line);
+        let mut console = world.resource_mut::<Console>();
+
+        console.print(format!("> {line}"));
+        match result {
+            Ok(Some(output)) => console.print(output),
+            Ok(None) => {}
+            Err(error) => console.print(format!("error: {error}")),
+        }
+    }
+}
+
+fn run_command(world: &mut World, line: &str) -> Result<Option<String>, String> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+
+    match words[..] {
+        ["help"] => Ok(Some(HELP.to_string())),
+        ["clear"] => {
+            world.resource_mut::<Console>().output.clear();
+            Ok(None)
+        }
+        ["spawn", "block", columns, rows, ref center @ ..] => {
+            let spacing = world.resource::<SimulationParams>().particle_spacing;
+            let extent = Vec2::new(size(columns)?, size(rows)?) * spacing;
+            let rect = Rect::from_center_size(point(center)?, extent);
+            spawn(world, SpawnShape::Block(rect))
+        }
+        ["spawn", "disc", radius, ref center @ ..] => {
+            let spacing = world.resource::<SimulationParams>().particle_spacing;
+            spawn(
+                world,
+                SpawnShape::Disc {
+                    center: point(center)?,
+                    radius: size(radius)? * spacing,
+                },
+            )
+        }
+        ["spawn", "particle", x, y] => spawn(
+            world,
+            SpawnShape::Points(vec![Vec2::new(number(x)?, number(y)?)]),
+        ),
+        ["set", "gravity", x, y] => {
+            let gravity = Vec2::new(number(x)?, number(y)?);
+            let mut params = world.resource_mut::<SimulationParams>();
+            params.gravity = gravity.length();
+            if gravity != Vec2::ZERO {
+                params.gravity_angle = Vec2::NEG_Y.angle_to(gravity).to_degrees();
+            }
+            Ok(None)
+        }
+        ["set", name, value] => {
+            let mut params = world.resource_mut::<SimulationParams>();
+            let field = params
+                .reflect_path_mut(name)
+                .map_err(|_| format!("no parameter named {name}"))?;
+            set_field(field, value)?;
+            Ok(None)
+        }
+        ["solver", name] => {
+            world.insert_resource(Solver::from_str(name, true)?);
+            Ok(None)
+        }
+        ["quality", name] => {
+            world.insert_resource(Quality::from_str(name, true)?);
+            Ok(None)
+        }
+        ["preset", name] => {
+            let preset = Preset::from_str(name, true)?;
+            world.send_event(LoadScene(preset.path().to_string()));
+            Ok(Some(format!("Loading {}", preset.label())))
+        }
+        ["save", path] => {
+            world.send_event(SaveState(path.into()));
+            Ok(None)
+        }
+        _ => Err(format!("unknown command {line:?}, try help")),
+    }
+}
+
+fn spawn(world: &mut World, shape: SpawnShape) -> Result<Option<String>, String> {
+    world.send_event(SpawnParticles {
+        shape,
+        count: None,
+        spacing: None,
+        material: FluidMaterial::default(),
+    });
+    Ok(None)
+}
+
+fn number(word: &str) -> Result<f32, String> {
+    word.parse()
+        .ok()
+        .filter(|number: &f32| number.is_finite())
+        .ok_or_else(|| format!("{word} is not a number"))
+}
+
+fn size(word: &str) -> Result<f32, String> {
+    let size = number(word)?;
+    if size <= 0.0 || size > MAX_SIZE {
+        return Err(format!("{word} is not a size between 0 and {MAX_SIZE}"));
+    }
+    Ok(size)
+}
+
+fn point(words: &[&str]) -> Result<Vec2, String> {
+    match *words {
+        [] => Ok(Vec2::ZERO),
+        [x, y] => Ok(Vec2::new(number(x)?, number(y)?)),
+        _ => Err("expected a position as x y".to_string()),
+    }
+}
+
+fn set_field(field: &mut dyn PartialReflect, value: &str) -> Result<(), String> {
+    if let Some(field) = field.try_downcast_mut::<f32>() {
+        *field = number(value)?;
+    } else if let Some(field) = field.try_downcast_mut::<u32>() {
+        *field = value
+            .parse()
+            .map_err(|_| format!("{value} is not a whole number"))?;
+    } else if let Some(field) = field.try_downcast_mut::<bool>() {
+        *field = value
+            .parse()
+            .map_err(|_| format!("{value} is not true or false"))?;
+    } else {
+        return Err("only numbers and flags can be set".to_string());
+    }
+
+    Ok(())
+}
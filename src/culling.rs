@@ -0,0 +1,77 @@
+use bevy::prelude::*;
+use bevy_pancam::PanCam;
+
+use crate::{
+    params::SimulationParams, stability::Quarantined, update_colors_system, ParticleColor,
+};
+
+// Hides particles well outside the particle camera's view, so zoomed in the renderer and
+// the palette recoloring only deal with what is on screen, as Bevy's own culling still
+// tests every drawn particle against the view each frame. They keep simulating all the
+// while. Toggled with the `enabled` field of the `ViewCulling` resource.
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct ViewCulling {
+    pub enabled: bool,
+    /// Distance past the edges of the view that particles stay drawn within, so panning
+    /// doesn't reveal a blank border before they reappear
+    pub margin: f32,
+}
+
+impl Default for ViewCulling {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            margin: 20.0,
+        }
+    }
+}
+
+// Particle hidden for being out of view. Hiding particles for other reasons, like
+// quarantine, is left to whatever hid them.
+#[derive(Component)]
+pub struct Culled;
+
+pub struct CullingPlugin;
+
+impl Plugin for CullingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ViewCulling>()
+            .register_type::<ViewCulling>()
+            .add_systems(Update, cull_system.before(update_colors_system));
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn cull_system(
+    mut commands: Commands,
+    culling: Res<ViewCulling>,
+    params: Res<SimulationParams>,
+    cameras: Query<(&GlobalTransform, &OrthographicProjection), With<PanCam>>,
+    mut particles: Query<
+        (Entity, &Transform, &mut Visibility, Has<Culled>),
+        (With<ParticleColor>, Without<Quarantined>),
+    >,
+) {
+    let view = match cameras.get_single() {
+        Ok((transform, projection)) if culling.enabled => {
+            let center = transform.translation().truncate();
+            let area =
+                Rect::from_corners(projection.area.min + center, projection.area.max + center);
+            Some(area.inflate(culling.margin.max(0.0) + params.radius))
+        }
+        _ => None,
+    };
+
+    for (entity, transform, mut visibility, culled) in particles.iter_mut() {
+        let visible = view.is_none_or(|view| view.contains(transform.translation.truncate()));
+
+        if visible && culled {
+            *visibility = Visibility::Inherited;
+            commands.entity(entity).remove::<Culled>();
+        } else if !visible && !culled {
+            *visibility = Visibility::Hidden;
+            commands.entity(entity).insert(Culled);
+        }
+    }
+}
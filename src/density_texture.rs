@@ -0,0 +1,137 @@
+use bevy::{
+    asset::RenderAssetUsages,
+    prelude::*,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+};
+
+use crate::{
+    budget::ParticleMass,
+    domain::Domain,
+    params::SimulationParams,
+    precision::{real, to_f32},
+    run_simulation_system, smoothing_kernel, ParticleColor,
+};
+
+// Field texels per smoothing radius, and the most along either axis.
+const TEXELS_PER_RADIUS: f32 = 2.0;
+const MAX_TEXELS: u32 = 256;
+
+/// SPH density over the whole domain, refreshed every frame as a single-channel float image
+/// for materials and overlays to sample. Rows run top to bottom, as in any image.
+#[derive(Resource)]
+pub struct DensityTexture {
+    pub image: Handle<Image>,
+    /// World-space area the image covers, overhanging the domain's top and right edges by
+    /// less than a texel
+    pub bounds: Rect,
+}
+
+// Texel grid covering the domain: texel size, texel counts, and the bottom-left corner.
+pub fn field_layout(params: &SimulationParams, domain: &Domain) -> (f32, UVec2, Vec2) {
+    let texel_size = (params.smoothing_radius / TEXELS_PER_RADIUS)
+        .max(domain.size.max_element() / MAX_TEXELS as f32);
+    let texels = (domain.size / texel_size).ceil().as_uvec2().max(UVec2::ONE);
+    (texel_size, texels, -domain.half_size())
+}
+
+// Calls `splat` with every texel index a particle's kernel reaches and the kernel value at
+// that texel's center.
+pub fn for_each_texel(
+    position: Vec2,
+    (texel_size, texels, corner): (f32, UVec2, Vec2),
+    radius: f32,
+    mut splat: impl FnMut(usize, f32),
+) {
+    let reach = (radius / texel_size).ceil() as i32;
+    let texel = ((position - corner) / texel_size).floor().as_ivec2();
+
+    for y in (texel.y - reach).max(0)..=(texel.y + reach).min(texels.y as i32 - 1) {
+        for x in (texel.x - reach).max(0)..=(texel.x + reach).min(texels.x as i32 - 1) {
+            let center = corner + (Vec2::new(x as f32, y as f32) + 0.5) * texel_size;
+            let distance = center.distance(position);
+
+            if distance < radius {
+                let weight = to_f32(smoothing_kernel(real(radius), real(distance)));
+                splat((y as u32 * texels.x + x as u32) as usize, weight);
+            }
+        }
+    }
+}
+
+// Image texel holding field texel `index`, counted from the bottom-left corner.
+pub fn image_index(index: usize, texels: UVec2) -> usize {
+    let (x, y) = (index as u32 % texels.x, index as u32 / texels.x);
+    ((texels.y - 1 - y) * texels.x + x) as usize
+}
+
+pub struct DensityTexturePlugin;
+
+impl Plugin for DensityTexturePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup_density_texture)
+            .add_systems(Update, density_texture_system.after(run_simulation_system));
+    }
+}
+
+fn setup_density_texture(mut commands: Commands, mut images: ResMut<Assets<Image>>) {
+    let image = images.add(Image::new_fill(
+        Extent3d::default(),
+        TextureDimension::D2,
+        &0.0f32.to_le_bytes(),
+        TextureFormat::R32Float,
+        RenderAssetUsages::default(),
+    ));
+
+    commands.insert_resource(DensityTexture {
+        image,
+        bounds: Rect::default(),
+    });
+}
+
+pub fn density_texture_system(
+    params: Res<SimulationParams>,
+    domains: Query<&Domain>,
+    particles: Query<(&Transform, &ParticleMass), With<ParticleColor>>,
+    mut texture: ResMut<DensityTexture>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let Ok(domain) = domains.get_single() else {
+        return;
+    };
+
+    let Some(image) = images.get_mut(&texture.image) else {
+        return;
+    };
+
+    let layout = field_layout(&params, domain);
+    let (texel_size, texels, corner) = layout;
+    let extent = Extent3d {
+        width: texels.x,
+        height: texels.y,
+        depth_or_array_layers: 1,
+    };
+
+    if image.texture_descriptor.size != extent {
+        image.resize(extent);
+    }
+
+    let bounds = Rect::from_corners(corner, corner + texels.as_vec2() * texel_size);
+    if texture.bounds != bounds {
+        texture.bounds = bounds;
+    }
+
+    let mut densities = vec![0.0; (texels.x * texels.y) as usize];
+    for (transform, mass) in particles.iter() {
+        for_each_texel(
+            transform.translation.truncate(),
+            layout,
+            params.smoothing_radius,
+            |index, weight| densities[index] += params.mass * mass.0 * weight,
+        );
+    }
+
+    for (index, density) in densities.into_iter().enumerate() {
+        let texel = image_index(index, texels) * 4;
+        image.data[texel..texel + 4].copy_from_slice(&density.to_le_bytes());
+    }
+}
@@ -0,0 +1,99 @@
+use bevy::{gizmos::GizmoPlugin, prelude::*};
+
+use crate::{
+    cache_density_system, params::SimulationParams, sdf::to_local, SimulationSet,
+    SIMULATION_SCHEDULE,
+};
+
+// Box of `half_size` around its `Transform` where time runs at `scale`, below 1 for
+// bullet-time pockets and above to hurry the fluid through.
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct TimeDilation {
+    pub half_size: Vec2,
+    pub scale: f32,
+}
+
+impl TimeDilation {
+    // How far `point` is into the region, from 0 a smoothing radius outside its edge to 1 as
+    // far inside, so particles crossing it speed up or slow down over a few steps instead of
+    // being sheared apart from their neighbors.
+    fn weight(&self, transform: &Transform, point: Vec2, blend: f32) -> f32 {
+        let q = to_local(transform, point).abs() - self.half_size;
+        let distance = q.max(Vec2::ZERO).length() + q.max_element().min(0.0);
+
+        (0.5 - distance / (2.0 * blend)).clamp(0.0, 1.0)
+    }
+}
+
+/// Multiplier on the step length for this particle, from the regions it is in
+#[derive(Component, Reflect, Clone, Copy, PartialEq, Debug)]
+#[reflect(Component)]
+pub struct TimeScale(pub f32);
+
+impl Default for TimeScale {
+    fn default() -> Self {
+        Self(1.0)
+    }
+}
+
+pub struct DilationPlugin;
+
+impl Plugin for DilationPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<TimeDilation>()
+            .register_type::<TimeScale>()
+            .add_systems(
+                SIMULATION_SCHEDULE,
+                time_scale_system
+                    .in_set(SimulationSet::Density)
+                    .before(cache_density_system),
+            );
+
+        if app.is_plugin_added::<GizmoPlugin>() {
+            app.add_systems(Update, draw_dilations_system);
+        }
+    }
+}
+
+fn time_scale_system(
+    params: Res<SimulationParams>,
+    regions: Query<(&TimeDilation, &Transform)>,
+    mut particles: Query<(&Transform, &mut TimeScale)>,
+) {
+    let blend = params.smoothing_radius.max(f32::EPSILON);
+
+    for (transform, mut time_scale) in particles.iter_mut() {
+        let position = transform.translation.truncate();
+
+        // Overlapping regions compound.
+        let scale = regions
+            .iter()
+            .map(|(region, region_transform)| {
+                let weight = region.weight(region_transform, position, blend);
+                1.0 + (region.scale.max(0.0) - 1.0) * weight
+            })
+            .product();
+
+        time_scale.set_if_neq(TimeScale(scale));
+    }
+}
+
+fn draw_dilations_system(mut gizmos: Gizmos, regions: Query<(&TimeDilation, &Transform)>) {
+    for (region, transform) in regions.iter() {
+        let color = if region.scale < 1.0 {
+            Color::srgb(0.5, 0.4, 0.9)
+        } else {
+            Color::srgb(0.9, 0.7, 0.3)
+        };
+
+        gizmos.rect_2d(
+            Isometry2d::new(
+                transform.translation.truncate(),
+                Rot2::radians(transform.rotation.to_euler(EulerRot::ZYX).0),
+            ),
+            region.half_size * 2.0,
+            color,
+        );
+    }
+}
@@ -0,0 +1,193 @@
+use bevy::{gizmos::GizmoPlugin, prelude::*};
+use bevy_pancam::PanCam;
+
+use serde::Deserialize;
+
+use crate::{params::SimulationParams, sdf::WallMaterial};
+
+const HANDLE_DISTANCE: f32 = 4.0;
+
+// Rectangular bounds of the simulation, centered on the origin. Exactly one exists.
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct Domain {
+    pub size: Vec2,
+    pub walls: DomainWalls,
+    pub outflow: Outflow,
+}
+
+#[derive(Reflect, Deserialize, Clone, Copy, Default)]
+#[serde(default)]
+pub struct DomainWalls {
+    pub left: WallMaterial,
+    pub right: WallMaterial,
+    pub bottom: WallMaterial,
+    pub top: WallMaterial,
+}
+
+// Sides of the domain left open for fluid to leave through instead of walled off. Particles
+// in a layer `depth` thick inside an open side keep more of the velocity they started each
+// step with the nearer they are to it, so the fluid there coasts out instead of piling up
+// against the side and pushing back upstream, and they are deleted as they cross it.
+#[derive(Reflect, Deserialize, Clone, Copy, Default)]
+#[serde(default)]
+pub struct Outflow {
+    pub left: bool,
+    pub right: bool,
+    pub bottom: bool,
+    pub top: bool,
+    /// Thickness of the layer, twice the smoothing radius when unset
+    pub depth: Option<f32>,
+}
+
+impl Outflow {
+    pub fn any(&self) -> bool {
+        self.left || self.right || self.bottom || self.top
+    }
+
+    pub fn depth(&self, params: &SimulationParams) -> f32 {
+        self.depth.unwrap_or(2.0 * params.smoothing_radius)
+    }
+
+    /// Sides open on each axis, toward negative and toward positive coordinates
+    fn open(&self) -> [BVec2; 2] {
+        [
+            BVec2::new(self.left, self.bottom),
+            BVec2::new(self.right, self.top),
+        ]
+    }
+
+    /// How far `position` is through the layer of the nearest open side, from 0 at the inner
+    /// edge of the layer to 1 at the side
+    pub fn fade(&self, position: Vec2, half_size: Vec2, depth: f32) -> f32 {
+        let [negative, positive] = self.open();
+        let inside = half_size - position.abs();
+        let open = BVec2::new(
+            if position.x < 0.0 {
+                negative.x
+            } else {
+                positive.x
+            },
+            if position.y < 0.0 {
+                negative.y
+            } else {
+                positive.y
+            },
+        );
+
+        let distance = Vec2::select(open, inside, Vec2::INFINITY).min_element();
+        (1.0 - distance / depth.max(f32::EPSILON)).clamp(0.0, 1.0)
+    }
+
+    /// Whether `position` has passed an open side
+    pub fn crossed(&self, position: Vec2, half_size: Vec2) -> bool {
+        let [negative, positive] = self.open();
+        (negative & position.cmplt(-half_size)).any()
+            || (positive & position.cmpgt(half_size)).any()
+    }
+}
+
+impl Default for Domain {
+    fn default() -> Self {
+        Self {
+            size: Vec2::new(200.0, 400.0),
+            walls: DomainWalls::default(),
+            outflow: Outflow::default(),
+        }
+    }
+}
+
+impl Domain {
+    pub fn half_size(&self) -> Vec2 {
+        self.size / 2.0
+    }
+}
+
+pub struct DomainPlugin;
+
+impl Plugin for DomainPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Domain>();
+        app.world_mut()
+            .spawn((Name::new("Domain"), Domain::default()));
+
+        if app.is_plugin_added::<GizmoPlugin>() {
+            app.add_systems(Update, draw_domain_system);
+        }
+    }
+}
+
+// Dragging an edge resizes the domain symmetrically along that axis, a corner along both.
+pub struct DomainHandlesPlugin;
+
+impl Plugin for DomainHandlesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, domain_drag_system);
+    }
+}
+
+pub fn draw_domain_system(mut gizmos: Gizmos, domains: Query<&Domain>) {
+    let color = Color::srgb(0.5, 0.5, 0.5);
+
+    for domain in domains.iter() {
+        gizmos.rect_2d(Vec2::ZERO, domain.size, color);
+
+        let half_size = domain.half_size();
+        for corner in [
+            half_size,
+            half_size * Vec2::new(-1.0, 1.0),
+            -half_size,
+            half_size * Vec2::new(1.0, -1.0),
+        ] {
+            gizmos.circle_2d(corner, HANDLE_DISTANCE / 2.0, color);
+        }
+    }
+}
+
+fn domain_drag_system(
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    windows: Query<&Window>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<PanCam>>,
+    params: Res<SimulationParams>,
+    mut domains: Query<&mut Domain>,
+    mut dragged_axes: Local<Option<BVec2>>,
+) {
+    if mouse_input.just_released(MouseButton::Left) {
+        *dragged_axes = None;
+    }
+
+    let Ok(mut domain) = domains.get_single_mut() else {
+        return;
+    };
+
+    let (camera, camera_transform) = camera_query.single();
+    let Some(cursor) = windows
+        .single()
+        .cursor_position()
+        .and_then(|cursor_position| {
+            camera
+                .viewport_to_world_2d(camera_transform, cursor_position)
+                .ok()
+        })
+    else {
+        return;
+    };
+
+    let reach = cursor.abs();
+
+    if mouse_input.just_pressed(MouseButton::Left) {
+        let half_size = domain.half_size();
+        let near_edge = (reach - half_size)
+            .abs()
+            .cmple(Vec2::splat(HANDLE_DISTANCE));
+        let within = reach.cmple(half_size + HANDLE_DISTANCE);
+        let axes = BVec2::new(near_edge.x && within.y, near_edge.y && within.x);
+
+        *dragged_axes = axes.any().then_some(axes);
+    }
+
+    if let Some(axes) = *dragged_axes {
+        let size = (reach * 2.0).max(Vec2::splat(2.0 * params.smoothing_radius));
+        domain.size = Vec2::select(axes, size, domain.size);
+    }
+}
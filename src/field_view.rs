@@ -0,0 +1,261 @@
+use bevy::{
+    asset::RenderAssetUsages,
+    color::ColorToPacked,
+    prelude::*,
+    render::{
+        camera::Viewport,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+        view::RenderLayers,
+    },
+};
+use bevy_pancam::PanCam;
+
+use crate::{
+    density_texture::{
+        density_texture_system, field_layout, for_each_texel, image_index, DensityTexture,
+    },
+    domain::Domain,
+    params::SimulationParams,
+    ParticleColor, Velocity,
+};
+
+const FIELD_LAYER: usize = 1;
+
+// Toggled with V: the right half of the window shows the domain again as a heatmap of the
+// density or the flow speed (Shift+V switches), panned and zoomed along with the particles.
+pub struct FieldViewPlugin;
+
+impl Plugin for FieldViewPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FieldView>()
+            .register_type::<FieldView>()
+            .add_systems(Startup, setup_field_view)
+            .add_systems(
+                Update,
+                (
+                    field_view_keys_system,
+                    layout_system,
+                    field_image_system.run_if(|view: Res<FieldView>| view.enabled),
+                )
+                    .chain()
+                    .after(density_texture_system),
+            );
+    }
+}
+
+#[derive(Reflect, Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum FieldKind {
+    #[default]
+    Density,
+    Speed,
+}
+
+#[derive(Resource, Reflect, Default)]
+#[reflect(Resource)]
+pub struct FieldView {
+    pub enabled: bool,
+    pub kind: FieldKind,
+}
+
+#[derive(Component)]
+struct FieldCamera;
+
+#[derive(Component)]
+struct FieldImage;
+
+fn setup_field_view(mut commands: Commands, mut images: ResMut<Assets<Image>>) {
+    let image = images.add(Image::new_fill(
+        Extent3d::default(),
+        TextureDimension::D2,
+        &[0, 0, 0, 255],
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    ));
+
+    commands.spawn((
+        Name::new("Field camera"),
+        FieldCamera,
+        Camera2d,
+        Camera {
+            order: 1,
+            is_active: false,
+            ..default()
+        },
+        RenderLayers::layer(FIELD_LAYER),
+    ));
+
+    commands.spawn((
+        Name::new("Field image"),
+        FieldImage,
+        Sprite::from_image(image),
+        RenderLayers::layer(FIELD_LAYER),
+    ));
+}
+
+fn field_view_keys_system(input: Res<ButtonInput<KeyCode>>, mut view: ResMut<FieldView>) {
+    if !input.just_pressed(KeyCode::KeyV) {
+        return;
+    }
+
+    if input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+        view.kind = match view.kind {
+            FieldKind::Density => FieldKind::Speed,
+            FieldKind::Speed => FieldKind::Density,
+        };
+        info!("Field view shows {:?}", view.kind);
+    } else {
+        view.enabled = !view.enabled;
+    }
+}
+
+// Splits the window between the two cameras and keeps the field camera on the same spot and
+// zoom as the particle camera.
+fn layout_system(
+    view: Res<FieldView>,
+    windows: Query<&Window>,
+    mut particle_cameras: Query<
+        (&mut Camera, &Transform, &OrthographicProjection),
+        (With<PanCam>, Without<FieldCamera>),
+    >,
+    mut field_cameras: Query<
+        (&mut Camera, &mut Transform, &mut OrthographicProjection),
+        With<FieldCamera>,
+    >,
+) {
+    let (Ok(window), Ok((mut camera, transform, projection)), Ok(mut field)) = (
+        windows.get_single(),
+        particle_cameras.get_single_mut(),
+        field_cameras.get_single_mut(),
+    ) else {
+        return;
+    };
+
+    let size = window.physical_size();
+    let half = UVec2::new(size.x / 2, size.y);
+
+    field.0.is_active = view.enabled && half.x > 0;
+
+    if !field.0.is_active {
+        camera.viewport = None;
+        return;
+    }
+
+    camera.viewport = Some(Viewport {
+        physical_position: UVec2::ZERO,
+        physical_size: half,
+        ..default()
+    });
+    field.0.viewport = Some(Viewport {
+        physical_position: UVec2::new(half.x, 0),
+        physical_size: half,
+        ..default()
+    });
+
+    *field.1 = *transform;
+    field.2.scale = projection.scale;
+}
+
+// The density comes straight from the `DensityTexture`. For the speed each particle spreads
+// its kernel over the texels it reaches, giving the kernel-weighted mean speed at every
+// texel center.
+fn field_image_system(
+    view: Res<FieldView>,
+    params: Res<SimulationParams>,
+    domains: Query<&Domain>,
+    density: Res<DensityTexture>,
+    particles: Query<(&Transform, &Velocity), With<ParticleColor>>,
+    mut sprites: Query<(&mut Sprite, &mut Transform), (With<FieldImage>, Without<ParticleColor>)>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let (Ok(domain), Ok((mut sprite, mut sprite_transform))) =
+        (domains.get_single(), sprites.get_single_mut())
+    else {
+        return;
+    };
+
+    let layout = field_layout(&params, domain);
+    let texels = layout.1;
+    let count = (texels.x * texels.y) as usize;
+
+    // Empty where no particle reaches.
+    let values: Vec<Option<f32>> = match view.kind {
+        FieldKind::Density => {
+            let Some(image) = images.get(&density.image) else {
+                return;
+            };
+
+            if image.data.len() != count * 4 {
+                return;
+            }
+
+            (0..count)
+                .map(|index| {
+                    let texel = image_index(index, texels) * 4;
+                    let bytes = image.data[texel..texel + 4].try_into().unwrap();
+                    let value = f32::from_le_bytes(bytes);
+                    (value > 0.0).then_some(value)
+                })
+                .collect()
+        }
+        FieldKind::Speed => {
+            let mut weights = vec![0.0; count];
+            let mut speeds = vec![0.0; count];
+
+            for (transform, velocity) in particles.iter() {
+                let speed = velocity.0.length();
+                for_each_texel(
+                    transform.translation.truncate(),
+                    layout,
+                    params.smoothing_radius,
+                    |index, weight| {
+                        weights[index] += weight;
+                        speeds[index] += speed * weight;
+                    },
+                );
+            }
+
+            weights
+                .iter()
+                .zip(&speeds)
+                .map(|(&weight, &speed)| (weight > 0.0).then(|| speed / weight))
+                .collect()
+        }
+    };
+
+    let Some(image) = images.get_mut(&sprite.image) else {
+        return;
+    };
+
+    let extent = Extent3d {
+        width: texels.x,
+        height: texels.y,
+        depth_or_array_layers: 1,
+    };
+
+    if image.texture_descriptor.size != extent {
+        image.resize(extent);
+    }
+
+    sprite.custom_size = Some(density.bounds.size());
+    sprite_transform.translation = density.bounds.center().extend(0.0);
+
+    // Scaled to the largest value in the domain, from dark blue through to red.
+    let max = values
+        .iter()
+        .flatten()
+        .copied()
+        .fold(f32::EPSILON, f32::max);
+
+    for (index, value) in values.into_iter().enumerate() {
+        let color = match value {
+            Some(value) => {
+                let level = value / max;
+                Color::hsl(240.0 * (1.0 - level), 0.9, 0.15 + 0.45 * level)
+            }
+            None => Color::BLACK,
+        };
+
+        let pixel = image_index(index, texels) * 4;
+        image.data[pixel..pixel + 4].copy_from_slice(&color.to_srgba().to_u8_array());
+    }
+}
@@ -0,0 +1,584 @@
+use std::f32::consts::TAU;
+
+use bevy::{gizmos::GizmoPlugin, prelude::*};
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::{
+    budget::ParticleBudget,
+    cli::Args,
+    collider::{draw_collider, Collider},
+    domain::Domain,
+    fluid_commands::{FluidCommands, FluidMaterial},
+    params::SimulationParams,
+    sdf::{to_local, SignedDistance},
+    spawn_jitter, update_system, velocity_system, SimulationRng, SimulationSet, Velocity,
+    SIMULATION_SCHEDULE,
+};
+
+// Emits rows of particles across a line of `width` centered on its `Transform`, moving at
+// `velocity` shaped by `profile` and `pulse`. Particles within `depth` downstream of the line
+// are held at their inflow velocity so the pressure of the fluid ahead cannot push back into
+// the emitter.
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct Inflow {
+    pub width: f32,
+    pub depth: f32,
+    /// Velocity at the peak of the profile
+    pub velocity: Vec2,
+    pub profile: InflowProfile,
+    pub pulse: Option<Pulse>,
+    /// Distance each column has moved since its last row was spawned
+    travelled: Vec<f32>,
+    elapsed: f32,
+}
+
+// How the inflow speed varies across the line.
+#[derive(Reflect, Deserialize, Clone, Copy, PartialEq, Default, Debug)]
+pub enum InflowProfile {
+    #[default]
+    Uniform,
+    /// Poiseuille flow between no-slip walls at the ends of the line, at the full velocity
+    /// in the middle and one and a half times the mean
+    Parabolic,
+}
+
+// Speed rising and falling over time, as a multiple of the inflow velocity that eases from
+// `minimum` up to 1 and back once every `period` seconds, starting at the minimum.
+#[derive(Reflect, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub struct Pulse {
+    pub period: f32,
+    pub minimum: f32,
+}
+
+impl Inflow {
+    pub fn new(width: f32, depth: f32, velocity: Vec2) -> Self {
+        Self {
+            width,
+            depth,
+            velocity,
+            profile: InflowProfile::Uniform,
+            pulse: None,
+            travelled: Vec::new(),
+            elapsed: 0.0,
+        }
+    }
+
+    // Position of a point in (across the line, downstream) coordinates.
+    fn local(&self, transform: &Transform, point: Vec2) -> Option<Vec2> {
+        let direction = self.velocity.try_normalize()?;
+        let offset = point - transform.translation.truncate();
+        Some(Vec2::new(
+            offset.dot(direction.perp()),
+            offset.dot(direction),
+        ))
+    }
+
+    /// Velocity `across` from the middle of the line, at the current point of the pulse
+    pub fn velocity_at(&self, across: f32) -> Vec2 {
+        let shape = match self.profile {
+            InflowProfile::Uniform => 1.0,
+            InflowProfile::Parabolic => {
+                let fraction = 2.0 * across / self.width.max(f32::EPSILON);
+                (1.0 - fraction * fraction).max(0.0)
+            }
+        };
+
+        let pulse = self.pulse.map_or(1.0, |pulse| {
+            let phase = TAU * self.elapsed / pulse.period.max(f32::EPSILON);
+            pulse.minimum + (1.0 - pulse.minimum) * (1.0 - phase.cos()) / 2.0
+        });
+
+        self.velocity * shape * pulse
+    }
+}
+
+// Spawns `rate` particles per second at its `Transform`, moving along `direction` at
+// `speed`. Each spawn position is offset by up to `jitter` on both axes.
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct Emitter {
+    pub enabled: bool,
+    pub direction: Vec2,
+    pub rate: f32,
+    pub speed: f32,
+    /// Fixed particle color, density coloring when unset
+    pub color: Option<Color>,
+    pub jitter: f32,
+    pending: f32,
+}
+
+impl Emitter {
+    pub fn new(direction: Vec2, rate: f32, speed: f32) -> Self {
+        Self {
+            enabled: true,
+            direction,
+            rate,
+            speed,
+            color: None,
+            jitter: 0.0,
+            pending: 0.0,
+        }
+    }
+}
+
+// Drops `rate` particles per second at random points along the top of the domain, already
+// falling at their terminal velocity `speed`. Particles sinking below `drain_line` are
+// deleted, so a scene can rain indefinitely without filling up.
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct Rain {
+    pub enabled: bool,
+    pub rate: f32,
+    pub speed: f32,
+    pub drain_line: Option<f32>,
+    /// Fixed particle color, density coloring when unset
+    pub color: Option<Color>,
+    pending: f32,
+}
+
+impl Rain {
+    pub fn new(rate: f32, speed: f32) -> Self {
+        Self {
+            enabled: true,
+            rate,
+            speed,
+            drain_line: None,
+            color: None,
+            pending: 0.0,
+        }
+    }
+}
+
+// Deletes every particle inside a box of `half_size` around its `Transform`.
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct Drain {
+    pub enabled: bool,
+    pub half_size: Vec2,
+}
+
+impl Drain {
+    pub fn new(half_size: Vec2) -> Self {
+        Self {
+            enabled: true,
+            half_size,
+        }
+    }
+}
+
+// Deletes every particle inside `shape`, placed by its `Transform`, for pits and other
+// places fluid should vanish that a box does not fit.
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct KillZone {
+    pub enabled: bool,
+    pub shape: Collider,
+}
+
+impl KillZone {
+    pub fn new(shape: Collider) -> Self {
+        Self {
+            enabled: true,
+            shape,
+        }
+    }
+}
+
+// Catch-all for particles that got past every wall, deleting those further than `margin`
+// outside the domain or whose position is no longer finite.
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct OutOfBounds {
+    pub enabled: bool,
+    pub margin: f32,
+}
+
+impl Default for OutOfBounds {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            margin: 50.0,
+        }
+    }
+}
+
+#[derive(Reflect, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DespawnCause {
+    Drain,
+    KillZone,
+    OutOfBounds,
+    Budget,
+    /// Poured into shallow water beyond an open side of the domain
+    Absorbed,
+}
+
+/// Sent for each particle a drain, kill zone, the out-of-bounds check, the particle budget or
+/// shallow water coupled to the particles deletes.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct ParticleDespawned {
+    pub entity: Entity,
+    pub position: Vec2,
+    pub cause: DespawnCause,
+}
+
+pub struct FlowPlugin;
+
+impl Plugin for FlowPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ParticleDespawned>()
+            .init_resource::<OutOfBounds>()
+            .register_type::<Inflow>()
+            .register_type::<Emitter>()
+            .register_type::<Rain>()
+            .register_type::<Drain>()
+            .register_type::<KillZone>()
+            .register_type::<OutOfBounds>()
+            .init_resource::<OutflowLayer>()
+            .add_systems(
+                SIMULATION_SCHEDULE,
+                (
+                    outflow_record_system
+                        .in_set(SimulationSet::Forces)
+                        .before(velocity_system),
+                    outflow_fade_system
+                        .in_set(SimulationSet::Integration)
+                        .before(update_system),
+                    inflow_buffer_system
+                        .in_set(SimulationSet::Forces)
+                        .after(velocity_system),
+                    (inflow_spawn_system, emitter_system, rain_system)
+                        .in_set(SimulationSet::Integration),
+                    drain_system.in_set(SimulationSet::Collision),
+                ),
+            )
+            .add_systems(Update, log_despawns_system);
+
+        if app.is_plugin_added::<GizmoPlugin>() {
+            app.add_systems(Update, draw_kill_zones_system);
+        }
+    }
+}
+
+// Particles in the layer inside an open side, with their velocity at the start of the step
+// and how far through the layer they are.
+#[derive(Resource, Default)]
+struct OutflowLayer(Vec<(Entity, Vec3, f32)>);
+
+fn outflow_record_system(
+    params: Res<SimulationParams>,
+    domains: Query<&Domain>,
+    mut layer: ResMut<OutflowLayer>,
+    particles: Query<(Entity, &Transform, &Velocity)>,
+) {
+    layer.0.clear();
+
+    let Ok(domain) = domains.get_single() else {
+        return;
+    };
+    if !domain.outflow.any() {
+        return;
+    }
+
+    let half_size = domain.half_size();
+    let depth = domain.outflow.depth(&params);
+
+    for (entity, transform, velocity) in particles.iter() {
+        let fade = domain
+            .outflow
+            .fade(transform.translation.truncate(), half_size, depth);
+        if fade > 0.0 {
+            layer.0.push((entity, velocity.0, fade));
+        }
+    }
+}
+
+// Undoes the forces of the step on the layer in proportion to how far through it each
+// particle is, so by the open side the fluid neither feels nor passes on any pressure.
+fn outflow_fade_system(layer: Res<OutflowLayer>, mut particles: Query<&mut Velocity>) {
+    for &(entity, start, fade) in &layer.0 {
+        if let Ok(mut velocity) = particles.get_mut(entity) {
+            velocity.0 = velocity.0.lerp(start, fade);
+        }
+    }
+}
+
+fn inflow_buffer_system(
+    inflows: Query<(&Inflow, &Transform)>,
+    mut particles: Query<(&Transform, &mut Velocity)>,
+) {
+    for (inflow, inflow_transform) in inflows.iter() {
+        for (transform, mut velocity) in particles.iter_mut() {
+            let Some(local) = inflow.local(inflow_transform, transform.translation.truncate())
+            else {
+                continue;
+            };
+
+            if local.x.abs() <= inflow.width / 2.0 && (0.0..=inflow.depth).contains(&local.y) {
+                velocity.0 = inflow.velocity_at(local.x).extend(0.0);
+            }
+        }
+    }
+}
+
+// Each column spawns a particle whenever it has moved a spacing, so faster columns spawn
+// more often and every column keeps the fluid at the same density.
+fn inflow_spawn_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    budget: Res<ParticleBudget>,
+    params: Res<SimulationParams>,
+    mut inflows: Query<(&mut Inflow, &Transform)>,
+    particles: Query<(), With<Velocity>>,
+) {
+    let spacing = params.particle_spacing;
+    let mut count = particles.iter().count();
+
+    for (mut inflow, transform) in inflows.iter_mut() {
+        inflow.elapsed += time.delta_secs();
+
+        let Some(direction) = inflow.velocity.try_normalize() else {
+            continue;
+        };
+
+        let columns = (inflow.width / spacing).floor().max(1.0) as usize;
+        let center = transform.translation.truncate();
+        inflow.travelled.resize(columns, 0.0);
+
+        for column in 0..columns {
+            let across = (column as f32 + 0.5 - columns as f32 / 2.0) * spacing;
+            let velocity = inflow.velocity_at(across);
+            let travelled = &mut inflow.travelled[column];
+            *travelled += velocity.dot(direction).max(0.0) * time.delta_secs();
+
+            let mut positions = Vec::new();
+
+            while *travelled >= spacing {
+                *travelled -= spacing;
+
+                if budget.remaining(count) > 0 {
+                    positions.push(center + direction.perp() * across + direction * *travelled);
+                    count += 1;
+                }
+            }
+
+            commands.spawn_fluid(
+                positions,
+                FluidMaterial {
+                    velocity,
+                    ..default()
+                },
+            );
+        }
+    }
+}
+
+fn emitter_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    args: Res<Args>,
+    budget: Res<ParticleBudget>,
+    params: Res<SimulationParams>,
+    mut rng: ResMut<SimulationRng>,
+    mut emitters: Query<(&mut Emitter, &Transform)>,
+    particles: Query<(), With<Velocity>>,
+) {
+    let mut count = particles.iter().count();
+
+    for (mut emitter, transform) in emitters.iter_mut() {
+        if !emitter.enabled {
+            continue;
+        }
+
+        emitter.pending += emitter.rate.max(0.0) * time.delta_secs();
+
+        let velocity = emitter.direction.normalize_or_zero() * emitter.speed;
+        let jitter = emitter.jitter.abs();
+
+        let mut positions = Vec::new();
+
+        while emitter.pending >= 1.0 {
+            emitter.pending -= 1.0;
+
+            if budget.remaining(count) == 0 {
+                emitter.pending = 0.0;
+                break;
+            }
+
+            let offset = Vec2::new(
+                rng.0.gen_range(-jitter..=jitter),
+                rng.0.gen_range(-jitter..=jitter),
+            );
+            positions.push(
+                transform.translation.truncate() + offset + spawn_jitter(&args, &params, &mut rng),
+            );
+
+            count += 1;
+        }
+
+        commands.spawn_fluid(
+            positions,
+            FluidMaterial {
+                color: emitter.color,
+                velocity,
+                ..default()
+            },
+        );
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn rain_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    budget: Res<ParticleBudget>,
+    params: Res<SimulationParams>,
+    mut rng: ResMut<SimulationRng>,
+    domains: Query<&Domain>,
+    mut rains: Query<&mut Rain>,
+    particles: Query<(), With<Velocity>>,
+) {
+    let Ok(domain) = domains.get_single() else {
+        return;
+    };
+
+    let half_size = domain.half_size();
+    let margin = params.particle_spacing.min(half_size.x);
+    let mut count = particles.iter().count();
+
+    for mut rain in rains.iter_mut() {
+        if !rain.enabled {
+            continue;
+        }
+
+        rain.pending += rain.rate.max(0.0) * time.delta_secs();
+
+        let mut positions = Vec::new();
+
+        while rain.pending >= 1.0 {
+            rain.pending -= 1.0;
+
+            if budget.remaining(count) == 0 {
+                rain.pending = 0.0;
+                break;
+            }
+
+            let x = rng
+                .0
+                .gen_range(-half_size.x + margin..=half_size.x - margin);
+            positions.push(Vec2::new(x, half_size.y - margin));
+            count += 1;
+        }
+
+        commands.spawn_fluid(
+            positions,
+            FluidMaterial {
+                color: rain.color,
+                velocity: Vec2::NEG_Y * rain.speed,
+                ..default()
+            },
+        );
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn drain_system(
+    mut commands: Commands,
+    out_of_bounds: Res<OutOfBounds>,
+    domains: Query<&Domain>,
+    drains: Query<(&Drain, &Transform)>,
+    rains: Query<&Rain>,
+    kill_zones: Query<(&KillZone, &Transform)>,
+    particles: Query<(Entity, &Transform), With<Velocity>>,
+    mut despawned: EventWriter<ParticleDespawned>,
+) {
+    let domain = domains.get_single().ok();
+    let bounds = domain.map(|domain| {
+        let extent = domain.half_size() + out_of_bounds.margin.max(0.0);
+        Rect::from_center_half_size(Vec2::ZERO, extent)
+    });
+
+    for (entity, transform) in particles.iter() {
+        let position = transform.translation.truncate();
+
+        let drained = drains.iter().any(|(drain, drain_transform)| {
+            drain.enabled
+                && to_local(drain_transform, position)
+                    .abs()
+                    .cmple(drain.half_size)
+                    .all()
+        }) || rains
+            .iter()
+            .any(|rain| rain.drain_line.is_some_and(|line| position.y < line))
+            || domain.is_some_and(|domain| domain.outflow.crossed(position, domain.half_size()));
+
+        let cause = if drained {
+            DespawnCause::Drain
+        } else if kill_zones.iter().any(|(zone, zone_transform)| {
+            zone.enabled
+                && zone
+                    .shape
+                    .signed_distance(to_local(zone_transform, position))
+                    <= 0.0
+        }) {
+            DespawnCause::KillZone
+        } else if out_of_bounds.enabled
+            && (!position.is_finite() || bounds.is_some_and(|bounds| !bounds.contains(position)))
+        {
+            DespawnCause::OutOfBounds
+        } else {
+            continue;
+        };
+
+        commands.entity(entity).despawn();
+        despawned.send(ParticleDespawned {
+            entity,
+            position,
+            cause,
+        });
+    }
+}
+
+// Drains are routine, but anything else deleting particles is worth a note, once per frame.
+fn log_despawns_system(mut despawned: EventReader<ParticleDespawned>) {
+    let mut kill_zone = 0;
+    let mut budget = 0;
+    let mut out_of_bounds = Vec::new();
+
+    for event in despawned.read() {
+        match event.cause {
+            DespawnCause::Drain | DespawnCause::Absorbed => {}
+            DespawnCause::KillZone => kill_zone += 1,
+            DespawnCause::Budget => budget += 1,
+            DespawnCause::OutOfBounds => out_of_bounds.push((event.entity, event.position)),
+        }
+    }
+
+    if kill_zone > 0 {
+        debug!("Kill zones removed {kill_zone} particles");
+    }
+
+    if budget > 0 {
+        debug!("Particle budget removed {budget} particles");
+    }
+
+    if let Some((entity, position)) = out_of_bounds.first() {
+        warn!(
+            "Removed {} particles outside the domain, {entity} at {position}",
+            out_of_bounds.len()
+        );
+    }
+}
+
+fn draw_kill_zones_system(mut gizmos: Gizmos, zones: Query<(&KillZone, &Transform)>) {
+    for (zone, transform) in zones.iter() {
+        let color = if zone.enabled {
+            Color::srgb(0.9, 0.25, 0.2)
+        } else {
+            Color::srgb(0.4, 0.2, 0.2)
+        };
+
+        draw_collider(&mut gizmos, &zone.shape, transform, color);
+    }
+}
@@ -0,0 +1,299 @@
+use bevy::{gizmos::GizmoPlugin, prelude::*};
+
+use crate::{
+    dilation::TimeScale, domain::Domain, velocity_system, SimulationSet, Velocity,
+    SIMULATION_SCHEDULE,
+};
+
+// Distance between the arrows drawn to show a field.
+const ARROW_SPACING: f32 = 20.0;
+// Seconds of travel each arrow's length represents.
+const ARROW_SCALE: f32 = 0.2;
+
+// Background velocity the particles are pulled towards, for wind over water, currents and
+// art-directed flows. Each second a particle loses `strength` of the difference between its
+// velocity and the field's, so high strengths carry it along almost exactly and low ones
+// only nudge it.
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct FlowField {
+    pub enabled: bool,
+    pub field: VelocityField,
+    pub strength: f32,
+}
+
+impl FlowField {
+    pub fn new(field: VelocityField, strength: f32) -> Self {
+        Self {
+            enabled: true,
+            field,
+            strength,
+        }
+    }
+}
+
+// Opaque to reflection, like `Sdf`, as the grid's samples aren't worth editing one by one.
+#[derive(Reflect, Clone)]
+#[reflect(opaque)]
+pub enum VelocityField {
+    Uniform(Vec2),
+    // `velocity` at `from` rising linearly to `velocity + change` at height `to`, and
+    // constant beyond either.
+    Shear {
+        from: f32,
+        to: f32,
+        velocity: Vec2,
+        change: Vec2,
+    },
+    // Rankine vortex turning counterclockwise for positive `speed`: rotating as a solid
+    // body inside `core`, where it reaches `speed`, and falling off with distance outside.
+    Vortex {
+        center: Vec2,
+        speed: f32,
+        core: f32,
+    },
+    Grid(VelocityGrid),
+    // Flow map whose red and green channels hold the x and y velocity, half intensity
+    // meaning still, stretched over `bounds`. Still until the image loads and is baked
+    // into a grid.
+    Image(FieldImage),
+}
+
+impl VelocityField {
+    pub fn velocity(&self, point: Vec2) -> Vec2 {
+        match self {
+            Self::Uniform(velocity) => *velocity,
+            Self::Shear {
+                from,
+                to,
+                velocity,
+                change,
+            } => {
+                let t = if to == from {
+                    if point.y >= *to {
+                        1.0
+                    } else {
+                        0.0
+                    }
+                } else {
+                    ((point.y - from) / (to - from)).clamp(0.0, 1.0)
+                };
+                *velocity + *change * t
+            }
+            Self::Vortex {
+                center,
+                speed,
+                core,
+            } => {
+                let offset = point - *center;
+                let distance = offset.length();
+                if distance == 0.0 || *core <= 0.0 {
+                    return Vec2::ZERO;
+                }
+
+                let profile = if distance < *core {
+                    distance / core
+                } else {
+                    core / distance
+                };
+                offset.perp() / distance * speed * profile
+            }
+            Self::Grid(grid) => grid.velocity(point),
+            Self::Image(_) => Vec2::ZERO,
+        }
+    }
+}
+
+// Velocities sampled on a regular grid with row 0 at `origin`, interpolated bilinearly and
+// clamped to the nearest edge outside it.
+#[derive(Clone)]
+pub struct VelocityGrid {
+    origin: Vec2,
+    cell_size: Vec2,
+    columns: usize,
+    rows: usize,
+    values: Vec<Vec2>,
+}
+
+impl VelocityGrid {
+    pub fn new(origin: Vec2, cell_size: Vec2, columns: usize, values: Vec<Vec2>) -> Option<Self> {
+        if columns < 2 || cell_size.min_element() <= 0.0 || values.len() % columns != 0 {
+            return None;
+        }
+
+        let rows = values.len() / columns;
+        if rows < 2 {
+            return None;
+        }
+
+        Some(Self {
+            origin,
+            cell_size,
+            columns,
+            rows,
+            values,
+        })
+    }
+
+    fn value(&self, x: usize, y: usize) -> Vec2 {
+        self.values[y * self.columns + x]
+    }
+
+    fn velocity(&self, point: Vec2) -> Vec2 {
+        let extent = Vec2::new((self.columns - 1) as f32, (self.rows - 1) as f32);
+        let cell = ((point - self.origin) / self.cell_size).clamp(Vec2::ZERO, extent);
+
+        let x = (cell.x.floor() as usize).min(self.columns - 2);
+        let y = (cell.y.floor() as usize).min(self.rows - 2);
+        let fraction = cell - Vec2::new(x as f32, y as f32);
+
+        let bottom = self.value(x, y).lerp(self.value(x + 1, y), fraction.x);
+        let top = self
+            .value(x, y + 1)
+            .lerp(self.value(x + 1, y + 1), fraction.x);
+
+        bottom.lerp(top, fraction.y)
+    }
+}
+
+#[derive(Reflect, Clone)]
+pub struct FieldImage {
+    pub path: String,
+    pub bounds: Rect,
+    /// Velocity of a fully saturated channel
+    pub speed: f32,
+    handle: Option<Handle<Image>>,
+}
+
+impl FieldImage {
+    pub fn new(path: String, bounds: Rect, speed: f32) -> Self {
+        Self {
+            path,
+            bounds,
+            speed,
+            handle: None,
+        }
+    }
+
+    // One sample per pixel, at the pixel centers, with the image's top row at the top.
+    fn bake(&self, image: &Image) -> Option<VelocityGrid> {
+        let size = image.size();
+        let columns = size.x as usize;
+        let cell_size = self.bounds.size() / size.as_vec2();
+
+        let values = (0..size.y)
+            .rev()
+            .flat_map(|y| (0..size.x).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let color = image
+                    .get_color_at(x, y)
+                    .unwrap_or(Color::srgb(0.5, 0.5, 0.5));
+                let color = color.to_srgba();
+                Vec2::new(color.red * 2.0 - 1.0, color.green * 2.0 - 1.0) * self.speed
+            })
+            .collect();
+
+        VelocityGrid::new(
+            self.bounds.min + cell_size / 2.0,
+            cell_size,
+            columns,
+            values,
+        )
+    }
+}
+
+pub struct FlowFieldPlugin;
+
+impl Plugin for FlowFieldPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<FlowField>()
+            .add_systems(Update, bake_field_images_system)
+            .add_systems(
+                SIMULATION_SCHEDULE,
+                flow_field_system
+                    .in_set(SimulationSet::Forces)
+                    .after(velocity_system),
+            );
+
+        if app.is_plugin_added::<GizmoPlugin>() {
+            app.add_systems(Update, draw_flow_fields_system);
+        }
+    }
+}
+
+// Starts loading image fields and replaces each with a grid once its pixels are available.
+fn bake_field_images_system(
+    asset_server: Res<AssetServer>,
+    images: Res<Assets<Image>>,
+    mut fields: Query<&mut FlowField>,
+) {
+    for mut flow_field in fields.iter_mut() {
+        let VelocityField::Image(field_image) = &mut flow_field.field else {
+            continue;
+        };
+
+        let handle = field_image
+            .handle
+            .get_or_insert_with(|| asset_server.load(field_image.path.clone()));
+
+        let Some(image) = images.get(&*handle) else {
+            continue;
+        };
+
+        let Some(grid) = field_image.bake(image) else {
+            warn!("Flow field image {} is too small", field_image.path);
+            flow_field.enabled = false;
+            flow_field.field = VelocityField::Uniform(Vec2::ZERO);
+            continue;
+        };
+
+        flow_field.field = VelocityField::Grid(grid);
+    }
+}
+
+fn flow_field_system(
+    time: Res<Time>,
+    fields: Query<&FlowField>,
+    mut particles: Query<(&Transform, &mut Velocity, &TimeScale)>,
+) {
+    for flow_field in fields.iter() {
+        if !flow_field.enabled || flow_field.strength <= 0.0 {
+            continue;
+        }
+
+        for (transform, mut velocity, time_scale) in particles.iter_mut() {
+            let target = flow_field.field.velocity(transform.translation.truncate());
+            let blend = (flow_field.strength * time.delta_secs() * time_scale.0).min(1.0);
+            let current = velocity.0.truncate();
+
+            velocity.0 = current.lerp(target, blend).extend(velocity.0.z);
+        }
+    }
+}
+
+fn draw_flow_fields_system(mut gizmos: Gizmos, domains: Query<&Domain>, fields: Query<&FlowField>) {
+    let Ok(domain) = domains.get_single() else {
+        return;
+    };
+
+    let half_size = domain.half_size();
+    let counts = (domain.size / ARROW_SPACING).floor().as_uvec2();
+
+    for flow_field in fields.iter().filter(|flow_field| flow_field.enabled) {
+        for column in 0..counts.x {
+            for row in 0..counts.y {
+                let point =
+                    Vec2::new(column as f32 + 0.5, row as f32 + 0.5) * ARROW_SPACING - half_size;
+                let velocity = flow_field.field.velocity(point);
+
+                if velocity != Vec2::ZERO {
+                    gizmos.arrow_2d(
+                        point,
+                        point + velocity * ARROW_SCALE,
+                        Color::srgba(0.6, 0.9, 0.6, 0.4),
+                    );
+                }
+            }
+        }
+    }
+}
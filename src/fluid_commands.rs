@@ -0,0 +1,410 @@
+use bevy::prelude::*;
+
+use crate::{
+    budget::ParticleBudget,
+    cli::{Args, Solver},
+    domain::Domain,
+    groups::ParticleGroup,
+    params::SimulationParams,
+    particle_bundle,
+    precision::{real, to_f32},
+    pressure_stiffness, pressure_to_density,
+    scene::fill_positions,
+    spawn_jitter,
+    user_data::ParticleUserData,
+    FixedColor, SimulationRng, Velocity,
+};
+
+// Steps per particle spacing when integrating the hydrostatic density profile.
+const HYDROSTATIC_STEPS: f32 = 16.0;
+
+/// What a batch of new particles carries.
+#[derive(Clone, Default, Debug)]
+pub struct FluidMaterial {
+    /// Fixed particle color, density coloring when unset
+    pub color: Option<Color>,
+    pub group: ParticleGroup,
+    pub velocity: Vec2,
+    /// Label attached to each particle as `ParticleUserData<String>`
+    pub tag: Option<String>,
+}
+
+// Spawns whole batches of particles in one command. Regions are filled at `spacing` with the
+// `--sampling` and `--spawn-jitter` options, and every batch is cut short at
+// `--max-particles`.
+pub trait FluidCommands {
+    fn spawn_fluid(&mut self, positions: Vec<Vec2>, material: FluidMaterial);
+
+    fn spawn_fluid_block(&mut self, rect: Rect, spacing: f32, material: FluidMaterial);
+
+    /// Fills the ellipse inscribed in `rect`
+    fn spawn_fluid_ellipse(&mut self, rect: Rect, spacing: f32, material: FluidMaterial);
+
+    /// Fills `shape` like the other spawns, then packs the particles tighter with depth to
+    /// the density the fluid has at rest under gravity, so a pre-filled tank starts calm
+    fn spawn_hydrostatic_fluid(&mut self, shape: SpawnShape, spacing: f32, material: FluidMaterial);
+
+    fn spawn_fluid_disc(
+        &mut self,
+        center: Vec2,
+        radius: f32,
+        spacing: f32,
+        material: FluidMaterial,
+    ) {
+        self.spawn_fluid_ellipse(
+            Rect::from_center_half_size(center, Vec2::splat(radius)),
+            spacing,
+            material,
+        );
+    }
+}
+
+impl FluidCommands for Commands<'_, '_> {
+    fn spawn_fluid(&mut self, positions: Vec<Vec2>, material: FluidMaterial) {
+        if positions.is_empty() {
+            return;
+        }
+
+        self.queue(move |world: &mut World| {
+            spawn_particles(world, positions, &material);
+        });
+    }
+
+    fn spawn_fluid_block(&mut self, rect: Rect, spacing: f32, material: FluidMaterial) {
+        self.queue(move |world: &mut World| {
+            let positions = shape_positions(world, &SpawnShape::Block(rect), spacing);
+            spawn_particles(world, positions, &material);
+        });
+    }
+
+    fn spawn_fluid_ellipse(&mut self, rect: Rect, spacing: f32, material: FluidMaterial) {
+        self.queue(move |world: &mut World| {
+            let positions = shape_positions(world, &SpawnShape::Ellipse(rect), spacing);
+            spawn_particles(world, positions, &material);
+        });
+    }
+
+    fn spawn_hydrostatic_fluid(
+        &mut self,
+        shape: SpawnShape,
+        spacing: f32,
+        material: FluidMaterial,
+    ) {
+        self.queue(move |world: &mut World| {
+            let positions = shape_positions(world, &shape, spacing);
+            let positions = hydrostatic_positions(world, positions, spacing);
+            spawn_particles(world, positions, &material);
+        });
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum SpawnShape {
+    Points(Vec<Vec2>),
+    Block(Rect),
+    /// Ellipse inscribed in the rectangle
+    Ellipse(Rect),
+    Disc {
+        center: Vec2,
+        radius: f32,
+    },
+}
+
+/// Asks for fluid to be created, for plugins and scripts without access to `Commands` or
+/// the particle meshes and materials.
+#[derive(Event, Clone, Debug)]
+pub struct SpawnParticles {
+    pub shape: SpawnShape,
+    /// Most particles to spawn, as many as fill the shape when unset
+    pub count: Option<usize>,
+    /// Distance between particles filling the shape, the particle spacing when unset
+    pub spacing: Option<f32>,
+    pub material: FluidMaterial,
+}
+
+pub struct FluidCommandsPlugin;
+
+impl Plugin for FluidCommandsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SpawnParticles>()
+            .add_systems(PreUpdate, spawn_particles_system);
+    }
+}
+
+fn spawn_particles_system(mut commands: Commands, mut requests: EventReader<SpawnParticles>) {
+    for request in requests.read() {
+        let request = request.clone();
+
+        commands.queue(move |world: &mut World| {
+            let spacing = request
+                .spacing
+                .unwrap_or_else(|| world.resource::<SimulationParams>().particle_spacing);
+            let mut positions = shape_positions(world, &request.shape, spacing);
+            positions.truncate(request.count.unwrap_or(usize::MAX));
+            spawn_particles(world, positions, &request.material);
+        });
+    }
+}
+
+fn shape_positions(world: &mut World, shape: &SpawnShape, spacing: f32) -> Vec<Vec2> {
+    match *shape {
+        SpawnShape::Points(ref positions) => positions.clone(),
+        SpawnShape::Block(rect) => fill(world, rect, spacing, |_| true),
+        SpawnShape::Ellipse(rect) => {
+            let half_size = rect.half_size();
+            fill(world, rect, spacing, |position| {
+                ((position - rect.center()) / half_size).length_squared() <= 1.0
+            })
+        }
+        SpawnShape::Disc { center, radius } => shape_positions(
+            world,
+            &SpawnShape::Ellipse(Rect::from_center_half_size(center, Vec2::splat(radius))),
+            spacing,
+        ),
+    }
+}
+
+// Only as many positions as the budget has room for are built, as the rest would be cut
+// anyway.
+fn fill(world: &mut World, rect: Rect, spacing: f32, inside: impl Fn(Vec2) -> bool) -> Vec<Vec2> {
+    let existing = world
+        .query_filtered::<(), With<Velocity>>()
+        .iter(world)
+        .count();
+    let remaining = world.resource::<ParticleBudget>().remaining(existing);
+
+    world.resource_scope(|world, mut rng: Mut<SimulationRng>| {
+        let args = world.resource::<Args>();
+        let params = world.resource::<SimulationParams>();
+
+        fill_positions(
+            rect.center(),
+            rect.size(),
+            spacing,
+            args.sampling,
+            inside,
+            remaining,
+            &mut rng.0,
+        )
+        .into_iter()
+        .map(|position| position + spawn_jitter(args, params, &mut rng))
+        .collect()
+    })
+}
+
+// Moves particles down along gravity to where the fluid above each one, packed to the
+// density its own weight presses it to, holds as much as lay above it before. The bottom
+// stays put and the surface sinks. The position-based solver keeps fluid at its target
+// density whatever the pressure, so its particles are left where they are.
+fn hydrostatic_positions(world: &mut World, positions: Vec<Vec2>, spacing: f32) -> Vec<Vec2> {
+    let params = world.resource::<SimulationParams>().clone();
+    let Some(down) = params.gravity_vector().try_normalize() else {
+        return positions;
+    };
+
+    if *world.resource::<Solver>() == Solver::Pbf {
+        return positions;
+    }
+
+    let mut domains = world.query::<&Domain>();
+    let Ok(domain) = domains.get_single(world) else {
+        return positions;
+    };
+    let stiffness = pressure_stiffness(&params, domain);
+
+    let top = positions
+        .iter()
+        .map(|position| -position.dot(down))
+        .fold(f32::NEG_INFINITY, f32::max);
+    let depth_of = |position: Vec2| top + position.dot(down);
+    let deepest = positions
+        .iter()
+        .map(|&position| depth_of(position))
+        .fold(0.0, f32::max);
+
+    if deepest <= 0.0 {
+        return positions;
+    }
+
+    // Uncompressed depth of fluid held above each compressed depth, by the midpoint rule.
+    let step = spacing / HYDROSTATIC_STEPS;
+    let target_density = real(params.target_density);
+    let weight = target_density * real(params.gravity_vector().length());
+    let mut table = vec![(0.0, 0.0)];
+    let (mut held, mut depth) = (0.0, 0.0);
+
+    while held < deepest {
+        let pressure = weight * real(depth + step / 2.0);
+        let density = pressure_to_density(pressure, stiffness, &params);
+        held += step * to_f32(density / target_density);
+        depth += step;
+        table.push((held, depth));
+    }
+
+    let compressed = |uncompressed: f32| {
+        let index = table
+            .partition_point(|&(held, _)| held < uncompressed)
+            .clamp(1, table.len() - 1);
+        let ((held_above, depth_above), (held_below, depth_below)) =
+            (table[index - 1], table[index]);
+        let fraction = (uncompressed - held_above) / (held_below - held_above).max(f32::EPSILON);
+        depth_above + (depth_below - depth_above) * fraction.clamp(0.0, 1.0)
+    };
+    let bottom = compressed(deepest);
+
+    positions
+        .into_iter()
+        .map(|position| {
+            let depth = depth_of(position);
+            let rise = (bottom - compressed(depth)) - (deepest - depth);
+            position - down * rise
+        })
+        .collect()
+}
+
+// Returns the new particles in the order of their positions, which may stop short at
+// `--max-particles`.
+pub fn spawn_particles(
+    world: &mut World,
+    positions: Vec<Vec2>,
+    material: &FluidMaterial,
+) -> Vec<Entity> {
+    let existing = world
+        .query_filtered::<(), With<Velocity>>()
+        .iter(world)
+        .count();
+    let remaining = world.resource::<ParticleBudget>().remaining(existing);
+    let color = material.color.unwrap_or(Color::hsl(0.5, 0.95, 0.7));
+
+    let entities: Vec<Entity> = world
+        .spawn_batch(
+            positions
+                .into_iter()
+                .take(remaining)
+                .map(move |position| particle_bundle(position.extend(0.0), color)),
+        )
+        .collect();
+
+    for &entity in &entities {
+        let mut particle = world.entity_mut(entity);
+        particle.insert((Velocity(material.velocity.extend(0.0)), material.group));
+
+        if material.color.is_some() {
+            particle.insert(FixedColor);
+        }
+
+        if let Some(tag) = &material.tag {
+            particle.insert(ParticleUserData(tag.clone()));
+        }
+    }
+
+    entities
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::cli::Sampling;
+
+    fn world() -> World {
+        let mut world = World::new();
+        world.insert_resource(Args::parse_from(["liquids_bevy"]));
+        world.insert_resource(SimulationParams::default());
+        world.insert_resource(SimulationRng(StdRng::seed_from_u64(0)));
+        world.insert_resource(ParticleBudget::default());
+        world
+    }
+
+    fn positions(world: &mut World) -> Vec<Vec2> {
+        world
+            .query_filtered::<&Transform, With<Velocity>>()
+            .iter(world)
+            .map(|transform| transform.translation.truncate())
+            .collect()
+    }
+
+    #[test]
+    fn disc_fills_the_circle_around_its_center() {
+        let mut world = world();
+        let center = Vec2::new(10.0, -5.0);
+        let material = FluidMaterial {
+            velocity: Vec2::new(1.0, 2.0),
+            ..default()
+        };
+
+        world
+            .commands()
+            .spawn_fluid_disc(center, 20.0, 2.0, material);
+        world.flush();
+
+        let positions = positions(&mut world);
+        // A disc of radius 20 at spacing 2 holds about pi * 10^2 particles.
+        assert!(
+            (290..=340).contains(&positions.len()),
+            "{} particles",
+            positions.len()
+        );
+        for position in &positions {
+            assert!(position.distance(center) <= 20.0 + 1e-4, "{position:?}");
+        }
+
+        for velocity in world.query::<&Velocity>().iter(&world) {
+            assert_eq!(velocity.0, Vec3::new(1.0, 2.0, 0.0));
+        }
+    }
+
+    #[test]
+    fn zero_and_tiny_spacings_fill_nothing() {
+        for sampling in [Sampling::Lattice, Sampling::Poisson] {
+            for spacing in [0.0, -1.0, f32::NAN, 1e-6] {
+                let mut world = world();
+                world.resource_mut::<Args>().sampling = sampling;
+
+                let rect = Rect::from_center_size(Vec2::ZERO, Vec2::splat(20.0));
+                world
+                    .commands()
+                    .spawn_fluid_block(rect, spacing, FluidMaterial::default());
+                world.flush();
+
+                assert!(
+                    positions(&mut world).is_empty(),
+                    "{sampling:?} at spacing {spacing}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn small_spacings_still_fill() {
+        for sampling in [Sampling::Lattice, Sampling::Poisson] {
+            let mut world = world();
+            world.resource_mut::<Args>().sampling = sampling;
+
+            let rect = Rect::from_center_size(Vec2::ZERO, Vec2::splat(2.0));
+            world
+                .commands()
+                .spawn_fluid_block(rect, 0.05, FluidMaterial::default());
+            world.flush();
+
+            // A 2 by 2 block at spacing 0.05 holds 40 by 40 lattice particles.
+            let count = positions(&mut world).len();
+            assert!((1200..=1700).contains(&count), "{sampling:?}: {count}");
+        }
+    }
+
+    #[test]
+    fn batches_stop_at_the_particle_budget() {
+        let mut world = world();
+        world.resource_mut::<ParticleBudget>().max = Some(10);
+
+        world
+            .commands()
+            .spawn_fluid_disc(Vec2::ZERO, 20.0, 2.0, FluidMaterial::default());
+        world.flush();
+
+        assert_eq!(positions(&mut world).len(), 10);
+    }
+}
@@ -0,0 +1,118 @@
+use bevy::prelude::*;
+
+use crate::{ColorPalette, ColoredDensity, FixedColor, Velocity};
+
+// Particle turned into part of the terrain. Without a velocity it is never moved, yet it
+// keeps its place in the neighbor grid, so the fluid around it still feels its density and
+// pressure as it would a resting particle's. Thawing gives it back a velocity, at rest.
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct Frozen {
+    /// Material and fixed color to restore when thawed
+    material: Option<Handle<ColorMaterial>>,
+    fixed_color: bool,
+}
+
+#[derive(Resource)]
+struct FrozenMaterial(Handle<ColorMaterial>);
+
+impl FromWorld for FrozenMaterial {
+    fn from_world(world: &mut World) -> Self {
+        let mut materials = world.resource_mut::<Assets<ColorMaterial>>();
+        Self(materials.add(Color::srgb(0.45, 0.4, 0.35)))
+    }
+}
+
+pub struct FrozenPlugin;
+
+impl Plugin for FrozenPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Frozen>();
+    }
+}
+
+pub trait FreezeCommands {
+    /// Pins fluid particles in place as static boundary particles
+    fn freeze_particles(&mut self, entities: Vec<Entity>);
+
+    /// Turns frozen particles back into fluid
+    fn thaw_particles(&mut self, entities: Vec<Entity>);
+}
+
+impl FreezeCommands for Commands<'_, '_> {
+    fn freeze_particles(&mut self, entities: Vec<Entity>) {
+        if entities.is_empty() {
+            return;
+        }
+
+        self.queue(move |world: &mut World| {
+            // Headless runs draw nothing, so have no materials to swap.
+            let stone = world
+                .contains_resource::<Assets<ColorMaterial>>()
+                .then(|| world.get_resource_or_init::<FrozenMaterial>().0.clone());
+
+            for entity in entities {
+                let Ok(mut particle) = world.get_entity_mut(entity) else {
+                    continue;
+                };
+
+                if !particle.contains::<Velocity>() {
+                    continue;
+                }
+
+                let frozen = Frozen {
+                    material: particle
+                        .get::<MeshMaterial2d<ColorMaterial>>()
+                        .map(|material| material.0.clone()),
+                    fixed_color: particle.contains::<FixedColor>(),
+                };
+                let drawn = frozen.material.is_some();
+
+                particle.remove::<Velocity>().insert((frozen, FixedColor));
+
+                if let (true, Some(stone)) = (drawn, &stone) {
+                    particle.insert(MeshMaterial2d(stone.clone()));
+                }
+            }
+        });
+    }
+
+    fn thaw_particles(&mut self, entities: Vec<Entity>) {
+        if entities.is_empty() {
+            return;
+        }
+
+        self.queue(move |world: &mut World| {
+            let unfixed = world
+                .get_resource::<ColorPalette>()
+                .map(|palette| palette.density[0].clone());
+
+            for entity in entities {
+                let Ok(mut particle) = world.get_entity_mut(entity) else {
+                    continue;
+                };
+
+                let Some(frozen) = particle.take::<Frozen>() else {
+                    continue;
+                };
+
+                // Density-colored particles go back to the palette and are recolored on the
+                // next pass.
+                let material = if frozen.fixed_color {
+                    frozen.material
+                } else {
+                    particle
+                        .remove::<FixedColor>()
+                        .insert(ColoredDensity::default());
+                    frozen.material.and(unfixed.clone())
+                };
+
+                if let Some(material) = material {
+                    particle.insert(MeshMaterial2d(material));
+                }
+
+                particle.insert(Velocity(Vec3::ZERO));
+            }
+        });
+    }
+}
@@ -0,0 +1,66 @@
+use bevy::prelude::*;
+
+use crate::{domain::Domain, params::SimulationParams};
+
+// Degrees per second while an arrow key is held.
+const ROTATION_SPEED: f32 = 90.0;
+// Factor by which gravity grows or shrinks per second while an arrow key is held.
+const MAGNITUDE_RATE: f32 = 2.0;
+const INDICATOR_LENGTH: f32 = 15.0;
+const INDICATOR_MARGIN: f32 = 25.0;
+
+// Left and right tilt gravity, up and down scale it, G points it straight down again. An
+// arrow in the top left corner of the domain shows the direction, its length the magnitude
+// relative to the starting gravity.
+pub struct GravityControlPlugin;
+
+impl Plugin for GravityControlPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (gravity_keys_system, draw_gravity_system));
+    }
+}
+
+fn gravity_keys_system(
+    input: Res<ButtonInput<KeyCode>>,
+    time: Res<Time<Real>>,
+    mut params: ResMut<SimulationParams>,
+) {
+    let delta_time = time.delta_secs();
+    let turn = f32::from(input.pressed(KeyCode::ArrowRight))
+        - f32::from(input.pressed(KeyCode::ArrowLeft));
+    let grow =
+        f32::from(input.pressed(KeyCode::ArrowUp)) - f32::from(input.pressed(KeyCode::ArrowDown));
+
+    if turn != 0.0 {
+        params.gravity_angle =
+            (params.gravity_angle + turn * ROTATION_SPEED * delta_time).rem_euclid(360.0);
+    }
+
+    if grow != 0.0 {
+        params.gravity *= MAGNITUDE_RATE.powf(grow * delta_time);
+    }
+
+    if input.just_pressed(KeyCode::KeyG) {
+        params.gravity_angle = 0.0;
+    }
+}
+
+fn draw_gravity_system(
+    mut gizmos: Gizmos,
+    params: Res<SimulationParams>,
+    domains: Query<&Domain>,
+    mut reference: Local<Option<f32>>,
+) {
+    let reference = *reference.get_or_insert(params.gravity.abs().max(f32::EPSILON));
+    let half_size = domains.single().half_size();
+    let center = Vec2::new(-half_size.x, half_size.y) + Vec2::new(1.0, -1.0) * INDICATOR_MARGIN;
+    let gravity = params.gravity_vector() / reference * INDICATOR_LENGTH;
+    let color = Color::srgb(0.9, 0.6, 0.2);
+
+    gizmos.circle_2d(center, INDICATOR_LENGTH, Color::srgb(0.5, 0.5, 0.5));
+    gizmos.arrow_2d(
+        center,
+        center + gravity.clamp_length_max(3.0 * INDICATOR_LENGTH),
+        color,
+    );
+}
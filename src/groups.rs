@@ -0,0 +1,107 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+
+// Index into the scene's particle groups. Particles without one belong to group 0.
+#[derive(Component, Reflect, Clone, Copy, Default, PartialEq, Eq, Debug)]
+#[reflect(Component)]
+pub struct ParticleGroup(pub usize);
+
+#[derive(Deserialize)]
+pub struct GroupDescription {
+    pub name: String,
+    /// Fixed color for the group's particles, density coloring when unset
+    #[serde(default)]
+    pub color: Option<[f32; 3]>,
+    /// Groups this one neither pushes nor is pushed by
+    #[serde(default)]
+    pub ignores: Vec<String>,
+    /// Carried along by other groups without pushing anything, for tracers
+    #[serde(default)]
+    pub passive: bool,
+    /// Settles into static terrain when resting, and is picked up again by fast flow
+    #[serde(default)]
+    pub sediment: Option<Sediment>,
+}
+
+// Deposition and erosion thresholds for a sediment group.
+#[derive(Reflect, Deserialize, Clone, Copy, Debug)]
+pub struct Sediment {
+    /// Speed below which a particle counts as resting
+    pub settle_speed: f32,
+    /// Seconds a particle must rest on the floor or on deposits before it is deposited
+    pub settle_time: f32,
+    /// Speed of the flow past a deposited particle above which it is eroded
+    pub erosion_speed: f32,
+}
+
+// Which groups exert pressure and collision forces on which. Everything interacts with
+// everything by default, including groups the scene doesn't name.
+#[derive(Resource, Reflect, Default)]
+#[reflect(Resource)]
+pub struct GroupRules {
+    names: Vec<String>,
+    colors: Vec<Option<Color>>,
+    sediments: Vec<Option<Sediment>>,
+    // `pushes[source][target]`
+    pushes: Vec<Vec<bool>>,
+}
+
+impl GroupRules {
+    pub fn new(groups: &[GroupDescription]) -> Self {
+        let mut rules = Self {
+            names: groups.iter().map(|group| group.name.clone()).collect(),
+            colors: groups
+                .iter()
+                .map(|group| {
+                    group
+                        .color
+                        .map(|[red, green, blue]| Color::srgb(red, green, blue))
+                })
+                .collect(),
+            sediments: groups.iter().map(|group| group.sediment).collect(),
+            pushes: vec![vec![true; groups.len()]; groups.len()],
+        };
+
+        for (index, group) in groups.iter().enumerate() {
+            if group.passive {
+                rules.pushes[index].fill(false);
+            }
+
+            for name in &group.ignores {
+                let Some(other) = rules.index(name) else {
+                    warn!("Group {} ignores unknown group {name}", group.name);
+                    continue;
+                };
+
+                rules.pushes[index][other] = false;
+                rules.pushes[other][index] = false;
+            }
+        }
+
+        rules
+    }
+
+    pub fn index(&self, name: &str) -> Option<usize> {
+        self.names.iter().position(|group| group == name)
+    }
+
+    pub fn color(&self, group: ParticleGroup) -> Option<Color> {
+        self.colors.get(group.0).copied().flatten()
+    }
+
+    pub fn sediment(&self, group: ParticleGroup) -> Option<Sediment> {
+        self.sediments.get(group.0).copied().flatten()
+    }
+
+    pub fn has_sediment(&self) -> bool {
+        self.sediments.iter().any(Option::is_some)
+    }
+
+    pub fn pushes(&self, source: ParticleGroup, target: ParticleGroup) -> bool {
+        self.pushes
+            .get(source.0)
+            .and_then(|row| row.get(target.0))
+            .copied()
+            .unwrap_or(true)
+    }
+}
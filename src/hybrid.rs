@@ -0,0 +1,177 @@
+use bevy::prelude::*;
+
+use crate::{
+    domain::Domain,
+    flow::{DespawnCause, ParticleDespawned},
+    fluid_commands::{FluidCommands, FluidMaterial},
+    groups::ParticleGroup,
+    params::SimulationParams,
+    shallow_water::{shallow_water_system, ShallowWater},
+    surface::ColorField,
+    update_system, SimulationSet, Velocity, SIMULATION_SCHEDULE,
+};
+
+// Ties a `ShallowWater` running under the domain to the particles inside it, for worlds too
+// big to fill with particles where only the area around the action needs them. The lake's
+// columns across the domain are handed to the particles. Particles leaving through an open
+// left or right side of the domain pour their share of water into the lake beyond it, and
+// when the lake stands higher than the particles at a side, the water it pushes in comes
+// back out as columns of particles just inside that side. Sides left walled keep the two
+// apart, the lake seeing them as walls.
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct HybridCoupling {
+    /// Group the particles spawned from the lake join
+    pub group: ParticleGroup,
+    /// Fixed color of those particles, density coloring when unset
+    pub color: Option<Color>,
+    /// Water that has run in through the left and right sides and is not yet particles, as
+    /// an area
+    pending: [f32; 2],
+}
+
+impl HybridCoupling {
+    pub fn new(group: ParticleGroup, color: Option<Color>) -> Self {
+        Self {
+            group,
+            color,
+            pending: [0.0; 2],
+        }
+    }
+}
+
+pub struct HybridPlugin;
+
+impl Plugin for HybridPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<HybridCoupling>().add_systems(
+            SIMULATION_SCHEDULE,
+            (
+                couple_system.before(shallow_water_system),
+                absorb_system.after(update_system),
+                release_system.after(shallow_water_system),
+            )
+                .in_set(SimulationSet::Integration),
+        );
+    }
+}
+
+fn couple_system(
+    params: Res<SimulationParams>,
+    field: Option<Res<ColorField>>,
+    domains: Query<&Domain>,
+    mut lakes: Query<&mut ShallowWater, With<HybridCoupling>>,
+) {
+    let Ok(domain) = domains.get_single() else {
+        return;
+    };
+    let half_size = domain.half_size();
+
+    // The particles' surface is sampled a spacing inside each side, clear of the thinning
+    // at an open side.
+    let inset = params.particle_spacing.min(half_size.x);
+    let levels = [-half_size.x + inset, half_size.x - inset].map(|x| {
+        field
+            .as_deref()
+            .and_then(|field| field.surface_height_at(x))
+    });
+
+    let open = [domain.outflow.left, domain.outflow.right];
+
+    for mut lake in lakes.iter_mut() {
+        lake.couple(-half_size.x, half_size.x, levels, open);
+    }
+}
+
+fn absorb_system(
+    mut commands: Commands,
+    params: Res<SimulationParams>,
+    domains: Query<&Domain>,
+    mut lakes: Query<&mut ShallowWater, With<HybridCoupling>>,
+    particles: Query<(Entity, &Transform, &Velocity)>,
+    mut despawned: EventWriter<ParticleDespawned>,
+) {
+    let Ok(domain) = domains.get_single() else {
+        return;
+    };
+    let half_size = domain.half_size();
+    let area = params.particle_spacing * params.particle_spacing;
+
+    for (entity, transform, velocity) in particles.iter() {
+        let position = transform.translation.truncate();
+        let left = domain.outflow.left && position.x < -half_size.x;
+        let right = domain.outflow.right && position.x > half_size.x;
+        if !left && !right {
+            continue;
+        }
+
+        let Some(mut lake) = lakes
+            .iter_mut()
+            .find(|lake| lake.covers(position.x) && position.y >= lake.bed)
+        else {
+            continue;
+        };
+
+        lake.add_water(position.x, area, velocity.0.x);
+        commands.entity(entity).despawn();
+        despawned.send(ParticleDespawned {
+            entity,
+            position,
+            cause: DespawnCause::Absorbed,
+        });
+    }
+}
+
+fn release_system(
+    mut commands: Commands,
+    params: Res<SimulationParams>,
+    domains: Query<&Domain>,
+    mut lakes: Query<(&mut ShallowWater, &mut HybridCoupling)>,
+) {
+    let Ok(domain) = domains.get_single() else {
+        return;
+    };
+    let half_size = domain.half_size();
+    let spacing = params.particle_spacing;
+
+    for (mut lake, mut coupling) in lakes.iter_mut() {
+        let transferred = lake.take_transferred();
+
+        for (side, inward) in [(0, 1.0), (1, -1.0)] {
+            coupling.pending[side] += transferred[side];
+
+            // The column the water comes from, just outside the side.
+            let edge = -inward * half_size.x;
+            let outside = edge - inward * lake.cell_size / 2.0;
+            let Some(surface) = lake.surface_height_at(outside) else {
+                continue;
+            };
+
+            let rows = ((surface - lake.bed) / spacing).floor().max(1.0) as usize;
+            let column = rows as f32 * spacing * spacing;
+            let flow = lake
+                .velocity_at(Vec2::new(outside, (lake.bed + surface) / 2.0))
+                .unwrap_or_default();
+
+            let mut positions = Vec::new();
+            let mut x = edge + inward * spacing / 2.0;
+            while coupling.pending[side] >= column {
+                coupling.pending[side] -= column;
+                positions.extend(
+                    (0..rows).map(|row| Vec2::new(x, lake.bed + spacing * (row as f32 + 0.5))),
+                );
+                x += inward * spacing;
+            }
+
+            commands.spawn_fluid(
+                positions,
+                FluidMaterial {
+                    color: coupling.color,
+                    group: coupling.group,
+                    velocity: flow,
+                    tag: None,
+                },
+            );
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use bevy::{prelude::*, transform::TransformSystem};
+
+use crate::{params::SimulationParams, ParticleColor};
+
+// Physics runs on fixed ticks here, so without help particles would move in steps whenever
+// the display refreshes faster. Between ticks their `Transform` is drawn partway from the
+// previous state to the current one, and put back before the next tick reads it.
+pub struct InterpolationPlugin;
+
+impl Plugin for InterpolationPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Interpolation>()
+            .add_systems(FixedFirst, (attach_interpolation_system, restore_system))
+            .add_systems(FixedPostUpdate, record_system)
+            .add_systems(
+                PostUpdate,
+                interpolate_system.before(TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+struct Interpolation {
+    previous: Vec3,
+    current: Vec3,
+    /// Last drawn translation, telling the physics state apart from edits made in between
+    rendered: Vec3,
+}
+
+impl Interpolation {
+    fn at(translation: Vec3) -> Self {
+        Self {
+            previous: translation,
+            current: translation,
+            rendered: translation,
+        }
+    }
+}
+
+fn attach_interpolation_system(
+    mut commands: Commands,
+    particles: Query<(Entity, &Transform), (With<ParticleColor>, Without<Interpolation>)>,
+) {
+    for (entity, transform) in particles.iter() {
+        commands
+            .entity(entity)
+            .insert(Interpolation::at(transform.translation));
+    }
+}
+
+// A translation other than the drawn one was set by dragging, tools or a rollback, and
+// becomes the new state as it is.
+fn restore_system(mut particles: Query<(&mut Transform, &mut Interpolation)>) {
+    for (mut transform, mut interpolation) in particles.iter_mut() {
+        if transform.translation == interpolation.rendered {
+            transform.translation = interpolation.current;
+        } else {
+            interpolation.current = transform.translation;
+        }
+
+        interpolation.previous = interpolation.current;
+    }
+}
+
+fn record_system(mut particles: Query<(&Transform, &mut Interpolation)>) {
+    for (transform, mut interpolation) in particles.iter_mut() {
+        interpolation.current = transform.translation;
+    }
+}
+
+fn interpolate_system(
+    time: Res<Time<Fixed>>,
+    params: Res<SimulationParams>,
+    mut particles: Query<(&mut Transform, &mut Interpolation)>,
+) {
+    let fraction = time.overstep_fraction();
+
+    for (mut transform, mut interpolation) in particles.iter_mut() {
+        let translation = transform.translation;
+        if translation != interpolation.current && translation != interpolation.rendered {
+            *interpolation = Interpolation::at(translation);
+            continue;
+        }
+
+        // No particle covers a smoothing radius in one tick, so a longer jump is a wrap
+        // around a periodic edge and is shown right away.
+        let rendered =
+            if interpolation.previous.distance(interpolation.current) > params.smoothing_radius {
+                interpolation.current
+            } else {
+                interpolation.previous.lerp(interpolation.current, fraction)
+            };
+
+        transform.translation = rendered;
+        interpolation.rendered = rendered;
+    }
+}
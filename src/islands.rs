@@ -0,0 +1,135 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{
+    budget::ParticleMass,
+    neighbors::NeighborGrid,
+    params::SimulationParams,
+    precision::{real, to_vec3},
+    SimulationSet, Velocity, SIMULATION_SCHEDULE,
+};
+
+// Body of fluid a particle belongs to: the connected component of the graph linking
+// particles within the smoothing radius. Ids are handed out every step from the largest
+// body down, so the main body is always 0 but a given droplet may change id as others form
+// or merge.
+#[derive(Component, Reflect, Clone, Copy, PartialEq, Eq, Default, Debug)]
+#[reflect(Component)]
+pub struct Island(pub u32);
+
+#[derive(Reflect, Clone, Copy, Default, Debug)]
+pub struct IslandStats {
+    pub particles: usize,
+    pub mass: f32,
+    pub center: Vec2,
+    pub velocity: Vec2,
+}
+
+/// Every island of the last step, indexed by its id.
+#[derive(Resource, Reflect, Default, Debug)]
+#[reflect(Resource)]
+pub struct Islands(pub Vec<IslandStats>);
+
+pub struct IslandsPlugin;
+
+impl Plugin for IslandsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Islands>()
+            .register_type::<Island>()
+            .register_type::<Islands>()
+            .add_systems(
+                SIMULATION_SCHEDULE,
+                island_system
+                    .after(SimulationSet::Density)
+                    .before(SimulationSet::Forces),
+            );
+    }
+}
+
+fn find(parents: &mut [usize], mut index: usize) -> usize {
+    while parents[index] != index {
+        parents[index] = parents[parents[index]];
+        index = parents[index];
+    }
+
+    index
+}
+
+fn island_system(
+    params: Res<SimulationParams>,
+    neighbor_grid: Res<NeighborGrid>,
+    mut islands: ResMut<Islands>,
+    mut particles: Query<(&Transform, &Velocity, &ParticleMass, &mut Island)>,
+) {
+    let NeighborGrid { grid, cells } = &*neighbor_grid;
+    let smoothing_radius = real(params.smoothing_radius);
+
+    let entities: Vec<_> = cells.values().flatten().collect();
+    let indices: HashMap<Entity, usize> = entities
+        .iter()
+        .enumerate()
+        .map(|(index, &&(entity, _, _))| (entity, index))
+        .collect();
+
+    // Union-find over every pair within reach.
+    let mut parents: Vec<usize> = (0..entities.len()).collect();
+    for (index, &&(_, position, _)) in entities.iter().enumerate() {
+        for cell in grid.neighbor_cells(grid.cell(to_vec3(position))) {
+            for &(neighbor, neighbor_position, _) in cells.get(&cell).into_iter().flatten() {
+                if grid.precise_offset(position, neighbor_position).length() >= smoothing_radius {
+                    continue;
+                }
+
+                let (a, b) = (
+                    find(&mut parents, index),
+                    find(&mut parents, indices[&neighbor]),
+                );
+                if a != b {
+                    parents[a.max(b)] = a.min(b);
+                }
+            }
+        }
+    }
+
+    let mut roots: HashMap<usize, Vec<Entity>> = HashMap::new();
+    for (index, &&(entity, _, _)) in entities.iter().enumerate() {
+        roots
+            .entry(find(&mut parents, index))
+            .or_default()
+            .push(entity);
+    }
+
+    let mut bodies: Vec<_> = roots.into_values().collect();
+    bodies.sort_by_key(|body| std::cmp::Reverse(body.len()));
+
+    let count = islands.0.len();
+    islands.0.clear();
+
+    for (id, body) in bodies.iter().enumerate() {
+        let mut stats = IslandStats::default();
+
+        for &entity in body {
+            let Ok((transform, velocity, particle_mass, mut island)) = particles.get_mut(entity)
+            else {
+                continue;
+            };
+
+            let mass = params.mass * particle_mass.0;
+            island.set_if_neq(Island(id as u32));
+            stats.particles += 1;
+            stats.mass += mass;
+            stats.center += transform.translation.truncate() * mass;
+            stats.velocity += velocity.0.truncate() * mass;
+        }
+
+        if stats.mass > 0.0 {
+            stats.center /= stats.mass;
+            stats.velocity /= stats.mass;
+        }
+
+        islands.0.push(stats);
+    }
+
+    if islands.0.len() != count {
+        debug!("Fluid forms {} islands", islands.0.len());
+    }
+}
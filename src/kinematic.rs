@@ -0,0 +1,95 @@
+use std::f32::consts::{PI, TAU};
+
+use bevy::prelude::*;
+
+use crate::{sdf::sdf_collision_system, SimulationSet, SIMULATION_SCHEDULE};
+
+// Velocity of a collider or boundary, measured from how its `Transform` changed since the
+// previous step. Anything may move such an entity, as long as it does so before
+// `SimulationSet::Collision`.
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
+pub struct Kinematic {
+    pub linear_velocity: Vec2,
+    pub angular_velocity: f32,
+    previous: Option<(Vec2, f32)>,
+}
+
+impl Kinematic {
+    pub fn surface_velocity(&self, center: Vec2, point: Vec2) -> Vec2 {
+        self.linear_velocity + self.angular_velocity * (point - center).perp()
+    }
+}
+
+#[derive(Component, Reflect, Clone, Copy)]
+#[reflect(Component)]
+pub enum Motion {
+    Oscillate {
+        origin: Vec2,
+        amplitude: Vec2,
+        period: f32,
+    },
+    Rotate {
+        angle: f32,
+        speed: f32,
+    },
+}
+
+pub struct KinematicPlugin;
+
+impl Plugin for KinematicPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Kinematic>()
+            .register_type::<Motion>()
+            .add_systems(
+                SIMULATION_SCHEDULE,
+                (
+                    motion_system.in_set(SimulationSet::Integration),
+                    kinematic_velocity_system
+                        .in_set(SimulationSet::Collision)
+                        .before(sdf_collision_system),
+                ),
+            );
+    }
+}
+
+fn motion_system(time: Res<Time>, mut query: Query<(&Motion, &mut Transform)>) {
+    let elapsed = time.elapsed_secs();
+
+    for (motion, mut transform) in query.iter_mut() {
+        match *motion {
+            Motion::Oscillate {
+                origin,
+                amplitude,
+                period,
+            } => {
+                let offset = amplitude * (TAU * elapsed / period.max(f32::EPSILON)).sin();
+                transform.translation = (origin + offset).extend(transform.translation.z);
+            }
+            Motion::Rotate { angle, speed } => {
+                transform.rotation = Quat::from_rotation_z(angle + speed * elapsed);
+            }
+        }
+    }
+}
+
+fn kinematic_velocity_system(time: Res<Time>, mut query: Query<(&mut Kinematic, &Transform)>) {
+    let delta_time = time.delta_secs();
+
+    for (mut kinematic, transform) in query.iter_mut() {
+        let position = transform.translation.truncate();
+        let angle = transform.rotation.to_euler(EulerRot::ZYX).0;
+
+        if delta_time <= 0.0 {
+            continue;
+        }
+
+        if let Some((previous_position, previous_angle)) = kinematic.previous {
+            let turned = (angle - previous_angle + PI).rem_euclid(TAU) - PI;
+            kinematic.linear_velocity = (position - previous_position) / delta_time;
+            kinematic.angular_velocity = turned / delta_time;
+        }
+
+        kinematic.previous = Some((position, angle));
+    }
+}
@@ -0,0 +1,206 @@
+use bevy::{
+    gizmos::GizmoPlugin,
+    prelude::*,
+    render::{
+        camera::{ClearColorConfig, Viewport},
+        view::RenderLayers,
+    },
+};
+use bevy_pancam::PanCam;
+
+const MAGNIFIER_LAYER: usize = 2;
+// Side of the inset as a fraction of the window's shorter side, and its gap from the
+// corner in logical pixels.
+const INSET_FRACTION: f32 = 0.35;
+const INSET_MARGIN: f32 = 12.0;
+// Factor the magnification changes by per key press.
+const ZOOM_STEP: f32 = 1.25;
+const MIN_ZOOM: f32 = 2.0;
+const MAX_ZOOM: f32 = 32.0;
+// Behind everything drawn, the lakes included.
+const BACKDROP_DEPTH: f32 = -100.0;
+
+// Toggled with Z: an inset in the bottom right corner showing the area around the cursor
+// magnified, with = and - changing how much. It has its own camera without `PanCam`, so
+// panning and zooming the main view only moves what it looks at, and its magnification
+// stays its own. It stays put while the cursor is over it.
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct Magnifier {
+    pub enabled: bool,
+    /// Times larger than the main view
+    pub zoom: f32,
+    /// World position at the middle of the inset
+    pub center: Vec2,
+}
+
+impl Default for Magnifier {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            zoom: 4.0,
+            center: Vec2::ZERO,
+        }
+    }
+}
+
+#[derive(Component)]
+struct MagnifierCamera;
+
+// Cameras drawing into part of the window don't clear just that part, so the inset is
+// backed by a sprite only the magnifier sees.
+#[derive(Component)]
+struct MagnifierBackdrop;
+
+pub struct MagnifierPlugin;
+
+impl Plugin for MagnifierPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Magnifier>()
+            .register_type::<Magnifier>()
+            .add_systems(Startup, setup_magnifier)
+            .add_systems(
+                Update,
+                (magnifier_keys_system, magnifier_layout_system).chain(),
+            );
+
+        if app.is_plugin_added::<GizmoPlugin>() {
+            app.add_systems(
+                Update,
+                draw_magnified_area_system.run_if(|magnifier: Res<Magnifier>| magnifier.enabled),
+            );
+        }
+    }
+}
+
+fn setup_magnifier(mut commands: Commands, clear_color: Res<ClearColor>) {
+    commands.spawn((
+        Name::new("Magnifier camera"),
+        MagnifierCamera,
+        Camera2d,
+        Camera {
+            order: 2,
+            is_active: false,
+            clear_color: ClearColorConfig::None,
+            ..default()
+        },
+        RenderLayers::from_layers(&[0, MAGNIFIER_LAYER]),
+    ));
+
+    commands.spawn((
+        Name::new("Magnifier backdrop"),
+        MagnifierBackdrop,
+        Sprite::from_color(clear_color.0, Vec2::ONE),
+        Transform::from_xyz(0.0, 0.0, BACKDROP_DEPTH),
+        RenderLayers::layer(MAGNIFIER_LAYER),
+    ));
+}
+
+fn magnifier_keys_system(input: Res<ButtonInput<KeyCode>>, mut magnifier: ResMut<Magnifier>) {
+    if input.just_pressed(KeyCode::KeyZ) {
+        magnifier.enabled = !magnifier.enabled;
+    }
+
+    if !magnifier.enabled {
+        return;
+    }
+
+    let steps = f32::from(input.just_pressed(KeyCode::Equal))
+        - f32::from(input.just_pressed(KeyCode::Minus));
+    if steps != 0.0 {
+        magnifier.zoom = (magnifier.zoom * ZOOM_STEP.powf(steps)).clamp(MIN_ZOOM, MAX_ZOOM);
+        info!("Magnifying {:.1} times", magnifier.zoom);
+    }
+}
+
+// Places the inset in the corner and points it at the cursor, at the main view's zoom times
+// the magnification.
+#[allow(clippy::type_complexity)]
+fn magnifier_layout_system(
+    mut magnifier: ResMut<Magnifier>,
+    windows: Query<&Window>,
+    main_cameras: Query<(&Camera, &GlobalTransform, &OrthographicProjection), With<PanCam>>,
+    mut cameras: Query<
+        (&mut Camera, &mut Transform, &mut OrthographicProjection),
+        (With<MagnifierCamera>, Without<PanCam>),
+    >,
+    mut backdrops: Query<
+        &mut Transform,
+        (
+            With<MagnifierBackdrop>,
+            Without<MagnifierCamera>,
+            Without<PanCam>,
+        ),
+    >,
+) {
+    let (
+        Ok(window),
+        Ok((main_camera, main_transform, main_projection)),
+        Ok((mut camera, mut transform, mut projection)),
+        Ok(mut backdrop),
+    ) = (
+        windows.get_single(),
+        main_cameras.get_single(),
+        cameras.get_single_mut(),
+        backdrops.get_single_mut(),
+    )
+    else {
+        return;
+    };
+
+    let size = window.physical_size();
+    let scale_factor = window.scale_factor();
+    let side = (size.min_element() as f32 * INSET_FRACTION) as u32;
+    let margin = (INSET_MARGIN * scale_factor) as u32;
+
+    camera.is_active = magnifier.enabled && side > 0 && side + margin <= size.min_element();
+    if !camera.is_active {
+        return;
+    }
+
+    let position = size - UVec2::splat(side + margin);
+    camera.viewport = Some(Viewport {
+        physical_position: position,
+        physical_size: UVec2::splat(side),
+        ..default()
+    });
+
+    let inset = Rect::from_corners(
+        position.as_vec2() / scale_factor,
+        (position + UVec2::splat(side)).as_vec2() / scale_factor,
+    );
+    if let Some(cursor) = window
+        .cursor_position()
+        .filter(|&cursor| !inset.contains(cursor))
+    {
+        if let Ok(world) = main_camera.viewport_to_world_2d(main_transform, cursor) {
+            magnifier.center = world;
+        }
+    }
+
+    projection.scale = main_projection.scale / magnifier.zoom;
+    transform.translation = magnifier.center.extend(transform.translation.z);
+
+    let world_side = side as f32 / scale_factor * projection.scale;
+    backdrop.translation = magnifier.center.extend(BACKDROP_DEPTH);
+    backdrop.scale = Vec2::splat(world_side).extend(1.0);
+}
+
+// Outline of the magnified area in the main view, which also frames the inset.
+fn draw_magnified_area_system(
+    mut gizmos: Gizmos,
+    magnifier: Res<Magnifier>,
+    cameras: Query<(&Camera, &OrthographicProjection), With<MagnifierCamera>>,
+) {
+    let Ok((camera, projection)) = cameras.get_single() else {
+        return;
+    };
+
+    if camera.is_active {
+        gizmos.rect_2d(
+            magnifier.center,
+            projection.area.size(),
+            Color::srgb(0.9, 0.9, 0.9),
+        );
+    }
+}
@@ -1,111 +1,775 @@
-use std::f32::consts::PI;
+use std::{ops::RangeInclusive, time::Duration};
 
 use bevy::{
     diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin},
+    ecs::schedule::ScheduleLabel,
+    log::LogPlugin,
     prelude::*,
+    time::TimeUpdateStrategy,
     utils::HashMap,
 };
-use bevy_inspector_egui::quick::WorldInspectorPlugin;
-use bevy_pancam::{PanCam, PanCamPlugin};
-
-const RADIUS: f32 = 1.0;
-const MASS: f32 = 50.0;
-const SMOOTHING_RADIUS: f32 = 7.0;
-const TARGET_DENSITY: f32 = 5000.0;
-const PRESSURE_MULTIPLIER: f32 = 2.0;
-const WIDTH: f32 = 200.0;
-const HEIGHT: f32 = 400.0;
-const GRAVITY: f32 = 10.0;
-const DAMPING_FACTOR: f32 = 0.99;
-const E: f32 = 0.01;
-const CELL_SIZE: f32 = SMOOTHING_RADIUS;
-
-#[derive(Component)]
+use bevy_inspector_egui::quick::{ResourceInspectorPlugin, WorldInspectorPlugin};
+use bevy_pancam::{DirectionKeys, PanCam, PanCamPlugin};
+use boat::BoatPlugin;
+use budget::{particle_mass, BudgetPlugin, ParticleBudget, ParticleMass};
+use buoyancy::BuoyancyPlugin;
+use cell_map::CellMap;
+use checkpoint::CheckpointPlugin;
+use chunks::solve_chunked;
+use cli::{Args, Solver};
+use clump::ClumpPlugin;
+use collider::ColliderPlugin;
+use conservation::ConservationPlugin;
+use console::ConsolePlugin;
+use culling::{Culled, CullingPlugin};
+use density_texture::DensityTexturePlugin;
+use dilation::{DilationPlugin, TimeScale};
+use domain::{Domain, DomainHandlesPlugin, DomainPlugin};
+use field_view::FieldViewPlugin;
+use flow::FlowPlugin;
+use flow_field::FlowFieldPlugin;
+use fluid_commands::{FluidCommands, FluidCommandsPlugin, FluidMaterial};
+use frozen::FrozenPlugin;
+use gravity::GravityControlPlugin;
+use groups::{GroupRules, ParticleGroup};
+use hybrid::HybridPlugin;
+#[cfg(target_arch = "wasm32")]
+use interpolation::InterpolationPlugin;
+use islands::{Island, IslandsPlugin};
+use kinematic::KinematicPlugin;
+use magnifier::MagnifierPlugin;
+use measure::MeasurePlugin;
+use neighbors::{neighbor_grid_system, NeighborGrid};
+#[cfg(not(target_arch = "wasm32"))]
+use network::NetworkPlugin;
+use obstacle::{Container, ObstaclePlugin};
+use params::{EquationOfState, ParamsPlugin, SimulationParams};
+use pbf::PbfPlugin;
+#[cfg(not(target_arch = "wasm32"))]
+use point_cache::PointCachePlugin;
+use precision::{real, real_vec, to_f32, to_vec3, Real, RealVec3, PI};
+use presets::PresetsPlugin;
+use quality::QualityPlugin;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use reconstruction::{Anisotropy, ReconstructionPlugin};
+use relax::RelaxPlugin;
+use rewind::RewindPlugin;
+use rope::RopePlugin;
+use rumble::RumblePlugin;
+use scene::{fill_positions, ScenePlugin};
+#[cfg(feature = "scripting")]
+use script::ScriptPlugin;
+use sdf::{SdfPlugin, WallMaterial};
+use sediment::SedimentPlugin;
+use shallow_water::ShallowWaterPlugin;
+use soft_body::SoftBodyPlugin;
+use spawn_mask::SpawnMaskPlugin;
+use splash::SplashPlugin;
+use stability::StabilityPlugin;
+use stats::StatsPlugin;
+use stream::StreamPlugin;
+use stress::StressPlugin;
+#[cfg(not(target_arch = "wasm32"))]
+use summary::SummaryPlugin;
+use surface::{Surface, SurfacePlugin};
+use svg::SvgObstaclePlugin;
+use tension::TensionPlugin;
+use timeline::TimelinePlugin;
+use tools::{Tool, ToolsPlugin};
+use turbulence::TurbulencePlugin;
+use user_data::{ParticleUserData, PassthroughPlugin};
+use validation::KernelValidationPlugin;
+#[cfg(not(target_arch = "wasm32"))]
+use wave_gauge::WaveGaugeCsvPlugin;
+use wave_gauge::WaveGaugePlugin;
+use wave_maker::WaveMakerPlugin;
+
+mod boat;
+mod budget;
+mod buoyancy;
+mod cell_map;
+mod checkpoint;
+mod chunks;
+mod cli;
+mod clump;
+mod collider;
+mod conservation;
+mod console;
+mod culling;
+mod density_texture;
+mod dilation;
+mod domain;
+mod field_view;
+mod flow;
+mod flow_field;
+mod fluid_commands;
+mod frozen;
+mod gravity;
+mod groups;
+mod hybrid;
+#[cfg(target_arch = "wasm32")]
+mod interpolation;
+mod islands;
+mod kinematic;
+mod magnifier;
+mod measure;
+mod neighbors;
+#[cfg(not(target_arch = "wasm32"))]
+mod network;
+mod obstacle;
+mod params;
+mod pbf;
+#[cfg(not(target_arch = "wasm32"))]
+mod point_cache;
+mod precision;
+mod presets;
+mod quality;
+mod reconstruction;
+mod relax;
+mod rewind;
+mod ron_asset;
+mod rope;
+mod rumble;
+mod scene;
+#[cfg(feature = "scripting")]
+mod script;
+mod sdf;
+mod sediment;
+mod shallow_water;
+mod soft_body;
+mod spawn_mask;
+mod splash;
+mod spline;
+mod stability;
+mod stats;
+mod stream;
+mod stress;
+#[cfg(not(target_arch = "wasm32"))]
+mod summary;
+mod surface;
+mod svg;
+mod tension;
+mod timeline;
+mod tools;
+mod turbulence;
+mod user_data;
+mod validation;
+mod wave_gauge;
+mod wave_maker;
+
+const HEADLESS_TIMESTEP: f32 = 1.0 / 60.0;
+const TAIT_EXPONENT: i32 = 7;
+const ARTIFICIAL_PRESSURE_EXPONENT: i32 = 4;
+const ARTIFICIAL_PRESSURE_DISTANCE: Real = 0.2;
+const MAX_DIFFUSION_FRACTION: Real = 0.5;
+// Neighbor count shown in the reddest hue.
+const MAX_COLORED_NEIGHBORS: f32 = 30.0;
+// Materials along each color gradient.
+const PALETTE_SIZE: usize = 64;
+// Density change that can move a particle to another palette material, half a step.
+const COLOR_DENSITY_STEP: f32 = 0.5 / PALETTE_SIZE as f32;
+// Ratio of the derived speed of sound to the fastest expected flow, keeping density
+// fluctuations around one percent.
+const SOUND_SPEED_MARGIN: f32 = 10.0;
+#[cfg(target_arch = "wasm32")]
+const WASM_MAX_PARTICLES: usize = 1500;
+#[cfg(target_arch = "wasm32")]
+const WASM_STEP_RATE: f64 = 60.0;
+
+// A single step of the simulation, run by `run_simulation_system` once per frame or, with
+// `SimulationParams::substeps`, several times over equal shares of it.
+#[derive(ScheduleLabel, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+struct Simulation;
+
+const SIMULATION_SCHEDULE: Simulation = Simulation;
+
+// Browsers deliver frames irregularly, so the web build steps the solver at a fixed rate.
+#[cfg(not(target_arch = "wasm32"))]
+const STEPPING_SCHEDULE: Update = Update;
+#[cfg(target_arch = "wasm32")]
+const STEPPING_SCHEDULE: FixedUpdate = FixedUpdate;
+
+#[derive(SystemSet, Reflect, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+enum SimulationSet {
+    Density,
+    Forces,
+    Integration,
+    Collision,
+}
+
+#[derive(Component, Reflect)]
+#[reflect(Component)]
 struct Velocity(Vec3);
 
+// Particle position in solver precision, mirrored into `Transform` for rendering.
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+struct Position(RealVec3);
+
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+struct ParticleColor(Color);
+
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+struct FixedColor;
+
+// Neighbors within the smoothing radius as of the last density stage, not counting the
+// particle itself.
+#[derive(Component, Reflect, Clone, Copy, PartialEq, Eq, Default, Debug)]
+#[reflect(Component)]
+struct NeighborCount(u32);
+
+// What the particle hues show, switched with C.
+#[derive(Resource, Reflect, Clone, Copy, PartialEq, Eq, Default, Debug)]
+#[reflect(Resource)]
+enum ColorMode {
+    #[default]
+    Density,
+    Neighbors,
+    /// Leave the particles as they are, skipping the recoloring altogether
+    Static,
+}
+
+// Density a particle was last colored for. Its color is only looked up again once the
+// density has moved by half a palette step.
+#[derive(Component, Default)]
+struct ColoredDensity(Option<f32>);
+
+// Materials along the density and neighbor gradients, shared by every particle colored by
+// them.
 #[derive(Resource)]
+struct ColorPalette {
+    density: Vec<Handle<ColorMaterial>>,
+    neighbors: Vec<Handle<ColorMaterial>>,
+}
+
+impl FromWorld for ColorPalette {
+    fn from_world(world: &mut World) -> Self {
+        let mut materials = world.resource_mut::<Assets<ColorMaterial>>();
+
+        Self {
+            // Hues wrap around, so the last step stops short of the first.
+            density: (0..PALETTE_SIZE)
+                .map(|step| {
+                    let hue = 360.0 * step as f32 / PALETTE_SIZE as f32;
+                    materials.add(Color::hsl(hue, 0.95, 0.7))
+                })
+                .collect(),
+            // Blue for isolated particles through to red for crowded ones.
+            neighbors: (0..PALETTE_SIZE)
+                .map(|step| {
+                    let crowding = step as f32 / (PALETTE_SIZE - 1) as f32;
+                    materials.add(Color::hsl(240.0 * (1.0 - crowding), 0.95, 0.6))
+                })
+                .collect(),
+        }
+    }
+}
+
+impl ColorPalette {
+    fn density(&self, density: f32) -> &Handle<ColorMaterial> {
+        let hue = (density * 360.0).rem_euclid(360.0);
+        let bucket = (hue / 360.0 * PALETTE_SIZE as f32).round() as usize % PALETTE_SIZE;
+        &self.density[bucket]
+    }
+
+    fn neighbors(&self, count: u32) -> &Handle<ColorMaterial> {
+        let crowding = (count as f32 / MAX_COLORED_NEIGHBORS).min(1.0);
+        &self.neighbors[(crowding * (PALETTE_SIZE - 1) as f32).round() as usize]
+    }
+}
+
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
 struct DensityCache {
-    densities: HashMap<Entity, f32>,
+    densities: HashMap<Entity, Real>,
 }
 
 #[derive(Resource)]
+struct SimulationRng(StdRng);
+
+type PositionQuery<'w, 's> =
+    Query<'w, 's, (Entity, &'static Position, Option<&'static ParticleGroup>)>;
+type PositionHash = CellMap<(Entity, RealVec3, ParticleGroup)>;
+
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
 struct DragState {
     selected_entity: Option<Entity>,
 }
 
 fn main() {
-    App::new()
-        .add_plugins(DefaultPlugins)
+    let args = cli::parse_args();
+    let mut app = App::new();
+
+    if args.headless.is_some() {
+        app.add_plugins((MinimalPlugins, AssetPlugin::default(), LogPlugin::default()))
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(
+                HEADLESS_TIMESTEP,
+            )))
+            .add_systems(Last, headless_exit_system);
+    } else {
+        app.add_plugins(
+            DefaultPlugins
+                .set(AssetPlugin {
+                    watch_for_changes_override: Some(cfg!(not(target_arch = "wasm32"))),
+                    ..default()
+                })
+                .set(WindowPlugin {
+                    primary_window: Some(Window {
+                        canvas: Some("#bevy".into()),
+                        fit_canvas_to_parent: true,
+                        ..default()
+                    }),
+                    ..default()
+                }),
+        )
         .add_plugins(WorldInspectorPlugin::new())
+        .add_plugins(ResourceInspectorPlugin::<SimulationParams>::default())
+        .add_plugins(ResourceInspectorPlugin::<Solver>::default())
         .add_plugins(PanCamPlugin::default())
         .add_plugins(FrameTimeDiagnosticsPlugin::default())
         .add_plugins(LogDiagnosticsPlugin::default())
-        .add_systems(Startup, setup)
-        .insert_resource(DensityCache {
-            densities: HashMap::new(),
-        })
-        .insert_resource(DragState {
-            selected_entity: None,
-        })
+        .add_plugins(ReconstructionPlugin)
+        .add_plugins(DensityTexturePlugin)
+        .add_plugins(FieldViewPlugin)
+        .add_plugins(CullingPlugin)
+        .add_plugins(MagnifierPlugin)
+        .add_systems(Startup, setup_camera)
         .add_systems(
             Update,
             (
-                cache_density_system,
-                velocity_system,
-                update_system,
-                collision_system,
-                boundary_collision_system,
-                update_colors_system,
-                mouse_input_system,
+                attach_particle_mesh_system,
+                resize_particle_meshes_system.run_if(resource_changed::<SimulationParams>),
+                (
+                    color_mode_system,
+                    update_colors_system.run_if(not(resource_equals(ColorMode::Static))),
+                )
+                    .chain(),
                 time_control_system,
-                mouse_object_spawn_system,
             ),
         )
+        .init_resource::<ColorMode>()
+        .init_resource::<ColorPalette>()
+        .register_type::<ColorMode>();
+
+        if !args.is_client() {
+            app.add_plugins(SpawnMaskPlugin)
+                .add_plugins(PresetsPlugin)
+                .add_plugins(ConsolePlugin)
+                .add_plugins(DomainHandlesPlugin)
+                .add_plugins(GravityControlPlugin)
+                .add_plugins(ToolsPlugin)
+                .add_plugins(RumblePlugin)
+                .insert_resource(DragState {
+                    selected_entity: None,
+                })
+                .add_systems(
+                    Update,
+                    (
+                        mouse_input_system.run_if(resource_equals(Tool::Drag)),
+                        mouse_object_spawn_system,
+                    ),
+                );
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        if let Some(address) = &args.serve {
+            app.add_plugins(NetworkPlugin::Server(address.clone()));
+        }
+
+        if let Some(address) = &args.connect {
+            app.add_plugins(NetworkPlugin::Client(address.clone()));
+        }
+
+        if let Some(path) = &args.summary {
+            app.add_plugins(SummaryPlugin(path.clone()));
+        }
+
+        if let Some(path) = &args.wave_gauge_csv {
+            app.add_plugins(WaveGaugeCsvPlugin(path.clone()));
+        }
+
+        app.add_plugins(PointCachePlugin);
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    app.insert_resource(Time::<Fixed>::from_hz(WASM_STEP_RATE))
+        .add_plugins(InterpolationPlugin);
+
+    if !args.is_client() {
+        app.add_plugins(ScenePlugin)
+            .init_resource::<GroupRules>()
+            .register_type::<GroupRules>()
+            .register_type::<ParticleGroup>()
+            .add_plugins(CheckpointPlugin)
+            .add_plugins(RewindPlugin)
+            .add_plugins(RelaxPlugin {
+                steps: args.relax_steps,
+                gravity: !args.relax_without_gravity,
+            })
+            .add_plugins(PassthroughPlugin::<ParticleGroup>::default())
+            .add_plugins(PassthroughPlugin::<ParticleUserData<String>>::default())
+            .add_plugins(DilationPlugin)
+            .add_plugins(SplashPlugin {
+                audio: args.splash_audio,
+            })
+            .add_plugins(ConservationPlugin)
+            .add_plugins(FluidCommandsPlugin)
+            .add_plugins(BudgetPlugin)
+            .add_plugins(QualityPlugin(args.quality))
+            .add_plugins(StabilityPlugin)
+            .add_plugins(StatsPlugin)
+            .add_plugins(IslandsPlugin)
+            .add_plugins(MeasurePlugin)
+            .add_plugins(WaveGaugePlugin)
+            .add_plugins(StressPlugin)
+            .add_plugins(TimelinePlugin)
+            .add_plugins(PbfPlugin)
+            .add_plugins(SurfacePlugin)
+            .add_plugins(BuoyancyPlugin)
+            .add_plugins(ClumpPlugin)
+            .add_plugins(FrozenPlugin)
+            .add_plugins(SedimentPlugin)
+            .add_plugins(SoftBodyPlugin)
+            .add_plugins(RopePlugin)
+            .add_plugins(BoatPlugin)
+            .add_plugins(WaveMakerPlugin)
+            .add_plugins(ShallowWaterPlugin)
+            .add_plugins(HybridPlugin)
+            .add_systems(Startup, setup)
+            .add_systems(
+                SIMULATION_SCHEDULE,
+                (
+                    (
+                        sync_positions_system,
+                        neighbor_grid_system,
+                        cache_density_system,
+                        density_diffusion_system.run_if(resource_equals(Solver::Sph)),
+                    )
+                        .chain()
+                        .in_set(SimulationSet::Density),
+                    velocity_system
+                        .in_set(SimulationSet::Forces)
+                        .run_if(resource_equals(Solver::Sph)),
+                    update_system.in_set(SimulationSet::Integration),
+                    (collision_system, boundary_collision_system).in_set(SimulationSet::Collision),
+                ),
+            );
+
+        if cfg!(debug_assertions) || args.validate_kernels {
+            app.add_plugins(KernelValidationPlugin);
+        }
+
+        #[cfg(feature = "scripting")]
+        if let Some(path) = &args.script {
+            app.add_plugins(ScriptPlugin(path.clone()));
+        }
+    }
+
+    let seed = args.seed;
+    let client = args.is_client();
+    app.insert_resource(args.solver)
+        .insert_resource(ParticleBudget {
+            max: args.max_particles,
+            overflow: args.overflow,
+        })
+        .register_type::<Solver>()
+        .insert_resource(args);
+
+    // Clients only mirror the server's particles, so nothing here runs for them.
+    if !client {
+        // Only the density stage runs while paused, keeping positions, densities and neighbor
+        // counts current with whatever is spawned, deleted or moved in the meantime. The rest
+        // waits, as collisions would otherwise keep pushing on edited particles with no time
+        // passing and hand them all that velocity on resume.
+        app.configure_sets(
+            SIMULATION_SCHEDULE,
+            (
+                SimulationSet::Density,
+                (
+                    SimulationSet::Forces,
+                    SimulationSet::Integration,
+                    SimulationSet::Collision,
+                )
+                    .chain()
+                    .run_if(simulation_running),
+            )
+                .chain(),
+        );
+
+        app.init_schedule(SIMULATION_SCHEDULE)
+            .add_systems(STEPPING_SCHEDULE, run_simulation_system)
+            .add_plugins(ParamsPlugin)
+            .add_plugins(DomainPlugin)
+            .add_plugins(ObstaclePlugin)
+            .add_plugins(ColliderPlugin)
+            .add_plugins(SdfPlugin)
+            .add_plugins(KinematicPlugin)
+            .add_plugins(FlowPlugin)
+            .add_plugins(StreamPlugin)
+            .add_plugins(FlowFieldPlugin)
+            .add_plugins(TensionPlugin)
+            .add_plugins(TurbulencePlugin)
+            .add_plugins(SvgObstaclePlugin);
+    }
+
+    app.insert_resource(SimulationRng(StdRng::seed_from_u64(seed)))
+        .insert_resource(DensityCache {
+            densities: HashMap::new(),
+        })
+        .register_type::<Velocity>()
+        .register_type::<Position>()
+        .register_type::<ParticleColor>()
+        .register_type::<FixedColor>()
+        .register_type::<NeighborCount>()
+        .register_type::<DensityCache>()
+        .register_type::<DragState>()
         .run();
 }
 
-fn setup(
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
-) {
+fn setup_camera(mut commands: Commands) {
     commands.spawn((
         Camera2d,
         PanCam {
             grab_buttons: vec![],
+            // The arrow keys steer gravity.
+            move_keys: DirectionKeys::wasd(),
             ..default()
         },
     ));
+}
 
-    let square_size = 20;
-    let spacing = SMOOTHING_RADIUS;
-
-    for x in 0..square_size {
-        for y in 0..square_size {
-            let position = Vec3::new(
-                x as f32 * spacing - (square_size as f32 * spacing / 2.0) + spacing / 2.0,
-                y as f32 * spacing - (square_size as f32 * spacing / 2.0) + spacing / 2.0,
-                0.0,
-            );
+fn setup(
+    mut commands: Commands,
+    args: Res<Args>,
+    budget: Res<ParticleBudget>,
+    solver: Res<Solver>,
+    params: Res<SimulationParams>,
+    mut rng: ResMut<SimulationRng>,
+) {
+    info!("Running {:?} solver with seed {}", *solver, args.seed);
 
-            commands.spawn((
-                Mesh2d(meshes.add(Circle::new(RADIUS))),
-                MeshMaterial2d(materials.add(Color::hsl(0.5, 0.95, 0.7))),
-                Transform::from_translation(position),
-                Velocity(Vec3::ZERO),
-            ));
+    if args.spawn_mask.is_some() {
+        if args.headless.is_some() {
+            warn!("Spawn masks are not supported in headless runs");
         }
+        return;
     }
+
+    if args.scene_path().is_some() || args.stress.is_some() {
+        return;
+    }
+
+    let count = args.particles.min(budget.remaining(0));
+    let square_size = (count as f32).sqrt().ceil();
+    let positions = fill_positions(
+        Vec2::ZERO,
+        Vec2::splat(square_size * params.particle_spacing),
+        params.particle_spacing,
+        args.sampling,
+        |_| true,
+        count,
+        &mut rng.0,
+    );
+
+    let positions = positions
+        .into_iter()
+        .map(|position| position + spawn_jitter(&args, &params, &mut rng))
+        .collect();
+    commands.spawn_fluid(positions, FluidMaterial::default());
+}
+
+// Random offset of up to `--spawn-jitter` particle spacings on each axis, drawn from the
+// seeded simulation RNG so runs stay reproducible.
+fn spawn_jitter(args: &Args, params: &SimulationParams, rng: &mut SimulationRng) -> Vec2 {
+    let jitter = args.spawn_jitter.abs() * params.particle_spacing;
+    if jitter == 0.0 {
+        return Vec2::ZERO;
+    }
+
+    Vec2::new(
+        rng.0.gen_range(-jitter..=jitter),
+        rng.0.gen_range(-jitter..=jitter),
+    )
 }
 
-fn hash_position(position: Vec3, cell_size: f32) -> (i32, i32) {
+fn particle_bundle(position: Vec3, color: Color) -> impl Bundle {
     (
-        (position.x / cell_size).floor() as i32,
-        (position.y / cell_size).floor() as i32,
+        Transform::from_translation(position),
+        Position(real_vec(position)),
+        Velocity(Vec3::ZERO),
+        ParticleColor(color),
+        Surface::default(),
+        Anisotropy::default(),
+        NeighborCount::default(),
+        TimeScale::default(),
+        Island::default(),
+        ParticleMass::default(),
     )
 }
 
-fn smoothing_kernel(radius: f32, distance: f32) -> f32 {
+// Every particle draws the same circle mesh. Particles with a fixed color share one material
+// per color, while the others start on the palette's first color until they are recolored.
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
+fn attach_particle_mesh_system(
+    mut commands: Commands,
+    particles: Query<(Entity, &ParticleColor, Has<FixedColor>), Added<ParticleColor>>,
+    params: Res<SimulationParams>,
+    palette: Res<ColorPalette>,
+    mut mesh: Local<Option<Handle<Mesh>>>,
+    mut fixed_materials: Local<HashMap<[u8; 4], Handle<ColorMaterial>>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let mesh = mesh
+        .get_or_insert_with(|| meshes.add(Circle::new(params.radius)))
+        .clone();
+
+    for (entity, color, fixed) in particles.iter() {
+        let material = if fixed {
+            fixed_materials
+                .entry(color.0.to_srgba().to_u8_array())
+                .or_insert_with(|| materials.add(color.0))
+                .clone()
+        } else {
+            palette.density[0].clone()
+        };
+
+        commands.entity(entity).insert((
+            Mesh2d(mesh.clone()),
+            MeshMaterial2d(material),
+            ColoredDensity::default(),
+        ));
+    }
+}
+
+// The particle mesh is sized when first made, so redraw it when the radius is edited.
+fn resize_particle_meshes_system(
+    params: Res<SimulationParams>,
+    mut radius: Local<Option<f32>>,
+    particles: Query<&Mesh2d, With<ParticleColor>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    if radius.replace(params.radius) == Some(params.radius) {
+        return;
+    }
+
+    for mesh in particles.iter() {
+        if let Some(circle) = meshes.get_mut(&mesh.0) {
+            *circle = Circle::new(params.radius).into();
+        }
+    }
+}
+
+fn headless_exit_system(args: Res<Args>, mut steps: Local<u32>, mut exit: EventWriter<AppExit>) {
+    *steps += 1;
+
+    if args.headless.is_some_and(|total| *steps >= total) {
+        exit.send(AppExit::Success);
+    }
+}
+
+// Uniform grid for neighbor lookups with cells at least a smoothing radius wide. Along
+// periodic axes the cells are stretched to tile the domain exactly, so lookups and
+// offsets wrap around to the opposite edge.
+struct SpatialGrid {
+    cell_size: Vec2,
+    cells: IVec2,
+    size: Vec2,
+    periodic: BVec2,
+}
+
+impl SpatialGrid {
+    fn new(params: &SimulationParams, domain: &Domain) -> Self {
+        let size = domain.size;
+        let cells = (size / params.smoothing_radius)
+            .floor()
+            .max(Vec2::ONE)
+            .as_ivec2();
+        let periodic = BVec2::new(params.periodic_x, params.periodic_y);
+
+        Self {
+            cell_size: Vec2::select(
+                periodic,
+                size / cells.as_vec2(),
+                Vec2::splat(params.smoothing_radius),
+            ),
+            cells,
+            size,
+            periodic,
+        }
+    }
+
+    fn cell(&self, position: Vec3) -> (i32, i32) {
+        let corner = position.truncate() + self.size / 2.0;
+        self.wrap((corner / self.cell_size).floor().as_ivec2())
+    }
+
+    fn wrap(&self, cell: IVec2) -> (i32, i32) {
+        let cell = IVec2::select(self.periodic, cell.rem_euclid(self.cells), cell);
+        (cell.x, cell.y)
+    }
+
+    // The cell and the eight around it.
+    fn neighbor_cells(&self, cell: (i32, i32)) -> impl Iterator<Item = (i32, i32)> + '_ {
+        self.cells_around(cell, IVec2::ONE)
+    }
+
+    // Cells that may hold points within `radius` of `position`, however many that spans.
+    fn cells_within(&self, position: Vec3, radius: f32) -> impl Iterator<Item = (i32, i32)> + '_ {
+        let reach = (Vec2::splat(radius) / self.cell_size).ceil().as_ivec2();
+        self.cells_around(self.cell(position), reach)
+    }
+
+    fn cells_around(
+        &self,
+        (x, y): (i32, i32),
+        reach: IVec2,
+    ) -> impl Iterator<Item = (i32, i32)> + '_ {
+        let rows = self.axis_offsets(1, reach.y);
+        self.axis_offsets(0, reach.x).flat_map(move |dx| {
+            rows.clone()
+                .map(move |dy| self.wrap(IVec2::new(x + dx, y + dy)))
+        })
+    }
+
+    // Offsets to the cells up to `reach` away along an axis. A periodic axis with too few
+    // cells for that many wraps them onto each other, so it takes each of its cells once.
+    fn axis_offsets(&self, axis: usize, reach: i32) -> RangeInclusive<i32> {
+        let cells = self.cells[axis];
+        if self.periodic.test(axis) && 2 * reach + 1 > cells {
+            0..=cells - 1
+        } else {
+            -reach..=reach
+        }
+    }
+
+    fn offset(&self, from: Vec3, to: Vec3) -> Vec3 {
+        to_vec3(self.precise_offset(real_vec(from), real_vec(to)))
+    }
+
+    // Shortest offset from `from` to `to`, crossing periodic edges where that is closer.
+    fn precise_offset(&self, from: RealVec3, to: RealVec3) -> RealVec3 {
+        let mut offset = to - from;
+        let size = real_vec(self.size.extend(0.0));
+
+        if self.periodic.x {
+            offset.x -= size.x * (offset.x / size.x).round();
+        }
+
+        if self.periodic.y {
+            offset.y -= size.y * (offset.y / size.y).round();
+        }
+
+        offset
+    }
+}
+
+fn smoothing_kernel(radius: Real, distance: Real) -> Real {
     if distance >= radius {
         0.0
     } else {
@@ -114,7 +778,7 @@ fn smoothing_kernel(radius: f32, distance: f32) -> f32 {
     }
 }
 
-fn smoothing_kernel_derivative(radius: f32, distance: f32) -> f32 {
+fn smoothing_kernel_derivative(radius: Real, distance: Real) -> Real {
     if distance > radius {
         0.0
     } else {
@@ -123,163 +787,438 @@ fn smoothing_kernel_derivative(radius: f32, distance: f32) -> f32 {
     }
 }
 
-fn density_to_pressure(density: f32) -> f32 {
-    (density - TARGET_DENSITY) * PRESSURE_MULTIPLIER
+// Tensile instability correction (Monaghan 2000, Macklin & Müller 2013), relative to the
+// pressure it is added to: negligible at range, steep once a pair is closer than
+// `ARTIFICIAL_PRESSURE_DISTANCE` smoothing radii.
+fn artificial_pressure(radius: Real, distance: Real, params: &SimulationParams) -> Real {
+    let reference = smoothing_kernel(radius, ARTIFICIAL_PRESSURE_DISTANCE * radius);
+    real(params.artificial_pressure)
+        * (smoothing_kernel(radius, distance) / reference).powi(ARTIFICIAL_PRESSURE_EXPONENT)
 }
 
-fn calculate_pressure_force(
-    point: Vec3,
-    point_cell: (i32, i32),
-    spatial_hash: &HashMap<(i32, i32), Vec<(Entity, Vec3)>>,
-    density: f32,
-) -> Vec3 {
-    let mut pressure_force = Vec3::ZERO;
-
-    for dx in -1..=1 {
-        for dy in -1..=1 {
-            if let Some(neighbors) = spatial_hash.get(&(point_cell.0 + dx, point_cell.1 + dy)) {
-                for &(_, neighbor_position) in neighbors {
-                    let distance = neighbor_position.distance(point);
-
-                    if distance <= f32::EPSILON || distance >= SMOOTHING_RADIUS || distance.is_nan()
-                    {
-                        continue;
-                    }
+// Pressure per unit density error for the linear law, Tait's B = ρ0 c² / γ otherwise.
+fn pressure_stiffness(params: &SimulationParams, domain: &Domain) -> Real {
+    match params.equation_of_state {
+        EquationOfState::Linear => real(params.pressure_multiplier),
+        EquationOfState::Tait => {
+            real(params.target_density) * speed_of_sound(params, domain).powi(2)
+                / TAIT_EXPONENT as Real
+        }
+    }
+}
 
-                    let direction = (neighbor_position - point) / distance;
-                    let slope = smoothing_kernel_derivative(SMOOTHING_RADIUS, distance);
+// c = sqrt(dp/dρ) at the target density.
+fn speed_of_sound(params: &SimulationParams, domain: &Domain) -> Real {
+    match params.equation_of_state {
+        EquationOfState::Linear => real(params.pressure_multiplier).sqrt(),
+        EquationOfState::Tait => real(params.speed_of_sound.unwrap_or_else(|| {
+            SOUND_SPEED_MARGIN * (2.0 * params.gravity_vector().abs().dot(domain.size)).sqrt()
+        })),
+    }
+}
 
-                    pressure_force +=
-                        -density_to_pressure(density) * direction * slope * MASS / density;
-                }
-            }
+fn density_to_pressure(density: Real, stiffness: Real, params: &SimulationParams) -> Real {
+    let target_density = real(params.target_density);
+
+    match params.equation_of_state {
+        EquationOfState::Linear => (density - target_density) * stiffness,
+        EquationOfState::Tait => stiffness * ((density / target_density).powi(TAIT_EXPONENT) - 1.0),
+    }
+}
+
+// Inverse of `density_to_pressure`.
+fn pressure_to_density(pressure: Real, stiffness: Real, params: &SimulationParams) -> Real {
+    let target_density = real(params.target_density);
+    let stiffness = stiffness.max(Real::EPSILON);
+
+    match params.equation_of_state {
+        EquationOfState::Linear => target_density + pressure / stiffness,
+        EquationOfState::Tait => {
+            target_density * (1.0 + pressure / stiffness).powf(1.0 / TAIT_EXPONENT as Real)
         }
     }
+}
+
+// Keeps only the `SimulationParams::max_neighbors` nearest of `neighbors`, and the particle
+// itself in lists that hold it.
+fn cap_neighbors<T>(
+    neighbors: &mut Vec<T>,
+    params: &SimulationParams,
+    with_self: bool,
+    distance: impl Fn(&T) -> Real,
+) {
+    let cap = params.max_neighbors as usize + usize::from(with_self);
+    if params.max_neighbors == 0 || neighbors.len() <= cap {
+        return;
+    }
+
+    neighbors.select_nth_unstable_by(cap - 1, |a, b| distance(a).total_cmp(&distance(b)));
+    neighbors.truncate(cap);
+}
+
+#[allow(clippy::too_many_arguments)]
+fn calculate_pressure_force(
+    point: RealVec3,
+    group: ParticleGroup,
+    grid: &SpatialGrid,
+    spatial_hash: &PositionHash,
+    rules: &GroupRules,
+    masses: &Query<&ParticleMass>,
+    density: Real,
+    stiffness: Real,
+    params: &SimulationParams,
+) -> RealVec3 {
+    let smoothing_radius = real(params.smoothing_radius);
+    let pressure = density_to_pressure(density, stiffness, params);
+
+    let neighbors = grid
+        .neighbor_cells(grid.cell(to_vec3(point)))
+        .filter_map(|cell| spatial_hash.get(&cell))
+        .flatten()
+        .filter(|&&(_, _, neighbor_group)| rules.pushes(neighbor_group, group))
+        .filter_map(|&(neighbor, neighbor_position, _)| {
+            let offset = grid.precise_offset(point, neighbor_position);
+            let distance = offset.length();
+            (distance > Real::EPSILON && distance < smoothing_radius).then(|| {
+                let mass = real(particle_mass(masses, neighbor, params));
+                (offset, distance, mass)
+            })
+        });
+
+    let force = |(offset, distance, mass): (RealVec3, Real, Real)| {
+        let direction = offset / distance;
+        let slope = smoothing_kernel_derivative(smoothing_radius, distance);
+        // Always pushes the pair apart, whatever the sign of the pressure.
+        let correction = artificial_pressure(smoothing_radius, distance, params) * pressure.abs();
+
+        (-pressure * slope + correction * slope) * direction * mass / density
+    };
+
+    // Without a cap the neighbors are summed as they are found.
+    if params.max_neighbors == 0 {
+        return neighbors.map(force).sum();
+    }
 
-    pressure_force
+    let mut neighbors: Vec<_> = neighbors.collect();
+    cap_neighbors(&mut neighbors, params, false, |&(_, distance, _)| distance);
+    neighbors.into_iter().map(force).sum()
 }
 
 fn calculate_spatial_hash(
-    transforms: &Query<(Entity, &Transform)>,
-    cell_size: f32,
-) -> HashMap<(i32, i32), Vec<(Entity, Vec3)>> {
-    let mut spatial_hash: HashMap<(i32, i32), Vec<(Entity, Vec3)>> = HashMap::new();
+    transforms: &Query<(Entity, &Transform), With<Velocity>>,
+    grid: &SpatialGrid,
+) -> CellMap<(Entity, Vec3)> {
+    let mut spatial_hash = CellMap::new(grid);
 
     for (entity, transform) in transforms.iter() {
         let position = transform.translation;
-        let cell = hash_position(position, cell_size);
-        spatial_hash
-            .entry(cell)
-            .or_default()
-            .push((entity, position));
+        spatial_hash.push(grid.cell(position), (entity, position));
+    }
+
+    spatial_hash
+}
+
+fn calculate_position_hash(positions: &PositionQuery, grid: &SpatialGrid) -> PositionHash {
+    let mut spatial_hash = PositionHash::new(grid);
+
+    for (entity, position, group) in positions.iter() {
+        spatial_hash.push(
+            grid.cell(to_vec3(position.0)),
+            (entity, position.0, group.copied().unwrap_or_default()),
+        );
     }
 
     spatial_hash
 }
 
+// Picks up moves made directly on `Transform`, by collisions, dragging or the network.
+fn sync_positions_system(mut particles: Query<(&Transform, &mut Position)>) {
+    for (transform, mut position) in particles.iter_mut() {
+        if to_vec3(position.0) != transform.translation {
+            position.0 = real_vec(transform.translation);
+        }
+    }
+}
+
 fn cache_density_system(
+    params: Res<SimulationParams>,
+    neighbor_grid: Res<NeighborGrid>,
+    rules: Res<GroupRules>,
     mut density_cache: ResMut<DensityCache>,
-    transforms_query: Query<(Entity, &Transform)>,
+    masses: Query<&ParticleMass>,
+    mut neighbor_counts: Query<&mut NeighborCount>,
 ) {
-    let spatial_hash = calculate_spatial_hash(&transforms_query, CELL_SIZE);
+    let NeighborGrid {
+        grid,
+        cells: spatial_hash,
+    } = &*neighbor_grid;
+    let smoothing_radius = real(params.smoothing_radius);
 
-    density_cache.densities.clear();
+    let densities = solve_chunked(grid, spatial_hash, |entity, position, group| {
+        // A particle always counts towards its own density, even in a passive group.
+        let mut neighbors: Vec<(Entity, Real)> = grid
+            .neighbor_cells(grid.cell(to_vec3(position)))
+            .filter_map(|cell| spatial_hash.get(&cell))
+            .flatten()
+            .filter(|&&(neighbor, _, neighbor_group)| {
+                neighbor == entity || rules.pushes(neighbor_group, group)
+            })
+            .map(|&(neighbor, neighbor_position, _)| {
+                (
+                    neighbor,
+                    grid.precise_offset(position, neighbor_position).length(),
+                )
+            })
+            .filter(|&(_, distance)| distance < smoothing_radius)
+            .collect();
+        cap_neighbors(&mut neighbors, &params, true, |&(_, distance)| distance);
 
-    for (entity, transform) in transforms_query.iter() {
-        let position = transform.translation;
-        let cell = hash_position(position, CELL_SIZE);
-        let default = Vec::new();
-        let neighbors = spatial_hash.get(&cell).unwrap_or(&default);
         let density = neighbors
             .iter()
-            .filter(|&&(_, neighbor_position)| {
-                position.distance(neighbor_position) < SMOOTHING_RADIUS
+            .map(|&(neighbor, distance)| {
+                real(particle_mass(&masses, neighbor, &params))
+                    * smoothing_kernel(smoothing_radius, distance)
             })
-            .map(|&(_, neighbor_position)| {
-                MASS * smoothing_kernel(SMOOTHING_RADIUS, position.distance(neighbor_position))
-            })
-            .sum();
+            .sum::<Real>();
+
+        Some((entity, density, neighbors.len().saturating_sub(1)))
+    });
 
+    density_cache.densities.clear();
+
+    for (entity, density, neighbors) in densities {
         density_cache.densities.insert(entity, density);
+
+        if let Ok(mut count) = neighbor_counts.get_mut(entity) {
+            count.set_if_neq(NeighborCount(neighbors as u32));
+        }
     }
 }
 
+// Delta-SPH density diffusion (Molteni & Colagrossi 2009): each density relaxes toward its
+// neighbors' at a rate of δ h c, damping the high-frequency noise that summation leaves in
+// the pressure field.
+#[allow(clippy::too_many_arguments)]
+fn density_diffusion_system(
+    time: Res<Time>,
+    params: Res<SimulationParams>,
+    domains: Query<&Domain>,
+    neighbor_grid: Res<NeighborGrid>,
+    rules: Res<GroupRules>,
+    mut density_cache: ResMut<DensityCache>,
+    positions_query: PositionQuery,
+    time_scales: Query<&TimeScale>,
+    masses: Query<&ParticleMass>,
+) {
+    if params.density_diffusion <= 0.0 {
+        return;
+    }
+
+    let domain = domains.single();
+    let NeighborGrid {
+        grid,
+        cells: spatial_hash,
+    } = &*neighbor_grid;
+    let smoothing_radius = real(params.smoothing_radius);
+    let rate = real(params.density_diffusion * time.delta_secs())
+        * smoothing_radius
+        * speed_of_sound(&params, domain);
+
+    let diffused: Vec<(Entity, Real)> = positions_query
+        .iter()
+        .filter_map(|(entity, position, group)| {
+            let density = *density_cache.densities.get(&entity)?;
+            let group = group.copied().unwrap_or_default();
+
+            // Sum of the pair weights and of the neighbor densities they weigh.
+            let (weight, weighted): (Real, Real) = grid
+                .neighbor_cells(grid.cell(to_vec3(position.0)))
+                .filter_map(|cell| spatial_hash.get(&cell))
+                .flatten()
+                .filter(|&&(neighbor, _, neighbor_group)| {
+                    neighbor != entity && rules.pushes(neighbor_group, group)
+                })
+                .filter_map(|&(neighbor, neighbor_position, _)| {
+                    let neighbor_density = *density_cache.densities.get(&neighbor)?;
+                    let distance = grid.precise_offset(position.0, neighbor_position).length();
+
+                    if distance <= Real::EPSILON || distance >= smoothing_radius {
+                        return None;
+                    }
+
+                    let mass = particle_mass(&masses, neighbor, &params);
+                    let volume = real(mass) / neighbor_density.max(1e-6);
+                    let slope = smoothing_kernel_derivative(smoothing_radius, distance);
+                    let weight = 2.0 * -slope / distance * volume;
+                    Some((weight, weight * neighbor_density))
+                })
+                .fold((0.0, 0.0), |(a, b), (weight, weighted)| {
+                    (a + weight, b + weighted)
+                });
+
+            if weight <= 0.0 {
+                return None;
+            }
+
+            // Explicit diffusion overshoots once a step would carry a density past its
+            // neighbors' mean, so large steps are capped there.
+            let time_scale = time_scales.get(entity).map_or(1.0, |scale| real(scale.0));
+            let fraction = (rate * time_scale * weight).min(MAX_DIFFUSION_FRACTION);
+            Some((entity, density + fraction * (weighted / weight - density)))
+        })
+        .collect();
+
+    density_cache.densities.extend(diffused);
+}
+
+#[allow(clippy::too_many_arguments)]
 fn velocity_system(
     time: Res<Time>,
+    params: Res<SimulationParams>,
+    domains: Query<&Domain>,
+    neighbor_grid: Res<NeighborGrid>,
+    rules: Res<GroupRules>,
     density_cache: Res<DensityCache>,
-    transforms_query: Query<(Entity, &Transform)>,
-    mut velocities_query: Query<(Entity, &mut Velocity)>,
+    masses: Query<&ParticleMass>,
+    mut velocities_query: Query<(&mut Velocity, &TimeScale)>,
 ) {
-    let delta_time = time.delta_secs();
-    let mut spatial_hash: HashMap<(i32, i32), Vec<(Entity, Vec3)>> = HashMap::new();
-
-    for (entity, transform) in transforms_query.iter() {
-        let position = transform.translation;
-        let cell = hash_position(position, SMOOTHING_RADIUS);
+    let domain = domains.single();
+    let NeighborGrid {
+        grid,
+        cells: spatial_hash,
+    } = &*neighbor_grid;
+    let stiffness = pressure_stiffness(&params, domain);
 
-        spatial_hash
-            .entry(cell)
-            .or_insert_with(Vec::new)
-            .push((entity, position));
-    }
+    let forces = solve_chunked(grid, spatial_hash, |entity, position, group| {
+        let density_safe = density_cache.densities.get(&entity)?.max(1e-6);
+        let pressure_force = calculate_pressure_force(
+            position,
+            group,
+            grid,
+            spatial_hash,
+            &rules,
+            &masses,
+            density_safe,
+            stiffness,
+            &params,
+        );
 
-    for (entity, mut velocity) in velocities_query.iter_mut() {
-        if let Some(&density) = density_cache.densities.get(&entity) {
-            let position = transforms_query.get(entity).unwrap().1.translation;
-            let cell = hash_position(position, SMOOTHING_RADIUS);
-            let density_safe = density.max(1e-6);
+        Some((entity, pressure_force / density_safe))
+    });
 
-            let pressure_force =
-                calculate_pressure_force(position, cell, &spatial_hash, density_safe);
+    for (entity, acceleration) in forces {
+        let Ok((mut velocity, time_scale)) = velocities_query.get_mut(entity) else {
+            continue;
+        };
+        let delta_time = time.delta_secs() * time_scale.0;
 
-            velocity.0 += pressure_force / density_safe * delta_time;
-            velocity.0 += Vec3::new(0.0, -GRAVITY, 0.0) * delta_time;
-            velocity.0 *= DAMPING_FACTOR;
-        }
+        velocity.0 += to_vec3(acceleration * real(delta_time));
+        velocity.0 += params.gravity_vector().extend(0.0) * delta_time;
+        velocity.0 *= params.damping_factor;
     }
 }
 
-fn update_system(time: Res<Time>, mut query: Query<(&mut Transform, &Velocity)>) {
-    let delta_time = time.delta_secs();
-    for (mut transform, velocity) in query.iter_mut() {
-        transform.translation += velocity.0 * delta_time;
+fn update_system(
+    time: Res<Time>,
+    mut query: Query<(&mut Transform, &mut Position, &Velocity, &TimeScale)>,
+) {
+    for (mut transform, mut position, velocity, time_scale) in query.iter_mut() {
+        let delta_time = real(time.delta_secs() * time_scale.0);
+        position.0 += real_vec(velocity.0) * delta_time;
+        transform.translation = to_vec3(position.0);
     }
 }
 
-fn boundary_collision_system(mut query: Query<(&mut Transform, &mut Velocity)>) {
+fn boundary_collision_system(
+    params: Res<SimulationParams>,
+    domains: Query<&Domain>,
+    containers: Query<(&Container, Option<&WallMaterial>)>,
+    mut query: Query<(&mut Transform, &mut Velocity)>,
+) {
+    if let Some((container, material)) = containers.iter().next() {
+        let material = material.copied().unwrap_or_default();
+
+        for (mut transform, mut velocity) in query.iter_mut() {
+            let mut position = transform.translation.truncate();
+            let mut planar_velocity = velocity.0.truncate();
+
+            if container.confine(&mut position, &mut planar_velocity, material, &params) {
+                transform.translation = position.extend(transform.translation.z);
+                velocity.0 = planar_velocity.extend(velocity.0.z);
+            }
+        }
+
+        return;
+    }
+
+    let domain = domains.single();
+    let size = domain.size;
+    let half_width = size.x / 2.0;
+    let half_height = size.y / 2.0;
+
     for (mut transform, mut velocity) in query.iter_mut() {
         let position = transform.translation;
 
-        if position.x < -WIDTH / 2.0 || position.x > WIDTH / 2.0 {
-            velocity.0.x *= -DAMPING_FACTOR;
-            transform.translation.x = position.x.clamp(-WIDTH / 2.0, WIDTH / 2.0);
+        if params.periodic_x {
+            transform.translation.x = (position.x + half_width).rem_euclid(size.x) - half_width;
+        } else if (position.x < -half_width && !domain.outflow.left)
+            || (position.x > half_width && !domain.outflow.right)
+        {
+            let wall = if position.x < 0.0 {
+                domain.walls.left
+            } else {
+                domain.walls.right
+            };
+
+            velocity.0.x *= -wall.restitution(&params);
+            velocity.0.y *= 1.0 - wall.friction(&params);
+            transform.translation.x = position.x.clamp(-half_width, half_width);
         }
 
-        if position.y < -HEIGHT / 2.0 || position.y > HEIGHT / 2.0 {
-            velocity.0.y *= -DAMPING_FACTOR;
-            transform.translation.y = position.y.clamp(-HEIGHT / 2.0, HEIGHT / 2.0);
+        if params.periodic_y {
+            transform.translation.y = (position.y + half_height).rem_euclid(size.y) - half_height;
+        } else if (position.y < -half_height && !domain.outflow.bottom)
+            || (position.y > half_height && !domain.outflow.top)
+        {
+            let wall = if position.y < 0.0 {
+                domain.walls.bottom
+            } else {
+                domain.walls.top
+            };
+
+            velocity.0.y *= -wall.restitution(&params);
+            velocity.0.x *= 1.0 - wall.friction(&params);
+            transform.translation.y = position.y.clamp(-half_height, half_height);
         }
     }
 }
 
 fn collision_system(
-    transforms_query: Query<(Entity, &Transform)>,
+    params: Res<SimulationParams>,
+    domains: Query<&Domain>,
+    rules: Res<GroupRules>,
+    groups: Query<&ParticleGroup>,
+    masses: Query<&ParticleMass>,
+    transforms_query: Query<(Entity, &Transform), With<Velocity>>,
     mut velocities_query: Query<(Entity, &mut Velocity)>,
 ) {
-    let spatial_hash = calculate_spatial_hash(&transforms_query, CELL_SIZE);
+    let grid = SpatialGrid::new(&params, domains.single());
+    let spatial_hash = calculate_spatial_hash(&transforms_query, &grid);
+    let group_of = |entity| groups.get(entity).copied().unwrap_or_default();
     let mut collision_impulses: Vec<(Entity, Vec3)> = vec![];
 
-    for (&_cell, entities_positions) in spatial_hash.iter() {
+    for (_, entities_positions) in spatial_hash.iter() {
         let len = entities_positions.len();
         for i in 0..len {
             for j in (i + 1)..len {
                 let (entity_a, position_a) = entities_positions[i];
                 let (entity_b, position_b) = entities_positions[j];
 
-                let distance = position_a.distance(position_b);
+                let offset = grid.offset(position_a, position_b);
+                let distance = offset.length();
 
-                if distance < 2.0 * RADIUS {
-                    let normal = (position_b - position_a).normalize();
+                if distance < 2.0 * params.radius {
+                    let normal = offset.normalize();
 
                     if let (Ok(velocity_a), Ok(velocity_b)) = (
                         velocities_query.get(entity_a),
@@ -292,13 +1231,26 @@ fn collision_system(
                             continue;
                         }
 
-                        let impulse = -(1.0 + E) * velocity_along_normal * MASS;
+                        // Twice the pair's reduced mass, one particle mass for equal ones.
+                        let (mass_a, mass_b) = (
+                            particle_mass(&masses, entity_a, &params),
+                            particle_mass(&masses, entity_b, &params),
+                        );
+                        let impulse = -(1.0 + params.restitution)
+                            * velocity_along_normal
+                            * (2.0 * mass_a * mass_b / (mass_a + mass_b));
 
                         let impulse_a = impulse * normal * -1.0;
                         let impulse_b = impulse * normal;
+                        let (group_a, group_b) = (group_of(entity_a), group_of(entity_b));
+
+                        if rules.pushes(group_b, group_a) {
+                            collision_impulses.push((entity_a, impulse_a));
+                        }
 
-                        collision_impulses.push((entity_a, impulse_a));
-                        collision_impulses.push((entity_b, impulse_b));
+                        if rules.pushes(group_a, group_b) {
+                            collision_impulses.push((entity_b, impulse_b));
+                        }
                     }
                 }
             }
@@ -307,23 +1259,76 @@ fn collision_system(
 
     for (entity, impulse) in collision_impulses {
         if let Ok(mut velocity) = velocities_query.get_mut(entity) {
-            velocity.1 .0 += impulse / MASS * DAMPING_FACTOR;
+            velocity.1 .0 +=
+                impulse / particle_mass(&masses, entity, &params) * params.damping_factor;
         }
     }
 }
 
+fn color_mode_system(input: Res<ButtonInput<KeyCode>>, mut mode: ResMut<ColorMode>) {
+    if input.just_pressed(KeyCode::KeyC) {
+        *mode = match *mode {
+            ColorMode::Density => ColorMode::Neighbors,
+            ColorMode::Neighbors => ColorMode::Static,
+            ColorMode::Static => ColorMode::Density,
+        };
+        info!("Coloring particles by {:?}", *mode);
+    }
+}
+
+// Swaps each particle onto the palette material closest to its value. Only particles whose
+// value moved since they were last colored are looked at, unless the mode just changed or
+// they just came back into view. Particles out of view are left alone.
+#[allow(clippy::type_complexity)]
 fn update_colors_system(
+    mode: Res<ColorMode>,
+    palette: Res<ColorPalette>,
     density_cache: Res<DensityCache>,
-    query: Query<(Entity, &mut MeshMaterial2d<ColorMaterial>)>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut query: Query<
+        (
+            Entity,
+            Ref<NeighborCount>,
+            Ref<Visibility>,
+            &mut ColoredDensity,
+            &mut MeshMaterial2d<ColorMaterial>,
+        ),
+        (Without<FixedColor>, Without<Culled>),
+    >,
 ) {
-    for (entity, material_handle) in query.iter() {
-        if let (Some(material), Some(density)) = (
-            materials.get_mut(material_handle),
-            density_cache.densities.get(&entity),
-        ) {
-            let hue = (density * 360.0) % 360.0;
-            material.color = Color::hsl(hue, 0.95, 0.7);
+    for (entity, neighbors, visibility, mut colored, mut material) in query.iter_mut() {
+        let refresh = mode.is_changed() || visibility.is_changed();
+
+        let handle = match *mode {
+            ColorMode::Density => {
+                let Some(&density) = density_cache.densities.get(&entity) else {
+                    continue;
+                };
+                let density = to_f32(density);
+
+                if !refresh
+                    && colored
+                        .0
+                        .is_some_and(|last| (density - last).abs() < COLOR_DENSITY_STEP)
+                {
+                    continue;
+                }
+
+                colored.0 = Some(density);
+                palette.density(density)
+            }
+            ColorMode::Neighbors => {
+                if !refresh && !neighbors.is_changed() {
+                    continue;
+                }
+
+                colored.0 = None;
+                palette.neighbors(neighbors.0)
+            }
+            ColorMode::Static => return,
+        };
+
+        if material.0 != *handle {
+            material.0 = handle.clone();
         }
     }
 }
@@ -331,7 +1336,8 @@ fn update_colors_system(
 fn mouse_input_system(
     mouse_input: Res<ButtonInput<MouseButton>>,
     windows: Query<&Window>,
-    camera_query: Query<(&Camera, &GlobalTransform)>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<PanCam>>,
+    params: Res<SimulationParams>,
     mut drag_state: ResMut<DragState>,
     mut query: Query<(Entity, &mut Transform, &mut Velocity)>,
 ) {
@@ -345,7 +1351,7 @@ fn mouse_input_system(
                 for (entity, transform, _) in query.iter_mut() {
                     let position = camera.viewport_to_world_2d(camera_transform, cursor_position);
                     if let Ok(position) = position {
-                        if transform.translation.truncate().distance(position) <= RADIUS {
+                        if transform.translation.truncate().distance(position) <= params.radius {
                             drag_state.selected_entity = Some(entity);
                             LAST_MOUSE_POSITION = Some(cursor_position);
                             break;
@@ -379,6 +1385,31 @@ fn mouse_input_system(
     }
 }
 
+fn run_simulation_system(world: &mut World) {
+    let substeps = world.resource::<SimulationParams>().substeps.max(1);
+    let frame = *world.resource::<Time>();
+
+    if substeps == 1 || frame.delta().is_zero() {
+        world.run_schedule(SIMULATION_SCHEDULE);
+        return;
+    }
+
+    let mut time = Time::<()>::default();
+    time.advance_to(frame.elapsed() - frame.delta());
+
+    for _ in 0..substeps {
+        time.advance_by(frame.delta() / substeps);
+        *world.resource_mut::<Time>() = time;
+        world.run_schedule(SIMULATION_SCHEDULE);
+    }
+
+    *world.resource_mut::<Time>() = frame;
+}
+
+fn simulation_running(time: Res<Time<Virtual>>) -> bool {
+    !time.is_paused()
+}
+
 fn time_control_system(input: Res<ButtonInput<KeyCode>>, mut time: ResMut<Time<Virtual>>) {
     if input.just_pressed(KeyCode::Space) {
         if time.is_paused() {
@@ -392,24 +1423,19 @@ fn time_control_system(input: Res<ButtonInput<KeyCode>>, mut time: ResMut<Time<V
 fn mouse_object_spawn_system(
     input: Res<ButtonInput<KeyCode>>,
     windows: Query<&Window>,
-    camera_query: Query<(&Camera, &GlobalTransform)>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<PanCam>>,
+    budget: Res<ParticleBudget>,
+    particles: Query<(), With<Velocity>>,
     mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
 ) {
     let (camera, camera_transform) = camera_query.single();
 
     if let Some(cursor_position) = windows.single().cursor_position() {
-        if input.just_pressed(KeyCode::KeyF) {
+        if input.just_pressed(KeyCode::KeyF) && budget.remaining(particles.iter().count()) > 0 {
             if let Ok(world_position) =
                 camera.viewport_to_world_2d(camera_transform, cursor_position)
             {
-                commands.spawn((
-                    Mesh2d(meshes.add(Circle::new(RADIUS))),
-                    MeshMaterial2d(materials.add(Color::hsl(0.5, 0.95, 0.7))),
-                    Transform::from_translation(Vec3::new(world_position.x, world_position.y, 0.0)),
-                    Velocity(Vec3::ZERO),
-                ));
+                commands.spawn_fluid(vec![world_position], FluidMaterial::default());
             }
         }
     }
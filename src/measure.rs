@@ -0,0 +1,233 @@
+use bevy::{gizmos::GizmoPlugin, prelude::*};
+
+use crate::{
+    budget::ParticleMass,
+    collider::{draw_collider, Collider},
+    params::SimulationParams,
+    sdf::{to_local, SignedDistance},
+    SimulationSet, Velocity, SIMULATION_SCHEDULE,
+};
+
+// Area placed by its `Transform` that counts the particles inside it after every step, for
+// goals like filling a beaker to a line. Polygons must be convex, as for colliders.
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct MeasureRegion {
+    pub shape: Collider,
+    /// Masses that send a `ThresholdCrossed` whenever the mass inside passes them either way
+    pub thresholds: Vec<f32>,
+    /// Particles inside as of the last step
+    pub count: usize,
+    /// Mass inside as of the last step
+    pub mass: f32,
+}
+
+impl MeasureRegion {
+    pub fn new(shape: Collider, thresholds: Vec<f32>) -> Self {
+        Self {
+            shape,
+            thresholds,
+            count: 0,
+            mass: 0.0,
+        }
+    }
+}
+
+// Turns a `MeasureRegion` into a switch: it fills once `enter` particles are inside and only
+// drains again at `exit` or fewer, so a few particles sloshing across the edge cannot flicker
+// it on and off.
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct TriggerZone {
+    pub enter: usize,
+    pub exit: usize,
+    /// Whether fluid is present, as of the last step
+    pub wet: bool,
+}
+
+impl TriggerZone {
+    pub fn new(enter: usize, exit: usize) -> Self {
+        Self {
+            enter: enter.max(1),
+            exit: exit.min(enter.saturating_sub(1)),
+            wet: false,
+        }
+    }
+}
+
+impl Default for TriggerZone {
+    fn default() -> Self {
+        Self::new(3, 0)
+    }
+}
+
+#[derive(Event, Clone, Copy, Debug)]
+pub struct FluidEntered {
+    pub zone: Entity,
+    /// Particles inside when it filled
+    pub count: usize,
+}
+
+#[derive(Event, Clone, Copy, Debug)]
+pub struct FluidExited {
+    pub zone: Entity,
+}
+
+#[derive(Event, Clone, Copy, Debug)]
+pub struct ThresholdCrossed {
+    pub region: Entity,
+    pub threshold: f32,
+    /// Mass inside after the crossing
+    pub mass: f32,
+    /// Whether the region filled past the threshold rather than emptied below it
+    pub rising: bool,
+}
+
+pub struct MeasurePlugin;
+
+impl Plugin for MeasurePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ThresholdCrossed>()
+            .add_event::<FluidEntered>()
+            .add_event::<FluidExited>()
+            .register_type::<MeasureRegion>()
+            .register_type::<TriggerZone>()
+            .add_systems(
+                SIMULATION_SCHEDULE,
+                (measure_system, trigger_system)
+                    .chain()
+                    .after(SimulationSet::Collision),
+            )
+            .add_systems(Update, (log_crossings_system, log_triggers_system));
+
+        if app.is_plugin_added::<GizmoPlugin>() {
+            app.add_systems(Update, draw_regions_system);
+        }
+    }
+}
+
+fn measure_system(
+    params: Res<SimulationParams>,
+    mut regions: Query<(Entity, &mut MeasureRegion, &Transform)>,
+    particles: Query<(&Transform, &ParticleMass), With<Velocity>>,
+    mut crossings: EventWriter<ThresholdCrossed>,
+) {
+    for (entity, mut region, region_transform) in regions.iter_mut() {
+        let (count, mass) = particles
+            .iter()
+            .filter(|(transform, _)| {
+                let point = to_local(region_transform, transform.translation.truncate());
+                region.shape.signed_distance(point) <= 0.0
+            })
+            .fold((0, 0.0), |(count, mass), (_, particle_mass)| {
+                (count + 1, mass + particle_mass.0 * params.mass)
+            });
+        let before = region.mass;
+
+        for &threshold in &region.thresholds {
+            let rising = before < threshold && mass >= threshold;
+            if rising || (before >= threshold && mass < threshold) {
+                crossings.send(ThresholdCrossed {
+                    region: entity,
+                    threshold,
+                    mass,
+                    rising,
+                });
+            }
+        }
+
+        if region.count != count || region.mass != mass {
+            region.count = count;
+            region.mass = mass;
+        }
+    }
+}
+
+fn trigger_system(
+    mut zones: Query<(Entity, &MeasureRegion, &mut TriggerZone)>,
+    mut entered: EventWriter<FluidEntered>,
+    mut exited: EventWriter<FluidExited>,
+) {
+    for (entity, region, mut zone) in zones.iter_mut() {
+        if !zone.wet && region.count >= zone.enter {
+            zone.wet = true;
+            entered.send(FluidEntered {
+                zone: entity,
+                count: region.count,
+            });
+        } else if zone.wet && region.count <= zone.exit {
+            zone.wet = false;
+            exited.send(FluidExited { zone: entity });
+        }
+    }
+}
+
+fn log_crossings_system(mut crossings: EventReader<ThresholdCrossed>, names: Query<&Name>) {
+    for crossing in crossings.read() {
+        let name = names
+            .get(crossing.region)
+            .map_or("Measure region", |name| name.as_str());
+        let direction = if crossing.rising { "above" } else { "below" };
+        info!(
+            "{name} {direction} {} ({} inside)",
+            crossing.threshold, crossing.mass
+        );
+    }
+}
+
+fn log_triggers_system(
+    mut entered: EventReader<FluidEntered>,
+    mut exited: EventReader<FluidExited>,
+    names: Query<&Name>,
+) {
+    let name = |entity| {
+        names
+            .get(entity)
+            .map_or("Trigger zone", |name: &Name| name.as_str())
+    };
+
+    for event in entered.read() {
+        info!(
+            "Fluid entered {} ({} inside)",
+            name(event.zone),
+            event.count
+        );
+    }
+
+    for event in exited.read() {
+        info!("Fluid left {}", name(event.zone));
+    }
+}
+
+// Outlined brighter the more of its thresholds the region has passed, and trigger zones
+// yellow while wet.
+fn draw_regions_system(
+    mut gizmos: Gizmos,
+    regions: Query<(&MeasureRegion, &Transform, Option<&TriggerZone>)>,
+) {
+    for (region, transform, trigger) in regions.iter() {
+        if let Some(trigger) = trigger {
+            let color = if trigger.wet {
+                Color::srgb(0.9, 0.8, 0.2)
+            } else {
+                Color::srgb(0.5, 0.5, 0.3)
+            };
+            draw_collider(&mut gizmos, &region.shape, transform, color);
+            continue;
+        }
+
+        let passed = region
+            .thresholds
+            .iter()
+            .filter(|&&threshold| region.mass >= threshold)
+            .count();
+        let level = passed as f32 / region.thresholds.len().max(1) as f32;
+
+        draw_collider(
+            &mut gizmos,
+            &region.shape,
+            transform,
+            Color::srgb(0.2, 0.4 + 0.5 * level, 0.5 + 0.4 * level),
+        );
+    }
+}
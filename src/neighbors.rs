@@ -0,0 +1,192 @@
+use bevy::{ecs::system::SystemParam, prelude::*, utils::HashSet};
+
+use crate::{
+    calculate_position_hash,
+    domain::Domain,
+    params::SimulationParams,
+    precision::{real, real_vec, to_vec3},
+    surface::ColorField,
+    PositionHash, PositionQuery, SpatialGrid,
+};
+
+// Stretches of a ray checked per smoothing radius, and the bisection steps refining where
+// it crosses the fluid surface.
+const RAY_STEPS: f32 = 4.0;
+const REFINE_STEPS: usize = 8;
+
+// Particles bucketed by grid cell at the start of each step, shared by the density and
+// pressure passes and by `FluidNeighbors`.
+#[derive(Resource)]
+pub struct NeighborGrid {
+    pub grid: SpatialGrid,
+    pub cells: PositionHash,
+}
+
+pub fn neighbor_grid_system(
+    mut commands: Commands,
+    params: Res<SimulationParams>,
+    domains: Query<&Domain>,
+    positions_query: PositionQuery,
+) {
+    let grid = SpatialGrid::new(&params, domains.single());
+    let cells = calculate_position_hash(&positions_query, &grid);
+    commands.insert_resource(NeighborGrid { grid, cells });
+}
+
+/// Finds fluid particles near a point without scanning them all. Positions are those at
+/// the start of the last simulation step.
+#[derive(SystemParam)]
+pub struct FluidNeighbors<'w> {
+    grid: Option<Res<'w, NeighborGrid>>,
+    field: Option<Res<'w, ColorField>>,
+    params: Res<'w, SimulationParams>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct FluidHit {
+    pub point: Vec2,
+    /// Distance along the ray from its origin
+    pub distance: f32,
+    /// Unit normal of the particle or surface, facing back along the ray
+    pub normal: Vec2,
+    /// Particle hit, or `None` where the ray crossed the fluid surface between particles
+    pub entity: Option<Entity>,
+}
+
+impl FluidNeighbors<'_> {
+    /// Particles within `radius` of `point`, wrapping around periodic edges
+    pub fn within_radius(
+        &self,
+        point: Vec3,
+        radius: f32,
+    ) -> impl Iterator<Item = (Entity, Vec3)> + '_ {
+        let center = real_vec(point);
+        let reach = real(radius);
+
+        self.grid.as_deref().into_iter().flat_map(move |shared| {
+            shared
+                .grid
+                .cells_within(point, radius)
+                .filter_map(move |cell| shared.cells.get(&cell))
+                .flatten()
+                .filter(move |&&(_, position, _)| {
+                    shared.grid.precise_offset(center, position).length() <= reach
+                })
+                .map(|&(entity, position, _)| (entity, to_vec3(position)))
+        })
+    }
+
+    /// First particle disc or fluid surface crossing within `max_distance` of `origin` along
+    /// `direction`. A ray starting in the fluid crosses the surface on its way out. An
+    /// infinite `max_distance` casts until the ray is past the domain.
+    pub fn raycast(&self, origin: Vec2, direction: Vec2, max_distance: f32) -> Option<FluidHit> {
+        let shared = self.grid.as_deref()?;
+        let direction = direction.try_normalize()?;
+        let radius = self.params.radius;
+
+        if !origin.is_finite() || max_distance.is_nan() {
+            return None;
+        }
+
+        // Nothing lies farther from the origin than the far corner of the domain.
+        let max_distance =
+            max_distance.min(origin.length() + shared.grid.size.length() / 2.0 + radius);
+        let step = self.params.smoothing_radius / RAY_STEPS;
+        let inside = self.field.as_ref().map(|field| field.is_submerged(origin));
+
+        let mut visited = HashSet::new();
+        let mut best: Option<FluidHit> = None;
+        let mut near = 0.0;
+
+        // Each stretch checks the particles in cells it reaches that no earlier one did, so
+        // a hit is final once no later stretch could start before it.
+        while near < max_distance {
+            let far = (near + step).min(max_distance);
+            let middle = origin + direction * (near + far) / 2.0;
+            let reach = (far - near) / 2.0 + radius;
+
+            for cell in shared.grid.cells_within(middle.extend(0.0), reach) {
+                if !visited.insert(cell) {
+                    continue;
+                }
+
+                for &(entity, position, _) in shared.cells.get(&cell).into_iter().flatten() {
+                    // The particle's image nearest this stretch, across periodic edges.
+                    let offset = shared
+                        .grid
+                        .precise_offset(real_vec(middle.extend(0.0)), position);
+                    let center = middle + to_vec3(offset).truncate();
+
+                    let Some(distance) = ray_circle(origin, direction, center, radius) else {
+                        continue;
+                    };
+
+                    if distance > max_distance || best.is_some_and(|hit| hit.distance <= distance) {
+                        continue;
+                    }
+
+                    let point = origin + direction * distance;
+                    best = Some(FluidHit {
+                        point,
+                        distance,
+                        normal: (point - center).normalize_or_zero(),
+                        entity: Some(entity),
+                    });
+                }
+            }
+
+            if let (Some(field), Some(inside)) = (self.field.as_deref(), inside) {
+                if field.is_submerged(origin + direction * far) != inside {
+                    let (mut before, mut after) = (near, far);
+                    for _ in 0..REFINE_STEPS {
+                        let middle = (before + after) / 2.0;
+                        if field.is_submerged(origin + direction * middle) == inside {
+                            before = middle;
+                        } else {
+                            after = middle;
+                        }
+                    }
+
+                    let distance = (before + after) / 2.0;
+                    if !best.is_some_and(|hit| hit.distance <= distance) {
+                        let point = origin + direction * distance;
+                        let normal = field.normal(point).unwrap_or(-direction);
+                        best = Some(FluidHit {
+                            point,
+                            distance,
+                            normal: if normal.dot(direction) > 0.0 {
+                                -normal
+                            } else {
+                                normal
+                            },
+                            entity: None,
+                        });
+                    }
+                }
+            }
+
+            if best.is_some_and(|hit| hit.distance <= far) {
+                break;
+            }
+
+            near = far;
+        }
+
+        best
+    }
+}
+
+// Distance along a unit `direction` to where the ray enters the circle, or `None` if it
+// misses or starts inside it.
+fn ray_circle(origin: Vec2, direction: Vec2, center: Vec2, radius: f32) -> Option<f32> {
+    let offset = origin - center;
+    let along = offset.dot(direction);
+    let outside = offset.length_squared() - radius * radius;
+
+    if outside < 0.0 || along > 0.0 {
+        return None;
+    }
+
+    let discriminant = along * along - outside;
+    (discriminant >= 0.0).then(|| -along - discriminant.sqrt())
+}
@@ -0,0 +1,507 @@
+use std::{
+    io::{self, ErrorKind, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+};
+
+use bevy::{gizmos::GizmoPlugin, prelude::*};
+use bevy_pancam::PanCam;
+
+use crate::{
+    domain::{draw_domain_system, Domain},
+    fluid_commands::{FluidCommands, FluidMaterial},
+    params::SimulationParams,
+    precision::{real, to_f32},
+    DensityCache, ParticleColor, Velocity,
+};
+
+const BROADCAST_INTERVAL: f32 = 1.0 / 30.0;
+const STATE_MESSAGE: u8 = 0;
+const SPAWN_MESSAGE: u8 = 1;
+const IMPULSE_MESSAGE: u8 = 2;
+// Longest frame either side accepts, room for the state of a couple of million particles.
+// Peers announcing anything longer are dropped before their frame is buffered.
+const MAX_FRAME: usize = 1 << 24;
+
+pub enum NetworkPlugin {
+    Server(String),
+    Client(String),
+}
+
+impl Plugin for NetworkPlugin {
+    fn build(&self, app: &mut App) {
+        match self {
+            NetworkPlugin::Server(address) => {
+                let listener = match TcpListener::bind(address)
+                    .and_then(|listener| listener.set_nonblocking(true).map(|_| listener))
+                {
+                    Ok(listener) => listener,
+                    Err(error) => {
+                        error!("Could not listen on {address}, networking disabled: {error}");
+                        return;
+                    }
+                };
+
+                info!("Serving simulation on {address}");
+
+                app.insert_resource(NetworkServer {
+                    listener,
+                    clients: Vec::new(),
+                    timer: Timer::from_seconds(BROADCAST_INTERVAL, TimerMode::Repeating),
+                })
+                .add_systems(
+                    PreUpdate,
+                    (accept_clients_system, receive_client_messages_system).chain(),
+                )
+                .add_systems(PostUpdate, broadcast_state_system);
+            }
+            NetworkPlugin::Client(address) => {
+                // Clients run none of the simulation, so they keep their own copies of what
+                // rendering reads from it, matched to the server as its state arrives.
+                app.init_resource::<SimulationParams>()
+                    .register_type::<SimulationParams>()
+                    .register_type::<Domain>();
+                app.world_mut()
+                    .spawn((Name::new("Domain"), Domain::default()));
+
+                if app.is_plugin_added::<GizmoPlugin>() {
+                    app.add_systems(Update, draw_domain_system);
+                }
+
+                let stream = match TcpStream::connect(address).and_then(Connection::new) {
+                    Ok(stream) => stream,
+                    Err(error) => {
+                        error!("Could not connect to {address}, networking disabled: {error}");
+                        return;
+                    }
+                };
+
+                app.insert_resource(NetworkClient {
+                    connection: stream,
+                    particles: Vec::new(),
+                })
+                .add_systems(PreUpdate, receive_state_system)
+                .add_systems(Update, client_input_system)
+                .add_systems(PostUpdate, flush_client_system);
+            }
+        }
+    }
+}
+
+struct Connection {
+    stream: TcpStream,
+    address: Option<SocketAddr>,
+    incoming: Vec<u8>,
+    outgoing: Vec<u8>,
+}
+
+impl Connection {
+    fn new(stream: TcpStream) -> io::Result<Self> {
+        stream.set_nonblocking(true)?;
+        stream.set_nodelay(true)?;
+
+        Ok(Self {
+            address: stream.peer_addr().ok(),
+            stream,
+            incoming: Vec::new(),
+            outgoing: Vec::new(),
+        })
+    }
+
+    fn receive(&mut self) -> io::Result<Vec<Vec<u8>>> {
+        let mut chunk = [0; 4096];
+        let mut frames = Vec::new();
+
+        // Frames are split off after every read, so no more than one frame and a chunk is
+        // ever buffered.
+        loop {
+            match self.stream.read(&mut chunk) {
+                Ok(0) => return Err(ErrorKind::ConnectionAborted.into()),
+                Ok(count) => {
+                    self.incoming.extend_from_slice(&chunk[..count]);
+                    self.split_frames(&mut frames)?;
+                }
+                Err(error) if error.kind() == ErrorKind::WouldBlock => break,
+                Err(error) => return Err(error),
+            }
+        }
+
+        Ok(frames)
+    }
+
+    fn split_frames(&mut self, frames: &mut Vec<Vec<u8>>) -> io::Result<()> {
+        while let Some(length) = self.incoming.get(..4) {
+            let length = u32::from_le_bytes(length.try_into().unwrap()) as usize;
+            if length > MAX_FRAME {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidData,
+                    format!("frame of {length} bytes is over the {MAX_FRAME} byte limit"),
+                ));
+            }
+            if self.incoming.len() < 4 + length {
+                break;
+            }
+            frames.push(self.incoming[4..4 + length].to_vec());
+            self.incoming.drain(..4 + length);
+        }
+
+        Ok(())
+    }
+
+    fn queue(&mut self, payload: &[u8]) {
+        self.outgoing
+            .extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        self.outgoing.extend_from_slice(payload);
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        while !self.outgoing.is_empty() {
+            match self.stream.write(&self.outgoing) {
+                Ok(0) => return Err(ErrorKind::WriteZero.into()),
+                Ok(count) => {
+                    self.outgoing.drain(..count);
+                }
+                Err(error) if error.kind() == ErrorKind::WouldBlock => break,
+                Err(error) => return Err(error),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+enum ClientMessage {
+    Spawn(Vec2),
+    Impulse { position: Vec2, velocity: Vec2 },
+}
+
+impl ClientMessage {
+    fn encode(&self) -> Vec<u8> {
+        let (tag, values) = match self {
+            ClientMessage::Spawn(position) => (SPAWN_MESSAGE, vec![position.x, position.y]),
+            ClientMessage::Impulse { position, velocity } => (
+                IMPULSE_MESSAGE,
+                vec![position.x, position.y, velocity.x, velocity.y],
+            ),
+        };
+
+        let mut payload = vec![tag];
+        for value in values {
+            payload.extend_from_slice(&value.to_le_bytes());
+        }
+        payload
+    }
+
+    fn decode(payload: &[u8]) -> Option<Self> {
+        let value = |index: usize| {
+            let start = 1 + index * 4;
+            payload
+                .get(start..start + 4)
+                .map(|bytes| f32::from_le_bytes(bytes.try_into().unwrap()))
+        };
+        let vector = |index: usize| Some(Vec2::new(value(index)?, value(index + 1)?));
+
+        match *payload.first()? {
+            SPAWN_MESSAGE => Some(ClientMessage::Spawn(vector(0)?)),
+            IMPULSE_MESSAGE => Some(ClientMessage::Impulse {
+                position: vector(0)?,
+                velocity: vector(2)?,
+            }),
+            _ => None,
+        }
+    }
+}
+
+// Positions are quantized to i16 across the domain and densities to u16 relative to
+// the target density, shrinking each particle to six bytes on the wire.
+fn encode_state(particles: &[(Vec3, f32)], extent: Vec2, params: &SimulationParams) -> Vec<u8> {
+    let mut payload = Vec::with_capacity(17 + particles.len() * 6);
+
+    payload.push(STATE_MESSAGE);
+    payload.extend_from_slice(&extent.x.to_le_bytes());
+    payload.extend_from_slice(&extent.y.to_le_bytes());
+    payload.extend_from_slice(&params.target_density.to_le_bytes());
+    payload.extend_from_slice(&(particles.len() as u32).to_le_bytes());
+
+    for &(position, density) in particles {
+        let quantized =
+            (position.truncate() / extent).clamp(Vec2::NEG_ONE, Vec2::ONE) * i16::MAX as f32;
+        let density = (density / params.target_density * 32768.0).clamp(0.0, u16::MAX as f32);
+
+        payload.extend_from_slice(&(quantized.x.round() as i16).to_le_bytes());
+        payload.extend_from_slice(&(quantized.y.round() as i16).to_le_bytes());
+        payload.extend_from_slice(&(density as u16).to_le_bytes());
+    }
+
+    payload
+}
+
+struct State {
+    extent: Vec2,
+    target_density: f32,
+    particles: Vec<(Vec2, f32)>,
+}
+
+fn decode_state(payload: &[u8]) -> Option<State> {
+    let float = |start: usize| {
+        payload
+            .get(start..start + 4)
+            .map(|bytes| f32::from_le_bytes(bytes.try_into().unwrap()))
+    };
+
+    let extent = Vec2::new(float(1)?, float(5)?);
+    let target_density = float(9)?;
+    let count = u32::from_le_bytes(payload.get(13..17)?.try_into().unwrap()) as usize;
+
+    let particles = payload
+        .get(17..17 + count * 6)?
+        .chunks_exact(6)
+        .map(|chunk| {
+            let x = i16::from_le_bytes([chunk[0], chunk[1]]) as f32 / i16::MAX as f32;
+            let y = i16::from_le_bytes([chunk[2], chunk[3]]) as f32 / i16::MAX as f32;
+            let density = u16::from_le_bytes([chunk[4], chunk[5]]) as f32 / 32768.0;
+            (Vec2::new(x, y) * extent, density * target_density)
+        })
+        .collect();
+
+    Some(State {
+        extent,
+        target_density,
+        particles,
+    })
+}
+
+#[derive(Resource)]
+struct NetworkServer {
+    listener: TcpListener,
+    clients: Vec<Connection>,
+    timer: Timer,
+}
+
+fn accept_clients_system(mut server: ResMut<NetworkServer>) {
+    loop {
+        match server.listener.accept() {
+            Ok((stream, address)) => match Connection::new(stream) {
+                Ok(connection) => {
+                    info!("Client connected from {address}");
+                    server.clients.push(connection);
+                }
+                Err(error) => warn!("Could not accept client {address}: {error}"),
+            },
+            Err(error) if error.kind() == ErrorKind::WouldBlock => break,
+            Err(error) => {
+                warn!("Could not accept client: {error}");
+                break;
+            }
+        }
+    }
+}
+
+fn receive_client_messages_system(
+    mut commands: Commands,
+    mut server: ResMut<NetworkServer>,
+    params: Res<SimulationParams>,
+    mut particles: Query<(&Transform, &mut Velocity)>,
+) {
+    let mut messages = Vec::new();
+
+    server.clients.retain_mut(|client| match client.receive() {
+        Ok(frames) => {
+            messages.extend(
+                frames
+                    .iter()
+                    .filter_map(|frame| ClientMessage::decode(frame)),
+            );
+            true
+        }
+        Err(error) => {
+            info!("Client {:?} disconnected: {error}", client.address);
+            false
+        }
+    });
+
+    let mut spawns = Vec::new();
+
+    for message in messages {
+        match message {
+            ClientMessage::Spawn(position) => spawns.push(position),
+            ClientMessage::Impulse { position, velocity } => {
+                let nearest = particles
+                    .iter_mut()
+                    .map(|(transform, velocity)| {
+                        (
+                            transform.translation.truncate().distance(position),
+                            velocity,
+                        )
+                    })
+                    .filter(|(distance, _)| *distance <= params.smoothing_radius)
+                    .min_by(|(a, _), (b, _)| a.total_cmp(b));
+
+                if let Some((_, mut particle_velocity)) = nearest {
+                    particle_velocity.0 = velocity.extend(0.0);
+                }
+            }
+        }
+    }
+
+    commands.spawn_fluid(spawns, FluidMaterial::default());
+}
+
+fn broadcast_state_system(
+    time: Res<Time>,
+    mut server: ResMut<NetworkServer>,
+    params: Res<SimulationParams>,
+    domains: Query<&Domain>,
+    density_cache: Res<DensityCache>,
+    particles: Query<(Entity, &Transform), With<Velocity>>,
+) {
+    if !server.timer.tick(time.delta()).just_finished() || server.clients.is_empty() {
+        return;
+    }
+
+    let mut state: Vec<(Entity, Vec3, f32)> = particles
+        .iter()
+        .map(|(entity, transform)| {
+            let density = density_cache.densities.get(&entity).copied();
+            (entity, transform.translation, density.map_or(0.0, to_f32))
+        })
+        .collect();
+    state.sort_by_key(|&(entity, _, _)| entity);
+    // Clients drop frames over the limit, so particles past it go unsent.
+    state.truncate((MAX_FRAME - 17) / 6);
+
+    let state: Vec<(Vec3, f32)> = state
+        .into_iter()
+        .map(|(_, position, density)| (position, density))
+        .collect();
+    let payload = encode_state(&state, domains.single().half_size(), &params);
+
+    server.clients.retain_mut(|client| {
+        if client.outgoing.is_empty() {
+            client.queue(&payload);
+        }
+
+        match client.flush() {
+            Ok(()) => true,
+            Err(error) => {
+                info!("Client {:?} disconnected: {error}", client.address);
+                false
+            }
+        }
+    });
+}
+
+#[derive(Resource)]
+struct NetworkClient {
+    connection: Connection,
+    particles: Vec<Entity>,
+}
+
+// Particles spawned for the state are adopted a frame later, once their commands have run.
+// Every mirrored particle is moved to its slot in the state each frame, so which slot each new
+// one takes does not matter.
+fn receive_state_system(
+    mut commands: Commands,
+    mut client: ResMut<NetworkClient>,
+    mut density_cache: ResMut<DensityCache>,
+    mut params: ResMut<SimulationParams>,
+    mut domains: Query<&mut Domain>,
+    spawned: Query<Entity, Added<ParticleColor>>,
+    mut transforms: Query<&mut Transform, With<ParticleColor>>,
+    mut exit: EventWriter<AppExit>,
+) {
+    client.particles.extend(spawned.iter());
+
+    let frames = match client.connection.receive() {
+        Ok(frames) => frames,
+        Err(error) => {
+            error!("Lost connection to server: {error}");
+            exit.send(AppExit::error());
+            return;
+        }
+    };
+
+    let Some(state) = frames
+        .iter()
+        .rev()
+        .filter(|frame| frame.first() == Some(&STATE_MESSAGE))
+        .find_map(|frame| decode_state(frame))
+    else {
+        return;
+    };
+
+    for mut domain in domains.iter_mut() {
+        if domain.size != state.extent * 2.0 {
+            domain.size = state.extent * 2.0;
+        }
+    }
+
+    if params.target_density != state.target_density {
+        params.target_density = state.target_density;
+    }
+
+    let state = state.particles;
+
+    while client.particles.len() > state.len() {
+        let entity = client.particles.pop().unwrap();
+        commands.entity(entity).despawn();
+    }
+
+    density_cache.densities.clear();
+
+    for (&entity, &(position, density)) in client.particles.iter().zip(&state) {
+        if let Ok(mut transform) = transforms.get_mut(entity) {
+            transform.translation = position.extend(0.0);
+        }
+
+        density_cache.densities.insert(entity, real(density));
+    }
+
+    let missing = state[client.particles.len()..]
+        .iter()
+        .map(|&(position, _)| position)
+        .collect();
+    commands.spawn_fluid(missing, FluidMaterial::default());
+}
+
+fn client_input_system(
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    windows: Query<&Window>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<PanCam>>,
+    mut client: ResMut<NetworkClient>,
+    mut press_position: Local<Option<Vec2>>,
+) {
+    let (camera, camera_transform) = camera_query.single();
+
+    let Some(cursor_position) = windows.single().cursor_position() else {
+        return;
+    };
+
+    let Ok(world_position) = camera.viewport_to_world_2d(camera_transform, cursor_position) else {
+        return;
+    };
+
+    if mouse_input.just_pressed(MouseButton::Left) {
+        *press_position = Some(world_position);
+    } else if mouse_input.just_released(MouseButton::Left) {
+        if let Some(position) = press_position.take() {
+            let message = ClientMessage::Impulse {
+                position,
+                velocity: (world_position - position) * 10.0,
+            };
+            client.connection.queue(&message.encode());
+        }
+    }
+
+    if keyboard_input.just_pressed(KeyCode::KeyF) {
+        client
+            .connection
+            .queue(&ClientMessage::Spawn(world_position).encode());
+    }
+}
+
+fn flush_client_system(mut client: ResMut<NetworkClient>) {
+    if let Err(error) = client.connection.flush() {
+        error!("Could not send to server: {error}");
+    }
+}
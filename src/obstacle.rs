@@ -0,0 +1,246 @@
+use bevy::{gizmos::GizmoPlugin, prelude::*};
+
+use crate::{
+    params::SimulationParams,
+    sdf::{wall_response, WallMaterial},
+    SimulationSet, Velocity, SIMULATION_SCHEDULE,
+};
+
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct Obstacle {
+    points: Vec<Vec2>,
+    closed: bool,
+    min: Vec2,
+    max: Vec2,
+}
+
+impl Obstacle {
+    pub fn new(points: Vec<Vec2>, closed: bool) -> Self {
+        let min = points
+            .iter()
+            .fold(Vec2::splat(f32::INFINITY), |min, &point| min.min(point));
+        let max = points
+            .iter()
+            .fold(Vec2::splat(f32::NEG_INFINITY), |max, &point| max.max(point));
+
+        Self {
+            points,
+            closed,
+            min,
+            max,
+        }
+    }
+
+    fn edges(&self) -> impl Iterator<Item = (Vec2, Vec2)> + '_ {
+        let count = if self.closed {
+            self.points.len()
+        } else {
+            self.points.len().saturating_sub(1)
+        };
+
+        (0..count).map(|i| (self.points[i], self.points[(i + 1) % self.points.len()]))
+    }
+
+    pub fn contains(&self, point: Vec2) -> bool {
+        if !self.closed {
+            return false;
+        }
+
+        let mut inside = false;
+
+        for (a, b) in self.edges() {
+            if (a.y > point.y) != (b.y > point.y) {
+                let t = (point.y - a.y) / (b.y - a.y);
+                if point.x < a.x + t * (b.x - a.x) {
+                    inside = !inside;
+                }
+            }
+        }
+
+        inside
+    }
+
+    fn closest_point(&self, point: Vec2) -> Option<Vec2> {
+        self.edges()
+            .map(|(a, b)| closest_point_on_segment(point, a, b))
+            .min_by(|a, b| {
+                a.distance_squared(point)
+                    .total_cmp(&b.distance_squared(point))
+            })
+    }
+
+    fn resolve(
+        &self,
+        position: &mut Vec2,
+        velocity: &mut Vec2,
+        material: WallMaterial,
+        params: &SimulationParams,
+    ) -> bool {
+        let radius = params.radius;
+
+        if position.x < self.min.x - radius
+            || position.y < self.min.y - radius
+            || position.x > self.max.x + radius
+            || position.y > self.max.y + radius
+        {
+            return false;
+        }
+
+        let Some(closest) = self.closest_point(*position) else {
+            return false;
+        };
+
+        let offset = *position - closest;
+        let distance = offset.length();
+
+        if distance <= f32::EPSILON {
+            return false;
+        }
+
+        let normal = if self.contains(*position) {
+            -offset / distance
+        } else if distance < radius {
+            offset / distance
+        } else {
+            return false;
+        };
+
+        *position = closest + normal * radius;
+
+        *velocity = wall_response(
+            *velocity,
+            Vec2::ZERO,
+            normal,
+            material.restitution(params),
+            material.friction(params),
+        );
+
+        true
+    }
+
+    fn confine(
+        &self,
+        position: &mut Vec2,
+        velocity: &mut Vec2,
+        material: WallMaterial,
+        params: &SimulationParams,
+    ) -> bool {
+        let Some(closest) = self.closest_point(*position) else {
+            return false;
+        };
+
+        let offset = closest - *position;
+        let distance = offset.length();
+        let inside = self.contains(*position);
+
+        if (inside && distance >= params.radius) || distance <= f32::EPSILON {
+            return false;
+        }
+
+        let normal = if inside {
+            -offset / distance
+        } else {
+            offset / distance
+        };
+
+        *position = closest + normal * params.radius;
+
+        *velocity = wall_response(
+            *velocity,
+            Vec2::ZERO,
+            normal,
+            material.restitution(params),
+            material.friction(params),
+        );
+
+        true
+    }
+}
+
+// Closed outline replacing the rectangular domain bounds: particles are kept inside it.
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct Container(pub Obstacle);
+
+impl Container {
+    pub fn confine(
+        &self,
+        position: &mut Vec2,
+        velocity: &mut Vec2,
+        material: WallMaterial,
+        params: &SimulationParams,
+    ) -> bool {
+        self.0.confine(position, velocity, material, params)
+    }
+}
+
+fn closest_point_on_segment(point: Vec2, a: Vec2, b: Vec2) -> Vec2 {
+    let segment = b - a;
+    let length_squared = segment.length_squared();
+
+    if length_squared <= f32::EPSILON {
+        return a;
+    }
+
+    let t = ((point - a).dot(segment) / length_squared).clamp(0.0, 1.0);
+    a + segment * t
+}
+
+pub struct ObstaclePlugin;
+
+impl Plugin for ObstaclePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Obstacle>()
+            .register_type::<Container>()
+            .add_systems(
+                SIMULATION_SCHEDULE,
+                obstacle_collision_system.in_set(SimulationSet::Collision),
+            );
+
+        if app.is_plugin_added::<GizmoPlugin>() {
+            app.add_systems(Update, draw_obstacles_system);
+        }
+    }
+}
+
+fn obstacle_collision_system(
+    params: Res<SimulationParams>,
+    obstacles: Query<(&Obstacle, Option<&WallMaterial>)>,
+    mut particles: Query<(&mut Transform, &mut Velocity)>,
+) {
+    for (obstacle, material) in obstacles.iter() {
+        let material = material.copied().unwrap_or_default();
+
+        for (mut transform, mut velocity) in particles.iter_mut() {
+            let mut position = transform.translation.truncate();
+            let mut planar_velocity = velocity.0.truncate();
+
+            if obstacle.resolve(&mut position, &mut planar_velocity, material, &params) {
+                transform.translation = position.extend(transform.translation.z);
+                velocity.0 = planar_velocity.extend(velocity.0.z);
+            }
+        }
+    }
+}
+
+fn draw_obstacles_system(
+    mut gizmos: Gizmos,
+    obstacles: Query<&Obstacle>,
+    containers: Query<&Container>,
+) {
+    for obstacle in obstacles
+        .iter()
+        .chain(containers.iter().map(|container| &container.0))
+    {
+        let closing_point = obstacle
+            .closed
+            .then(|| obstacle.points.first().copied())
+            .flatten();
+
+        gizmos.linestrip_2d(
+            obstacle.points.iter().copied().chain(closing_point),
+            Color::srgb(0.8, 0.8, 0.8),
+        );
+    }
+}
@@ -0,0 +1,286 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{cli::Args, ron_asset::RonAssetLoader};
+
+// Smallest length or mass an edit may set, as the solver divides by all of them.
+const MIN_SCALE: f32 = 1e-3;
+
+#[derive(Resource, Asset, Reflect, Deserialize, Serialize, Clone, PartialEq)]
+#[reflect(Resource)]
+#[serde(default)]
+pub struct SimulationParams {
+    pub radius: f32,
+    pub mass: f32,
+    pub smoothing_radius: f32,
+    pub particle_spacing: f32,
+    pub target_density: f32,
+    pub equation_of_state: EquationOfState,
+    pub pressure_multiplier: f32,
+    /// Strength of the short-range repulsion that keeps particles from clumping, 0 to disable
+    pub artificial_pressure: f32,
+    /// Delta-SPH density diffusion coefficient δ for the explicit solver, 0 to disable
+    pub density_diffusion: f32,
+    /// Sets the Tait stiffness, ten times the free-fall speed over the domain height when unset
+    pub speed_of_sound: Option<f32>,
+    /// Most constraint iterations per step for the iterative solvers
+    pub solver_iterations: u32,
+    /// Mean relative density error at which the iterative solvers stop early
+    pub solver_tolerance: f32,
+    /// Simulation steps per frame, each over an equal share of the frame's time
+    pub substeps: u32,
+    /// Most neighbors a particle interacts with, the nearest ones, 0 for no limit
+    pub max_neighbors: u32,
+    pub gravity: f32,
+    /// Direction gravity pulls in, in degrees counterclockwise from straight down
+    pub gravity_angle: f32,
+    pub damping_factor: f32,
+    pub restitution: f32,
+    pub wall_restitution: f32,
+    pub wall_friction: f32,
+    pub surface_tension: f32,
+    /// Equilibrium contact angle in degrees, from 0 (fully wetting) to 180 (non-wetting)
+    pub contact_angle: f32,
+    pub periodic_x: bool,
+    pub periodic_y: bool,
+    /// Particles moving faster than this are treated as unstable and quarantined
+    pub max_speed: f32,
+    pub turbulence: Turbulence,
+    /// Derives mass, spacing, density, gravity and stiffness from real units when set
+    pub units: Option<PhysicalUnits>,
+}
+
+#[derive(Reflect, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Debug)]
+pub enum EquationOfState {
+    /// Pressure proportional to the density error, scaled by `pressure_multiplier`
+    #[default]
+    Linear,
+    /// Tait's equation, stiff near the target density
+    Tait,
+}
+
+// Curl noise stirring the fluid, to break up unnaturally smooth flow in calm scenes. Being
+// divergence free, it swirls particles around without compressing them.
+#[derive(Reflect, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct Turbulence {
+    /// Typical acceleration from the noise, 0 to disable
+    pub amplitude: f32,
+    /// Noise features per unit length, the inverse of the eddy size
+    pub frequency: f32,
+    /// Rate at which the noise pattern changes over time
+    pub evolution: f32,
+}
+
+impl Default for Turbulence {
+    fn default() -> Self {
+        Self {
+            amplitude: 0.0,
+            frequency: 0.02,
+            evolution: 0.5,
+        }
+    }
+}
+
+// Fluid described in SI units, for 2D slices one meter deep. Lengths are multiplied by
+// `scale` to get simulation units, so scenes and the domain keep their usual sizes.
+#[derive(Reflect, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct PhysicalUnits {
+    /// Rest density in kg/m³
+    pub rest_density: f32,
+    /// Particle spacing in meters
+    pub particle_spacing: f32,
+    /// Gravitational acceleration in m/s²
+    pub gravity: f32,
+    /// Speed of sound in m/s, setting the pressure stiffness
+    pub speed_of_sound: f32,
+    /// Simulation units per meter
+    pub scale: f32,
+}
+
+impl Default for PhysicalUnits {
+    fn default() -> Self {
+        Self {
+            rest_density: 1000.0,
+            particle_spacing: 0.02,
+            gravity: 9.81,
+            speed_of_sound: 2.0,
+            scale: 350.0,
+        }
+    }
+}
+
+impl Default for SimulationParams {
+    fn default() -> Self {
+        Self {
+            radius: 1.0,
+            mass: 50.0,
+            smoothing_radius: 7.0,
+            particle_spacing: 7.0,
+            target_density: 5000.0,
+            equation_of_state: EquationOfState::Linear,
+            pressure_multiplier: 2.0,
+            artificial_pressure: 0.1,
+            density_diffusion: 0.1,
+            speed_of_sound: None,
+            solver_iterations: 4,
+            solver_tolerance: 0.01,
+            substeps: 1,
+            max_neighbors: 0,
+            gravity: 10.0,
+            gravity_angle: 0.0,
+            damping_factor: 0.99,
+            restitution: 0.01,
+            wall_restitution: 0.99,
+            wall_friction: 0.0,
+            surface_tension: 0.0,
+            contact_angle: 90.0,
+            periodic_x: false,
+            periodic_y: false,
+            max_speed: 1000.0,
+            turbulence: Turbulence::default(),
+            units: None,
+        }
+    }
+}
+
+impl SimulationParams {
+    // Young-Dupré: cos θ = 2 adhesion / cohesion - 1.
+    pub fn adhesion(&self, contact_angle: f32) -> f32 {
+        self.surface_tension * (1.0 + contact_angle.to_radians().cos()) / 2.0
+    }
+
+    pub fn gravity_vector(&self) -> Vec2 {
+        Vec2::from_angle(self.gravity_angle.to_radians()).rotate(Vec2::NEG_Y) * self.gravity
+    }
+
+    // Internal parameters for `units`, keeping the smoothing and particle radii in
+    // proportion to the spacing. Each particle carries the mass of its share of the slice.
+    pub fn resolved(&self) -> Self {
+        let Some(units) = self.units else {
+            return self.clone();
+        };
+
+        let spacing = units.particle_spacing * units.scale;
+        let spacing_ratio = spacing / self.particle_spacing;
+        let speed_of_sound = units.speed_of_sound * units.scale;
+
+        Self {
+            radius: self.radius * spacing_ratio,
+            mass: units.rest_density * units.particle_spacing.powi(2),
+            smoothing_radius: self.smoothing_radius * spacing_ratio,
+            particle_spacing: spacing,
+            target_density: units.rest_density / units.scale.powi(2),
+            equation_of_state: EquationOfState::Tait,
+            speed_of_sound: Some(speed_of_sound),
+            gravity: units.gravity * units.scale,
+            ..self.clone()
+        }
+    }
+}
+
+pub struct ParamsPlugin;
+
+impl Plugin for ParamsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SimulationParams>()
+            .register_type::<SimulationParams>()
+            .init_asset::<SimulationParams>()
+            .register_asset_loader(RonAssetLoader::<SimulationParams>::new(&["params.ron"]))
+            .add_systems(Startup, load_config)
+            .add_systems(
+                PreUpdate,
+                (
+                    apply_config_system,
+                    params_edit_system.run_if(resource_changed::<SimulationParams>),
+                )
+                    .chain(),
+            );
+    }
+}
+
+#[derive(Resource)]
+pub struct ConfigHandle(Handle<SimulationParams>);
+
+// What a freshly loaded scene starts from, the `--config` parameters once they have loaded.
+pub fn base_params(
+    config: Option<&ConfigHandle>,
+    configs: &Assets<SimulationParams>,
+) -> SimulationParams {
+    config
+        .and_then(|config| configs.get(&config.0))
+        .map_or_else(SimulationParams::default, SimulationParams::resolved)
+}
+
+fn load_config(mut commands: Commands, args: Res<Args>, asset_server: Res<AssetServer>) {
+    if let Some(path) = &args.config {
+        commands.insert_resource(ConfigHandle(asset_server.load(path.clone())));
+    }
+}
+
+fn apply_config_system(
+    mut events: EventReader<AssetEvent<SimulationParams>>,
+    handle: Option<Res<ConfigHandle>>,
+    configs: Res<Assets<SimulationParams>>,
+    mut params: ResMut<SimulationParams>,
+) {
+    let Some(handle) = handle else {
+        return;
+    };
+
+    for event in events.read() {
+        let (AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id }) = event
+        else {
+            continue;
+        };
+
+        if *id != handle.0.id() {
+            continue;
+        }
+
+        if let Some(config) = configs.get(*id) {
+            *params = config.resolved();
+            info!("Applied simulation parameters");
+        }
+    }
+}
+
+// Keeps hand-edited parameters, e.g. from the inspector, usable: lengths and mass stay
+// positive, and changing `units` re-derives everything that depends on them.
+fn params_edit_system(
+    mut params: ResMut<SimulationParams>,
+    mut previous_units: Local<Option<PhysicalUnits>>,
+) {
+    if params.units != *previous_units {
+        *previous_units = params.units;
+        let resolved = params.resolved();
+        params.set_if_neq(resolved);
+    }
+
+    let invalid = |value: f32| !value.is_finite() || value < MIN_SCALE;
+    if ![
+        params.radius,
+        params.mass,
+        params.smoothing_radius,
+        params.particle_spacing,
+    ]
+    .into_iter()
+    .any(invalid)
+    {
+        return;
+    }
+
+    let params = params.as_mut();
+    for (name, value) in [
+        ("radius", &mut params.radius),
+        ("mass", &mut params.mass),
+        ("smoothing_radius", &mut params.smoothing_radius),
+        ("particle_spacing", &mut params.particle_spacing),
+    ] {
+        if invalid(*value) {
+            warn!("{name} must be positive, clamping {value} to {MIN_SCALE}");
+            *value = MIN_SCALE;
+        }
+    }
+}
@@ -0,0 +1,312 @@
+use bevy::{
+    diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic},
+    prelude::*,
+};
+
+use crate::{
+    artificial_pressure,
+    budget::ParticleMass,
+    cap_neighbors,
+    cell_map::CellMap,
+    cli::Solver,
+    dilation::TimeScale,
+    domain::Domain,
+    groups::{GroupRules, ParticleGroup},
+    params::SimulationParams,
+    precision::{real, real_vec, to_f32, to_vec3, Real, RealVec3},
+    smoothing_kernel, smoothing_kernel_derivative, velocity_system, Position, SimulationSet,
+    SpatialGrid, Velocity, SIMULATION_SCHEDULE,
+};
+
+pub const SOLVER_ITERATIONS: DiagnosticPath = DiagnosticPath::const_new("solver/iterations");
+pub const SOLVER_RESIDUAL: DiagnosticPath = DiagnosticPath::const_new("solver/residual");
+
+// Softens each constraint relative to a full neighborhood's gradient, keeping sparse
+// particles from being thrown apart.
+const RELAXATION: Real = 0.01;
+// Seconds between warnings while the solver keeps missing its tolerance.
+const WARNING_INTERVAL: f32 = 5.0;
+
+/// Sent for each step the iterative solver ended above `SimulationParams::solver_tolerance`.
+#[derive(Event, Clone, Debug)]
+pub struct SolverNotConverged {
+    pub iterations: u32,
+    /// Mean relative density error left over
+    pub residual: f32,
+}
+
+// Position-based fluids (Macklin & Müller 2013): particles are moved ahead by their velocity
+// and gravity, then pushed apart until no neighborhood is denser than the target density.
+// Their velocity becomes the distance covered over the step, which `update_system` then
+// integrates.
+pub struct PbfPlugin;
+
+impl Plugin for PbfPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SolverNotConverged>()
+            .register_diagnostic(Diagnostic::new(SOLVER_ITERATIONS))
+            .register_diagnostic(Diagnostic::new(SOLVER_RESIDUAL))
+            .add_systems(
+                SIMULATION_SCHEDULE,
+                (
+                    pbf_system
+                        .in_set(SimulationSet::Forces)
+                        .before(velocity_system)
+                        .run_if(resource_equals(Solver::Pbf)),
+                    report_convergence_system
+                        .after(SimulationSet::Forces)
+                        .run_if(resource_equals(Solver::Pbf)),
+                ),
+            );
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn pbf_system(
+    time: Res<Time>,
+    params: Res<SimulationParams>,
+    domains: Query<&Domain>,
+    rules: Res<GroupRules>,
+    mut particles: Query<(
+        &Position,
+        &mut Velocity,
+        &TimeScale,
+        Option<&ParticleGroup>,
+        &ParticleMass,
+    )>,
+    frozen: Query<(&Position, Option<&ParticleGroup>, &ParticleMass), Without<Velocity>>,
+    mut diagnostics: Diagnostics,
+    mut failures: EventWriter<SolverNotConverged>,
+) {
+    let delta_time = real(time.delta_secs());
+    if delta_time <= 0.0 {
+        return;
+    }
+
+    let grid = SpatialGrid::new(&params, domains.single());
+    let radius = real(params.smoothing_radius);
+    let mass = real(params.mass);
+    let target_density = real(params.target_density);
+    let gravity = real_vec(params.gravity_vector().extend(0.0));
+
+    // Frozen particles are listed after the moving ones, taking part in their densities but
+    // never corrected themselves.
+    let moving = particles.iter().count();
+    let (origins, groups): (Vec<RealVec3>, Vec<ParticleGroup>) = particles
+        .iter()
+        .map(|(position, _, _, group, _)| (position, group))
+        .chain(frozen.iter().map(|(position, group, _)| (position, group)))
+        .map(|(position, group)| (position.0, group.copied().unwrap_or_default()))
+        .unzip();
+    let masses: Vec<Real> = particles
+        .iter()
+        .map(|(_, _, _, _, particle_mass)| particle_mass)
+        .chain(frozen.iter().map(|(_, _, particle_mass)| particle_mass))
+        .map(|particle_mass| mass * real(particle_mass.0))
+        .collect();
+    // Each particle looks ahead by its own step, shorter or longer in dilated regions.
+    let steps: Vec<Real> = particles
+        .iter()
+        .map(|(_, _, time_scale, _, _)| delta_time * real(time_scale.0))
+        .collect();
+    let mut predicted: Vec<RealVec3> = particles
+        .iter()
+        .zip(&steps)
+        .map(|((position, velocity, _, _, _), &step)| {
+            position.0 + (real_vec(velocity.0) + gravity * step) * step
+        })
+        .chain(origins[moving..].iter().copied())
+        .collect();
+
+    // Neighborhoods stay fixed over the step, found where the particles are headed.
+    let mut spatial_hash = CellMap::new(&grid);
+    for (index, &position) in predicted.iter().enumerate() {
+        spatial_hash.push(grid.cell(to_vec3(position)), index);
+    }
+
+    let neighbors: Vec<Vec<usize>> = predicted
+        .iter()
+        .enumerate()
+        .map(|(index, &position)| {
+            let mut list = grid
+                .neighbor_cells(grid.cell(to_vec3(position)))
+                .filter_map(|cell| spatial_hash.get(&cell))
+                .flatten()
+                .copied()
+                .filter(|&neighbor| {
+                    neighbor == index || rules.pushes(groups[neighbor], groups[index])
+                })
+                .map(|neighbor| {
+                    let distance = grid.precise_offset(position, predicted[neighbor]).length();
+                    (neighbor, distance)
+                })
+                .filter(|&(_, distance)| distance < radius)
+                .collect::<Vec<_>>();
+            cap_neighbors(&mut list, &params, true, |&(_, distance)| distance);
+            list.into_iter().map(|(neighbor, _)| neighbor).collect()
+        })
+        .collect();
+
+    let epsilon = RELAXATION / (radius * radius);
+    let mut iterations = 0;
+
+    let residual = loop {
+        // Gradient of the neighbor's kernel with respect to this particle's position.
+        let gradient = |index: usize, neighbor: usize, positions: &[RealVec3]| {
+            let offset = grid.precise_offset(positions[neighbor], positions[index]);
+            let distance = offset.length();
+            if distance <= Real::EPSILON {
+                RealVec3::ZERO
+            } else {
+                offset / distance * smoothing_kernel_derivative(radius, distance)
+            }
+        };
+
+        let errors: Vec<Real> = neighbors
+            .iter()
+            .enumerate()
+            .map(|(index, list)| {
+                let density: Real = list
+                    .iter()
+                    .map(|&neighbor| {
+                        let distance = grid
+                            .precise_offset(predicted[index], predicted[neighbor])
+                            .length();
+                        masses[neighbor] * smoothing_kernel(radius, distance)
+                    })
+                    .sum();
+
+                // Only compression is corrected, so free surfaces don't pull together.
+                (density / target_density - 1.0).max(0.0)
+            })
+            .collect();
+
+        let residual = errors[..moving].iter().sum::<Real>() / moving.max(1) as Real;
+
+        if residual <= real(params.solver_tolerance) || iterations >= params.solver_iterations {
+            break residual;
+        }
+
+        let scale = |neighbor: usize| masses[neighbor] / target_density;
+        let lambdas: Vec<Real> = neighbors
+            .iter()
+            .enumerate()
+            .map(|(index, list)| {
+                if index >= moving || errors[index] <= 0.0 {
+                    return 0.0;
+                }
+
+                let mut own = RealVec3::ZERO;
+                let mut sum = 0.0;
+                for &neighbor in list.iter().filter(|&&neighbor| neighbor != index) {
+                    let gradient = gradient(index, neighbor, &predicted) * scale(neighbor);
+                    own += gradient;
+                    sum += gradient.length_squared();
+                }
+
+                -errors[index] / (sum + own.length_squared() + epsilon)
+            })
+            .collect();
+
+        let corrections: Vec<RealVec3> = neighbors[..moving]
+            .iter()
+            .enumerate()
+            .map(|(index, list)| {
+                list.iter()
+                    .filter(|&&neighbor| neighbor != index)
+                    .map(|&neighbor| {
+                        let distance = grid
+                            .precise_offset(predicted[index], predicted[neighbor])
+                            .length();
+                        let correction = -artificial_pressure(radius, distance, &params);
+
+                        gradient(index, neighbor, &predicted)
+                            * (scale(neighbor) * (lambdas[index] + lambdas[neighbor] + correction))
+                    })
+                    .sum()
+            })
+            .collect();
+
+        for (position, correction) in predicted.iter_mut().zip(corrections) {
+            *position += correction;
+        }
+
+        iterations += 1;
+    };
+
+    for ((((_, mut velocity, _, _, _), origin), target), step) in
+        particles.iter_mut().zip(origins).zip(predicted).zip(steps)
+    {
+        // A frozen particle keeps its velocity for when time starts again.
+        if step > Real::EPSILON {
+            velocity.0 = to_vec3((target - origin) / step) * params.damping_factor;
+        }
+    }
+
+    diagnostics.add_measurement(&SOLVER_ITERATIONS, || f64::from(iterations));
+    diagnostics.add_measurement(&SOLVER_RESIDUAL, || f64::from(to_f32(residual)));
+
+    if residual > real(params.solver_tolerance) {
+        failures.send(SolverNotConverged {
+            iterations,
+            residual: to_f32(residual),
+        });
+    }
+}
+
+#[derive(Default)]
+struct ConvergenceReport {
+    failing: bool,
+    /// Time of the last warning
+    warned_at: f32,
+    /// Steps that fell short since the last warning
+    missed: u32,
+}
+
+// Warns when the solver starts falling short of the tolerance and then at most every
+// `WARNING_INTERVAL` while it keeps doing so, as splashy scenes miss it on many steps in a
+// row, and notes when it converges again.
+fn report_convergence_system(
+    time: Res<Time>,
+    mut failures: EventReader<SolverNotConverged>,
+    mut report: Local<ConvergenceReport>,
+) {
+    let now = time.elapsed_secs();
+    let worst = failures
+        .read()
+        .max_by(|a, b| a.residual.total_cmp(&b.residual));
+
+    let Some(failure) = worst else {
+        if report.failing {
+            info!("Solver converging again");
+            *report = ConvergenceReport::default();
+        }
+        return;
+    };
+
+    report.missed += 1;
+    if report.failing && now - report.warned_at < WARNING_INTERVAL {
+        return;
+    }
+
+    if report.failing {
+        warn!(
+            "Solver still short of its tolerance, on {} steps since the last warning, the last \
+             stopping after {} iterations with a density error of {:.2}%",
+            report.missed,
+            failure.iterations,
+            failure.residual * 100.0
+        );
+    } else {
+        warn!(
+            "Solver stopped after {} iterations with a density error of {:.2}%",
+            failure.iterations,
+            failure.residual * 100.0
+        );
+    }
+    *report = ConvergenceReport {
+        failing: true,
+        warned_at: now,
+        missed: 0,
+    };
+}
@@ -0,0 +1,132 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+use bevy::prelude::*;
+
+use crate::{cli::Args, Velocity};
+
+const SAMPLE_COUNT_OFFSET: u64 = 28;
+
+pub struct PointCachePlugin;
+
+impl Plugin for PointCachePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PostUpdate, record_point_cache_system);
+    }
+}
+
+// Blender-compatible PC2 cache. The point count is fixed by the particles alive when
+// recording starts; particles despawned later keep their last position. A companion
+// OBJ holding the initial positions is written next to it to attach the cache to.
+struct PointCache {
+    file: File,
+    entities: Vec<Entity>,
+    positions: Vec<Vec3>,
+    samples: i32,
+}
+
+impl PointCache {
+    fn create(path: &Path, particles: Vec<(Entity, Vec3)>) -> io::Result<Self> {
+        let (entities, positions): (Vec<_>, Vec<_>) = particles.into_iter().unzip();
+
+        let mut file = File::create(path)?;
+        file.write_all(b"POINTCACHE2\0")?;
+        file.write_all(&1i32.to_le_bytes())?;
+        file.write_all(&(positions.len() as i32).to_le_bytes())?;
+        file.write_all(&0.0f32.to_le_bytes())?;
+        file.write_all(&1.0f32.to_le_bytes())?;
+        file.write_all(&0i32.to_le_bytes())?;
+
+        let mut mesh = BufWriter::new(File::create(path.with_extension("obj"))?);
+        for &position in &positions {
+            let [x, y, z] = to_blender(position);
+            writeln!(mesh, "v {x} {y} {z}")?;
+        }
+        mesh.flush()?;
+
+        Ok(Self {
+            file,
+            entities,
+            positions,
+            samples: 0,
+        })
+    }
+
+    fn record(
+        &mut self,
+        particles: &Query<(Entity, &Transform), With<Velocity>>,
+    ) -> io::Result<()> {
+        let mut sample = Vec::with_capacity(self.positions.len() * 12);
+
+        for (entity, position) in self.entities.iter().zip(self.positions.iter_mut()) {
+            if let Ok((_, transform)) = particles.get(*entity) {
+                *position = transform.translation;
+            }
+
+            for coordinate in to_blender(*position) {
+                sample.extend_from_slice(&coordinate.to_le_bytes());
+            }
+        }
+
+        self.file.write_all(&sample)?;
+        self.samples += 1;
+
+        self.file.seek(SeekFrom::Start(SAMPLE_COUNT_OFFSET))?;
+        self.file.write_all(&self.samples.to_le_bytes())?;
+        self.file.seek(SeekFrom::End(0))?;
+
+        Ok(())
+    }
+}
+
+fn to_blender(position: Vec3) -> [f32; 3] {
+    [position.x, -position.z, position.y]
+}
+
+fn record_point_cache_system(
+    args: Res<Args>,
+    particles: Query<(Entity, &Transform), With<Velocity>>,
+    mut cache: Local<Option<PointCache>>,
+    mut failed: Local<bool>,
+) {
+    let Some(path) = &args.point_cache else {
+        return;
+    };
+
+    if *failed {
+        return;
+    }
+
+    if cache.is_none() {
+        let mut particles: Vec<_> = particles
+            .iter()
+            .map(|(entity, transform)| (entity, transform.translation))
+            .collect();
+
+        if particles.is_empty() {
+            return;
+        }
+
+        particles.sort_by_key(|&(entity, _)| entity);
+
+        match PointCache::create(path, particles) {
+            Ok(created) => *cache = Some(created),
+            Err(error) => {
+                error!("Failed to create point cache {}: {error}", path.display());
+                *failed = true;
+                return;
+            }
+        }
+    }
+
+    if let Some(recording) = cache.as_mut() {
+        if let Err(error) = recording.record(&particles) {
+            error!("Failed to write point cache {}: {error}", path.display());
+            *failed = true;
+            *cache = None;
+        }
+    }
+}
@@ -0,0 +1,61 @@
+// Scalar and vector types of the solver's internal math. The `f64` feature widens them to
+// double precision; `Transform` and everything rendered stays `f32`.
+
+#[cfg(feature = "f64")]
+use bevy::math::DVec3;
+use bevy::prelude::*;
+
+#[cfg(not(feature = "f64"))]
+pub use std::f32::consts::PI;
+#[cfg(feature = "f64")]
+pub use std::f64::consts::PI;
+
+#[cfg(feature = "f64")]
+pub type Real = f64;
+#[cfg(not(feature = "f64"))]
+pub type Real = f32;
+
+#[cfg(feature = "f64")]
+pub type RealVec3 = DVec3;
+#[cfg(not(feature = "f64"))]
+pub type RealVec3 = Vec3;
+
+#[cfg(feature = "f64")]
+pub fn real(value: f32) -> Real {
+    value.into()
+}
+
+#[cfg(not(feature = "f64"))]
+pub fn real(value: f32) -> Real {
+    value
+}
+
+#[cfg(feature = "f64")]
+pub fn real_vec(vector: Vec3) -> RealVec3 {
+    vector.as_dvec3()
+}
+
+#[cfg(not(feature = "f64"))]
+pub fn real_vec(vector: Vec3) -> RealVec3 {
+    vector
+}
+
+#[cfg(feature = "f64")]
+pub fn to_f32(value: Real) -> f32 {
+    value as f32
+}
+
+#[cfg(not(feature = "f64"))]
+pub fn to_f32(value: Real) -> f32 {
+    value
+}
+
+#[cfg(feature = "f64")]
+pub fn to_vec3(vector: RealVec3) -> Vec3 {
+    vector.as_vec3()
+}
+
+#[cfg(not(feature = "f64"))]
+pub fn to_vec3(vector: RealVec3) -> Vec3 {
+    vector
+}
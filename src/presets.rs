@@ -0,0 +1,178 @@
+use bevy::prelude::*;
+use bevy_inspector_egui::bevy_egui::{egui, EguiContexts};
+use clap::ValueEnum;
+
+use crate::{
+    cli::Args,
+    flow::{Drain, Emitter},
+    scene::LoadScene,
+    Velocity,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, ValueEnum)]
+pub enum Preset {
+    Basin,
+    Bowl,
+    Piston,
+    Drum,
+    Channel,
+    Hourglass,
+    Fountain,
+    /// Faucet filling a basin that drains through its floor
+    Faucet,
+    /// Collapse of a water column (Martin & Moyce 1952)
+    DamBreak,
+    /// Two water columns collapsing into each other
+    DoubleDamBreak,
+    /// Droplet falling into a shallow pool
+    DropletSplash,
+    /// Fluid rising up narrow tubes that dip into a pool
+    Capillary,
+    /// Fluid running down noise-generated hills
+    Hills,
+    /// Fluid pouring down a hillside into a valley
+    Valley,
+    /// Two fluids that pass through each other, with passive tracers
+    TwoFluids,
+    /// Scripted sequence of opening a gate, tilting gravity and starting a faucet
+    Gate,
+    /// Droplets on a wetting and a non-wetting shelf
+    Wetting,
+    /// Droplet splash slowed down around the point of impact
+    BulletTime,
+    /// Rain falling into a pool that drains through its floor
+    Rain,
+    /// Stream poured into a pipe that splits in two
+    Plumbing,
+    /// Soft bodies dropped into a tank of water
+    Jelly,
+    /// Boat steered with I, J, K and L on a pool hit by a wave
+    Boat,
+    /// Tank sloshed at resonance by a wave maker, watched by wave gauges
+    WaveTank,
+    /// Boat on a shallow water lake beside a particle pool
+    Lake,
+    /// Splash in a small particle domain coupled to a wide shallow water lake
+    Hybrid,
+}
+
+impl Preset {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Basin => "Basin",
+            Self::Bowl => "Bowl",
+            Self::Piston => "Piston",
+            Self::Drum => "Rotating drum",
+            Self::Channel => "Channel flow",
+            Self::Hourglass => "Hourglass",
+            Self::Fountain => "Fountain",
+            Self::Faucet => "Faucet and sink",
+            Self::DamBreak => "Dam break",
+            Self::DoubleDamBreak => "Double dam break",
+            Self::DropletSplash => "Droplet splash",
+            Self::Capillary => "Capillary rise",
+            Self::Hills => "Hills",
+            Self::Valley => "Valley",
+            Self::TwoFluids => "Two fluids",
+            Self::Gate => "Scripted gate",
+            Self::Wetting => "Wetting",
+            Self::BulletTime => "Bullet time",
+            Self::Rain => "Rain",
+            Self::Plumbing => "Plumbing",
+            Self::Jelly => "Jelly",
+            Self::Boat => "Boat",
+            Self::WaveTank => "Wave tank",
+            Self::Lake => "Lake",
+            Self::Hybrid => "Hybrid lake",
+        }
+    }
+
+    pub fn path(self) -> &'static str {
+        match self {
+            Self::Basin => "scenes/basin.scene.ron",
+            Self::Bowl => "scenes/bowl.scene.ron",
+            Self::Piston => "scenes/piston.scene.ron",
+            Self::Drum => "scenes/drum.scene.ron",
+            Self::Channel => "scenes/channel.scene.ron",
+            Self::Hourglass => "scenes/hourglass.scene.ron",
+            Self::Fountain => "scenes/fountain.scene.ron",
+            Self::Faucet => "scenes/faucet.scene.ron",
+            Self::DamBreak => "scenes/dam_break.scene.ron",
+            Self::DoubleDamBreak => "scenes/double_dam_break.scene.ron",
+            Self::DropletSplash => "scenes/droplet_splash.scene.ron",
+            Self::Capillary => "scenes/capillary.scene.ron",
+            Self::Hills => "scenes/hills.scene.ron",
+            Self::Valley => "scenes/valley.scene.ron",
+            Self::TwoFluids => "scenes/two_fluids.scene.ron",
+            Self::Gate => "scenes/gate.scene.ron",
+            Self::Wetting => "scenes/wetting.scene.ron",
+            Self::BulletTime => "scenes/bullet_time.scene.ron",
+            Self::Rain => "scenes/rain.scene.ron",
+            Self::Plumbing => "scenes/plumbing.scene.ron",
+            Self::Jelly => "scenes/jelly.scene.ron",
+            Self::Boat => "scenes/boat.scene.ron",
+            Self::WaveTank => "scenes/wave_tank.scene.ron",
+            Self::Lake => "scenes/lake.scene.ron",
+            Self::Hybrid => "scenes/hybrid.scene.ron",
+        }
+    }
+}
+
+pub struct PresetsPlugin;
+
+impl Plugin for PresetsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, preset_menu_system);
+    }
+}
+
+// Lists the bundled scenes, the one loaded last highlighted and each described on hover.
+fn preset_menu_system(
+    mut contexts: EguiContexts,
+    args: Res<Args>,
+    mut current: Local<Option<Option<Preset>>>,
+    mut events: EventWriter<LoadScene>,
+    particles: Query<(), With<Velocity>>,
+    mut emitters: Query<&mut Emitter>,
+    mut drains: Query<&mut Drain>,
+) {
+    let current = current.get_or_insert(args.preset);
+
+    egui::Window::new("Presets").show(contexts.ctx_mut(), |ui| {
+        for &preset in Preset::value_variants() {
+            let mut button = ui.selectable_label(*current == Some(preset), preset.label());
+            if let Some(help) = preset
+                .to_possible_value()
+                .and_then(|value| value.get_help().cloned())
+            {
+                button = button.on_hover_text(help.to_string());
+            }
+
+            if button.clicked() {
+                *current = Some(preset);
+                events.send(LoadScene(preset.path().to_string()));
+            }
+        }
+
+        ui.separator();
+        ui.label(format!("Particles: {}", particles.iter().count()));
+
+        if !emitters.is_empty() {
+            let mut enabled = emitters.iter().any(|emitter| emitter.enabled);
+            if ui.checkbox(&mut enabled, "Emitters").changed() {
+                for mut emitter in emitters.iter_mut() {
+                    emitter.enabled = enabled;
+                }
+            }
+        }
+
+        if !drains.is_empty() {
+            let mut enabled = drains.iter().any(|drain| drain.enabled);
+            if ui.checkbox(&mut enabled, "Drains").changed() {
+                for mut drain in drains.iter_mut() {
+                    drain.enabled = enabled;
+                }
+            }
+        }
+    });
+}
@@ -0,0 +1,159 @@
+use bevy::prelude::*;
+use bevy_inspector_egui::bevy_egui::{egui, EguiContexts, EguiPlugin};
+use clap::ValueEnum;
+
+use crate::{
+    budget::ParticleBudget, params::SimulationParams, reconstruction::ShowOutline, ColorMode,
+};
+
+// Bundles of the settings that decide how heavy the simulation is to run and draw, picked
+// with `--quality` or from the Quality window. While a level is set it owns the solver
+// iterations, substeps and neighbor cap, so scenes and config files reloading the parameters
+// don't undo them.
+#[derive(Resource, Reflect, Clone, Copy, PartialEq, Eq, Debug, ValueEnum)]
+#[reflect(Resource)]
+pub enum Quality {
+    Low,
+    Medium,
+    High,
+    Ultra,
+}
+
+impl Quality {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Low => "Low",
+            Self::Medium => "Medium",
+            Self::High => "High",
+            Self::Ultra => "Ultra",
+        }
+    }
+
+    pub fn solver_iterations(self) -> u32 {
+        match self {
+            Self::Low => 2,
+            Self::Medium => 4,
+            Self::High => 8,
+            Self::Ultra => 16,
+        }
+    }
+
+    pub fn substeps(self) -> u32 {
+        match self {
+            Self::Low | Self::Medium => 1,
+            Self::High => 2,
+            Self::Ultra => 3,
+        }
+    }
+
+    /// Neighbors each particle interacts with, 0 for all of them
+    pub fn max_neighbors(self) -> u32 {
+        match self {
+            Self::Low => 8,
+            Self::Medium => 12,
+            Self::High => 24,
+            Self::Ultra => 0,
+        }
+    }
+
+    pub fn max_particles(self) -> usize {
+        match self {
+            Self::Low => 1_500,
+            Self::Medium => 4_000,
+            Self::High => 10_000,
+            Self::Ultra => 30_000,
+        }
+    }
+
+    /// Particle colors, left flat at the lowest level to skip recoloring every frame
+    pub fn color_mode(self) -> ColorMode {
+        match self {
+            Self::Low => ColorMode::Static,
+            _ => ColorMode::Density,
+        }
+    }
+
+    /// Whether the reconstructed outline is drawn over the particles
+    pub fn outline(self) -> bool {
+        matches!(self, Self::High | Self::Ultra)
+    }
+
+    fn apply(self, params: &mut SimulationParams) {
+        params.solver_iterations = self.solver_iterations();
+        params.substeps = self.substeps();
+        params.max_neighbors = self.max_neighbors();
+    }
+}
+
+pub struct QualityPlugin(pub Option<Quality>);
+
+impl Plugin for QualityPlugin {
+    fn build(&self, app: &mut App) {
+        if let Some(quality) = self.0 {
+            app.insert_resource(quality);
+        }
+
+        app.register_type::<Quality>().add_systems(
+            PreUpdate,
+            (
+                apply_quality_system.run_if(resource_changed::<Quality>),
+                apply_params_system.run_if(resource_changed::<SimulationParams>),
+            )
+                .chain()
+                .run_if(resource_exists::<Quality>),
+        );
+
+        if app.is_plugin_added::<EguiPlugin>() {
+            app.add_systems(Update, quality_menu_system);
+        }
+    }
+}
+
+fn apply_quality_system(
+    quality: Res<Quality>,
+    mut budget: ResMut<ParticleBudget>,
+    mut params: ResMut<SimulationParams>,
+    color_mode: Option<ResMut<ColorMode>>,
+    outline: Option<ResMut<ShowOutline>>,
+) {
+    quality.apply(&mut params);
+    budget.max = Some(quality.max_particles());
+
+    if let Some(mut color_mode) = color_mode {
+        *color_mode = quality.color_mode();
+    }
+
+    if let Some(mut outline) = outline {
+        outline.0 = quality.outline();
+    }
+
+    info!("Quality set to {}", quality.label());
+}
+
+fn apply_params_system(quality: Res<Quality>, mut params: ResMut<SimulationParams>) {
+    let mut owned = params.clone();
+    quality.apply(&mut owned);
+    params.set_if_neq(owned);
+}
+
+fn quality_menu_system(
+    mut commands: Commands,
+    mut contexts: EguiContexts,
+    quality: Option<Res<Quality>>,
+) {
+    let current = quality.as_deref().copied();
+
+    egui::Window::new("Quality").show(contexts.ctx_mut(), |ui| {
+        ui.horizontal(|ui| {
+            for &level in Quality::value_variants() {
+                if ui
+                    .selectable_label(current == Some(level), level.label())
+                    .clicked()
+                    && current != Some(level)
+                {
+                    commands.insert_resource(level);
+                }
+            }
+        });
+    });
+}
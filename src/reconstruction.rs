@@ -0,0 +1,205 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{
+    params::SimulationParams,
+    sdf::{draw_grid_contour, SdfGrid},
+    ParticleColor,
+};
+
+// Reach of each particle's kernel and of the neighborhood its shape is estimated from, in
+// particle spacings.
+const SUPPORT: f32 = 2.0;
+// Blend of each kernel's center toward the neighborhood mean, pulling stray surface
+// particles in.
+const CENTER_SMOOTHING: f32 = 0.9;
+// Neighbors needed to trust the covariance, below which the kernel stays round.
+const MIN_NEIGHBORS: usize = 6;
+// Largest ratio between the long and short axis of a kernel.
+const MAX_STRETCH: f32 = 4.0;
+// Field value traced as the surface, where a kernel's peak is 1.
+const ISO_LEVEL: f32 = 0.5;
+// Contour samples per particle spacing, and the most samples along either axis.
+const SAMPLES_PER_SPACING: f32 = 2.0;
+const MAX_SAMPLES: usize = 400;
+
+// Kernel shape of a particle for surface reconstruction (Yu & Turk 2013). The weighted
+// covariance of its neighbors gives an ellipse, flattened across thin sheets and round in
+// the bulk. Unlike the paper's fixed scale factor, the axes are normalized to keep the
+// kernel's area, so the fluid's outline doesn't grow or shrink with the stretching.
+#[derive(Component, Reflect, Clone, Copy, Default, Debug)]
+#[reflect(Component)]
+pub struct Anisotropy {
+    /// Smoothed kernel center
+    pub center: Vec2,
+    /// Maps offsets from the center into the unit kernel
+    pub transform: Mat2,
+}
+
+impl Anisotropy {
+    // Smooth bump falling from 1 at the center to 0 at the kernel's edge.
+    fn weight(&self, point: Vec2, support: f32) -> f32 {
+        let q = (self.transform * (point - self.center)).length_squared() / (support * support);
+        (1.0 - q).max(0.0).powi(3)
+    }
+}
+
+// Toggled with M: the fluid's outline traced with marching squares through the anisotropic
+// kernels.
+pub struct ReconstructionPlugin;
+
+impl Plugin for ReconstructionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ShowOutline>()
+            .register_type::<Anisotropy>()
+            .add_systems(
+                Update,
+                (
+                    toggle_outline_system,
+                    (anisotropy_system, draw_outline_system)
+                        .chain()
+                        .run_if(|show: Res<ShowOutline>| show.0),
+                )
+                    .chain(),
+            );
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct ShowOutline(pub bool);
+
+fn toggle_outline_system(input: Res<ButtonInput<KeyCode>>, mut show: ResMut<ShowOutline>) {
+    if input.just_pressed(KeyCode::KeyM) {
+        show.0 = !show.0;
+    }
+}
+
+fn bucket(position: Vec2, size: f32) -> IVec2 {
+    (position / size).floor().as_ivec2()
+}
+
+fn neighborhood(cell: IVec2) -> impl Iterator<Item = IVec2> {
+    (-1..=1).flat_map(move |dx| (-1..=1).map(move |dy| cell + IVec2::new(dx, dy)))
+}
+
+fn anisotropy_system(
+    params: Res<SimulationParams>,
+    mut particles: Query<(&Transform, &mut Anisotropy), With<ParticleColor>>,
+) {
+    let support = SUPPORT * params.particle_spacing;
+    let positions: Vec<Vec2> = particles
+        .iter()
+        .map(|(transform, _)| transform.translation.truncate())
+        .collect();
+
+    let mut buckets: HashMap<IVec2, Vec<usize>> = HashMap::new();
+    for (index, &position) in positions.iter().enumerate() {
+        buckets
+            .entry(bucket(position, support))
+            .or_default()
+            .push(index);
+    }
+
+    for ((_, mut anisotropy), &position) in particles.iter_mut().zip(&positions) {
+        let neighbors: Vec<(Vec2, f32)> = neighborhood(bucket(position, support))
+            .filter_map(|cell| buckets.get(&cell))
+            .flatten()
+            .filter_map(|&neighbor| {
+                let distance = positions[neighbor].distance(position);
+                (distance < support)
+                    .then(|| (positions[neighbor], 1.0 - (distance / support).powi(3)))
+            })
+            .collect();
+
+        let total: f32 = neighbors.iter().map(|&(_, weight)| weight).sum();
+        let mean = neighbors
+            .iter()
+            .map(|&(neighbor, weight)| neighbor * weight)
+            .sum::<Vec2>()
+            / total;
+
+        let (mut xx, mut xy, mut yy) = (0.0, 0.0, 0.0);
+        for &(neighbor, weight) in &neighbors {
+            let offset = neighbor - mean;
+            xx += weight * offset.x * offset.x;
+            xy += weight * offset.x * offset.y;
+            yy += weight * offset.y * offset.y;
+        }
+        let (xx, xy, yy) = (xx / total, xy / total, yy / total);
+
+        // Eigen decomposition of the symmetric 2x2 covariance.
+        let middle = (xx + yy) / 2.0;
+        let spread = (((xx - yy) / 2.0).powi(2) + xy * xy).sqrt();
+        let major = (middle + spread).sqrt();
+        let minor = (middle - spread).max(0.0).sqrt();
+
+        let transform = if neighbors.len() < MIN_NEIGHBORS || major <= f32::EPSILON {
+            Mat2::IDENTITY
+        } else {
+            let minor = minor.max(major / MAX_STRETCH);
+            let area = (major * minor).sqrt();
+            let rotation = Mat2::from_angle(0.5 * (2.0 * xy).atan2(xx - yy));
+            rotation
+                * Mat2::from_diagonal(Vec2::new(area / major, area / minor))
+                * rotation.transpose()
+        };
+
+        *anisotropy = Anisotropy {
+            center: position.lerp(mean, CENTER_SMOOTHING),
+            transform,
+        };
+    }
+}
+
+fn draw_outline_system(
+    mut gizmos: Gizmos,
+    params: Res<SimulationParams>,
+    particles: Query<&Anisotropy, With<ParticleColor>>,
+) {
+    let support = SUPPORT * params.particle_spacing;
+    // Stretched kernels reach out to their longest axis.
+    let reach = support * MAX_STRETCH.sqrt();
+
+    let Some((min, max)) = particles.iter().fold(None, |bounds, kernel| {
+        let (min, max) = bounds.unwrap_or((kernel.center, kernel.center));
+        Some((min.min(kernel.center), max.max(kernel.center)))
+    }) else {
+        return;
+    };
+
+    let (min, max) = (min - Vec2::splat(reach), max + Vec2::splat(reach));
+    let extent = max - min;
+    let cell_size = (params.particle_spacing / SAMPLES_PER_SPACING)
+        .max(extent.max_element() / (MAX_SAMPLES - 1) as f32);
+    let columns = (extent.x / cell_size).ceil() as usize + 1;
+    let rows = (extent.y / cell_size).ceil() as usize + 1;
+
+    let mut buckets: HashMap<IVec2, Vec<&Anisotropy>> = HashMap::new();
+    for kernel in particles.iter() {
+        buckets
+            .entry(bucket(kernel.center, reach))
+            .or_default()
+            .push(kernel);
+    }
+
+    // Negative inside the fluid, matching the solid side of a signed distance.
+    let values = (0..rows)
+        .flat_map(|y| (0..columns).map(move |x| min + Vec2::new(x as f32, y as f32) * cell_size))
+        .map(|point| {
+            let field: f32 = neighborhood(bucket(point, reach))
+                .filter_map(|cell| buckets.get(&cell))
+                .flatten()
+                .map(|kernel| kernel.weight(point, support))
+                .sum();
+            ISO_LEVEL - field
+        })
+        .collect();
+
+    if let Some(grid) = SdfGrid::new(min, cell_size, columns, values) {
+        draw_grid_contour(
+            &mut gizmos,
+            &grid,
+            &Transform::IDENTITY,
+            Color::srgb(0.4, 0.8, 1.0),
+        );
+    }
+}
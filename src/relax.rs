@@ -0,0 +1,114 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::{
+    flow::Emitter,
+    params::SimulationParams,
+    scene::{LoadScene, SceneSpawnSet},
+    Velocity, SIMULATION_SCHEDULE,
+};
+
+const RELAX_STEP: Duration = Duration::from_micros(16_667);
+// Velocity kept after each hidden step, draining the energy of the initial slump.
+const RELAX_DAMPING: f32 = 0.8;
+
+// Runs `steps` solver steps out of sight as soon as a scene or the default block has been
+// spawned, so that tanks filled on a lattice settle into hydrostatic equilibrium before they
+// are first drawn instead of visibly slumping. Without `gravity` the particles only even out
+// their spacing.
+pub struct RelaxPlugin {
+    pub steps: u32,
+    pub gravity: bool,
+}
+
+impl Plugin for RelaxPlugin {
+    fn build(&self, app: &mut App) {
+        if self.steps == 0 {
+            return;
+        }
+
+        app.insert_resource(Relaxation {
+            steps: self.steps,
+            gravity: self.gravity,
+            pending: true,
+        })
+        .add_systems(
+            PreUpdate,
+            (rearm_system, relax_system).chain().after(SceneSpawnSet),
+        );
+    }
+}
+
+#[derive(Resource)]
+struct Relaxation {
+    steps: u32,
+    gravity: bool,
+    /// Waiting for particles to relax
+    pending: bool,
+}
+
+fn rearm_system(mut events: EventReader<LoadScene>, mut relaxation: ResMut<Relaxation>) {
+    if events.read().count() > 0 {
+        relaxation.pending = true;
+    }
+}
+
+fn relax_system(world: &mut World) {
+    let Some(&Relaxation {
+        steps,
+        gravity,
+        pending: true,
+    }) = world.get_resource::<Relaxation>()
+    else {
+        return;
+    };
+
+    let mut particles = world.query::<&mut Velocity>();
+    if particles.iter(world).next().is_none() {
+        return;
+    }
+    world.resource_mut::<Relaxation>().pending = false;
+
+    let params = world.resource::<SimulationParams>().clone();
+    let time = *world.resource::<Time>();
+
+    {
+        let mut relaxed = world.resource_mut::<SimulationParams>();
+        relaxed.damping_factor = RELAX_DAMPING;
+        if !gravity {
+            relaxed.gravity = 0.0;
+        }
+    }
+
+    // Emitters would otherwise pour in everything they emit over the hidden steps.
+    let mut emitters = world.query::<(Entity, &mut Emitter)>();
+    let paused: Vec<Entity> = emitters
+        .iter_mut(world)
+        .filter(|(_, emitter)| emitter.enabled)
+        .map(|(entity, mut emitter)| {
+            emitter.enabled = false;
+            entity
+        })
+        .collect();
+
+    for _ in 0..steps {
+        world.resource_mut::<Time>().advance_by(RELAX_STEP);
+        world.run_schedule(SIMULATION_SCHEDULE);
+    }
+
+    *world.resource_mut::<SimulationParams>() = params;
+    *world.resource_mut::<Time>() = time;
+
+    for entity in paused {
+        if let Some(mut emitter) = world.get_mut::<Emitter>(entity) {
+            emitter.enabled = true;
+        }
+    }
+
+    for mut velocity in particles.iter_mut(world) {
+        velocity.0 = Vec3::ZERO;
+    }
+
+    info!("Relaxed the initial particles over {steps} steps");
+}
@@ -0,0 +1,142 @@
+use std::{collections::VecDeque, mem};
+
+use bevy::{input::InputPlugin, prelude::*};
+
+use crate::{
+    checkpoint::{capture, restore, EntityRemap, ParticleState, ParticleStateQuery, RestoreQuery},
+    cli::Args,
+};
+
+const MEGABYTE: usize = 1024 * 1024;
+
+// Frame-by-frame history for finding the moment something went wrong. Holding R pauses the
+// simulation and steps back one recorded frame per frame, Shift+R steps forward again, and
+// unpausing with Space resumes from the frame shown, discarding the ones after it.
+pub struct RewindPlugin;
+
+impl Plugin for RewindPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EntityRemap>()
+            .add_systems(Startup, init_rewind)
+            .add_systems(PostUpdate, record_system);
+
+        if app.is_plugin_added::<InputPlugin>() {
+            app.add_systems(Update, scrub_system);
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct RewindBuffer {
+    duration: f32,
+    budget: usize,
+    frames: VecDeque<Frame>,
+    bytes: usize,
+    // Frame shown while scrubbing, `None` while recording.
+    cursor: Option<usize>,
+}
+
+struct Frame {
+    elapsed: f32,
+    particles: Vec<ParticleState>,
+}
+
+impl Frame {
+    fn size(&self) -> usize {
+        mem::size_of::<Self>() + self.particles.capacity() * mem::size_of::<ParticleState>()
+    }
+}
+
+impl RewindBuffer {
+    /// Every particle state in the recorded frames
+    pub fn states(&self) -> impl Iterator<Item = &ParticleState> {
+        self.frames.iter().flat_map(|frame| &frame.particles)
+    }
+
+    fn pop_front(&mut self) {
+        if let Some(frame) = self.frames.pop_front() {
+            self.bytes -= frame.size();
+        }
+    }
+
+    fn truncate(&mut self, length: usize) {
+        for frame in self.frames.drain(length..) {
+            self.bytes -= frame.size();
+        }
+    }
+}
+
+fn init_rewind(mut commands: Commands, args: Res<Args>) {
+    commands.insert_resource(RewindBuffer {
+        duration: args.rewind_seconds.max(0.0),
+        budget: args.rewind_memory * MEGABYTE,
+        frames: VecDeque::new(),
+        bytes: 0,
+        cursor: None,
+    });
+}
+
+fn record_system(
+    time: Res<Time<Virtual>>,
+    mut buffer: ResMut<RewindBuffer>,
+    particles: ParticleStateQuery,
+) {
+    if buffer.budget == 0 || buffer.duration <= 0.0 || time.is_paused() {
+        return;
+    }
+
+    if let Some(cursor) = buffer.cursor.take() {
+        buffer.truncate(cursor + 1);
+        info!("Resumed from {:.2}s", buffer.frames[cursor].elapsed);
+    }
+
+    let frame = Frame {
+        elapsed: time.elapsed_secs(),
+        particles: capture(&particles),
+    };
+    let elapsed = frame.elapsed;
+
+    buffer.bytes += frame.size();
+    buffer.frames.push_back(frame);
+
+    while buffer.frames.len() > 1
+        && (buffer.bytes > buffer.budget || buffer.frames[0].elapsed < elapsed - buffer.duration)
+    {
+        buffer.pop_front();
+    }
+}
+
+fn scrub_system(
+    mut commands: Commands,
+    input: Res<ButtonInput<KeyCode>>,
+    mut time: ResMut<Time<Virtual>>,
+    mut buffer: ResMut<RewindBuffer>,
+    mut remap: ResMut<EntityRemap>,
+    mut particles: RestoreQuery,
+) {
+    if !input.pressed(KeyCode::KeyR) || buffer.frames.is_empty() {
+        return;
+    }
+
+    let last = buffer.frames.len() - 1;
+    let current = buffer.cursor.unwrap_or(last);
+    let next = if input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+        (current + 1).min(last)
+    } else {
+        current.saturating_sub(1)
+    };
+
+    if buffer.cursor.is_none() {
+        time.pause();
+    } else if buffer.cursor == Some(next) {
+        return;
+    }
+
+    buffer.cursor = Some(next);
+    restore(
+        &mut commands,
+        &buffer.frames[next].particles,
+        &mut remap,
+        &mut particles,
+    );
+}
@@ -0,0 +1,66 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    io,
+    marker::PhantomData,
+};
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, LoadContext},
+    prelude::*,
+};
+use serde::de::DeserializeOwned;
+
+pub struct RonAssetLoader<T> {
+    extensions: &'static [&'static str],
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> RonAssetLoader<T> {
+    pub fn new(extensions: &'static [&'static str]) -> Self {
+        Self {
+            extensions,
+            marker: PhantomData,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum RonAssetError {
+    Io(io::Error),
+    Parse(ron::error::SpannedError),
+}
+
+impl Display for RonAssetError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            RonAssetError::Io(error) => write!(f, "could not read file: {error}"),
+            RonAssetError::Parse(error) => write!(f, "could not parse file: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for RonAssetError {}
+
+impl<T: Asset + DeserializeOwned> AssetLoader for RonAssetLoader<T> {
+    type Asset = T;
+    type Settings = ();
+    type Error = RonAssetError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader
+            .read_to_end(&mut bytes)
+            .await
+            .map_err(RonAssetError::Io)?;
+        ron::de::from_bytes(&bytes).map_err(RonAssetError::Parse)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        self.extensions
+    }
+}
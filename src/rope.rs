@@ -0,0 +1,245 @@
+use bevy::{gizmos::GizmoPlugin, prelude::*};
+
+use crate::{
+    budget::ParticleMass, domain::Domain, neighbors::FluidNeighbors, params::SimulationParams,
+    velocity_system, SimulationSet, Velocity, SIMULATION_SCHEDULE,
+};
+
+// Passes over the links per step. More keep a long rope from stretching under load.
+const LINK_ITERATIONS: usize = 8;
+const ANCHOR_SIZE: f32 = 4.0;
+
+// Chain of point masses joined by links of fixed length, like a rope or a strand of
+// seaweed, hanging from an anchor and optionally tied down at its other end too. The nodes
+// are not fluid particles: each one drags toward the velocity of the fluid sampled around
+// it and bumps particles out of its way, and the fluid takes back whatever momentum the
+// node gains or loses, so a rope sways in currents and stirs still water.
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct Rope {
+    nodes: Vec<RopeNode>,
+    link_length: f32,
+    /// Where the first node is held
+    pub anchor: Vec2,
+    /// Where the last node is held, loose when unset
+    pub end_anchor: Option<Vec2>,
+    /// Rate at which nodes take on the velocity of the surrounding fluid, in 1/s
+    pub drag: f32,
+    /// Mass of each node, in particle masses
+    pub node_mass: f32,
+    /// Multiplier on gravity for the nodes, negative for strands that float upward
+    pub weight: f32,
+}
+
+#[derive(Reflect, Clone, Copy)]
+struct RopeNode {
+    position: Vec2,
+    velocity: Vec2,
+}
+
+impl Rope {
+    /// Rope laid straight from `anchor` to `end` in `links` links of equal length
+    pub fn new(anchor: Vec2, end: Vec2, links: usize) -> Self {
+        let links = links.max(1);
+
+        Self {
+            nodes: (0..=links)
+                .map(|index| RopeNode {
+                    position: anchor.lerp(end, index as f32 / links as f32),
+                    velocity: Vec2::ZERO,
+                })
+                .collect(),
+            link_length: anchor.distance(end) / links as f32,
+            anchor,
+            end_anchor: None,
+            drag: 5.0,
+            node_mass: 1.0,
+            weight: 1.0,
+        }
+    }
+
+    fn pinned(&self, index: usize) -> bool {
+        index == 0 || (self.end_anchor.is_some() && index == self.nodes.len() - 1)
+    }
+}
+
+pub struct RopePlugin;
+
+impl Plugin for RopePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Rope>().add_systems(
+            SIMULATION_SCHEDULE,
+            rope_system
+                .in_set(SimulationSet::Forces)
+                .after(velocity_system),
+        );
+
+        if app.is_plugin_added::<GizmoPlugin>() {
+            app.add_systems(Update, draw_ropes_system);
+        }
+    }
+}
+
+fn rope_system(
+    time: Res<Time>,
+    params: Res<SimulationParams>,
+    domains: Query<&Domain>,
+    neighbors: FluidNeighbors,
+    mut ropes: Query<&mut Rope>,
+    mut particles: Query<(&Transform, &mut Velocity, &ParticleMass)>,
+) {
+    let delta_time = time.delta_secs();
+    if delta_time <= 0.0 {
+        return;
+    }
+
+    let bounds = domains
+        .get_single()
+        .map(|domain| Rect::from_center_half_size(Vec2::ZERO, domain.half_size()))
+        .ok();
+    let reach = params.smoothing_radius;
+    let contact = 2.0 * params.radius;
+
+    for mut rope in ropes.iter_mut() {
+        let node_mass = rope.node_mass.max(f32::EPSILON) * params.mass;
+        let fall = params.gravity_vector() * rope.weight * delta_time;
+        let blend = (rope.drag * delta_time).clamp(0.0, 1.0);
+
+        for index in 0..rope.nodes.len() {
+            if rope.pinned(index) {
+                continue;
+            }
+
+            let node = &mut rope.nodes[index];
+
+            // Nearer particles count for more, and frozen ones have no flow to sample.
+            let nearby: Vec<(Entity, f32)> = neighbors
+                .within_radius(node.position.extend(0.0), reach)
+                .filter(|&(entity, _)| particles.contains(entity))
+                .map(|(entity, position)| {
+                    let distance = position.truncate().distance(node.position);
+                    (entity, 1.0 - distance / reach)
+                })
+                .collect();
+            let total: f32 = nearby.iter().map(|&(_, weight)| weight).sum();
+
+            if total > 0.0 {
+                let flow = nearby
+                    .iter()
+                    .filter_map(|&(entity, weight)| {
+                        Some(particles.get(entity).ok()?.1 .0.truncate() * weight)
+                    })
+                    .sum::<Vec2>()
+                    / total;
+
+                // Barely wet nodes are dragged less than submerged ones.
+                let change = (flow - node.velocity) * blend * total.min(1.0);
+                node.velocity += change;
+
+                // The fluid loses the momentum the node gained, shared by weight.
+                let recoil = -change * node_mass / (params.mass * total);
+                for &(entity, weight) in &nearby {
+                    if let Ok((_, mut velocity, mass)) = particles.get_mut(entity) {
+                        velocity.0 += (recoil * weight / mass.0).extend(0.0);
+                    }
+                }
+            }
+
+            // Particles running into the node bounce off it as off another particle of the
+            // node's mass.
+            for &(entity, _) in &nearby {
+                let Ok((transform, mut velocity, mass)) = particles.get_mut(entity) else {
+                    continue;
+                };
+                let particle_mass = params.mass * mass.0;
+
+                let offset = transform.translation.truncate() - node.position;
+                if offset.length() >= contact {
+                    continue;
+                }
+
+                let Some(normal) = offset.try_normalize() else {
+                    continue;
+                };
+
+                let approach = (velocity.0.truncate() - node.velocity).dot(normal);
+                if approach >= 0.0 {
+                    continue;
+                }
+
+                let impulse = -approach * node_mass * particle_mass / (node_mass + particle_mass);
+                velocity.0 += (normal * impulse / particle_mass).extend(0.0);
+                node.velocity -= normal * impulse / node_mass;
+            }
+
+            node.velocity += fall;
+        }
+
+        let previous: Vec<Vec2> = rope.nodes.iter().map(|node| node.position).collect();
+
+        for node in rope.nodes.iter_mut() {
+            node.position += node.velocity * delta_time;
+        }
+
+        pin_ends(&mut rope);
+
+        for _ in 0..LINK_ITERATIONS {
+            for index in 0..rope.nodes.len() - 1 {
+                let free = [!rope.pinned(index), !rope.pinned(index + 1)].map(f32::from);
+                let share = free[0] + free[1];
+                if share == 0.0 {
+                    continue;
+                }
+
+                let offset = rope.nodes[index + 1].position - rope.nodes[index].position;
+                let Some(direction) = offset.try_normalize() else {
+                    continue;
+                };
+
+                let correction = direction * (offset.length() - rope.link_length) / share;
+                rope.nodes[index].position += correction * free[0];
+                rope.nodes[index + 1].position -= correction * free[1];
+            }
+        }
+
+        if let Some(bounds) = bounds {
+            for node in rope.nodes.iter_mut() {
+                node.position = node.position.clamp(bounds.min, bounds.max);
+            }
+        }
+
+        // Velocities follow the corrected positions, so links never fling the rope apart.
+        for (node, previous) in rope.nodes.iter_mut().zip(previous) {
+            node.velocity = (node.position - previous) / delta_time;
+        }
+
+        pin_ends(&mut rope);
+    }
+}
+
+fn pin_ends(rope: &mut Rope) {
+    let last = rope.nodes.len() - 1;
+    rope.nodes[0] = RopeNode {
+        position: rope.anchor,
+        velocity: Vec2::ZERO,
+    };
+
+    if let Some(end_anchor) = rope.end_anchor {
+        rope.nodes[last] = RopeNode {
+            position: end_anchor,
+            velocity: Vec2::ZERO,
+        };
+    }
+}
+
+fn draw_ropes_system(mut gizmos: Gizmos, ropes: Query<&Rope>) {
+    let color = Color::srgb(0.6, 0.75, 0.35);
+
+    for rope in ropes.iter() {
+        gizmos.linestrip_2d(rope.nodes.iter().map(|node| node.position), color);
+
+        for anchor in std::iter::once(rope.anchor).chain(rope.end_anchor) {
+            gizmos.circle_2d(anchor, ANCHOR_SIZE, color);
+        }
+    }
+}
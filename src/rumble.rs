@@ -0,0 +1,86 @@
+use std::time::Duration;
+
+use bevy::{
+    input::gamepad::{GamepadRumbleIntensity, GamepadRumbleRequest},
+    prelude::*,
+};
+use bevy_pancam::PanCam;
+
+use crate::{
+    surface::ColorField,
+    tools::{cursor_world_position, Tool},
+    DragState,
+};
+
+// Length of each rumble pulse. Pulses are sent back to back while the interaction lasts,
+// as overlapping ones would add up.
+const PULSE: Duration = Duration::from_millis(100);
+
+// Shakes connected gamepads while a particle is dragged or the attract and repel tools are
+// held, harder the fuller the fluid is where they act.
+pub struct RumblePlugin;
+
+impl Plugin for RumblePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, rumble_system);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn rumble_system(
+    time: Res<Time<Real>>,
+    tool: Res<Tool>,
+    drag_state: Res<DragState>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    field: Option<Res<ColorField>>,
+    gamepads: Query<Entity, With<Gamepad>>,
+    windows: Query<&Window>,
+    cameras: Query<(&Camera, &GlobalTransform), With<PanCam>>,
+    particles: Query<&Transform>,
+    mut pulse_end: Local<Option<Duration>>,
+    mut requests: EventWriter<GamepadRumbleRequest>,
+) {
+    if gamepads.is_empty() {
+        return;
+    }
+
+    let point = match *tool {
+        Tool::Drag => drag_state
+            .selected_entity
+            .and_then(|entity| particles.get(entity).ok())
+            .map(|transform| transform.translation.truncate()),
+        Tool::Attract | Tool::Repel if mouse_input.pressed(MouseButton::Left) => {
+            cursor_world_position(&windows, &cameras)
+        }
+        _ => None,
+    };
+
+    let fill = point
+        .zip(field)
+        .map_or(0.0, |(point, field)| field.color(point).clamp(0.0, 1.0));
+
+    let now = time.elapsed();
+
+    if fill > 0.0 {
+        if pulse_end.is_some_and(|end| now < end) {
+            return;
+        }
+
+        *pulse_end = Some(now + PULSE);
+
+        for gamepad in gamepads.iter() {
+            requests.send(GamepadRumbleRequest::Add {
+                gamepad,
+                duration: PULSE,
+                intensity: GamepadRumbleIntensity {
+                    strong_motor: fill * fill,
+                    weak_motor: fill,
+                },
+            });
+        }
+    } else if pulse_end.take().is_some() {
+        for gamepad in gamepads.iter() {
+            requests.send(GamepadRumbleRequest::Stop { gamepad });
+        }
+    }
+}
@@ -0,0 +1,1346 @@
+use bevy::prelude::*;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::Deserialize;
+
+use crate::{
+    boat::Boat,
+    cli::{Args, Sampling},
+    collider::Collider,
+    dilation::TimeDilation,
+    domain::{Domain, DomainWalls, Outflow},
+    flow::{Drain, Emitter, Inflow, InflowProfile, KillZone, Pulse, Rain},
+    flow_field::{FieldImage, FlowField, VelocityField, VelocityGrid},
+    fluid_commands::{FluidCommands, FluidMaterial, SpawnShape},
+    groups::{GroupDescription, GroupRules, ParticleGroup},
+    hybrid::HybridCoupling,
+    kinematic::{Kinematic, Motion},
+    measure::{MeasureRegion, TriggerZone},
+    obstacle::{Container, Obstacle},
+    params::{base_params, ConfigHandle, SimulationParams},
+    ron_asset::RonAssetLoader,
+    rope::Rope,
+    sdf::{HeightField, Pipe, Sdf, SdfBoundary, SdfGrid, WallMaterial},
+    shallow_water::ShallowWater,
+    soft_body::{SoftBody, SoftBodyCommands},
+    spline::Spline,
+    stream::Stream,
+    timeline::{Timeline, TimelineEvent},
+    wave_gauge::WaveGauge,
+    wave_maker::{WaveMaker, WaveMakerKind, WaveMakerSide},
+    Position,
+};
+
+const NOISE_SAMPLES_PER_WAVELENGTH: f32 = 8.0;
+// Bridson's sampling packs about 0.69 points per squared radius, so points kept this
+// fraction of the spacing apart come out at the lattice's count per area.
+const POISSON_RADIUS: f32 = 0.83;
+const POISSON_ATTEMPTS: usize = 30;
+// Regions that would take more lattice positions than this to fill are left empty.
+const MAX_FILL: f32 = (1 << 24) as f32;
+// Soft body particles are tied to lattice neighbors up to this many spacings away, which
+// takes in the diagonals.
+const SOFT_BODY_REACH: f32 = 1.5;
+
+#[derive(Asset, TypePath, Deserialize, Default)]
+#[serde(default)]
+pub struct SceneDescription {
+    pub blocks: Vec<BlockDescription>,
+    pub obstacles: Vec<ObstacleDescription>,
+    pub colliders: Vec<ColliderDescription>,
+    pub boundaries: Vec<BoundaryDescription>,
+    pub inflows: Vec<InflowDescription>,
+    pub emitters: Vec<EmitterDescription>,
+    /// Particles falling from random points along the top of the domain
+    pub rain: Option<RainDescription>,
+    /// Particles emitted along curves, moving in the curve's direction
+    pub streams: Vec<StreamDescription>,
+    /// Background velocities the particles are pulled towards
+    pub flow_fields: Vec<FlowFieldDescription>,
+    pub drains: Vec<DrainDescription>,
+    /// Shapes deleting every particle that enters them
+    pub kill_zones: Vec<KillZoneDescription>,
+    /// Regions where time runs slower or faster
+    pub time_dilations: Vec<TimeDilationDescription>,
+    /// Regions reporting the particles and mass inside them
+    pub measure_regions: Vec<MeasureRegionDescription>,
+    /// Vertical lines recording the height of the free surface over time
+    pub wave_gauges: Vec<WaveGaugeDescription>,
+    /// Lattices of particles held together by springs
+    pub soft_bodies: Vec<SoftBodyDescription>,
+    /// Chains swaying in the flow, hanging from an anchor
+    pub ropes: Vec<RopeDescription>,
+    /// Floating hulls steered with I, J, K and L
+    pub boats: Vec<BoatDescription>,
+    /// Bodies of water simulated as a height field instead of particles
+    pub lakes: Vec<LakeDescription>,
+    /// Closed outline used instead of the rectangular domain bounds
+    pub container: Option<Vec<[f32; 2]>>,
+    /// Restitution and friction overrides for the container's outline
+    pub container_walls: WallMaterial,
+    /// Restitution and friction overrides for each side of the domain
+    pub walls: DomainWalls,
+    /// Sides of the domain fluid flows out through instead of walls
+    pub outflow: Outflow,
+    /// Paddle driving waves in from one side of the domain
+    pub wave_maker: Option<WaveMakerDescription>,
+    /// Domain size, left unchanged when not given
+    pub domain: Option<[f32; 2]>,
+    /// Simulation parameters applied when the scene is spawned, unlisted fields at their defaults
+    pub params: Option<SimulationParams>,
+    /// Named particle groups, the first of which is the default for ungrouped particles
+    pub groups: Vec<GroupDescription>,
+    /// Actions run at set times after the scene is spawned
+    pub timeline: Vec<TimelineEvent>,
+}
+
+#[derive(Deserialize)]
+pub struct BlockDescription {
+    pub center: [f32; 2],
+    pub size: [f32; 2],
+    #[serde(default)]
+    pub shape: BlockShape,
+    #[serde(default)]
+    pub velocity: [f32; 2],
+    /// Name of one of the scene's `groups`
+    #[serde(default)]
+    pub group: Option<String>,
+    /// Label attached to each particle as `ParticleUserData<String>`
+    #[serde(default)]
+    pub tag: Option<String>,
+    /// Packs the particles to the density of fluid at rest under gravity, for tanks that
+    /// should start calm
+    #[serde(default)]
+    pub hydrostatic: bool,
+}
+
+#[derive(Deserialize, Default, Clone, Copy)]
+pub enum BlockShape {
+    #[default]
+    Rectangle,
+    /// Ellipse inscribed in the block's rectangle
+    Ellipse,
+}
+
+#[derive(Deserialize)]
+pub struct ObstacleDescription {
+    /// Name the timeline refers to it by
+    #[serde(default)]
+    pub name: Option<String>,
+    pub points: Vec<[f32; 2]>,
+    #[serde(default = "closed_by_default")]
+    pub closed: bool,
+    #[serde(default)]
+    pub restitution: Option<f32>,
+    #[serde(default)]
+    pub friction: Option<f32>,
+}
+
+#[derive(Deserialize)]
+pub struct ColliderDescription {
+    /// Name the timeline refers to it by
+    #[serde(default)]
+    pub name: Option<String>,
+    pub center: [f32; 2],
+    #[serde(default)]
+    pub angle: f32,
+    pub shape: ShapeDescription,
+    #[serde(default)]
+    pub motion: Option<MotionDescription>,
+    #[serde(default)]
+    pub restitution: Option<f32>,
+    #[serde(default)]
+    pub friction: Option<f32>,
+    /// Overrides `SimulationParams::contact_angle` for this surface
+    #[serde(default)]
+    pub contact_angle: Option<f32>,
+}
+
+#[derive(Deserialize)]
+pub enum ShapeDescription {
+    Circle { radius: f32 },
+    Box { size: [f32; 2] },
+    Polygon { points: Vec<[f32; 2]> },
+}
+
+impl ShapeDescription {
+    fn collider(&self) -> Collider {
+        match self {
+            Self::Circle { radius } => Collider::Circle { radius: *radius },
+            Self::Box { size } => Collider::Box {
+                half_size: Vec2::from(*size) / 2.0,
+            },
+            Self::Polygon { points } => {
+                Collider::convex_polygon(points.iter().map(|&point| point.into()).collect())
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct BoundaryDescription {
+    /// Name the timeline refers to it by
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub center: [f32; 2],
+    #[serde(default)]
+    pub angle: f32,
+    pub sdf: SdfDescription,
+    #[serde(default)]
+    pub motion: Option<MotionDescription>,
+    #[serde(default)]
+    pub restitution: Option<f32>,
+    #[serde(default)]
+    pub friction: Option<f32>,
+    /// Overrides `SimulationParams::contact_angle` for this surface
+    #[serde(default)]
+    pub contact_angle: Option<f32>,
+}
+
+#[derive(Deserialize)]
+pub enum MotionDescription {
+    /// Sinusoidal translation around the center
+    Oscillate { amplitude: [f32; 2], period: f32 },
+    /// Constant rotation in degrees per second
+    Rotate { speed: f32 },
+}
+
+impl MotionDescription {
+    fn motion(&self, center: [f32; 2], angle: f32) -> Motion {
+        match *self {
+            Self::Oscillate { amplitude, period } => Motion::Oscillate {
+                origin: center.into(),
+                amplitude: amplitude.into(),
+                period,
+            },
+            Self::Rotate { speed } => Motion::Rotate {
+                angle: angle.to_radians(),
+                speed: speed.to_radians(),
+            },
+        }
+    }
+}
+
+fn placement(center: [f32; 2], angle: f32) -> Transform {
+    Transform::from_translation(Vec2::from(center).extend(0.0))
+        .with_rotation(Quat::from_rotation_z(angle.to_radians()))
+}
+
+#[derive(Deserialize)]
+pub enum SdfDescription {
+    Shape(ShapeDescription),
+    Inverted(Box<SdfDescription>),
+    Union(Vec<SdfDescription>),
+    Grid {
+        origin: [f32; 2],
+        cell_size: f32,
+        columns: usize,
+        values: Vec<f32>,
+    },
+    /// Floor that is solid below the given heights
+    HeightField(HeightsDescription),
+    /// Channels the fluid flows inside, joined where they overlap
+    Pipes(Vec<PipeDescription>),
+}
+
+#[derive(Deserialize)]
+pub struct PipeDescription {
+    /// Control points the centerline passes through, in order
+    pub points: Vec<[f32; 2]>,
+    pub width: f32,
+    /// Lets fluid leave past the first point instead of capping it
+    #[serde(default)]
+    pub open_start: bool,
+    #[serde(default)]
+    pub open_end: bool,
+}
+
+impl PipeDescription {
+    fn pipe(&self) -> Option<Pipe> {
+        let path = Spline::new(self.points.iter().copied().map(Vec2::from).collect());
+        Pipe::new(&path, self.width, [self.open_start, self.open_end])
+    }
+}
+
+#[derive(Deserialize)]
+pub enum HeightsDescription {
+    /// Points the floor passes through, joined by straight segments
+    Curve(Vec<[f32; 2]>),
+    /// Smoothed random heights between `base - amplitude` and `base + amplitude` over
+    /// `from..to`, varying over roughly `wavelength`
+    Noise {
+        from: f32,
+        to: f32,
+        base: f32,
+        amplitude: f32,
+        wavelength: f32,
+        #[serde(default)]
+        seed: u64,
+    },
+}
+
+impl HeightsDescription {
+    fn points(&self) -> Vec<Vec2> {
+        match *self {
+            Self::Curve(ref points) => points.iter().copied().map(Vec2::from).collect(),
+            Self::Noise {
+                from,
+                to,
+                base,
+                amplitude,
+                wavelength,
+                seed,
+            } => {
+                if wavelength <= 0.0 || to <= from {
+                    return Vec::new();
+                }
+
+                let mut rng = StdRng::seed_from_u64(seed);
+                let knots: Vec<f32> = (0..=((to - from) / wavelength).ceil() as usize + 1)
+                    .map(|_| rng.gen_range(-1.0..=1.0))
+                    .collect();
+
+                let step = wavelength / NOISE_SAMPLES_PER_WAVELENGTH;
+                let count = ((to - from) / step).ceil() as usize;
+
+                (0..=count)
+                    .map(|i| {
+                        let x = (from + i as f32 * step).min(to);
+                        let knot = (x - from) / wavelength;
+                        let index = knot.floor() as usize;
+                        let t = knot.fract();
+                        let t = t * t * (3.0 - 2.0 * t);
+                        let noise = knots[index] + (knots[index + 1] - knots[index]) * t;
+
+                        Vec2::new(x, base + amplitude * noise)
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+impl SdfDescription {
+    fn sdf(&self) -> Option<Sdf> {
+        Some(match self {
+            Self::Shape(shape) => Sdf::Shape(shape.collider()),
+            Self::Inverted(inner) => Sdf::Inverted(Box::new(inner.sdf()?)),
+            Self::Union(parts) => Sdf::Union(parts.iter().map(Self::sdf).collect::<Option<_>>()?),
+            Self::Grid {
+                origin,
+                cell_size,
+                columns,
+                values,
+            } => Sdf::Grid(SdfGrid::new(
+                (*origin).into(),
+                *cell_size,
+                *columns,
+                values.clone(),
+            )?),
+            Self::HeightField(heights) => Sdf::HeightField(HeightField::new(heights.points())?),
+            Self::Pipes(pipes) => Sdf::Pipes(
+                pipes
+                    .iter()
+                    .map(PipeDescription::pipe)
+                    .collect::<Option<_>>()?,
+            ),
+        })
+    }
+}
+
+#[derive(Deserialize)]
+pub struct InflowDescription {
+    /// Name the timeline refers to it by
+    #[serde(default)]
+    pub name: Option<String>,
+    pub center: [f32; 2],
+    pub width: f32,
+    pub velocity: [f32; 2],
+    /// Distance downstream over which particles are held at the inflow velocity,
+    /// defaults to twice the smoothing radius
+    #[serde(default)]
+    pub depth: Option<f32>,
+    /// How the speed varies across the line, `velocity` being the peak
+    #[serde(default)]
+    pub profile: InflowProfile,
+    /// Speed rising and falling over time
+    #[serde(default)]
+    pub pulse: Option<Pulse>,
+}
+
+#[derive(Deserialize)]
+pub struct EmitterDescription {
+    /// Name the timeline refers to it by
+    #[serde(default)]
+    pub name: Option<String>,
+    pub center: [f32; 2],
+    pub direction: [f32; 2],
+    /// Particles per second
+    pub rate: f32,
+    pub speed: f32,
+    #[serde(default)]
+    pub color: Option<[f32; 3]>,
+    #[serde(default)]
+    pub jitter: f32,
+    /// Off until the timeline starts it when false
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+}
+
+#[derive(Deserialize)]
+pub struct RainDescription {
+    /// Particles per second
+    pub rate: f32,
+    /// Speed the particles fall at
+    pub speed: f32,
+    /// Height below which particles are deleted
+    #[serde(default)]
+    pub drain_line: Option<f32>,
+    #[serde(default)]
+    pub color: Option<[f32; 3]>,
+}
+
+#[derive(Deserialize)]
+pub struct SoftBodyDescription {
+    #[serde(default)]
+    pub name: Option<String>,
+    pub center: [f32; 2],
+    pub size: [f32; 2],
+    #[serde(default)]
+    pub shape: BlockShape,
+    /// Acceleration per unit of stretch, in 1/s²
+    pub stiffness: f32,
+    /// Acceleration per unit of stretching speed, in 1/s
+    #[serde(default)]
+    pub damping: f32,
+    #[serde(default)]
+    pub velocity: [f32; 2],
+    /// Name of one of the scene's `groups`
+    #[serde(default)]
+    pub group: Option<String>,
+    /// Color of the particles, the group's color when unset
+    #[serde(default)]
+    pub color: Option<[f32; 3]>,
+}
+
+#[derive(Deserialize)]
+pub struct BoatDescription {
+    #[serde(default)]
+    pub name: Option<String>,
+    pub center: [f32; 2],
+    #[serde(default)]
+    pub angle: f32,
+    pub hull: ShapeDescription,
+    /// Points sampling the flow, spread evenly around the hull
+    #[serde(default = "default_stations")]
+    pub stations: usize,
+    /// Density relative to the fluid's target density, below 1 to float
+    #[serde(default)]
+    pub relative_density: Option<f32>,
+    #[serde(default)]
+    pub thrust: Option<f32>,
+    #[serde(default)]
+    pub turn_rate: Option<f32>,
+    #[serde(default)]
+    pub drag: Option<f32>,
+}
+
+fn default_stations() -> usize {
+    16
+}
+
+#[derive(Deserialize)]
+pub struct WaveMakerDescription {
+    #[serde(default)]
+    pub side: WaveMakerSide,
+    #[serde(default)]
+    pub kind: WaveMakerKind,
+    /// Greatest travel of the paddle from its mean position, measured at the top for a flap
+    pub amplitude: f32,
+    /// Strokes per second
+    pub frequency: f32,
+    /// Seconds over which the stroke grows to its full amplitude
+    #[serde(default)]
+    pub ramp: Option<f32>,
+    #[serde(default)]
+    pub thickness: Option<f32>,
+}
+
+#[derive(Deserialize)]
+pub struct LakeDescription {
+    #[serde(default)]
+    pub name: Option<String>,
+    pub left: f32,
+    pub right: f32,
+    /// Height of the flat bottom
+    pub bed: f32,
+    /// Height of the still surface
+    pub level: f32,
+    /// Width of each water column, the particle spacing when unset
+    #[serde(default)]
+    pub cell_size: Option<f32>,
+    #[serde(default)]
+    pub damping: Option<f32>,
+    #[serde(default)]
+    pub color: Option<[f32; 3]>,
+    /// Trades water with the particles through the open left and right sides of the domain
+    #[serde(default)]
+    pub coupled: bool,
+    /// Name of one of the scene's `groups`, for particles the lake spawns when coupled
+    #[serde(default)]
+    pub group: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct RopeDescription {
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Fixed point the rope hangs from
+    pub anchor: [f32; 2],
+    /// Where the rope starts out reaching, in a straight line from the anchor
+    pub end: [f32; 2],
+    pub links: usize,
+    /// Holds the end in place as well
+    #[serde(default)]
+    pub pinned_end: bool,
+    #[serde(default)]
+    pub drag: Option<f32>,
+    /// Mass of each node, in particle masses
+    #[serde(default)]
+    pub node_mass: Option<f32>,
+    /// Multiplier on gravity, negative for strands that float upward
+    #[serde(default)]
+    pub weight: Option<f32>,
+}
+
+#[derive(Deserialize)]
+pub struct StreamDescription {
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Control points the curve passes through, in order
+    pub points: Vec<[f32; 2]>,
+    /// Particles per second
+    pub rate: f32,
+    pub speed: f32,
+    #[serde(default)]
+    pub color: Option<[f32; 3]>,
+    /// Starts off when false, to be enabled from the inspector
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+}
+
+#[derive(Deserialize)]
+pub struct FlowFieldDescription {
+    #[serde(default)]
+    pub name: Option<String>,
+    pub field: VelocityFieldDescription,
+    /// Fraction of the difference from the field's velocity removed each second
+    pub strength: f32,
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+}
+
+#[derive(Deserialize)]
+pub enum VelocityFieldDescription {
+    Uniform([f32; 2]),
+    /// `velocity` at height `from`, changing linearly by `change` up to height `to`
+    Shear {
+        from: f32,
+        to: f32,
+        velocity: [f32; 2],
+        change: [f32; 2],
+    },
+    /// Counterclockwise for positive `speed`, the speed reached at the edge of `core`
+    Vortex {
+        center: [f32; 2],
+        speed: f32,
+        core: f32,
+    },
+    Grid {
+        origin: [f32; 2],
+        cell_size: [f32; 2],
+        columns: usize,
+        values: Vec<[f32; 2]>,
+    },
+    /// Flow map with the x and y velocity in the red and green channels, mid gray still
+    Image {
+        path: String,
+        center: [f32; 2],
+        size: [f32; 2],
+        /// Velocity of a fully saturated channel
+        speed: f32,
+    },
+}
+
+impl VelocityFieldDescription {
+    fn field(&self) -> Option<VelocityField> {
+        Some(match self {
+            Self::Uniform(velocity) => VelocityField::Uniform((*velocity).into()),
+            Self::Shear {
+                from,
+                to,
+                velocity,
+                change,
+            } => VelocityField::Shear {
+                from: *from,
+                to: *to,
+                velocity: (*velocity).into(),
+                change: (*change).into(),
+            },
+            Self::Vortex {
+                center,
+                speed,
+                core,
+            } => VelocityField::Vortex {
+                center: (*center).into(),
+                speed: *speed,
+                core: *core,
+            },
+            Self::Grid {
+                origin,
+                cell_size,
+                columns,
+                values,
+            } => VelocityField::Grid(VelocityGrid::new(
+                (*origin).into(),
+                (*cell_size).into(),
+                *columns,
+                values.iter().copied().map(Vec2::from).collect(),
+            )?),
+            Self::Image {
+                path,
+                center,
+                size,
+                speed,
+            } => VelocityField::Image(FieldImage::new(
+                path.clone(),
+                Rect::from_center_size((*center).into(), (*size).into()),
+                *speed,
+            )),
+        })
+    }
+}
+
+#[derive(Deserialize)]
+pub struct DrainDescription {
+    /// Name the timeline refers to it by
+    #[serde(default)]
+    pub name: Option<String>,
+    pub center: [f32; 2],
+    pub size: [f32; 2],
+    #[serde(default)]
+    pub angle: f32,
+    /// Off until the timeline starts it when false
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+}
+
+#[derive(Deserialize)]
+pub struct KillZoneDescription {
+    #[serde(default)]
+    pub name: Option<String>,
+    pub center: [f32; 2],
+    #[serde(default)]
+    pub angle: f32,
+    pub shape: ShapeDescription,
+}
+
+#[derive(Deserialize)]
+pub struct TimeDilationDescription {
+    /// Name the timeline refers to it by
+    #[serde(default)]
+    pub name: Option<String>,
+    pub center: [f32; 2],
+    pub size: [f32; 2],
+    #[serde(default)]
+    pub angle: f32,
+    /// Speed of time inside, 0.5 for half speed
+    pub scale: f32,
+}
+
+#[derive(Deserialize)]
+pub struct MeasureRegionDescription {
+    #[serde(default)]
+    pub name: Option<String>,
+    pub center: [f32; 2],
+    #[serde(default)]
+    pub angle: f32,
+    pub shape: ShapeDescription,
+    /// Masses inside that send an event when passed
+    #[serde(default)]
+    pub thresholds: Vec<f32>,
+    /// Makes the region a trigger zone, sending events as fluid arrives and leaves
+    #[serde(default)]
+    pub trigger: Option<TriggerDescription>,
+}
+
+#[derive(Deserialize)]
+pub struct WaveGaugeDescription {
+    #[serde(default)]
+    pub name: Option<String>,
+    pub x: f32,
+}
+
+#[derive(Deserialize)]
+pub struct TriggerDescription {
+    /// Particles inside at which the zone counts as wet
+    pub enter: usize,
+    /// Particles inside at or below which it dries again
+    #[serde(default)]
+    pub exit: usize,
+}
+
+fn closed_by_default() -> bool {
+    true
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+fn group(rules: &GroupRules, name: &Option<String>, what: &str) -> ParticleGroup {
+    name.as_ref().map_or(ParticleGroup::default(), |name| {
+        ParticleGroup(rules.index(name).unwrap_or_else(|| {
+            warn!("{what} refers to unknown group {name}");
+            0
+        }))
+    })
+}
+
+fn name(name: &Option<String>, default: &str) -> Name {
+    Name::new(name.clone().unwrap_or_else(|| default.to_string()))
+}
+
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct SceneEntity;
+
+// Replaces everything spawned so far with the scene at this asset path.
+#[derive(Event)]
+pub struct LoadScene(pub String);
+
+// Switching scenes and spawning a loaded one, for systems that work on freshly spawned
+// scenes to order themselves after.
+#[derive(SystemSet, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct SceneSpawnSet;
+
+pub struct ScenePlugin;
+
+impl Plugin for ScenePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<SceneDescription>()
+            .register_type::<SceneEntity>()
+            .register_asset_loader(RonAssetLoader::<SceneDescription>::new(&["scene.ron"]))
+            .add_event::<LoadScene>()
+            .add_systems(Startup, load_scene)
+            .add_systems(
+                PreUpdate,
+                (switch_scene_system, scene_reload_system)
+                    .chain()
+                    .in_set(SceneSpawnSet),
+            );
+    }
+}
+
+#[derive(Resource)]
+struct SceneHandle {
+    handle: Handle<SceneDescription>,
+    spawned: bool,
+}
+
+fn load_scene(mut commands: Commands, args: Res<Args>, asset_server: Res<AssetServer>) {
+    if let Some(path) = args.scene_path() {
+        commands.insert_resource(SceneHandle {
+            handle: asset_server.load(path),
+            spawned: false,
+        });
+    }
+}
+
+fn switch_scene_system(
+    mut commands: Commands,
+    mut events: EventReader<LoadScene>,
+    asset_server: Res<AssetServer>,
+    spawned: Query<Entity, Or<(With<Position>, With<SceneEntity>)>>,
+) {
+    let Some(LoadScene(path)) = events.read().last() else {
+        return;
+    };
+
+    for entity in spawned.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    info!("Loading scene {path}");
+    commands.insert_resource(SceneHandle {
+        handle: asset_server.load(path.clone()),
+        spawned: false,
+    });
+}
+
+// Scenery is replaced on every change to the scene file, while the fluid already spawned is
+// left to keep flowing unless `--rebuild-scene` asks for it to be respawned too.
+#[allow(clippy::too_many_arguments)]
+#[allow(clippy::type_complexity)]
+fn scene_reload_system(
+    mut commands: Commands,
+    mut events: EventReader<AssetEvent<SceneDescription>>,
+    scene_handle: Option<ResMut<SceneHandle>>,
+    scenes: Res<Assets<SceneDescription>>,
+    args: Res<Args>,
+    mut params: ResMut<SimulationParams>,
+    config: Option<Res<ConfigHandle>>,
+    configs: Res<Assets<SimulationParams>>,
+    fluid: Query<Entity, Or<(With<Position>, With<SoftBody>)>>,
+    scenery: Query<Entity, (With<SceneEntity>, Without<SoftBody>)>,
+    mut domains: Query<&mut Domain>,
+) {
+    let Some(mut scene_handle) = scene_handle else {
+        return;
+    };
+
+    let changed = events.read().any(|event| match event {
+        AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id } => {
+            *id == scene_handle.handle.id()
+        }
+        _ => false,
+    });
+
+    if scene_handle.spawned && !changed {
+        return;
+    }
+
+    let Some(scene) = scenes.get(&scene_handle.handle) else {
+        return;
+    };
+
+    // A scene loaded in place of another starts over from the base parameters and domain,
+    // rather than inheriting whatever the previous scene overrode.
+    let fresh = !scene_handle.spawned;
+    let respawn_fluid = fresh || args.rebuild_scene;
+
+    if scene_handle.spawned {
+        for entity in scenery.iter() {
+            commands.entity(entity).despawn();
+        }
+
+        if respawn_fluid {
+            for entity in fluid.iter() {
+                commands.entity(entity).despawn();
+            }
+            info!("Rebuilding scene");
+        } else {
+            info!("Reloading scene");
+        }
+    }
+
+    if fresh {
+        params.set_if_neq(base_params(config.as_deref(), &configs));
+    }
+
+    if let Some(scene_params) = &scene.params {
+        *params = scene_params.resolved();
+    }
+
+    for mut domain in domains.iter_mut() {
+        if fresh {
+            *domain = Domain::default();
+        }
+
+        domain.walls = scene.walls;
+        domain.outflow = scene.outflow;
+
+        if let Some(size) = scene.domain {
+            domain.size = size.into();
+        }
+    }
+
+    let rules = GroupRules::new(&scene.groups);
+    if respawn_fluid {
+        spawn_fluid(&mut commands, scene, &params, &rules);
+    }
+    spawn_scene(&mut commands, scene, &params, &rules);
+    commands.insert_resource(rules);
+    commands.insert_resource(Timeline::new(scene.timeline.clone()));
+    scene_handle.spawned = true;
+}
+
+// Blocks and soft bodies, everything in the scene made of particles.
+fn spawn_fluid(
+    commands: &mut Commands,
+    scene: &SceneDescription,
+    params: &SimulationParams,
+    rules: &GroupRules,
+) {
+    for block in &scene.blocks {
+        let group = group(rules, &block.group, "Block");
+        let material = FluidMaterial {
+            color: rules.color(group),
+            group,
+            velocity: block.velocity.into(),
+            tag: block.tag.clone(),
+        };
+        let rect = Rect::from_center_size(block.center.into(), block.size.into());
+
+        match (block.shape, block.hydrostatic) {
+            (BlockShape::Rectangle, false) => {
+                commands.spawn_fluid_block(rect, params.particle_spacing, material)
+            }
+            (BlockShape::Ellipse, false) => {
+                commands.spawn_fluid_ellipse(rect, params.particle_spacing, material)
+            }
+            (BlockShape::Rectangle, true) => commands.spawn_hydrostatic_fluid(
+                SpawnShape::Block(rect),
+                params.particle_spacing,
+                material,
+            ),
+            (BlockShape::Ellipse, true) => commands.spawn_hydrostatic_fluid(
+                SpawnShape::Ellipse(rect),
+                params.particle_spacing,
+                material,
+            ),
+        }
+    }
+
+    for body in &scene.soft_bodies {
+        let center = Vec2::from(body.center);
+        let half_size = Vec2::from(body.size) / 2.0;
+        let positions = block_positions(center, half_size * 2.0, params.particle_spacing)
+            .into_iter()
+            .filter(|&position| match body.shape {
+                BlockShape::Rectangle => true,
+                BlockShape::Ellipse => ((position - center) / half_size).length_squared() <= 1.0,
+            })
+            .collect();
+
+        let group = group(rules, &body.group, "Soft body");
+        let material = FluidMaterial {
+            color: body
+                .color
+                .map(|[red, green, blue]| Color::srgb(red, green, blue))
+                .or(rules.color(group)),
+            group,
+            velocity: body.velocity.into(),
+            tag: None,
+        };
+
+        commands.spawn_soft_body(
+            positions,
+            params.particle_spacing * SOFT_BODY_REACH,
+            material,
+            (
+                name(&body.name, "Soft body"),
+                SoftBody::new(body.stiffness, body.damping),
+                SceneEntity,
+            ),
+        );
+    }
+}
+
+fn spawn_scene(
+    commands: &mut Commands,
+    scene: &SceneDescription,
+    params: &SimulationParams,
+    rules: &GroupRules,
+) {
+    if let Some(container) = &scene.container {
+        let points = container.iter().map(|&point| point.into()).collect();
+        commands.spawn((
+            Name::new("Container"),
+            Container(Obstacle::new(points, true)),
+            scene.container_walls,
+            SceneEntity,
+        ));
+    }
+
+    for obstacle in &scene.obstacles {
+        let points = obstacle.points.iter().map(|&point| point.into()).collect();
+        let mut entity = commands.spawn((
+            name(&obstacle.name, "Obstacle"),
+            Obstacle::new(points, obstacle.closed),
+            SceneEntity,
+        ));
+
+        if obstacle.restitution.is_some() || obstacle.friction.is_some() {
+            entity.insert(WallMaterial {
+                restitution: obstacle.restitution,
+                friction: obstacle.friction,
+                contact_angle: None,
+            });
+        }
+    }
+
+    for collider in &scene.colliders {
+        let mut entity = commands.spawn((
+            name(&collider.name, "Collider"),
+            collider.shape.collider(),
+            placement(collider.center, collider.angle),
+            SceneEntity,
+        ));
+
+        if let Some(motion) = &collider.motion {
+            entity.insert((
+                motion.motion(collider.center, collider.angle),
+                Kinematic::default(),
+            ));
+        }
+
+        if collider.restitution.is_some()
+            || collider.friction.is_some()
+            || collider.contact_angle.is_some()
+        {
+            entity.insert(WallMaterial {
+                restitution: collider.restitution,
+                friction: collider.friction,
+                contact_angle: collider.contact_angle,
+            });
+        }
+    }
+
+    for inflow in &scene.inflows {
+        let mut component = Inflow::new(
+            inflow.width,
+            inflow.depth.unwrap_or(2.0 * params.smoothing_radius),
+            inflow.velocity.into(),
+        );
+        component.profile = inflow.profile;
+        component.pulse = inflow.pulse;
+
+        commands.spawn((
+            name(&inflow.name, "Inflow"),
+            component,
+            placement(inflow.center, 0.0),
+            SceneEntity,
+        ));
+    }
+
+    for emitter in &scene.emitters {
+        let mut component = Emitter::new(emitter.direction.into(), emitter.rate, emitter.speed);
+        component.color = emitter
+            .color
+            .map(|[red, green, blue]| Color::srgb(red, green, blue));
+        component.jitter = emitter.jitter;
+        component.enabled = emitter.enabled;
+
+        commands.spawn((
+            name(&emitter.name, "Emitter"),
+            component,
+            placement(emitter.center, 0.0),
+            SceneEntity,
+        ));
+    }
+
+    if let Some(rain) = &scene.rain {
+        let mut component = Rain::new(rain.rate, rain.speed);
+        component.drain_line = rain.drain_line;
+        component.color = rain
+            .color
+            .map(|[red, green, blue]| Color::srgb(red, green, blue));
+
+        commands.spawn((Name::new("Rain"), component, SceneEntity));
+    }
+
+    for boat in &scene.boats {
+        let hull = boat.hull.collider();
+        let mut component = Boat::new(&hull, boat.stations);
+        component.relative_density = boat.relative_density.unwrap_or(component.relative_density);
+        component.thrust = boat.thrust.unwrap_or(component.thrust);
+        component.turn_rate = boat.turn_rate.unwrap_or(component.turn_rate);
+        component.drag = boat.drag.unwrap_or(component.drag);
+
+        commands.spawn((
+            name(&boat.name, "Boat"),
+            component,
+            hull,
+            placement(boat.center, boat.angle),
+            Kinematic::default(),
+            SceneEntity,
+        ));
+    }
+
+    if let Some(maker) = &scene.wave_maker {
+        let mut component =
+            WaveMaker::new(maker.side, maker.kind, maker.amplitude, maker.frequency);
+        component.ramp = maker.ramp.unwrap_or(component.ramp);
+        component.thickness = maker.thickness.unwrap_or(component.thickness);
+
+        // The wave maker system sizes and places the paddle against the domain.
+        commands.spawn((
+            Name::new("Wave maker"),
+            component,
+            Collider::Box {
+                half_size: Vec2::ZERO,
+            },
+            Transform::default(),
+            Kinematic::default(),
+            SceneEntity,
+        ));
+    }
+
+    for lake in &scene.lakes {
+        let mut component = ShallowWater::new(
+            lake.left,
+            lake.right,
+            lake.bed,
+            lake.level,
+            lake.cell_size.unwrap_or(params.particle_spacing),
+        );
+        component.damping = lake.damping.unwrap_or(component.damping);
+        if let Some([red, green, blue]) = lake.color {
+            component.color = Color::srgb(red, green, blue);
+        }
+
+        let mut entity = commands.spawn((name(&lake.name, "Lake"), component, SceneEntity));
+
+        if lake.coupled {
+            let group = group(rules, &lake.group, "Lake");
+            entity.insert(HybridCoupling::new(group, rules.color(group)));
+        }
+    }
+
+    for rope in &scene.ropes {
+        let mut component = Rope::new(rope.anchor.into(), rope.end.into(), rope.links);
+        component.end_anchor = rope.pinned_end.then_some(rope.end.into());
+        component.drag = rope.drag.unwrap_or(component.drag);
+        component.node_mass = rope.node_mass.unwrap_or(component.node_mass);
+        component.weight = rope.weight.unwrap_or(component.weight);
+
+        commands.spawn((name(&rope.name, "Rope"), component, SceneEntity));
+    }
+
+    for stream in &scene.streams {
+        let path = Spline::new(stream.points.iter().copied().map(Vec2::from).collect());
+        let mut component = Stream::new(path, stream.rate, stream.speed);
+        component.enabled = stream.enabled;
+        component.color = stream
+            .color
+            .map(|[red, green, blue]| Color::srgb(red, green, blue));
+
+        commands.spawn((name(&stream.name, "Stream"), component, SceneEntity));
+    }
+
+    for flow_field in &scene.flow_fields {
+        let Some(field) = flow_field.field.field() else {
+            warn!("Skipping flow field with an invalid velocity grid");
+            continue;
+        };
+
+        let mut component = FlowField::new(field, flow_field.strength);
+        component.enabled = flow_field.enabled;
+
+        commands.spawn((name(&flow_field.name, "Flow field"), component, SceneEntity));
+    }
+
+    for drain in &scene.drains {
+        let mut component = Drain::new(Vec2::from(drain.size) / 2.0);
+        component.enabled = drain.enabled;
+
+        commands.spawn((
+            name(&drain.name, "Drain"),
+            component,
+            placement(drain.center, drain.angle),
+            SceneEntity,
+        ));
+    }
+
+    for zone in &scene.kill_zones {
+        commands.spawn((
+            name(&zone.name, "Kill zone"),
+            KillZone::new(zone.shape.collider()),
+            placement(zone.center, zone.angle),
+            SceneEntity,
+        ));
+    }
+
+    for dilation in &scene.time_dilations {
+        commands.spawn((
+            name(&dilation.name, "Time dilation"),
+            TimeDilation {
+                half_size: Vec2::from(dilation.size) / 2.0,
+                scale: dilation.scale,
+            },
+            placement(dilation.center, dilation.angle),
+            SceneEntity,
+        ));
+    }
+
+    for region in &scene.measure_regions {
+        let mut entity = commands.spawn((
+            name(&region.name, "Measure region"),
+            MeasureRegion::new(region.shape.collider(), region.thresholds.clone()),
+            placement(region.center, region.angle),
+            SceneEntity,
+        ));
+
+        if let Some(trigger) = &region.trigger {
+            entity.insert(TriggerZone::new(trigger.enter, trigger.exit));
+        }
+    }
+
+    for gauge in &scene.wave_gauges {
+        commands.spawn((
+            name(&gauge.name, "Wave gauge"),
+            WaveGauge::new(gauge.x),
+            SceneEntity,
+        ));
+    }
+
+    for boundary in &scene.boundaries {
+        let Some(sdf) = boundary.sdf.sdf() else {
+            warn!("Skipping boundary with an invalid distance grid, height field or pipe");
+            continue;
+        };
+
+        let mut entity = commands.spawn((
+            name(&boundary.name, "Boundary"),
+            SdfBoundary(sdf),
+            placement(boundary.center, boundary.angle),
+            SceneEntity,
+        ));
+
+        if let Some(motion) = &boundary.motion {
+            entity.insert((
+                motion.motion(boundary.center, boundary.angle),
+                Kinematic::default(),
+            ));
+        }
+
+        if boundary.restitution.is_some()
+            || boundary.friction.is_some()
+            || boundary.contact_angle.is_some()
+        {
+            entity.insert(WallMaterial {
+                restitution: boundary.restitution,
+                friction: boundary.friction,
+                contact_angle: boundary.contact_angle,
+            });
+        }
+    }
+}
+
+// At most `limit` of the positions filling the rectangle, with those `inside` the shape being
+// filled counted toward it.
+pub fn fill_positions(
+    center: Vec2,
+    size: Vec2,
+    spacing: f32,
+    sampling: Sampling,
+    inside: impl Fn(Vec2) -> bool,
+    limit: usize,
+    rng: &mut StdRng,
+) -> Vec<Vec2> {
+    if !fillable(size, spacing) {
+        return Vec::new();
+    }
+
+    match sampling {
+        Sampling::Lattice => block_points(center, size, spacing)
+            .filter(|&position| inside(position))
+            .take(limit)
+            .collect(),
+        Sampling::Poisson => poisson_disk_positions(center, size, spacing, inside, limit, rng),
+    }
+}
+
+// Bridson's sampling: new points are tried in the ring between one and two radii around a
+// random active point, which retires once every try lands too close to an existing one.
+// Points keep half a spacing from the edges, like the lattice's.
+fn poisson_disk_positions(
+    center: Vec2,
+    size: Vec2,
+    spacing: f32,
+    inside: impl Fn(Vec2) -> bool,
+    limit: usize,
+    rng: &mut StdRng,
+) -> Vec<Vec2> {
+    let radius = spacing * POISSON_RADIUS;
+    let area = Rect::from_center_size(center, (size - spacing).max(Vec2::ZERO));
+    let cell = radius / std::f32::consts::SQRT_2;
+    let cells = (area.size() / cell).ceil().as_ivec2().max(IVec2::ONE);
+    let cell_of = |point: Vec2| {
+        ((point - area.min) / cell)
+            .as_ivec2()
+            .clamp(IVec2::ZERO, cells - 1)
+    };
+
+    // Each cell is small enough to hold at most one point.
+    let mut grid: Vec<Option<usize>> = vec![None; (cells.x * cells.y) as usize];
+    let first = area.min + area.size() * Vec2::new(rng.gen(), rng.gen());
+    let mut points = vec![first];
+    let mut active = vec![0];
+    let mut kept = usize::from(inside(first));
+    let start = cell_of(first);
+    grid[(start.y * cells.x + start.x) as usize] = Some(0);
+
+    while !active.is_empty() && kept < limit {
+        let slot = rng.gen_range(0..active.len());
+        let origin = points[active[slot]];
+
+        let found = (0..POISSON_ATTEMPTS).find_map(|_| {
+            let angle = rng.gen_range(0.0..std::f32::consts::TAU);
+            let distance = rng.gen_range(radius..2.0 * radius);
+            let candidate = origin + Vec2::from_angle(angle) * distance;
+
+            if !area.contains(candidate) {
+                return None;
+            }
+
+            let home = cell_of(candidate);
+            let crowded = (-2..=2).any(|dy| {
+                (-2..=2).any(|dx| {
+                    let neighbor = home + IVec2::new(dx, dy);
+                    neighbor.cmpge(IVec2::ZERO).all()
+                        && neighbor.cmplt(cells).all()
+                        && grid[(neighbor.y * cells.x + neighbor.x) as usize]
+                            .is_some_and(|index| points[index].distance(candidate) < radius)
+                })
+            });
+
+            (!crowded).then_some((candidate, home))
+        });
+
+        match found {
+            Some((point, home)) => {
+                grid[(home.y * cells.x + home.x) as usize] = Some(points.len());
+                active.push(points.len());
+                points.push(point);
+                kept += usize::from(inside(point));
+            }
+            None => {
+                active.swap_remove(slot);
+            }
+        }
+    }
+
+    points
+        .into_iter()
+        .filter(|&point| inside(point))
+        .take(limit)
+        .collect()
+}
+
+pub fn block_positions(center: Vec2, size: Vec2, spacing: f32) -> Vec<Vec2> {
+    if !fillable(size, spacing) {
+        return Vec::new();
+    }
+
+    block_points(center, size, spacing).collect()
+}
+
+// Whether `spacing` is something to fill a region of `size` at, as zero, negative and
+// non-finite spacings never finish and tiny ones would outgrow memory.
+fn fillable(size: Vec2, spacing: f32) -> bool {
+    spacing.is_finite()
+        && spacing > 0.0
+        && (size / spacing).max(Vec2::ONE).element_product() <= MAX_FILL
+}
+
+fn block_points(center: Vec2, size: Vec2, spacing: f32) -> impl Iterator<Item = Vec2> {
+    let columns = (size.x / spacing + 1e-4).floor().max(1.0) as usize;
+    let rows = (size.y / spacing + 1e-4).floor().max(1.0) as usize;
+    let corner = center - Vec2::new(columns as f32, rows as f32) * spacing / 2.0;
+
+    (0..columns)
+        .flat_map(|x| (0..rows).map(move |y| (x, y)))
+        .map(move |(x, y)| corner + Vec2::new(x as f32 + 0.5, y as f32 + 0.5) * spacing)
+}
@@ -0,0 +1,273 @@
+use std::io;
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, LoadContext},
+    prelude::*,
+};
+use rhai::{Array, Dynamic, Engine, Scope, AST, FLOAT};
+
+use crate::{
+    budget::{ParticleBudget, ParticleMass},
+    dilation::TimeScale,
+    fluid_commands::{FluidMaterial, SpawnParticles, SpawnShape},
+    params::SimulationParams,
+    velocity_system, DensityCache, SimulationSet, Velocity, SIMULATION_SCHEDULE,
+};
+
+// Bounds the work of a single script call so a runaway loop fails instead of hanging.
+const MAX_OPERATIONS: u64 = 100_000;
+
+// User script, recompiled whenever its file changes. It may define either of
+//
+// - `force(x, y, vx, vy, density, t)`, returning `[fx, fy]` added to each particle every
+//   step on top of pressure and gravity
+// - `spawn(t, dt)`, returning an array of `[x, y]` or `[x, y, vx, vy]` particles to add
+//
+// A call that fails disables the script until the next reload.
+#[derive(Asset, TypePath)]
+pub struct ScriptSource(String);
+
+#[derive(Default)]
+struct ScriptSourceLoader;
+
+impl AssetLoader for ScriptSourceLoader {
+    type Asset = ScriptSource;
+    type Settings = ();
+    type Error = io::Error;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        String::from_utf8(bytes)
+            .map(ScriptSource)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["rhai"]
+    }
+}
+
+#[derive(Resource)]
+struct Script {
+    handle: Handle<ScriptSource>,
+    engine: Engine,
+    ast: Option<AST>,
+    has_force: bool,
+    has_spawn: bool,
+}
+
+impl Script {
+    fn defines(ast: &AST, name: &str, arity: usize) -> bool {
+        ast.iter_functions()
+            .any(|function| function.name == name && function.params.len() == arity)
+    }
+
+    fn call(&mut self, name: &str, args: impl rhai::FuncArgs) -> Option<Dynamic> {
+        let ast = self.ast.as_ref()?;
+
+        match self
+            .engine
+            .call_fn::<Dynamic>(&mut Scope::new(), ast, name, args)
+        {
+            Ok(result) => Some(result),
+            Err(error) => {
+                error!(
+                    "Script function {name} failed, disabling the script until it changes: {error}"
+                );
+                self.ast = None;
+                None
+            }
+        }
+    }
+}
+
+pub struct ScriptPlugin(pub String);
+
+impl Plugin for ScriptPlugin {
+    fn build(&self, app: &mut App) {
+        let handle = app.world().resource::<AssetServer>().load(self.0.clone());
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+
+        app.init_asset::<ScriptSource>()
+            .init_asset_loader::<ScriptSourceLoader>()
+            .insert_resource(Script {
+                handle,
+                engine,
+                ast: None,
+                has_force: false,
+                has_spawn: false,
+            })
+            .add_systems(PreUpdate, script_reload_system)
+            .add_systems(
+                SIMULATION_SCHEDULE,
+                (
+                    script_force_system
+                        .in_set(SimulationSet::Forces)
+                        .after(velocity_system),
+                    script_spawn_system.in_set(SimulationSet::Integration),
+                ),
+            );
+    }
+}
+
+fn script_reload_system(
+    mut events: EventReader<AssetEvent<ScriptSource>>,
+    sources: Res<Assets<ScriptSource>>,
+    mut script: ResMut<Script>,
+) {
+    let changed = events.read().any(|event| match event {
+        AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id } => {
+            *id == script.handle.id()
+        }
+        _ => false,
+    });
+
+    if !changed {
+        return;
+    }
+
+    let Some(ScriptSource(source)) = sources.get(&script.handle) else {
+        return;
+    };
+
+    match script.engine.compile(source) {
+        Ok(ast) => {
+            script.has_force = Script::defines(&ast, "force", 6);
+            script.has_spawn = Script::defines(&ast, "spawn", 2);
+            script.ast = Some(ast);
+            info!(
+                "Loaded script (force: {}, spawn: {})",
+                script.has_force, script.has_spawn
+            );
+        }
+        Err(error) => {
+            error!("Could not compile script: {error}");
+            script.ast = None;
+        }
+    }
+}
+
+fn script_force_system(
+    time: Res<Time>,
+    params: Res<SimulationParams>,
+    density_cache: Res<DensityCache>,
+    mut script: ResMut<Script>,
+    mut particles: Query<(Entity, &Transform, &mut Velocity, &TimeScale, &ParticleMass)>,
+) {
+    if !script.has_force || script.ast.is_none() {
+        return;
+    }
+
+    let elapsed = time.elapsed_secs() as FLOAT;
+
+    for (entity, transform, mut velocity, time_scale, mass) in particles.iter_mut() {
+        let density = density_cache
+            .densities
+            .get(&entity)
+            .map_or(0.0, |&density| density as FLOAT);
+        let position = transform.translation;
+
+        let Some(result) = script.call(
+            "force",
+            (
+                position.x as FLOAT,
+                position.y as FLOAT,
+                velocity.0.x as FLOAT,
+                velocity.0.y as FLOAT,
+                density,
+                elapsed,
+            ),
+        ) else {
+            return;
+        };
+
+        let Some(force) = vector(result) else {
+            error!("Script function force must return [fx, fy]");
+            script.ast = None;
+            return;
+        };
+
+        let mass = params.mass * mass.0;
+        velocity.0 += (force / mass * time.delta_secs() * time_scale.0).extend(0.0);
+    }
+}
+
+fn script_spawn_system(
+    mut requests: EventWriter<SpawnParticles>,
+    time: Res<Time>,
+    budget: Res<ParticleBudget>,
+    mut script: ResMut<Script>,
+    particles: Query<(), With<Velocity>>,
+) {
+    if !script.has_spawn {
+        return;
+    }
+
+    let Some(result) = script.call(
+        "spawn",
+        (time.elapsed_secs() as FLOAT, time.delta_secs() as FLOAT),
+    ) else {
+        return;
+    };
+
+    let Some(spawned) = result.try_cast::<Array>() else {
+        error!("Script function spawn must return an array of particles");
+        script.ast = None;
+        return;
+    };
+
+    let remaining = budget.remaining(particles.iter().count());
+
+    for particle in spawned.into_iter().take(remaining) {
+        let Some(values) = particle.try_cast::<Array>().and_then(floats) else {
+            warn!("Ignoring spawned particle that is not an array of numbers");
+            continue;
+        };
+
+        let (position, velocity) = match values[..] {
+            [x, y] => (Vec2::new(x, y), Vec2::ZERO),
+            [x, y, vx, vy] => (Vec2::new(x, y), Vec2::new(vx, vy)),
+            _ => {
+                warn!("Ignoring spawned particle with {} values", values.len());
+                continue;
+            }
+        };
+
+        requests.send(SpawnParticles {
+            shape: SpawnShape::Points(vec![position]),
+            count: None,
+            spacing: None,
+            material: FluidMaterial {
+                velocity,
+                ..default()
+            },
+        });
+    }
+}
+
+fn floats(values: Array) -> Option<Vec<f32>> {
+    values
+        .into_iter()
+        .map(|value| {
+            value
+                .as_float()
+                .ok()
+                .or_else(|| value.as_int().ok().map(|int| int as FLOAT))
+                .map(|float| float as f32)
+        })
+        .collect()
+}
+
+fn vector(value: Dynamic) -> Option<Vec2> {
+    match floats(value.try_cast::<Array>()?)?[..] {
+        [x, y] => Some(Vec2::new(x, y)),
+        _ => None,
+    }
+}
@@ -0,0 +1,490 @@
+use bevy::{gizmos::GizmoPlugin, prelude::*};
+use serde::Deserialize;
+
+use crate::{
+    collider::{draw_collider, Collider},
+    kinematic::Kinematic,
+    params::SimulationParams,
+    spline::Spline,
+    SimulationSet, Velocity, SIMULATION_SCHEDULE,
+};
+
+const GRADIENT_STEP: f32 = 0.05;
+// Line segments drawn for the half circle closing a pipe end.
+const PIPE_CAP_SEGMENTS: usize = 8;
+
+pub trait SignedDistance {
+    // Negative inside the solid, positive in the fluid.
+    fn signed_distance(&self, point: Vec2) -> f32;
+
+    fn gradient(&self, point: Vec2) -> Vec2 {
+        let dx = Vec2::X * GRADIENT_STEP;
+        let dy = Vec2::Y * GRADIENT_STEP;
+
+        Vec2::new(
+            self.signed_distance(point + dx) - self.signed_distance(point - dx),
+            self.signed_distance(point + dy) - self.signed_distance(point - dy),
+        ) / (2.0 * GRADIENT_STEP)
+    }
+}
+
+// Opaque to reflection, as boxed variants can't be reflected field by field.
+#[derive(Reflect, Clone)]
+#[reflect(opaque)]
+pub enum Sdf {
+    Shape(Collider),
+    Grid(SdfGrid),
+    HeightField(HeightField),
+    // Hollow inside the pipes and solid around them, joining where they overlap.
+    Pipes(Vec<Pipe>),
+    // Swaps solid and fluid, turning a shape into a container.
+    Inverted(Box<Sdf>),
+    Union(Vec<Sdf>),
+}
+
+impl SignedDistance for Sdf {
+    fn signed_distance(&self, point: Vec2) -> f32 {
+        match self {
+            Self::Shape(collider) => collider.signed_distance(point),
+            Self::Grid(grid) => grid.signed_distance(point),
+            Self::HeightField(field) => field.signed_distance(point),
+            Self::Pipes(pipes) => pipes
+                .iter()
+                .map(|pipe| pipe.signed_distance(point))
+                .fold(f32::NEG_INFINITY, f32::max),
+            Self::Inverted(inner) => -inner.signed_distance(point),
+            Self::Union(parts) => parts
+                .iter()
+                .map(|part| part.signed_distance(point))
+                .fold(f32::INFINITY, f32::min),
+        }
+    }
+}
+
+// Distances sampled on a regular grid with row 0 at `origin`, interpolated bilinearly.
+// Outside the grid the distance to its edge is added to the clamped sample.
+#[derive(Clone)]
+pub struct SdfGrid {
+    origin: Vec2,
+    cell_size: f32,
+    columns: usize,
+    rows: usize,
+    values: Vec<f32>,
+}
+
+impl SdfGrid {
+    pub fn new(origin: Vec2, cell_size: f32, columns: usize, values: Vec<f32>) -> Option<Self> {
+        if columns < 2 || cell_size <= 0.0 || values.len() % columns != 0 {
+            return None;
+        }
+
+        let rows = values.len() / columns;
+        if rows < 2 {
+            return None;
+        }
+
+        Some(Self {
+            origin,
+            cell_size,
+            columns,
+            rows,
+            values,
+        })
+    }
+
+    fn value(&self, x: usize, y: usize) -> f32 {
+        self.values[y * self.columns + x]
+    }
+
+    fn corner(&self, x: usize, y: usize) -> Vec2 {
+        self.origin + Vec2::new(x as f32, y as f32) * self.cell_size
+    }
+}
+
+impl SignedDistance for SdfGrid {
+    fn signed_distance(&self, point: Vec2) -> f32 {
+        let extent = self.corner(self.columns - 1, self.rows - 1);
+        let clamped = point.clamp(self.origin, extent);
+        let cell = (clamped - self.origin) / self.cell_size;
+
+        let x = (cell.x.floor() as usize).min(self.columns - 2);
+        let y = (cell.y.floor() as usize).min(self.rows - 2);
+        let fraction = cell - Vec2::new(x as f32, y as f32);
+
+        let bottom = lerp(self.value(x, y), self.value(x + 1, y), fraction.x);
+        let top = lerp(self.value(x, y + 1), self.value(x + 1, y + 1), fraction.x);
+
+        lerp(bottom, top, fraction.y) + point.distance(clamped)
+    }
+}
+
+// Floor through `points` sorted by x, joined by straight segments and continued flat past
+// either end. Solid below the curve.
+#[derive(Clone)]
+pub struct HeightField {
+    points: Vec<Vec2>,
+}
+
+impl HeightField {
+    pub fn new(mut points: Vec<Vec2>) -> Option<Self> {
+        points.sort_by(|a, b| a.x.total_cmp(&b.x));
+        points.dedup_by(|a, b| a.x == b.x);
+
+        (points.len() >= 2).then_some(Self { points })
+    }
+
+    // Height and slope of the floor at `x`.
+    fn sample(&self, x: f32) -> (f32, f32) {
+        let index = self.points.partition_point(|point| point.x <= x);
+
+        if index == 0 {
+            return (self.points[0].y, 0.0);
+        }
+
+        if index == self.points.len() {
+            return (self.points[index - 1].y, 0.0);
+        }
+
+        let (a, b) = (self.points[index - 1], self.points[index]);
+        let slope = (b.y - a.y) / (b.x - a.x);
+
+        (a.y + slope * (x - a.x), slope)
+    }
+}
+
+impl SignedDistance for HeightField {
+    // Vertical offset projected onto the local segment normal.
+    fn signed_distance(&self, point: Vec2) -> f32 {
+        let (height, slope) = self.sample(point.x);
+        (point.y - height) / (1.0 + slope * slope).sqrt()
+    }
+
+    fn gradient(&self, point: Vec2) -> Vec2 {
+        let (_, slope) = self.sample(point.x);
+        Vec2::new(-slope, 1.0).normalize()
+    }
+}
+
+// Tube of `width` around a spline centerline, capped with a half circle at each closed end.
+// Past an open end the pipe stops constraining anything, so fluid can pour out of it.
+#[derive(Clone)]
+pub struct Pipe {
+    centerline: Vec<Vec2>,
+    half_width: f32,
+    open: [bool; 2],
+}
+
+impl Pipe {
+    pub fn new(path: &Spline, width: f32, open: [bool; 2]) -> Option<Self> {
+        let mut centerline = path.polyline();
+        centerline.dedup();
+
+        (centerline.len() >= 2 && width > 0.0).then_some(Self {
+            centerline,
+            half_width: width / 2.0,
+            open,
+        })
+    }
+
+    // Offsets of the two walls from the centerline at each of its points.
+    fn wall_offsets(&self) -> impl Iterator<Item = Vec2> + '_ {
+        let last = self.centerline.len() - 1;
+
+        (0..=last).map(move |index| {
+            let before = self.centerline[index.saturating_sub(1)];
+            let after = self.centerline[(index + 1).min(last)];
+            (after - before).normalize_or_zero().perp() * self.half_width
+        })
+    }
+}
+
+impl SignedDistance for Pipe {
+    // Infinite where the closest point of the centerline is an open tip and `point` lies
+    // beyond it, which leaves the gradient undefined so particles there are never pushed
+    // back in.
+    fn signed_distance(&self, point: Vec2) -> f32 {
+        let last_segment = self.centerline.len() - 2;
+        let (distance, segment, t) = self
+            .centerline
+            .windows(2)
+            .enumerate()
+            .map(|(index, pair)| {
+                let along = pair[1] - pair[0];
+                let t = (point - pair[0]).dot(along) / along.length_squared();
+                let closest = pair[0] + along * t.clamp(0.0, 1.0);
+                (point.distance(closest), index, t)
+            })
+            .fold((f32::INFINITY, 0, 0.0), |nearest, candidate| {
+                if candidate.0 < nearest.0 {
+                    candidate
+                } else {
+                    nearest
+                }
+            });
+
+        let past_start = self.open[0] && segment == 0 && t < 0.0;
+        let past_end = self.open[1] && segment == last_segment && t > 1.0;
+        if past_start || past_end {
+            return f32::INFINITY;
+        }
+
+        self.half_width - distance
+    }
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct SdfBoundary(pub Sdf);
+
+// Per-surface overrides of `SimulationParams::wall_restitution`, `wall_friction` and
+// `contact_angle`.
+#[derive(Component, Reflect, Deserialize, Clone, Copy, Default)]
+#[reflect(Component)]
+#[serde(default)]
+pub struct WallMaterial {
+    pub restitution: Option<f32>,
+    pub friction: Option<f32>,
+    /// Degrees, below 90 for surfaces the fluid spreads on and above for ones it beads on
+    pub contact_angle: Option<f32>,
+}
+
+impl WallMaterial {
+    pub fn restitution(&self, params: &SimulationParams) -> f32 {
+        self.restitution.unwrap_or(params.wall_restitution)
+    }
+
+    pub fn friction(&self, params: &SimulationParams) -> f32 {
+        self.friction.unwrap_or(params.wall_friction)
+    }
+
+    pub fn adhesion(&self, params: &SimulationParams) -> f32 {
+        params.adhesion(self.contact_angle.unwrap_or(params.contact_angle))
+    }
+}
+
+pub fn to_local(transform: &Transform, point: Vec2) -> Vec2 {
+    (transform.rotation.inverse() * (point.extend(0.0) - transform.translation)).truncate()
+}
+
+pub fn to_world(transform: &Transform, point: Vec2) -> Vec2 {
+    (transform.rotation * point.extend(0.0) + transform.translation).truncate()
+}
+
+pub struct SdfPlugin;
+
+impl Plugin for SdfPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<SdfBoundary>()
+            .register_type::<WallMaterial>()
+            .add_systems(
+                SIMULATION_SCHEDULE,
+                sdf_collision_system.in_set(SimulationSet::Collision),
+            );
+
+        if app.is_plugin_added::<GizmoPlugin>() {
+            app.add_systems(Update, draw_sdf_boundaries_system);
+        }
+    }
+}
+
+// Velocity of a particle touching a surface with outward `normal`: the part moving into the
+// surface is reflected with `bounce`, and `friction` of the tangential slip is removed, both
+// relative to the surface's own velocity.
+pub fn wall_response(
+    velocity: Vec2,
+    surface_velocity: Vec2,
+    normal: Vec2,
+    bounce: f32,
+    friction: f32,
+) -> Vec2 {
+    let relative_velocity = velocity - surface_velocity;
+    let velocity_along_normal = relative_velocity.dot(normal);
+    let tangential_velocity = relative_velocity - velocity_along_normal * normal;
+
+    let mut response = velocity - friction.clamp(0.0, 1.0) * tangential_velocity;
+    if velocity_along_normal < 0.0 {
+        response -= (1.0 + bounce) * velocity_along_normal * normal;
+    }
+
+    response
+}
+
+type SurfaceQuery<'w, 's, T> = Query<
+    'w,
+    's,
+    (
+        &'static T,
+        &'static Transform,
+        Option<&'static Kinematic>,
+        Option<&'static WallMaterial>,
+    ),
+>;
+
+// Colliders and SDF boundaries share this path: particles closer to the surface than
+// their radius are projected out along the gradient and lose their velocity into the
+// surface, measured relative to the surface itself when it moves. Friction then drags
+// them along with the surface.
+pub fn sdf_collision_system(
+    params: Res<SimulationParams>,
+    colliders: SurfaceQuery<Collider>,
+    boundaries: SurfaceQuery<SdfBoundary>,
+    mut particles: Query<
+        (&mut Transform, &mut Velocity),
+        (Without<Collider>, Without<SdfBoundary>),
+    >,
+) {
+    let shapes = colliders
+        .iter()
+        .map(|(collider, transform, kinematic, material)| {
+            (
+                collider as &dyn SignedDistance,
+                transform,
+                kinematic,
+                material,
+            )
+        })
+        .chain(
+            boundaries
+                .iter()
+                .map(|(boundary, transform, kinematic, material)| {
+                    (
+                        &boundary.0 as &dyn SignedDistance,
+                        transform,
+                        kinematic,
+                        material,
+                    )
+                }),
+        );
+
+    for (shape, shape_transform, kinematic, material) in shapes {
+        for (mut transform, mut velocity) in particles.iter_mut() {
+            let local = to_local(shape_transform, transform.translation.truncate());
+            let distance = shape.signed_distance(local);
+
+            if distance >= params.radius {
+                continue;
+            }
+
+            let Some(normal) = shape.gradient(local).try_normalize() else {
+                continue;
+            };
+
+            let normal = (shape_transform.rotation * normal.extend(0.0)).truncate();
+            let position = transform.translation.truncate() + normal * (params.radius - distance);
+            transform.translation = position.extend(transform.translation.z);
+
+            let surface_velocity = kinematic.map_or(Vec2::ZERO, |kinematic| {
+                kinematic.surface_velocity(shape_transform.translation.truncate(), position)
+            });
+
+            let material = material.copied().unwrap_or_default();
+            let response = wall_response(
+                velocity.0.truncate(),
+                surface_velocity,
+                normal,
+                material.restitution(&params),
+                material.friction(&params),
+            );
+            velocity.0 = response.extend(velocity.0.z);
+        }
+    }
+}
+
+fn draw_sdf_boundaries_system(mut gizmos: Gizmos, boundaries: Query<(&SdfBoundary, &Transform)>) {
+    for (boundary, transform) in boundaries.iter() {
+        draw_sdf(&mut gizmos, &boundary.0, transform);
+    }
+}
+
+fn draw_sdf(gizmos: &mut Gizmos, sdf: &Sdf, transform: &Transform) {
+    match sdf {
+        Sdf::Shape(collider) => {
+            draw_collider(gizmos, collider, transform, Color::srgb(0.8, 0.8, 0.8))
+        }
+        Sdf::Inverted(inner) => draw_sdf(gizmos, inner, transform),
+        Sdf::Union(parts) => {
+            for part in parts {
+                draw_sdf(gizmos, part, transform);
+            }
+        }
+        Sdf::Grid(grid) => draw_grid_contour(gizmos, grid, transform, Color::srgb(0.8, 0.8, 0.8)),
+        Sdf::Pipes(pipes) => {
+            for pipe in pipes {
+                draw_pipe(gizmos, pipe, transform);
+            }
+        }
+        Sdf::HeightField(field) => gizmos.linestrip_2d(
+            field.points.iter().map(|&point| to_world(transform, point)),
+            Color::srgb(0.8, 0.8, 0.8),
+        ),
+    }
+}
+
+fn draw_pipe(gizmos: &mut Gizmos, pipe: &Pipe, transform: &Transform) {
+    let color = Color::srgb(0.8, 0.8, 0.8);
+    let offsets: Vec<Vec2> = pipe.wall_offsets().collect();
+
+    for side in [1.0, -1.0] {
+        gizmos.linestrip_2d(
+            pipe.centerline
+                .iter()
+                .zip(&offsets)
+                .map(|(&point, &offset)| to_world(transform, point + offset * side)),
+            color,
+        );
+    }
+
+    let last = offsets.len() - 1;
+    let ends = [
+        (pipe.centerline[0], offsets[0]),
+        (pipe.centerline[last], -offsets[last]),
+    ];
+
+    for ((tip, offset), open) in ends.into_iter().zip(pipe.open) {
+        if open {
+            continue;
+        }
+
+        // Half circle from one wall to the other, bulging away from the pipe.
+        gizmos.linestrip_2d(
+            (0..=PIPE_CAP_SEGMENTS).map(|step| {
+                let angle = std::f32::consts::PI * step as f32 / PIPE_CAP_SEGMENTS as f32;
+                to_world(transform, tip + Vec2::from_angle(angle).rotate(offset))
+            }),
+            color,
+        );
+    }
+}
+
+// Marching squares over the zero level set, ignoring saddle disambiguation.
+pub fn draw_grid_contour(gizmos: &mut Gizmos, grid: &SdfGrid, transform: &Transform, color: Color) {
+    for y in 0..grid.rows - 1 {
+        for x in 0..grid.columns - 1 {
+            let corners = [(x, y), (x + 1, y), (x + 1, y + 1), (x, y + 1)];
+
+            let crossings: Vec<Vec2> = (0..4)
+                .filter_map(|i| {
+                    let (ax, ay) = corners[i];
+                    let (bx, by) = corners[(i + 1) % 4];
+                    let (a, b) = (grid.value(ax, ay), grid.value(bx, by));
+
+                    if (a < 0.0) == (b < 0.0) {
+                        return None;
+                    }
+
+                    let t = a / (a - b);
+                    Some(grid.corner(ax, ay).lerp(grid.corner(bx, by), t))
+                })
+                .map(|point| to_world(transform, point))
+                .collect();
+
+            for pair in crossings.chunks_exact(2) {
+                gizmos.line_2d(pair[0], pair[1], color);
+            }
+        }
+    }
+}
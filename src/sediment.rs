@@ -0,0 +1,133 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{
+    collider::Collider,
+    dilation::TimeScale,
+    domain::Domain,
+    frozen::{FreezeCommands, Frozen},
+    groups::{GroupRules, ParticleGroup},
+    neighbors::FluidNeighbors,
+    params::SimulationParams,
+    sdf::{to_local, SdfBoundary, SignedDistance},
+    SimulationSet, Velocity, SIMULATION_SCHEDULE,
+};
+
+// Particles of sediment groups that rest on the floor, a wall or earlier deposits for long
+// enough are frozen into the terrain, and deposits are thawed again where the flow past them
+// is fast enough to pick them up, so beds build up in calm water and wash away in currents.
+pub struct SedimentPlugin;
+
+impl Plugin for SedimentPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            SIMULATION_SCHEDULE,
+            (deposit_system, erode_system)
+                .after(SimulationSet::Collision)
+                .run_if(|rules: Res<GroupRules>| rules.has_sediment()),
+        );
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn deposit_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    params: Res<SimulationParams>,
+    rules: Res<GroupRules>,
+    domains: Query<&Domain>,
+    colliders: Query<(&Collider, &Transform)>,
+    boundaries: Query<(&SdfBoundary, &Transform)>,
+    neighbors: FluidNeighbors,
+    particles: Query<(
+        Entity,
+        &Transform,
+        &Velocity,
+        &TimeScale,
+        Option<&ParticleGroup>,
+    )>,
+    frozen: Query<(), With<Frozen>>,
+    mut resting: Local<HashMap<Entity, f32>>,
+) {
+    let spacing = params.particle_spacing;
+    let down = params.gravity_vector().normalize_or_zero();
+    let bounds = domains
+        .get_single()
+        .map(|domain| Rect::from_center_half_size(Vec2::ZERO, domain.half_size()))
+        .ok();
+
+    let shapes: Vec<(&dyn SignedDistance, &Transform)> = colliders
+        .iter()
+        .map(|(collider, transform)| (collider as &dyn SignedDistance, transform))
+        .chain(
+            boundaries
+                .iter()
+                .map(|(boundary, transform)| (&boundary.0 as &dyn SignedDistance, transform)),
+        )
+        .collect();
+
+    // A particle is held up when the spot a spacing below it is solid or taken by a deposit.
+    let supported = |position: Vec2| {
+        let below = position + down * spacing;
+
+        bounds.is_some_and(|bounds| !bounds.contains(below))
+            || shapes.iter().any(|&(shape, transform)| {
+                shape.signed_distance(to_local(transform, below)) < params.radius
+            })
+            || neighbors
+                .within_radius(below.extend(0.0), spacing / 2.0)
+                .any(|(entity, _)| frozen.contains(entity))
+    };
+
+    let mut deposited = Vec::new();
+    let mut still_resting = HashMap::new();
+
+    for (entity, transform, velocity, time_scale, group) in particles.iter() {
+        let Some(sediment) = rules.sediment(group.copied().unwrap_or_default()) else {
+            continue;
+        };
+
+        let position = transform.translation.truncate();
+        if velocity.0.length() > sediment.settle_speed || !supported(position) {
+            continue;
+        }
+
+        let rested =
+            resting.get(&entity).copied().unwrap_or(0.0) + time.delta_secs() * time_scale.0;
+
+        if rested >= sediment.settle_time {
+            deposited.push(entity);
+        } else {
+            still_resting.insert(entity, rested);
+        }
+    }
+
+    // Particles that moved, or were deleted, start over.
+    *resting = still_resting;
+    commands.freeze_particles(deposited);
+}
+
+fn erode_system(
+    mut commands: Commands,
+    params: Res<SimulationParams>,
+    rules: Res<GroupRules>,
+    neighbors: FluidNeighbors,
+    deposits: Query<(Entity, &Transform, Option<&ParticleGroup>), With<Frozen>>,
+    velocities: Query<&Velocity>,
+) {
+    let eroded = deposits
+        .iter()
+        .filter(|&(_, transform, group)| {
+            let Some(sediment) = rules.sediment(group.copied().unwrap_or_default()) else {
+                return false;
+            };
+
+            neighbors
+                .within_radius(transform.translation, params.smoothing_radius)
+                .filter_map(|(neighbor, _)| velocities.get(neighbor).ok())
+                .any(|velocity| velocity.0.length() > sediment.erosion_speed)
+        })
+        .map(|(entity, _, _)| entity)
+        .collect();
+
+    commands.thaw_particles(eroded);
+}
@@ -0,0 +1,427 @@
+use bevy::{
+    asset::RenderAssetUsages,
+    prelude::*,
+    render::mesh::{Indices, PrimitiveTopology},
+};
+
+use crate::{params::SimulationParams, SimulationSet, SIMULATION_SCHEDULE};
+
+// Shallow water travels at sqrt(g h), and each substep lets waves cross this fraction of a
+// cell at most.
+const COURANT_NUMBER: f32 = 0.5;
+const MAX_SUBSTEPS: usize = 32;
+// Columns shallower than this count as dry.
+const DRY_DEPTH: f32 = 0.01;
+// Drawn behind the particles.
+const LAKE_DEPTH: f32 = -1.0;
+
+// Body of water stored as a column height per cell along x, over a flat bed, and advanced
+// with the shallow water equations instead of particles. It costs a few floats per cell
+// however deep or wide it is, so it suits lakes and calm pools, while SPH handles what
+// splashes. Waves travel and reflect off its ends, but it cannot overturn or break, and
+// particles and colliders pass through it without disturbing it unless a `HybridCoupling`
+// trades water with the particles. Buoyancy, wave gauges and boats read it alongside the
+// particles through the same queries as `ColorField`.
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct ShallowWater {
+    /// Left end of the first cell
+    pub left: f32,
+    pub cell_size: f32,
+    /// Height of the flat bottom the water rests on
+    pub bed: f32,
+    /// Fraction of the flow lost per second, settling waves over time
+    pub damping: f32,
+    pub color: Color,
+    /// Water depth of each cell
+    depths: Vec<f32>,
+    /// Horizontal velocity across each cell boundary, both ends held at zero
+    velocities: Vec<f32>,
+    gap: Option<Gap>,
+    /// Water carried into the gap through its left and right ends since last taken, as an
+    /// area
+    transferred: [f32; 2],
+}
+
+// Cells `first..end` handed over to the particles. The faces at either end of the gap see
+// the particles' surface just inside it as the neighboring water level, so water runs in
+// when the lake stands higher, while water leaving the gap does so as particles.
+#[derive(Reflect, Clone, Copy, Debug)]
+struct Gap {
+    first: usize,
+    end: usize,
+    /// Surface of the particles just inside the left and right ends, `None` where dry
+    levels: [Option<f32>; 2],
+    /// Whether water passes through the left and right ends, which are walls otherwise
+    open: [bool; 2],
+}
+
+impl Gap {
+    fn contains(&self, cell: usize) -> bool {
+        (self.first..self.end).contains(&cell)
+    }
+}
+
+impl ShallowWater {
+    /// Still water filling `left..right` from `bed` up to `level`
+    pub fn new(left: f32, right: f32, bed: f32, level: f32, cell_size: f32) -> Self {
+        let cell_size = cell_size.max(f32::EPSILON);
+        let cells = ((right - left) / cell_size).round().max(1.0) as usize;
+
+        Self {
+            left,
+            cell_size,
+            bed,
+            damping: 0.05,
+            color: Color::srgb(0.25, 0.5, 0.9),
+            depths: vec![(level - bed).max(0.0); cells],
+            velocities: vec![0.0; cells + 1],
+            gap: None,
+            transferred: [0.0; 2],
+        }
+    }
+
+    fn in_gap(&self, cell: usize) -> bool {
+        self.gap.is_some_and(|gap| gap.contains(cell))
+    }
+
+    /// Hands the columns between `left` and `right` over to the particles, whose surface
+    /// just inside each end is at `levels`, and which trade water with the lake through the
+    /// `open` ends. Water left in columns newly handed over is dropped, as the particles
+    /// stand in for it.
+    pub fn couple(&mut self, left: f32, right: f32, levels: [Option<f32>; 2], open: [bool; 2]) {
+        let cells = self.depths.len();
+        let cell_at = |x: f32| {
+            ((x - self.left) / self.cell_size)
+                .round()
+                .clamp(0.0, cells as f32)
+        };
+        let (first, end) = (cell_at(left) as usize, cell_at(right) as usize);
+
+        let moved = !self
+            .gap
+            .is_some_and(|gap| gap.first == first && gap.end == end);
+        if moved && first < end {
+            self.depths[first..end].fill(0.0);
+            self.velocities[first..=end].fill(0.0);
+        }
+
+        self.gap = (first < end).then_some(Gap {
+            first,
+            end,
+            levels,
+            open,
+        });
+    }
+
+    /// Water that has run into the particles' columns through their left and right ends
+    /// since the last call, as an area
+    pub fn take_transferred(&mut self) -> [f32; 2] {
+        std::mem::take(&mut self.transferred)
+    }
+
+    /// Pours `area` of water moving at `velocity` into the column at `x`, or the nearest
+    /// one not handed over to the particles, mixing its momentum into the flow there
+    pub fn add_water(&mut self, x: f32, area: f32, velocity: f32) {
+        let cells = self.depths.len();
+        let mut cell = (((x - self.left) / self.cell_size).max(0.0) as usize).min(cells - 1);
+        if let Some(gap) = self.gap.filter(|gap| gap.contains(cell)) {
+            let before = gap.first.checked_sub(1);
+            let after = (gap.end < cells).then_some(gap.end);
+            let center = (gap.first + gap.end) as f32 / 2.0;
+            cell = match (before, after) {
+                (Some(before), Some(after)) => {
+                    if (cell as f32) < center {
+                        before
+                    } else {
+                        after
+                    }
+                }
+                (Some(before), None) => before,
+                (None, Some(after)) => after,
+                (None, None) => return,
+            };
+        }
+
+        let added = area / self.cell_size;
+        if added <= 0.0 {
+            return;
+        }
+
+        let depth = self.depths[cell];
+        for face in [cell, cell + 1] {
+            if face > 0 && face < cells && !self.in_gap(face - 1) && !self.in_gap(face) {
+                let flow = self.velocities[face];
+                self.velocities[face] = (flow * depth + velocity * added) / (depth + added);
+            }
+        }
+        self.depths[cell] += added;
+    }
+
+    fn right(&self) -> f32 {
+        self.left + self.cell_size * self.depths.len() as f32
+    }
+
+    /// Whether `x` lies within the body's columns
+    pub fn covers(&self, x: f32) -> bool {
+        (self.left..=self.right()).contains(&x)
+    }
+
+    /// Depth of the water column at `x`, interpolated between cell centers, `None` outside
+    /// the body or over the particles' columns
+    fn depth_at(&self, x: f32) -> Option<f32> {
+        if !self.covers(x) {
+            return None;
+        }
+
+        let position = (x - self.left) / self.cell_size;
+        let cell = (position as usize).min(self.depths.len() - 1);
+        if self.in_gap(cell) {
+            return None;
+        }
+
+        // Blend toward the neighbor on the side of `x`, unless that is past an end.
+        let offset = position - cell as f32 - 0.5;
+        let neighbor = if offset < 0.0 {
+            cell.checked_sub(1)
+        } else {
+            Some(cell + 1).filter(|&next| next < self.depths.len())
+        };
+
+        Some(match neighbor.filter(|&neighbor| !self.in_gap(neighbor)) {
+            Some(neighbor) => {
+                self.depths[cell] + (self.depths[neighbor] - self.depths[cell]) * offset.abs()
+            }
+            None => self.depths[cell],
+        })
+    }
+
+    /// Height of the water surface above `x`, `None` outside the body or where it has run
+    /// dry
+    pub fn surface_height_at(&self, x: f32) -> Option<f32> {
+        self.depth_at(x)
+            .filter(|&depth| depth > DRY_DEPTH)
+            .map(|depth| self.bed + depth)
+    }
+
+    pub fn is_submerged(&self, point: Vec2) -> bool {
+        self.surface_height_at(point.x)
+            .is_some_and(|height| (self.bed..=height).contains(&point.y))
+    }
+
+    /// Velocity of the water at `point`, the same through the depth of its column, `None`
+    /// unless it is submerged
+    pub fn velocity_at(&self, point: Vec2) -> Option<Vec2> {
+        if !self.is_submerged(point) {
+            return None;
+        }
+
+        let position = (point.x - self.left) / self.cell_size;
+        let index = (position as usize).min(self.depths.len() - 1);
+        let velocity = self.velocities[index]
+            + (self.velocities[index + 1] - self.velocities[index]) * (position - index as f32);
+
+        Some(Vec2::new(velocity, 0.0))
+    }
+
+    // One explicit step on the staggered grid: the flow at each cell boundary is carried
+    // along itself and pushed down the slope of the surface, then each cell's depth changes
+    // by the water carried across its boundaries, taken from the upstream cell.
+    fn step(&mut self, delta_time: f32, gravity: Vec2) {
+        let cells = self.depths.len();
+        let spacing = self.cell_size;
+        let fall = (-gravity.y).max(0.0);
+        let gap = self.gap;
+        let in_gap = |cell: usize| gap.is_some_and(|gap| gap.contains(cell));
+        let gap_depth = |level: Option<f32>| level.map_or(0.0, |level| (level - self.bed).max(0.0));
+
+        let previous = self.velocities.clone();
+        for face in 1..cells {
+            let (left_gap, right_gap) = (in_gap(face - 1), in_gap(face));
+            let (left, right, open) = match (gap, left_gap, right_gap) {
+                (_, true, true) => (0.0, 0.0, false),
+                (Some(gap), false, true) => {
+                    (self.depths[face - 1], gap_depth(gap.levels[0]), gap.open[0])
+                }
+                (Some(gap), true, false) => {
+                    (gap_depth(gap.levels[1]), self.depths[face], gap.open[1])
+                }
+                _ => (self.depths[face - 1], self.depths[face], true),
+            };
+            if !open || (left <= DRY_DEPTH && right <= DRY_DEPTH) {
+                self.velocities[face] = 0.0;
+                continue;
+            }
+
+            let velocity = previous[face];
+            let upwind = if velocity > 0.0 {
+                velocity - previous[face - 1]
+            } else {
+                previous[face + 1] - velocity
+            };
+
+            let mut updated = velocity
+                + delta_time
+                    * (gravity.x - velocity * upwind / spacing - fall * (right - left) / spacing);
+            updated *= (1.0 - self.damping * delta_time).max(0.0);
+
+            // Water only leaves the gap as particles.
+            if (left_gap && updated > 0.0) || (right_gap && updated < 0.0) {
+                updated = 0.0;
+            }
+            self.velocities[face] = updated;
+        }
+
+        let mut fluxes: Vec<f32> = (0..=cells)
+            .map(|face| {
+                let velocity = self.velocities[face];
+                match face {
+                    0 => 0.0,
+                    _ if face == cells => 0.0,
+                    _ if velocity > 0.0 => velocity * self.depths[face - 1],
+                    _ => velocity * self.depths[face],
+                }
+            })
+            .collect();
+
+        // Cells never give away more water than they hold.
+        for cell in (0..cells).filter(|&cell| !in_gap(cell)) {
+            let outflow = fluxes[cell + 1].max(0.0) - fluxes[cell].min(0.0);
+            let available = self.depths[cell] * spacing / delta_time;
+            if outflow > available {
+                let scale = available / outflow;
+                if fluxes[cell + 1] > 0.0 {
+                    fluxes[cell + 1] *= scale;
+                }
+                if fluxes[cell] < 0.0 {
+                    fluxes[cell] *= scale;
+                }
+            }
+        }
+
+        if let Some(gap) = gap {
+            if gap.first > 0 {
+                self.transferred[0] += fluxes[gap.first] * delta_time;
+            }
+            if gap.end < cells {
+                self.transferred[1] -= fluxes[gap.end] * delta_time;
+            }
+        }
+
+        for cell in (0..cells).filter(|&cell| !in_gap(cell)) {
+            self.depths[cell] = (self.depths[cell]
+                - delta_time * (fluxes[cell + 1] - fluxes[cell]) / spacing)
+                .max(0.0);
+        }
+    }
+}
+
+pub struct ShallowWaterPlugin;
+
+impl Plugin for ShallowWaterPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<ShallowWater>().add_systems(
+            SIMULATION_SCHEDULE,
+            shallow_water_system.in_set(SimulationSet::Integration),
+        );
+
+        // Headless runs draw nothing.
+        if app.world().contains_resource::<Assets<ColorMaterial>>() {
+            app.add_systems(
+                Update,
+                (attach_lake_mesh_system, update_lake_meshes_system).chain(),
+            );
+        }
+    }
+}
+
+pub fn shallow_water_system(
+    time: Res<Time>,
+    params: Res<SimulationParams>,
+    mut bodies: Query<&mut ShallowWater>,
+) {
+    let delta_time = time.delta_secs();
+    if delta_time <= 0.0 {
+        return;
+    }
+
+    let gravity = params.gravity_vector();
+
+    for mut water in bodies.iter_mut() {
+        let deepest = water.depths.iter().copied().fold(0.0, f32::max);
+        let fastest = water
+            .velocities
+            .iter()
+            .map(|velocity| velocity.abs())
+            .fold(0.0, f32::max);
+        let speed = fastest + (gravity.length() * deepest).sqrt();
+        let substeps = ((delta_time * speed / (COURANT_NUMBER * water.cell_size)).ceil() as usize)
+            .clamp(1, MAX_SUBSTEPS);
+
+        for _ in 0..substeps {
+            water.step(delta_time / substeps as f32, gravity);
+        }
+    }
+}
+
+// Each body is drawn as one mesh filling the space between its bed and surface, in a
+// material of its own color.
+fn attach_lake_mesh_system(
+    mut commands: Commands,
+    bodies: Query<(Entity, &ShallowWater), Added<ShallowWater>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    for (entity, water) in bodies.iter() {
+        commands.entity(entity).insert((
+            Mesh2d(meshes.add(lake_mesh(water))),
+            MeshMaterial2d(materials.add(water.color)),
+            Transform::from_xyz(0.0, 0.0, LAKE_DEPTH),
+        ));
+    }
+}
+
+fn update_lake_meshes_system(
+    bodies: Query<(&ShallowWater, &Mesh2d), Changed<ShallowWater>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    for (water, mesh) in bodies.iter() {
+        if let Some(mesh) = meshes.get_mut(&mesh.0) {
+            *mesh = lake_mesh(water);
+        }
+    }
+}
+
+// A bottom and a top vertex on every cell boundary, the top at the mean depth of the cells
+// either side, leaving out the particles' columns.
+fn lake_mesh(water: &ShallowWater) -> Mesh {
+    let cells = water.depths.len();
+    let positions: Vec<[f32; 3]> = (0..=cells)
+        .flat_map(|face| {
+            let x = water.left + water.cell_size * face as f32;
+            let sides: Vec<f32> = [face.checked_sub(1), Some(face).filter(|&face| face < cells)]
+                .into_iter()
+                .flatten()
+                .filter(|&cell| !water.in_gap(cell))
+                .map(|cell| water.depths[cell])
+                .collect();
+            let depth = sides.iter().sum::<f32>() / sides.len().max(1) as f32;
+            [[x, water.bed, 0.0], [x, water.bed + depth, 0.0]]
+        })
+        .collect();
+    let indices: Vec<u32> = (0..cells)
+        .filter(|&cell| !water.in_gap(cell))
+        .flat_map(|cell| {
+            let [bottom, top, next_bottom, next_top] =
+                [0, 1, 2, 3].map(|offset| 2 * cell as u32 + offset);
+            [bottom, next_bottom, top, top, next_bottom, next_top]
+        })
+        .collect();
+
+    Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    )
+    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+    .with_inserted_indices(Indices::U32(indices))
+}
@@ -0,0 +1,162 @@
+use bevy::{gizmos::GizmoPlugin, prelude::*};
+
+use crate::{
+    dilation::TimeScale,
+    fluid_commands::{spawn_particles, FluidMaterial},
+    velocity_system, SimulationSet, Velocity, SIMULATION_SCHEDULE,
+};
+
+// Floppy object made of particles tied together by springs. The particles take part in the
+// fluid solver like any other, so they push fluid aside and are pushed back by its pressure
+// and collisions, while the springs pull them toward the shape they were spawned in.
+// Despawning the body cuts the springs and leaves the particles as fluid.
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct SoftBody {
+    springs: Vec<Spring>,
+    /// Acceleration per unit of stretch, in 1/s². Stiffer than about the square of the step
+    /// rate oscillates out of control.
+    pub stiffness: f32,
+    /// Acceleration per unit of stretching speed, in 1/s
+    pub damping: f32,
+}
+
+#[derive(Reflect, Clone, Copy)]
+struct Spring {
+    ends: [Entity; 2],
+    rest_length: f32,
+}
+
+impl SoftBody {
+    pub fn new(stiffness: f32, damping: f32) -> Self {
+        Self {
+            springs: Vec::new(),
+            stiffness,
+            damping,
+        }
+    }
+}
+
+pub trait SoftBodyCommands {
+    /// Spawns particles at `positions`, ties each to every other within `reach` of it, and
+    /// spawns `body`, which must include the `SoftBody`, to hold the springs. Reaching past
+    /// the nearest neighbors of a lattice to its diagonals keeps the mesh from shearing flat.
+    fn spawn_soft_body(
+        &mut self,
+        positions: Vec<Vec2>,
+        reach: f32,
+        material: FluidMaterial,
+        body: impl Bundle,
+    );
+}
+
+impl SoftBodyCommands for Commands<'_, '_> {
+    fn spawn_soft_body(
+        &mut self,
+        positions: Vec<Vec2>,
+        reach: f32,
+        material: FluidMaterial,
+        body: impl Bundle,
+    ) {
+        self.queue(move |world: &mut World| {
+            let particles: Vec<(Entity, Vec2)> =
+                spawn_particles(world, positions.clone(), &material)
+                    .into_iter()
+                    .zip(positions)
+                    .collect();
+
+            let mut springs = Vec::new();
+            for (index, &(a, position_a)) in particles.iter().enumerate() {
+                for &(b, position_b) in &particles[index + 1..] {
+                    let rest_length = position_a.distance(position_b);
+                    if rest_length <= reach {
+                        springs.push(Spring {
+                            ends: [a, b],
+                            rest_length,
+                        });
+                    }
+                }
+            }
+
+            let mut body = world.spawn(body);
+            if let Some(mut soft_body) = body.get_mut::<SoftBody>() {
+                soft_body.springs = springs;
+            }
+        });
+    }
+}
+
+pub struct SoftBodyPlugin;
+
+impl Plugin for SoftBodyPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<SoftBody>().add_systems(
+            SIMULATION_SCHEDULE,
+            spring_system
+                .in_set(SimulationSet::Forces)
+                .after(velocity_system),
+        );
+
+        if app.is_plugin_added::<GizmoPlugin>() {
+            app.add_systems(Update, draw_soft_bodies_system);
+        }
+    }
+}
+
+fn spring_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut bodies: Query<(Entity, &mut SoftBody)>,
+    mut particles: Query<(&Transform, &mut Velocity, &TimeScale)>,
+) {
+    for (body_entity, mut body) in bodies.iter_mut() {
+        // Springs to deleted or frozen particles are cut.
+        body.springs
+            .retain(|spring| particles.get_many(spring.ends).is_ok());
+
+        if body.springs.is_empty() {
+            commands.entity(body_entity).despawn();
+            continue;
+        }
+
+        for spring in &body.springs {
+            let Ok(
+                [(transform_a, mut velocity_a, scale_a), (transform_b, mut velocity_b, scale_b)],
+            ) = particles.get_many_mut(spring.ends)
+            else {
+                continue;
+            };
+
+            let offset = (transform_b.translation - transform_a.translation).truncate();
+            let Some(direction) = offset.try_normalize() else {
+                continue;
+            };
+
+            let stretch = offset.length() - spring.rest_length;
+            let stretching = (velocity_b.0 - velocity_a.0).truncate().dot(direction);
+            let delta_time = time.delta_secs() * (scale_a.0 + scale_b.0) / 2.0;
+            let pull = (body.stiffness * stretch + body.damping * stretching) * delta_time;
+
+            velocity_a.0 += (direction * pull).extend(0.0);
+            velocity_b.0 -= (direction * pull).extend(0.0);
+        }
+    }
+}
+
+fn draw_soft_bodies_system(
+    mut gizmos: Gizmos,
+    bodies: Query<&SoftBody>,
+    particles: Query<&Transform, With<Velocity>>,
+) {
+    for body in bodies.iter() {
+        for spring in &body.springs {
+            if let Ok([a, b]) = particles.get_many(spring.ends) {
+                gizmos.line_2d(
+                    a.translation.truncate(),
+                    b.translation.truncate(),
+                    Color::srgb(0.95, 0.6, 0.7),
+                );
+            }
+        }
+    }
+}
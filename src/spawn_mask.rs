@@ -0,0 +1,101 @@
+use bevy::{color::Alpha, prelude::*, utils::HashMap};
+use clap::ValueEnum;
+
+use crate::{
+    cli::Args,
+    domain::Domain,
+    fluid_commands::{FluidCommands, FluidMaterial},
+    params::SimulationParams,
+};
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum MaskChannel {
+    Alpha,
+    Luminance,
+}
+
+impl MaskChannel {
+    fn sample(self, color: Color) -> f32 {
+        match self {
+            MaskChannel::Alpha => color.alpha(),
+            MaskChannel::Luminance => {
+                let linear = color.to_linear();
+                (0.2126 * linear.red + 0.7152 * linear.green + 0.0722 * linear.blue) * linear.alpha
+            }
+        }
+    }
+}
+
+pub struct SpawnMaskPlugin;
+
+impl Plugin for SpawnMaskPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, load_spawn_mask)
+            .add_systems(Update, spawn_from_mask_system);
+    }
+}
+
+#[derive(Resource)]
+struct SpawnMaskHandle(Handle<Image>);
+
+fn load_spawn_mask(mut commands: Commands, args: Res<Args>, asset_server: Res<AssetServer>) {
+    if let Some(path) = &args.spawn_mask {
+        commands.insert_resource(SpawnMaskHandle(asset_server.load(path.clone())));
+    }
+}
+
+fn spawn_from_mask_system(
+    mut commands: Commands,
+    args: Res<Args>,
+    params: Res<SimulationParams>,
+    domains: Query<&Domain>,
+    mask: Option<Res<SpawnMaskHandle>>,
+    images: Res<Assets<Image>>,
+) {
+    let Some(mask) = mask else {
+        return;
+    };
+
+    let Some(image) = images.get(&mask.0) else {
+        return;
+    };
+
+    commands.remove_resource::<SpawnMaskHandle>();
+
+    let image_size = image.size();
+    let scale = (domains.single().size / image_size.as_vec2()).min_element();
+    let extent = image_size.as_vec2() * scale;
+    let columns = (extent.x / params.particle_spacing) as u32;
+    let rows = (extent.y / params.particle_spacing) as u32;
+    // One batch per pixel color, each kept in the mask's column and row order.
+    let mut batches: HashMap<[u8; 4], (Option<Color>, Vec<Vec2>)> = HashMap::new();
+
+    for column in 0..columns {
+        for row in 0..rows {
+            let offset = Vec2::new(column as f32 + 0.5, row as f32 + 0.5) * params.particle_spacing;
+            let pixel = (offset / scale).as_uvec2().min(image_size - 1);
+
+            let Ok(color) = image.get_color_at(pixel.x, pixel.y) else {
+                continue;
+            };
+
+            if args.mask_channel.sample(color) <= args.mask_threshold {
+                continue;
+            }
+
+            let position = Vec2::new(offset.x - extent.x / 2.0, extent.y / 2.0 - offset.y);
+            let color = (!args.mask_uncolored).then(|| color.with_alpha(1.0));
+            let key = color.map_or([0; 4], |color| color.to_srgba().to_u8_array());
+
+            batches
+                .entry(key)
+                .or_insert_with(|| (color, Vec::new()))
+                .1
+                .push(position);
+        }
+    }
+
+    for (color, positions) in batches.into_values() {
+        commands.spawn_fluid(positions, FluidMaterial { color, ..default() });
+    }
+}
@@ -0,0 +1,206 @@
+use std::time::Duration;
+
+use bevy::{
+    audio::{AudioPlugin, Pitch, SpatialScale, Volume},
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use bevy_pancam::PanCam;
+
+use crate::{
+    cache_density_system, precision::to_vec3, NeighborCount, Position, SimulationSet, Velocity,
+    SIMULATION_SCHEDULE,
+};
+
+// Neighbors a falling particle needs to count as back in the fluid.
+const REENTRY_NEIGHBORS: u32 = 3;
+// Shortest gap between two splash sounds, keeping a wave breaking on a wall from buzzing.
+const MIN_SOUND_INTERVAL: f32 = 0.05;
+const SOUND_DURATION: Duration = Duration::from_millis(60);
+// Pitch of the quietest and loudest splashes, heavier impacts sounding deeper.
+const SOFT_FREQUENCY: f32 = 900.0;
+const HARD_FREQUENCY: f32 = 300.0;
+
+/// Sent when a particle hits a wall, collider or boundary, or falls back into the fluid,
+/// faster than `SplashSettings::min_speed`.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct SplashEvent {
+    pub position: Vec2,
+    /// Speed of the impact
+    pub intensity: f32,
+}
+
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct SplashSettings {
+    /// Slowest impact reported as a splash
+    pub min_speed: f32,
+    /// Impact speed played at full volume
+    pub loud_speed: f32,
+}
+
+impl Default for SplashSettings {
+    fn default() -> Self {
+        Self {
+            min_speed: 40.0,
+            loud_speed: 200.0,
+        }
+    }
+}
+
+// Velocities after integration, before the collision systems respond to any contacts.
+#[derive(Resource, Default)]
+struct IncomingVelocities(HashMap<Entity, Vec3>);
+
+// Plays a tone for each splash when `audio` is set.
+pub struct SplashPlugin {
+    pub audio: bool,
+}
+
+impl Plugin for SplashPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SplashEvent>()
+            .init_resource::<SplashSettings>()
+            .init_resource::<IncomingVelocities>()
+            .register_type::<SplashSettings>()
+            .add_systems(
+                SIMULATION_SCHEDULE,
+                (
+                    reentry_system
+                        .in_set(SimulationSet::Density)
+                        .after(cache_density_system),
+                    record_velocities_system
+                        .after(SimulationSet::Integration)
+                        .before(SimulationSet::Collision),
+                    impact_system.after(SimulationSet::Collision),
+                ),
+            );
+
+        if self.audio && app.is_plugin_added::<AudioPlugin>() {
+            app.add_systems(Update, splash_audio_system);
+        }
+    }
+}
+
+fn record_velocities_system(
+    mut incoming: ResMut<IncomingVelocities>,
+    particles: Query<(Entity, &Velocity)>,
+) {
+    incoming.0.clear();
+    incoming.0.extend(
+        particles
+            .iter()
+            .map(|(entity, velocity)| (entity, velocity.0)),
+    );
+}
+
+// Collisions push particles out of whatever they hit without touching `Position`, so a
+// particle whose `Transform` moved away from it along with a bounce made contact this step.
+fn impact_system(
+    settings: Res<SplashSettings>,
+    incoming: Res<IncomingVelocities>,
+    particles: Query<(Entity, &Transform, &Position, &Velocity)>,
+    mut splashes: EventWriter<SplashEvent>,
+) {
+    for (entity, transform, position, velocity) in particles.iter() {
+        let Some(&before) = incoming.0.get(&entity) else {
+            continue;
+        };
+
+        let Some(normal) = (transform.translation - to_vec3(position.0)).try_normalize() else {
+            continue;
+        };
+
+        // Wrapping around a periodic edge moves the particle without changing its velocity.
+        if (velocity.0 - before).dot(normal) <= 0.0 {
+            continue;
+        }
+
+        let speed = -before.dot(normal);
+        if speed >= settings.min_speed {
+            splashes.send(SplashEvent {
+                position: transform.translation.truncate(),
+                intensity: speed,
+            });
+        }
+    }
+}
+
+// Particles with no neighbors are flying free, and splash when they land back in the fluid.
+fn reentry_system(
+    settings: Res<SplashSettings>,
+    particles: Query<(Entity, &Transform, &Velocity, &NeighborCount)>,
+    mut airborne: Local<HashSet<Entity>>,
+    mut splashes: EventWriter<SplashEvent>,
+) {
+    let mut still_airborne = HashSet::new();
+
+    for (entity, transform, velocity, neighbors) in particles.iter() {
+        if neighbors.0 == 0 {
+            still_airborne.insert(entity);
+            continue;
+        }
+
+        let speed = velocity.0.length();
+        if airborne.contains(&entity)
+            && neighbors.0 >= REENTRY_NEIGHBORS
+            && speed >= settings.min_speed
+        {
+            splashes.send(SplashEvent {
+                position: transform.translation.truncate(),
+                intensity: speed,
+            });
+        }
+    }
+
+    *airborne = still_airborne;
+}
+
+// Plays the strongest splash of each frame as a short tone, louder and deeper the harder
+// the impact. The tone is panned toward where the splash is across the view, the ears of
+// the listener sitting at the view's left and right edges.
+fn splash_audio_system(
+    mut commands: Commands,
+    time: Res<Time<Real>>,
+    settings: Res<SplashSettings>,
+    mut splashes: EventReader<SplashEvent>,
+    mut pitches: ResMut<Assets<Pitch>>,
+    cameras: Query<(Entity, &OrthographicProjection), With<PanCam>>,
+    mut last_sound: Local<Option<f32>>,
+) {
+    let Some(splash) =
+        splashes
+            .read()
+            .copied()
+            .reduce(|a, b| if b.intensity > a.intensity { b } else { a })
+    else {
+        return;
+    };
+    let intensity = splash.intensity;
+
+    let now = time.elapsed_secs();
+    if last_sound.is_some_and(|last| now - last < MIN_SOUND_INTERVAL) {
+        return;
+    }
+    *last_sound = Some(now);
+
+    let loudness = (intensity / settings.loud_speed.max(f32::EPSILON)).clamp(0.0, 1.0);
+    let frequency = SOFT_FREQUENCY + (HARD_FREQUENCY - SOFT_FREQUENCY) * loudness;
+
+    let mut playback = PlaybackSettings::DESPAWN.with_volume(Volume::new(loudness));
+    if let Ok((camera, projection)) = cameras.get_single() {
+        let width = projection.area.width();
+        if width > 0.0 {
+            commands.entity(camera).insert(SpatialListener::new(width));
+            playback = playback
+                .with_spatial(true)
+                .with_spatial_scale(SpatialScale::new_2d(2.0 / width));
+        }
+    }
+
+    commands.spawn((
+        AudioPlayer(pitches.add(Pitch::new(frequency, SOUND_DURATION))),
+        playback,
+        Transform::from_translation(splash.position.extend(0.0)),
+    ));
+}
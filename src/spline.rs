@@ -0,0 +1,74 @@
+use bevy::prelude::*;
+
+// Points traced along each span between two control points when flattening a spline.
+const SAMPLES_PER_SPAN: usize = 16;
+
+// Uniform Catmull-Rom curve through every control point, with the end points repeated so it
+// starts and stops exactly at the first and last of them.
+#[derive(Reflect, Clone, Default, Debug)]
+pub struct Spline {
+    pub points: Vec<Vec2>,
+}
+
+impl Spline {
+    pub fn new(points: Vec<Vec2>) -> Self {
+        Self { points }
+    }
+
+    fn point(&self, index: isize) -> Vec2 {
+        self.points[index.clamp(0, self.points.len() as isize - 1) as usize]
+    }
+
+    // Position `t` of the way through the span starting at control point `span`.
+    fn span_position(&self, span: usize, t: f32) -> Vec2 {
+        let span = span as isize;
+        let [p0, p1, p2, p3] = [span - 1, span, span + 1, span + 2].map(|index| self.point(index));
+        let (t2, t3) = (t * t, t * t * t);
+
+        0.5 * (2.0 * p1
+            + (p2 - p0) * t
+            + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t2
+            + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t3)
+    }
+
+    /// Points along the curve, close enough together to draw or walk it as straight lines
+    pub fn polyline(&self) -> Vec<Vec2> {
+        match self.points.len() {
+            0 => Vec::new(),
+            1 => self.points.clone(),
+            count => (0..count - 1)
+                .flat_map(|span| {
+                    (0..SAMPLES_PER_SPAN)
+                        .map(move |step| (span, step as f32 / SAMPLES_PER_SPAN as f32))
+                })
+                .map(|(span, t)| self.span_position(span, t))
+                .chain(self.points.last().copied())
+                .collect(),
+        }
+    }
+
+    /// Point `fraction` of the way along the curve by length, and the unit tangent there
+    pub fn sample(&self, fraction: f32) -> Option<(Vec2, Vec2)> {
+        let polyline = self.polyline();
+        let segments: Vec<(Vec2, Vec2, f32)> = polyline
+            .windows(2)
+            .map(|pair| (pair[0], pair[1], pair[0].distance(pair[1])))
+            .filter(|&(_, _, length)| length > 0.0)
+            .collect();
+
+        let total: f32 = segments.iter().map(|&(_, _, length)| length).sum();
+        let mut remaining = fraction.clamp(0.0, 1.0) * total;
+
+        for &(start, end, length) in &segments {
+            if remaining <= length {
+                let direction = (end - start) / length;
+                return Some((start + direction * remaining, direction));
+            }
+            remaining -= length;
+        }
+
+        segments
+            .last()
+            .map(|&(start, end, length)| (end, (end - start) / length))
+    }
+}
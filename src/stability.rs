@@ -0,0 +1,140 @@
+use bevy::prelude::*;
+
+use crate::{
+    params::SimulationParams, precision::to_f32, DensityCache, NeighborCount, SimulationSet,
+    Velocity, SIMULATION_SCHEDULE,
+};
+
+#[derive(Reflect, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InstabilityKind {
+    /// Position or velocity became NaN or infinite
+    NonFinite,
+    /// Speed exceeded `SimulationParams::max_speed`
+    TooFast,
+}
+
+/// Sent once for each particle taken out of the simulation, with its state at the end of
+/// the step that blew it up.
+#[derive(Event, Clone, Debug)]
+pub struct SolverInstability {
+    pub entity: Entity,
+    pub kind: InstabilityKind,
+    pub position: Vec3,
+    pub velocity: Vec3,
+    pub density: Option<f32>,
+    /// Neighbors it had in the density stage of that step
+    pub neighbors: usize,
+}
+
+// Marks a particle removed from the simulation. It keeps its components apart from
+// `Velocity`, so it stays around, hidden, for inspection.
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct Quarantined(pub InstabilityKind);
+
+#[derive(Resource, Reflect, Default)]
+#[reflect(Resource)]
+pub struct InstabilityCounts {
+    pub non_finite: usize,
+    pub too_fast: usize,
+}
+
+pub struct StabilityPlugin;
+
+impl Plugin for StabilityPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SolverInstability>()
+            .init_resource::<InstabilityCounts>()
+            .register_type::<Quarantined>()
+            .register_type::<InstabilityCounts>()
+            .add_systems(
+                SIMULATION_SCHEDULE,
+                (quarantine_system, count_instabilities_system)
+                    .chain()
+                    .after(SimulationSet::Collision),
+            );
+    }
+}
+
+fn quarantine_system(
+    mut commands: Commands,
+    params: Res<SimulationParams>,
+    density_cache: Res<DensityCache>,
+    particles: Query<(Entity, &Transform, &Velocity, Option<&NeighborCount>)>,
+    mut instabilities: EventWriter<SolverInstability>,
+) {
+    let unstable: Vec<_> = particles
+        .iter()
+        .filter_map(|(entity, transform, velocity, neighbors)| {
+            let kind = if !transform.translation.is_finite() || !velocity.0.is_finite() {
+                InstabilityKind::NonFinite
+            } else if velocity.0.length() > params.max_speed {
+                InstabilityKind::TooFast
+            } else {
+                return None;
+            };
+
+            let neighbors = neighbors.map_or(0, |count| count.0 as usize);
+            Some((entity, kind, transform.translation, velocity.0, neighbors))
+        })
+        .collect();
+
+    if unstable.is_empty() {
+        return;
+    }
+
+    for (entity, kind, position, velocity, neighbors) in unstable {
+        instabilities.send(SolverInstability {
+            entity,
+            kind,
+            position,
+            velocity,
+            density: density_cache.densities.get(&entity).copied().map(to_f32),
+            neighbors,
+        });
+
+        commands
+            .entity(entity)
+            .remove::<Velocity>()
+            .insert((Quarantined(kind), Visibility::Hidden));
+    }
+}
+
+fn count_instabilities_system(
+    mut instabilities: EventReader<SolverInstability>,
+    mut counts: ResMut<InstabilityCounts>,
+) {
+    let mut step = InstabilityCounts::default();
+
+    for instability in instabilities.read() {
+        let SolverInstability {
+            entity,
+            kind,
+            position,
+            velocity,
+            density,
+            neighbors,
+        } = instability;
+        debug!(
+            "Quarantined {entity} ({kind:?}) at {position} moving at {velocity}, \
+             density {density:?} with {neighbors} neighbors"
+        );
+
+        match instability.kind {
+            InstabilityKind::NonFinite => step.non_finite += 1,
+            InstabilityKind::TooFast => step.too_fast += 1,
+        }
+    }
+
+    if step.non_finite + step.too_fast == 0 {
+        return;
+    }
+
+    counts.non_finite += step.non_finite;
+    counts.too_fast += step.too_fast;
+
+    warn!(
+        "Quarantined {} non-finite and {} runaway particles this step ({} and {} in total)",
+        step.non_finite, step.too_fast, counts.non_finite, counts.too_fast
+    );
+}
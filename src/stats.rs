@@ -0,0 +1,136 @@
+use std::time::Duration;
+
+use bevy::{
+    prelude::*,
+    utils::{HashMap, Instant},
+};
+
+use crate::{
+    precision::to_f32, DensityCache, NeighborCount, SimulationSet, Velocity, SIMULATION_SCHEDULE,
+};
+
+pub const STAGES: [SimulationSet; 4] = [
+    SimulationSet::Density,
+    SimulationSet::Forces,
+    SimulationSet::Integration,
+    SimulationSet::Collision,
+];
+
+const NEIGHBOR_BUCKETS: usize = 16;
+
+/// Measurements of the most recent simulation step.
+#[derive(Resource, Reflect, Default, Clone, Debug)]
+#[reflect(Resource)]
+pub struct SimulationStats {
+    pub particle_count: usize,
+    pub min_density: f32,
+    pub mean_density: f32,
+    pub max_density: f32,
+    pub max_speed: f32,
+    /// Neighbors each particle interacted with in the density stage
+    pub neighbors: NeighborStats,
+    pub stage_durations: HashMap<SimulationSet, Duration>,
+    #[reflect(ignore)]
+    stage_started: HashMap<SimulationSet, Instant>,
+}
+
+#[derive(Reflect, Default, Clone, Debug)]
+pub struct NeighborStats {
+    pub min: usize,
+    pub mean: f32,
+    pub max: usize,
+    /// Number of particles with each neighbor count, the last bucket holding every count
+    /// beyond it
+    pub histogram: [usize; NEIGHBOR_BUCKETS],
+}
+
+impl SimulationStats {
+    fn begin_stage(&mut self, stage: SimulationSet) {
+        self.stage_started.insert(stage, Instant::now());
+    }
+
+    fn end_stage(&mut self, stage: SimulationSet) {
+        if let Some(started) = self.stage_started.remove(&stage) {
+            self.stage_durations.insert(stage, started.elapsed());
+        }
+    }
+}
+
+pub struct StatsPlugin;
+
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SimulationStats>()
+            .register_type::<SimulationStats>()
+            .add_systems(
+                SIMULATION_SCHEDULE,
+                stats_system.after(SimulationSet::Collision),
+            );
+
+        for (index, stage) in STAGES.into_iter().enumerate() {
+            let mut begin =
+                (move |mut stats: ResMut<SimulationStats>| stats.begin_stage(stage)).before(stage);
+            let mut end =
+                (move |mut stats: ResMut<SimulationStats>| stats.end_stage(stage)).after(stage);
+
+            if let Some(&previous) = index.checked_sub(1).and_then(|i| STAGES.get(i)) {
+                begin = begin.after(previous);
+            }
+
+            if let Some(&next) = STAGES.get(index + 1) {
+                end = end.before(next);
+            }
+
+            app.add_systems(SIMULATION_SCHEDULE, (begin, end));
+        }
+    }
+}
+
+fn stats_system(
+    density_cache: Res<DensityCache>,
+    velocities: Query<&Velocity>,
+    neighbor_counts: Query<&NeighborCount, With<Velocity>>,
+    mut stats: ResMut<SimulationStats>,
+) {
+    let densities: Vec<f32> = density_cache
+        .densities
+        .values()
+        .copied()
+        .map(to_f32)
+        .collect();
+
+    stats.particle_count = velocities.iter().count();
+    stats.min_density = densities.iter().copied().reduce(f32::min).unwrap_or(0.0);
+    stats.max_density = densities.iter().copied().reduce(f32::max).unwrap_or(0.0);
+    stats.mean_density = if densities.is_empty() {
+        0.0
+    } else {
+        densities.iter().sum::<f32>() / densities.len() as f32
+    };
+    stats.max_speed = velocities
+        .iter()
+        .map(|velocity| velocity.0.length())
+        .fold(0.0, f32::max);
+
+    let counts: Vec<usize> = neighbor_counts
+        .iter()
+        .map(|count| count.0 as usize)
+        .collect();
+
+    let mut neighbors = NeighborStats {
+        min: counts.iter().copied().min().unwrap_or(0),
+        max: counts.iter().copied().max().unwrap_or(0),
+        mean: if counts.is_empty() {
+            0.0
+        } else {
+            counts.iter().sum::<usize>() as f32 / counts.len() as f32
+        },
+        ..default()
+    };
+
+    for count in counts {
+        neighbors.histogram[count.min(NEIGHBOR_BUCKETS - 1)] += 1;
+    }
+
+    stats.neighbors = neighbors;
+}
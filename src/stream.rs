@@ -0,0 +1,168 @@
+use bevy::{gizmos::GizmoPlugin, input::InputPlugin, prelude::*};
+use bevy_pancam::PanCam;
+use rand::Rng;
+
+use crate::{
+    budget::ParticleBudget,
+    fluid_commands::{FluidCommands, FluidMaterial},
+    spline::Spline,
+    tools::{cursor_world_position, Tool},
+    SimulationRng, SimulationSet, Velocity, SIMULATION_SCHEDULE,
+};
+
+// Distance from a control point within which the cursor grabs it.
+const HANDLE_DISTANCE: f32 = 4.0;
+
+// Spawns `rate` particles per second at random points along `path`, each moving along the
+// path's tangent at `speed`, for waterfalls and rivers that follow the level. The control
+// points can be dragged with the left mouse button while the drag tool is selected.
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct Stream {
+    pub enabled: bool,
+    pub path: Spline,
+    pub rate: f32,
+    pub speed: f32,
+    /// Fixed particle color, density coloring when unset
+    pub color: Option<Color>,
+    pending: f32,
+}
+
+impl Stream {
+    pub fn new(path: Spline, rate: f32, speed: f32) -> Self {
+        Self {
+            enabled: true,
+            path,
+            rate,
+            speed,
+            color: None,
+            pending: 0.0,
+        }
+    }
+}
+
+pub struct StreamPlugin;
+
+impl Plugin for StreamPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Stream>().add_systems(
+            SIMULATION_SCHEDULE,
+            stream_system.in_set(SimulationSet::Integration),
+        );
+
+        if app.is_plugin_added::<GizmoPlugin>() {
+            app.add_systems(Update, draw_streams_system);
+        }
+
+        if app.is_plugin_added::<InputPlugin>() {
+            app.add_systems(
+                Update,
+                stream_handles_system.run_if(resource_exists_and_equals(Tool::Drag)),
+            );
+        }
+    }
+}
+
+fn stream_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    budget: Res<ParticleBudget>,
+    mut rng: ResMut<SimulationRng>,
+    mut streams: Query<&mut Stream>,
+    particles: Query<(), With<Velocity>>,
+) {
+    let mut count = particles.iter().count();
+
+    for mut stream in streams.iter_mut() {
+        if !stream.enabled {
+            continue;
+        }
+
+        stream.pending += stream.rate.max(0.0) * time.delta_secs();
+
+        let mut positions = Vec::new();
+        let mut velocities = Vec::new();
+
+        while stream.pending >= 1.0 {
+            stream.pending -= 1.0;
+
+            if budget.remaining(count) == 0 {
+                stream.pending = 0.0;
+                break;
+            }
+
+            let Some((position, tangent)) = stream.path.sample(rng.0.gen()) else {
+                stream.pending = 0.0;
+                break;
+            };
+
+            positions.push(position);
+            velocities.push(tangent * stream.speed);
+            count += 1;
+        }
+
+        // Each particle moves along the tangent where it appears.
+        for (position, velocity) in positions.into_iter().zip(velocities) {
+            commands.spawn_fluid(
+                vec![position],
+                FluidMaterial {
+                    color: stream.color,
+                    velocity,
+                    ..default()
+                },
+            );
+        }
+    }
+}
+
+fn stream_handles_system(
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    windows: Query<&Window>,
+    cameras: Query<(&Camera, &GlobalTransform), With<PanCam>>,
+    mut streams: Query<(Entity, &mut Stream)>,
+    mut dragged: Local<Option<(Entity, usize)>>,
+) {
+    if !mouse_input.pressed(MouseButton::Left) {
+        *dragged = None;
+        return;
+    }
+
+    let Some(cursor) = cursor_world_position(&windows, &cameras) else {
+        return;
+    };
+
+    if mouse_input.just_pressed(MouseButton::Left) {
+        *dragged = streams.iter().find_map(|(entity, stream)| {
+            stream
+                .path
+                .points
+                .iter()
+                .position(|point| point.distance(cursor) <= HANDLE_DISTANCE)
+                .map(|index| (entity, index))
+        });
+    }
+
+    if let Some((entity, index)) = *dragged {
+        if let Ok((_, mut stream)) = streams.get_mut(entity) {
+            if let Some(point) = stream.path.points.get_mut(index) {
+                *point = cursor;
+            }
+        }
+    }
+}
+
+fn draw_streams_system(mut gizmos: Gizmos, streams: Query<&Stream>) {
+    for stream in streams.iter() {
+        let color = if stream.enabled {
+            Color::srgb(0.3, 0.7, 1.0)
+        } else {
+            Color::srgb(0.3, 0.4, 0.5)
+        };
+
+        gizmos.linestrip_2d(stream.path.polyline(), color);
+
+        for &point in &stream.path.points {
+            gizmos.circle_2d(point, HANDLE_DISTANCE / 2.0, color);
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use bevy::{input::InputPlugin, prelude::*};
+use rand::Rng;
+
+use crate::{
+    budget::ParticleBudget, cli::Args, domain::Domain, params::SimulationParams, particle_bundle,
+    scene::SceneEntity, timeline::Timeline, Position, SimulationRng,
+};
+
+const DEFAULT_STRESS_PARTICLES: usize = 20_000;
+// Height of the column relative to its width.
+const COLUMN_ASPECT: f32 = 4.0;
+// Room left around the column, relative to its size.
+const DOMAIN_MARGIN: Vec2 = Vec2::new(1.5, 1.25);
+// Largest random offset of each particle from its lattice site, relative to the spacing.
+const JITTER: f32 = 0.1;
+
+// Tall column of particles for profiling, spawned by `--stress` at startup or replacing the
+// current scene on F5. The layout only depends on the particle count, spacing and seed.
+pub struct StressPlugin;
+
+impl Plugin for StressPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, stress_startup_system);
+
+        if app.is_plugin_added::<InputPlugin>() {
+            app.add_systems(Update, stress_hotkey_system);
+        }
+    }
+}
+
+fn stress_startup_system(
+    mut commands: Commands,
+    args: Res<Args>,
+    budget: Res<ParticleBudget>,
+    params: Res<SimulationParams>,
+    mut rng: ResMut<SimulationRng>,
+    mut domains: Query<&mut Domain>,
+) {
+    if let Some(count) = args.stress {
+        spawn_stress_column(
+            &mut commands,
+            count.min(budget.remaining(0)),
+            &params,
+            &mut rng,
+            &mut domains.single_mut(),
+        );
+    }
+}
+
+fn stress_hotkey_system(
+    mut commands: Commands,
+    input: Res<ButtonInput<KeyCode>>,
+    args: Res<Args>,
+    budget: Res<ParticleBudget>,
+    params: Res<SimulationParams>,
+    mut rng: ResMut<SimulationRng>,
+    mut domains: Query<&mut Domain>,
+    spawned: Query<Entity, Or<(With<Position>, With<SceneEntity>)>>,
+) {
+    if !input.just_pressed(KeyCode::F5) {
+        return;
+    }
+
+    for entity in spawned.iter() {
+        commands.entity(entity).despawn();
+    }
+    commands.insert_resource(Timeline::default());
+
+    let count = args.stress.unwrap_or(DEFAULT_STRESS_PARTICLES);
+    spawn_stress_column(
+        &mut commands,
+        count.min(budget.remaining(0)),
+        &params,
+        &mut rng,
+        &mut domains.single_mut(),
+    );
+}
+
+fn spawn_stress_column(
+    commands: &mut Commands,
+    count: usize,
+    params: &SimulationParams,
+    rng: &mut SimulationRng,
+    domain: &mut Domain,
+) {
+    let spacing = params.particle_spacing;
+    let columns = ((count as f32 / COLUMN_ASPECT).sqrt().ceil() as usize).max(1);
+    let rows = count.div_ceil(columns);
+    let size = Vec2::new(columns as f32, rows as f32) * spacing;
+
+    domain.size = domain.size.max(size * DOMAIN_MARGIN);
+
+    // Rest the column on the floor, against the left wall.
+    let corner = -domain.half_size() + Vec2::splat(spacing / 2.0);
+    let jitter = JITTER * spacing;
+
+    for index in 0..count {
+        let site = Vec2::new((index % columns) as f32, (index / columns) as f32);
+        let offset = Vec2::new(
+            rng.0.gen_range(-jitter..=jitter),
+            rng.0.gen_range(-jitter..=jitter),
+        );
+
+        commands.spawn(particle_bundle(
+            (corner + site * spacing + offset).extend(0.0),
+            Color::hsl(0.5, 0.95, 0.7),
+        ));
+    }
+
+    info!("Spawned stress column of {count} particles, {columns} wide and {rows} tall");
+}
@@ -0,0 +1,196 @@
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use bevy::{prelude::*, utils::HashMap};
+use serde::Serialize;
+
+use crate::{
+    conservation::Conservation,
+    domain::Domain,
+    headless_exit_system,
+    params::SimulationParams,
+    precision::to_f32,
+    stats::{SimulationStats, STAGES},
+    DensityCache, SimulationSet, Velocity,
+};
+
+pub struct SummaryPlugin(pub PathBuf);
+
+impl Plugin for SummaryPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(RunStatistics::new(self.0.clone()))
+            .add_systems(PostUpdate, record_statistics_system)
+            .add_systems(Last, write_summary_system.after(headless_exit_system));
+    }
+}
+
+#[derive(Resource)]
+struct RunStatistics {
+    path: PathBuf,
+    started: Instant,
+    stage_totals: HashMap<SimulationSet, Duration>,
+    steps: u32,
+    density_error_sum: f64,
+    density_error_samples: u32,
+    max_velocity: f32,
+    particle_counts: Vec<ParticleCountSample>,
+}
+
+impl RunStatistics {
+    fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            started: Instant::now(),
+            stage_totals: HashMap::new(),
+            steps: 0,
+            density_error_sum: 0.0,
+            density_error_samples: 0,
+            max_velocity: 0.0,
+            particle_counts: Vec::new(),
+        }
+    }
+
+    fn summary(
+        &self,
+        params: &SimulationParams,
+        domain: &Domain,
+        conservation: Option<&Conservation>,
+    ) -> RunSummary {
+        let stages = STAGES
+            .into_iter()
+            .map(|stage| {
+                let total = self.stage_totals.get(&stage).copied().unwrap_or_default();
+                let mean = total.checked_div(self.steps).unwrap_or_default();
+
+                (
+                    format!("{stage:?}").to_lowercase(),
+                    StageSummary {
+                        total_secs: total.as_secs_f64(),
+                        mean_ms: mean.as_secs_f64() * 1000.0,
+                    },
+                )
+            })
+            .collect();
+
+        RunSummary {
+            steps: self.steps,
+            wall_time_secs: self.started.elapsed().as_secs_f64(),
+            stages,
+            average_density_error: if self.density_error_samples > 0 {
+                self.density_error_sum / self.density_error_samples as f64
+            } else {
+                0.0
+            },
+            max_velocity: self.max_velocity,
+            particle_counts: self.particle_counts.clone(),
+            conservation: conservation.cloned(),
+            params: params.clone(),
+            domain_size: domain.size.to_array(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct RunSummary {
+    steps: u32,
+    wall_time_secs: f64,
+    stages: BTreeMap<String, StageSummary>,
+    /// Mean relative deviation from the target density, averaged over steps
+    average_density_error: f64,
+    max_velocity: f32,
+    /// Particle count at the first step and whenever it changed afterwards
+    particle_counts: Vec<ParticleCountSample>,
+    /// Momentum and energy totals after the last step
+    conservation: Option<Conservation>,
+    params: SimulationParams,
+    domain_size: [f32; 2],
+}
+
+#[derive(Serialize)]
+struct StageSummary {
+    total_secs: f64,
+    mean_ms: f64,
+}
+
+#[derive(Serialize, Clone, Copy)]
+struct ParticleCountSample {
+    step: u32,
+    count: usize,
+}
+
+fn record_statistics_system(
+    params: Res<SimulationParams>,
+    density_cache: Res<DensityCache>,
+    stats: Option<Res<SimulationStats>>,
+    particles: Query<&Velocity>,
+    mut statistics: ResMut<RunStatistics>,
+) {
+    let step = statistics.steps;
+    statistics.steps += 1;
+
+    for (&stage, &duration) in stats.iter().flat_map(|stats| stats.stage_durations.iter()) {
+        *statistics.stage_totals.entry(stage).or_default() += duration;
+    }
+
+    let count = particles.iter().count();
+    if statistics
+        .particle_counts
+        .last()
+        .is_none_or(|sample| sample.count != count)
+    {
+        statistics
+            .particle_counts
+            .push(ParticleCountSample { step, count });
+    }
+
+    for velocity in particles.iter() {
+        statistics.max_velocity = statistics.max_velocity.max(velocity.0.length());
+    }
+
+    if !density_cache.densities.is_empty() && params.target_density > 0.0 {
+        let error: f64 = density_cache
+            .densities
+            .values()
+            .map(|&density| {
+                ((to_f32(density) - params.target_density).abs() / params.target_density) as f64
+            })
+            .sum();
+
+        statistics.density_error_sum += error / density_cache.densities.len() as f64;
+        statistics.density_error_samples += 1;
+    }
+}
+
+fn write_summary_system(
+    mut exits: EventReader<AppExit>,
+    params: Res<SimulationParams>,
+    domains: Query<&Domain>,
+    statistics: Res<RunStatistics>,
+    conservation: Option<Res<Conservation>>,
+    mut written: Local<bool>,
+) {
+    if *written || exits.read().next().is_none() {
+        return;
+    }
+
+    *written = true;
+
+    let path = &statistics.path;
+    let summary = statistics.summary(&params, domains.single(), conservation.as_deref());
+    match write_summary(path, &summary) {
+        Ok(()) => info!("Wrote run summary to {}", path.display()),
+        Err(error) => error!("Failed to write run summary {}: {error}", path.display()),
+    }
+}
+
+fn write_summary(path: &Path, summary: &RunSummary) -> io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    serde_json::to_writer_pretty(&mut file, summary)?;
+    writeln!(file)?;
+    file.flush()
+}
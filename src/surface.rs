@@ -0,0 +1,376 @@
+use bevy::{gizmos::GizmoPlugin, prelude::*, utils::HashMap};
+use bevy_pancam::PanCam;
+
+use crate::{
+    budget::{particle_mass, ParticleMass},
+    density_diffusion_system,
+    domain::Domain,
+    neighbors::FluidNeighbors,
+    params::SimulationParams,
+    precision::{real, real_vec, to_f32, to_vec3, Real, RealVec3},
+    smoothing_kernel, smoothing_kernel_derivative,
+    tools::cursor_world_position,
+    DensityCache, Position, SimulationSet, SpatialGrid, SIMULATION_SCHEDULE,
+};
+
+// Color field gradient, relative to one over the smoothing radius, above which a particle
+// counts as being on the surface.
+const SURFACE_THRESHOLD: Real = 0.3;
+// Color field value separating fluid from air.
+const SUBMERGED_LEVEL: f32 = 0.5;
+// Steps per smoothing radius when scanning a column down for the surface, and the bisection
+// steps refining the crossing.
+const SCAN_STEPS: f32 = 4.0;
+const REFINE_STEPS: usize = 8;
+const NORMAL_LENGTH: f32 = 8.0;
+
+// Shape of the fluid around a particle, refreshed every step from the color field: the sum
+// of the neighbors' kernels weighted by their volume, about 1 inside the fluid and falling
+// to 0 across its surface.
+#[derive(Component, Reflect, Clone, Copy, Default, Debug)]
+#[reflect(Component)]
+pub struct Surface {
+    /// Outward unit normal, zero inside the fluid
+    pub normal: Vec2,
+    /// Divergence of the normals, positive where the surface bulges out like a droplet
+    pub curvature: f32,
+    pub on_surface: bool,
+}
+
+// Particle volumes and masses bucketed on the spatial grid as of the last density stage,
+// for sampling the color field anywhere.
+#[derive(Resource)]
+pub struct ColorField {
+    grid: SpatialGrid,
+    radius: Real,
+    cells: HashMap<(i32, i32), Vec<(RealVec3, Real, Real)>>,
+}
+
+impl ColorField {
+    fn samples(&self, point: RealVec3) -> impl Iterator<Item = (RealVec3, Real, Real, Real)> + '_ {
+        self.grid
+            .neighbor_cells(self.grid.cell(to_vec3(point)))
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .filter_map(move |&(position, volume, mass)| {
+                let offset = self.grid.precise_offset(position, point);
+                let distance = offset.length();
+                (distance < self.radius).then_some((offset, distance, volume, mass))
+            })
+    }
+
+    /// Color field at `point`: about 1 inside the fluid, 0 away from it
+    pub fn color(&self, point: Vec2) -> f32 {
+        to_f32(
+            self.samples(real_vec(point.extend(0.0)))
+                .map(|(_, distance, volume, _)| volume * smoothing_kernel(self.radius, distance))
+                .sum(),
+        )
+    }
+
+    /// Gradient of the color field, pointing into the fluid
+    pub fn gradient(&self, point: Vec2) -> Vec2 {
+        let gradient: RealVec3 = self
+            .samples(real_vec(point.extend(0.0)))
+            .filter(|&(_, distance, _, _)| distance > Real::EPSILON)
+            .map(|(offset, distance, volume, _)| {
+                // `offset` points away from the particle, the kernel falls off along it.
+                offset / distance * (volume * smoothing_kernel_derivative(self.radius, distance))
+            })
+            .sum();
+
+        to_vec3(gradient).truncate()
+    }
+
+    /// Outward surface normal near `point`, `None` inside or away from the fluid
+    pub fn normal(&self, point: Vec2) -> Option<Vec2> {
+        let gradient = self.gradient(point);
+        (real(gradient.length()) * self.radius > SURFACE_THRESHOLD).then(|| -gradient.normalize())
+    }
+
+    /// Density of the fluid around `point`, averaged over the particles in reach so it
+    /// does not thin out toward the surface like the plain kernel sum. Zero away from the fluid.
+    pub fn density(&self, point: Vec2) -> f32 {
+        let (weighted_mass, color): (Real, Real) = self
+            .samples(real_vec(point.extend(0.0)))
+            .map(|(_, distance, volume, mass)| {
+                let weight = smoothing_kernel(self.radius, distance);
+                (mass * weight, volume * weight)
+            })
+            .fold((0.0, 0.0), |(a, b), (weighted_mass, color)| {
+                (a + weighted_mass, b + color)
+            });
+
+        if color <= Real::EPSILON {
+            0.0
+        } else {
+            to_f32(weighted_mass / color)
+        }
+    }
+
+    pub fn is_on_surface(&self, point: Vec2) -> bool {
+        self.normal(point).is_some()
+    }
+
+    pub fn is_submerged(&self, point: Vec2) -> bool {
+        self.color(point) >= SUBMERGED_LEVEL
+    }
+
+    /// Height of the topmost fluid surface above `x`, `None` where no fluid is nearby
+    pub fn surface_height_at(&self, x: f32) -> Option<f32> {
+        let radius = to_f32(self.radius);
+        let column = real_vec(Vec3::new(x, 0.0, 0.0));
+        let columns: Vec<i32> = self
+            .grid
+            .neighbor_cells(self.grid.cell(to_vec3(column)))
+            .map(|(cell_x, _)| cell_x)
+            .collect();
+
+        let (bottom, top) = self
+            .cells
+            .iter()
+            .filter(|((cell_x, _), _)| columns.contains(cell_x))
+            .flat_map(|(_, particles)| particles)
+            .filter(|&&(position, _)| {
+                self.grid.precise_offset(column, position).x.abs() < self.radius
+            })
+            .map(|&(position, _)| to_f32(position.y))
+            .fold(None, |bounds: Option<(f32, f32)>, y| {
+                let (bottom, top) = bounds.unwrap_or((y, y));
+                Some((bottom.min(y), top.max(y)))
+            })?;
+
+        // Walk down from above the highest particle to the first submerged sample.
+        let step = radius / SCAN_STEPS;
+        let mut above = top + radius;
+        let mut below = above;
+        while !self.is_submerged(Vec2::new(x, below)) {
+            above = below;
+            below -= step;
+            if below < bottom - radius {
+                return None;
+            }
+        }
+
+        for _ in 0..REFINE_STEPS {
+            let middle = (above + below) / 2.0;
+            if self.is_submerged(Vec2::new(x, middle)) {
+                below = middle;
+            } else {
+                above = middle;
+            }
+        }
+
+        Some((above + below) / 2.0)
+    }
+
+    /// Distance from `point` up to the topmost surface above it, `None` unless it is submerged
+    pub fn depth(&self, point: Vec2) -> Option<f32> {
+        if !self.is_submerged(point) {
+            return None;
+        }
+
+        self.surface_height_at(point.x)
+            .map(|height| (height - point.y).max(0.0))
+    }
+}
+
+pub struct SurfacePlugin;
+
+impl Plugin for SurfacePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Surface>().add_systems(
+            SIMULATION_SCHEDULE,
+            surface_system
+                .in_set(SimulationSet::Density)
+                .after(density_diffusion_system),
+        );
+
+        if app.is_plugin_added::<GizmoPlugin>() {
+            app.init_resource::<ShowNormals>()
+                .add_systems(Update, (toggle_normals_system, draw_normals_system).chain());
+        }
+    }
+}
+
+fn surface_system(
+    mut commands: Commands,
+    params: Res<SimulationParams>,
+    domains: Query<&Domain>,
+    density_cache: Res<DensityCache>,
+    masses: Query<&ParticleMass>,
+    mut particles: Query<(Entity, &Position, &mut Surface)>,
+) {
+    let grid = SpatialGrid::new(&params, domains.single());
+    let mass_of = |entity| real(particle_mass(&masses, entity, &params));
+    let mut cells: HashMap<(i32, i32), Vec<(RealVec3, Real, Real)>> = HashMap::new();
+
+    for (entity, position, _) in particles.iter() {
+        if let Some(&density) = density_cache.densities.get(&entity) {
+            let mass = mass_of(entity);
+            cells
+                .entry(grid.cell(to_vec3(position.0)))
+                .or_default()
+                .push((position.0, mass / density.max(1e-6), mass));
+        }
+    }
+
+    let field = ColorField {
+        grid,
+        radius: real(params.smoothing_radius),
+        cells,
+    };
+
+    let normals: HashMap<Entity, Vec2> = particles
+        .iter()
+        .map(|(entity, position, _)| {
+            let normal = field
+                .normal(to_vec3(position.0).truncate())
+                .unwrap_or(Vec2::ZERO);
+            (entity, normal)
+        })
+        .collect();
+
+    // Divergence of the normal field, ∇·n ≈ Σ V_j (n_j - n_i)·∇W_ij.
+    let normal_cells: HashMap<(i32, i32), Vec<(RealVec3, Real, Vec2)>> = {
+        let mut cells: HashMap<_, Vec<_>> = HashMap::new();
+        for (entity, position, _) in particles.iter() {
+            if let Some(&density) = density_cache.densities.get(&entity) {
+                cells
+                    .entry(field.grid.cell(to_vec3(position.0)))
+                    .or_default()
+                    .push((
+                        position.0,
+                        mass_of(entity) / density.max(1e-6),
+                        normals[&entity],
+                    ));
+            }
+        }
+        cells
+    };
+
+    for (entity, position, mut surface) in particles.iter_mut() {
+        let normal = normals[&entity];
+        let point = position.0;
+
+        let curvature: Real = if normal == Vec2::ZERO {
+            0.0
+        } else {
+            field
+                .grid
+                .neighbor_cells(field.grid.cell(to_vec3(point)))
+                .filter_map(|cell| normal_cells.get(&cell))
+                .flatten()
+                .filter_map(|&(neighbor, volume, neighbor_normal)| {
+                    let offset = field.grid.precise_offset(neighbor, point);
+                    let distance = offset.length();
+                    if distance <= Real::EPSILON || distance >= field.radius {
+                        return None;
+                    }
+
+                    let slope = smoothing_kernel_derivative(field.radius, distance);
+                    let kernel_gradient = offset / distance * slope;
+                    let difference = real_vec((neighbor_normal - normal).extend(0.0));
+                    Some(volume * difference.dot(kernel_gradient))
+                })
+                .sum()
+        };
+
+        *surface = Surface {
+            normal,
+            curvature: to_f32(curvature),
+            on_surface: normal != Vec2::ZERO,
+        };
+    }
+
+    commands.insert_resource(field);
+}
+
+// Toggled with N: surface normals of the particles, tinted from blue on flat or hollow
+// stretches to red on bulges, and a probe at the cursor showing the sampled color field and,
+// when submerged, its depth below the surface.
+#[derive(Resource, Default)]
+pub struct ShowNormals(pub bool);
+
+fn toggle_normals_system(input: Res<ButtonInput<KeyCode>>, mut show: ResMut<ShowNormals>) {
+    if input.just_pressed(KeyCode::KeyN) {
+        show.0 = !show.0;
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn draw_normals_system(
+    mut gizmos: Gizmos,
+    show: Res<ShowNormals>,
+    params: Res<SimulationParams>,
+    field: Option<Res<ColorField>>,
+    neighbors: FluidNeighbors,
+    domains: Query<&Domain>,
+    windows: Query<&Window>,
+    cameras: Query<(&Camera, &GlobalTransform), With<PanCam>>,
+    particles: Query<(&Transform, &Surface)>,
+) {
+    if !show.0 {
+        return;
+    }
+
+    for (transform, surface) in particles.iter() {
+        if !surface.on_surface {
+            continue;
+        }
+
+        // Curvature of one over the smoothing radius is a droplet a couple of particles wide.
+        let bulge = (surface.curvature * params.smoothing_radius).clamp(-1.0, 1.0);
+        let start = transform.translation.truncate();
+        gizmos.line_2d(
+            start,
+            start + surface.normal * NORMAL_LENGTH,
+            Color::srgb(0.5 + bulge * 0.5, 0.4, 0.5 - bulge * 0.5),
+        );
+    }
+
+    let (Some(field), Some(cursor)) = (field, cursor_world_position(&windows, &cameras)) else {
+        return;
+    };
+
+    let color = if field.is_on_surface(cursor) {
+        Color::WHITE
+    } else {
+        let color = field.color(cursor).clamp(0.0, 1.0);
+        Color::srgb(color * 0.5, color * 0.5, color)
+    };
+    gizmos.circle_2d(cursor, params.radius * 2.0, color);
+
+    if let Some(normal) = field.normal(cursor) {
+        gizmos.arrow_2d(cursor, cursor + normal * NORMAL_LENGTH * 2.0, Color::WHITE);
+    }
+
+    if let Some(depth) = field.depth(cursor) {
+        let surface = cursor + Vec2::Y * depth;
+        gizmos.line_2d(cursor, surface, Color::srgb(0.4, 0.8, 1.0));
+        gizmos.line_2d(
+            surface - Vec2::X * NORMAL_LENGTH,
+            surface + Vec2::X * NORMAL_LENGTH,
+            Color::srgb(0.4, 0.8, 1.0),
+        );
+    }
+
+    // Where something dropped from the cursor would first meet the fluid.
+    let fall = params
+        .gravity_vector()
+        .try_normalize()
+        .unwrap_or(Vec2::NEG_Y);
+    let reach = domains
+        .get_single()
+        .map_or(0.0, |domain| domain.size.length());
+    if let Some(hit) = neighbors.raycast(cursor, fall, reach) {
+        // Orange on a particle, yellow where the surface runs between particles.
+        let color = if hit.entity.is_some() {
+            Color::srgb(0.9, 0.6, 0.3)
+        } else {
+            Color::srgb(0.9, 0.9, 0.3)
+        };
+        gizmos.line_2d(cursor, hit.point, color);
+        gizmos.arrow_2d(hit.point, hit.point + hit.normal * NORMAL_LENGTH, color);
+    }
+}
@@ -0,0 +1,833 @@
+use std::{
+    f32::consts::TAU,
+    fmt::{self, Display, Formatter},
+};
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, LoadContext},
+    math::{Affine2, Rect},
+    prelude::*,
+};
+
+use crate::{cli::Args, domain::Domain, obstacle::Obstacle};
+
+const CURVE_SEGMENTS: usize = 8;
+const CIRCLE_SEGMENTS: usize = 32;
+
+#[derive(Asset, TypePath)]
+pub struct SvgObstacles {
+    pub view_box: Rect,
+    pub paths: Vec<SvgPath>,
+}
+
+pub struct SvgPath {
+    pub points: Vec<Vec2>,
+    pub closed: bool,
+}
+
+impl SvgObstacles {
+    pub fn world_points(&self, path: &SvgPath, width: f32) -> Vec<Vec2> {
+        let scale = width / self.view_box.width().max(f32::EPSILON);
+        let center = self.view_box.center();
+
+        path.points
+            .iter()
+            .map(|&point| (point - center) * Vec2::new(scale, -scale))
+            .collect()
+    }
+}
+
+#[derive(Debug)]
+pub enum SvgError {
+    Io(std::io::Error),
+    Utf8(std::string::FromUtf8Error),
+}
+
+impl Display for SvgError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            SvgError::Io(error) => write!(f, "could not read SVG: {error}"),
+            SvgError::Utf8(error) => write!(f, "SVG is not valid UTF-8: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for SvgError {}
+
+impl From<std::io::Error> for SvgError {
+    fn from(error: std::io::Error) -> Self {
+        SvgError::Io(error)
+    }
+}
+
+impl From<std::string::FromUtf8Error> for SvgError {
+    fn from(error: std::string::FromUtf8Error) -> Self {
+        SvgError::Utf8(error)
+    }
+}
+
+#[derive(Default)]
+pub struct SvgObstacleLoader;
+
+impl AssetLoader for SvgObstacleLoader {
+    type Asset = SvgObstacles;
+    type Settings = ();
+    type Error = SvgError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Ok(parse_svg(&String::from_utf8(bytes)?))
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["svg"]
+    }
+}
+
+pub struct SvgObstaclePlugin;
+
+impl Plugin for SvgObstaclePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<SvgObstacles>()
+            .init_asset_loader::<SvgObstacleLoader>()
+            .add_systems(Startup, load_svg_obstacles)
+            .add_systems(Update, spawn_svg_obstacles_system);
+    }
+}
+
+// The document passed with `--obstacles`, kept loaded so edits to it are picked up.
+#[derive(Resource)]
+struct SvgObstacleHandle(Handle<SvgObstacles>);
+
+// Marks an obstacle spawned from the SVG document with this id, replaced when it reloads.
+#[derive(Component)]
+struct SvgObstacle(AssetId<SvgObstacles>);
+
+fn load_svg_obstacles(mut commands: Commands, args: Res<Args>, asset_server: Res<AssetServer>) {
+    if let Some(path) = &args.obstacles {
+        commands.insert_resource(SvgObstacleHandle(asset_server.load(path.clone())));
+    }
+}
+
+fn spawn_svg_obstacles_system(
+    mut commands: Commands,
+    handle: Option<Res<SvgObstacleHandle>>,
+    mut events: EventReader<AssetEvent<SvgObstacles>>,
+    documents: Res<Assets<SvgObstacles>>,
+    domains: Query<&Domain>,
+    spawned: Query<(Entity, &SvgObstacle)>,
+) {
+    let Some(handle) = handle else {
+        return;
+    };
+
+    let id = handle.0.id();
+    let changed = events.read().any(|event| {
+        matches!(
+            event,
+            AssetEvent::LoadedWithDependencies { id: changed } | AssetEvent::Modified { id: changed }
+                if *changed == id
+        )
+    });
+
+    if !changed {
+        return;
+    }
+
+    let Some(document) = documents.get(id) else {
+        return;
+    };
+
+    for (entity, obstacle) in spawned.iter() {
+        if obstacle.0 == id {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+
+    if document.paths.is_empty() {
+        warn!("SVG obstacle file contains no usable shapes");
+    }
+
+    for path in &document.paths {
+        commands.spawn((
+            Name::new("Obstacle"),
+            SvgObstacle(id),
+            Obstacle::new(
+                document.world_points(path, domains.single().size.x),
+                path.closed,
+            ),
+        ));
+    }
+}
+
+fn parse_svg(text: &str) -> SvgObstacles {
+    let mut view_box = None;
+    let mut transforms = vec![Affine2::IDENTITY];
+    let mut paths = Vec::new();
+
+    for tag in tags(text) {
+        let parent = *transforms.last().unwrap();
+
+        if tag.kind == TagKind::Close {
+            if tag.name == "g" && transforms.len() > 1 {
+                transforms.pop();
+            }
+            continue;
+        }
+
+        let transform = parent
+            * tag
+                .attribute("transform")
+                .map_or(Affine2::IDENTITY, parse_transform);
+
+        match tag.name {
+            "svg" => view_box = view_box.or_else(|| parse_view_box(&tag)),
+            "g" if tag.kind == TagKind::Open => transforms.push(transform),
+            "path" => {
+                if let Some(data) = tag.attribute("d") {
+                    parse_path(data, transform, &mut paths);
+                }
+            }
+            "polygon" | "polyline" => {
+                if let Some(points) = tag.attribute("points") {
+                    let mut lexer = Lexer::new(points);
+                    let mut polygon = Vec::new();
+                    while let Some(point) = lexer.point() {
+                        polygon.push(point);
+                    }
+                    push_path(&mut paths, polygon, tag.name == "polygon", transform);
+                }
+            }
+            "rect" => {
+                let min = Vec2::new(tag.number("x"), tag.number("y"));
+                let max = min + Vec2::new(tag.number("width"), tag.number("height"));
+                let points = vec![min, Vec2::new(max.x, min.y), max, Vec2::new(min.x, max.y)];
+                push_path(&mut paths, points, true, transform);
+            }
+            "circle" | "ellipse" => {
+                let center = Vec2::new(tag.number("cx"), tag.number("cy"));
+                let radii = if tag.name == "circle" {
+                    Vec2::splat(tag.number("r"))
+                } else {
+                    Vec2::new(tag.number("rx"), tag.number("ry"))
+                };
+                let points = (0..CIRCLE_SEGMENTS)
+                    .map(|i| {
+                        let angle = TAU * i as f32 / CIRCLE_SEGMENTS as f32;
+                        center + radii * Vec2::new(angle.cos(), angle.sin())
+                    })
+                    .collect();
+                push_path(&mut paths, points, true, transform);
+            }
+            "line" => {
+                let points = vec![
+                    Vec2::new(tag.number("x1"), tag.number("y1")),
+                    Vec2::new(tag.number("x2"), tag.number("y2")),
+                ];
+                push_path(&mut paths, points, false, transform);
+            }
+            _ => {}
+        }
+    }
+
+    let view_box = view_box.unwrap_or_else(|| {
+        paths
+            .iter()
+            .flat_map(|path| path.points.iter())
+            .fold(Rect::EMPTY, |rect, &point| rect.union_point(point))
+    });
+
+    SvgObstacles { view_box, paths }
+}
+
+fn parse_view_box(tag: &Tag) -> Option<Rect> {
+    if let Some(view_box) = tag.attribute("viewBox") {
+        let mut lexer = Lexer::new(view_box);
+        let min = lexer.point()?;
+        let size = lexer.point()?;
+        return Some(Rect::from_corners(min, min + size));
+    }
+
+    let width = Lexer::new(tag.attribute("width")?).number()?;
+    let height = Lexer::new(tag.attribute("height")?).number()?;
+    Some(Rect::new(0.0, 0.0, width, height))
+}
+
+fn parse_transform(text: &str) -> Affine2 {
+    let mut transform = Affine2::IDENTITY;
+    let mut rest = text;
+
+    while let Some(open) = rest.find('(') {
+        let Some(close) = rest[open..].find(')') else {
+            break;
+        };
+
+        let name = rest[..open].trim_matches(|c: char| c.is_whitespace() || c == ',');
+        let mut lexer = Lexer::new(&rest[open + 1..open + close]);
+        let mut arguments = Vec::new();
+        while let Some(number) = lexer.number() {
+            arguments.push(number);
+        }
+        let argument = |i: usize, default: f32| arguments.get(i).copied().unwrap_or(default);
+
+        transform = transform
+            * match name {
+                "matrix" if arguments.len() == 6 => Affine2::from_cols_array(&[
+                    arguments[0],
+                    arguments[1],
+                    arguments[2],
+                    arguments[3],
+                    arguments[4],
+                    arguments[5],
+                ]),
+                "translate" => {
+                    Affine2::from_translation(Vec2::new(argument(0, 0.0), argument(1, 0.0)))
+                }
+                "scale" => {
+                    let x = argument(0, 1.0);
+                    Affine2::from_scale(Vec2::new(x, argument(1, x)))
+                }
+                "rotate" => {
+                    let pivot = Vec2::new(argument(1, 0.0), argument(2, 0.0));
+                    Affine2::from_translation(pivot)
+                        * Affine2::from_angle(argument(0, 0.0).to_radians())
+                        * Affine2::from_translation(-pivot)
+                }
+                _ => Affine2::IDENTITY,
+            };
+
+        rest = &rest[open + close + 1..];
+    }
+
+    transform
+}
+
+fn parse_path(data: &str, transform: Affine2, paths: &mut Vec<SvgPath>) {
+    let mut lexer = Lexer::new(data);
+    let mut command = b'M';
+    let mut current = Vec2::ZERO;
+    let mut start = Vec2::ZERO;
+    let mut last_cubic_control: Option<Vec2> = None;
+    let mut last_quadratic_control: Option<Vec2> = None;
+    let mut points: Vec<Vec2> = Vec::new();
+
+    loop {
+        match lexer.command() {
+            Some(next) => command = next,
+            None if lexer.at_end() || command.eq_ignore_ascii_case(&b'z') => break,
+            None => {}
+        }
+
+        let relative = command.is_ascii_lowercase();
+        let origin = if relative { current } else { Vec2::ZERO };
+        let mut cubic_control = None;
+        let mut quadratic_control = None;
+
+        match command.to_ascii_uppercase() {
+            b'M' => {
+                let Some(point) = lexer.point() else { break };
+                push_path(paths, std::mem::take(&mut points), false, transform);
+                current = origin + point;
+                start = current;
+                points.push(current);
+                command = if relative { b'l' } else { b'L' };
+            }
+            b'L' => {
+                let Some(point) = lexer.point() else { break };
+                current = origin + point;
+                points.push(current);
+            }
+            b'H' => {
+                let Some(x) = lexer.number() else { break };
+                current.x = origin.x + x;
+                points.push(current);
+            }
+            b'V' => {
+                let Some(y) = lexer.number() else { break };
+                current.y = origin.y + y;
+                points.push(current);
+            }
+            b'C' | b'S' => {
+                let first = if command.eq_ignore_ascii_case(&b'C') {
+                    let Some(control) = lexer.point() else { break };
+                    origin + control
+                } else {
+                    last_cubic_control.map_or(current, |control| 2.0 * current - control)
+                };
+                let (Some(second), Some(end)) = (lexer.point(), lexer.point()) else {
+                    break;
+                };
+                let (second, end) = (origin + second, origin + end);
+                flatten_cubic(current, first, second, end, &mut points);
+                cubic_control = Some(second);
+                current = end;
+            }
+            b'Q' | b'T' => {
+                let control = if command.eq_ignore_ascii_case(&b'Q') {
+                    let Some(control) = lexer.point() else { break };
+                    origin + control
+                } else {
+                    last_quadratic_control.map_or(current, |control| 2.0 * current - control)
+                };
+                let Some(end) = lexer.point() else { break };
+                let end = origin + end;
+                flatten_quadratic(current, control, end, &mut points);
+                quadratic_control = Some(control);
+                current = end;
+            }
+            b'A' => {
+                let (Some(radii), Some(rotation), Some(large_arc), Some(sweep), Some(end)) = (
+                    lexer.point(),
+                    lexer.number(),
+                    lexer.flag(),
+                    lexer.flag(),
+                    lexer.point(),
+                ) else {
+                    break;
+                };
+                let end = origin + end;
+                flatten_arc(current, radii, rotation, large_arc, sweep, end, &mut points);
+                current = end;
+            }
+            b'Z' => {
+                push_path(paths, std::mem::take(&mut points), true, transform);
+                current = start;
+                points.push(current);
+            }
+            _ => break,
+        }
+
+        last_cubic_control = cubic_control;
+        last_quadratic_control = quadratic_control;
+    }
+
+    push_path(paths, points, false, transform);
+}
+
+fn push_path(paths: &mut Vec<SvgPath>, mut points: Vec<Vec2>, closed: bool, transform: Affine2) {
+    if closed && points.len() > 1 && points.first() == points.last() {
+        points.pop();
+    }
+
+    if points.len() < if closed { 3 } else { 2 } {
+        return;
+    }
+
+    paths.push(SvgPath {
+        points: points
+            .into_iter()
+            .map(|point| transform.transform_point2(point))
+            .collect(),
+        closed,
+    });
+}
+
+fn flatten_cubic(start: Vec2, first: Vec2, second: Vec2, end: Vec2, points: &mut Vec<Vec2>) {
+    for i in 1..=CURVE_SEGMENTS {
+        let t = i as f32 / CURVE_SEGMENTS as f32;
+        let u = 1.0 - t;
+        points.push(
+            u * u * u * start
+                + 3.0 * u * u * t * first
+                + 3.0 * u * t * t * second
+                + t * t * t * end,
+        );
+    }
+}
+
+fn flatten_quadratic(start: Vec2, control: Vec2, end: Vec2, points: &mut Vec<Vec2>) {
+    for i in 1..=CURVE_SEGMENTS {
+        let t = i as f32 / CURVE_SEGMENTS as f32;
+        let u = 1.0 - t;
+        points.push(u * u * start + 2.0 * u * t * control + t * t * end);
+    }
+}
+
+fn flatten_arc(
+    start: Vec2,
+    radii: Vec2,
+    rotation: f32,
+    large_arc: bool,
+    sweep: bool,
+    end: Vec2,
+    points: &mut Vec<Vec2>,
+) {
+    let mut rx = radii.x.abs();
+    let mut ry = radii.y.abs();
+
+    if rx <= f32::EPSILON || ry <= f32::EPSILON || start == end {
+        points.push(end);
+        return;
+    }
+
+    let (sin_phi, cos_phi) = rotation.to_radians().sin_cos();
+    let rotate = |v: Vec2| Vec2::new(cos_phi * v.x - sin_phi * v.y, sin_phi * v.x + cos_phi * v.y);
+
+    let half = (start - end) / 2.0;
+    let p = Vec2::new(
+        cos_phi * half.x + sin_phi * half.y,
+        -sin_phi * half.x + cos_phi * half.y,
+    );
+
+    let lambda = (p.x * p.x) / (rx * rx) + (p.y * p.y) / (ry * ry);
+    if lambda > 1.0 {
+        rx *= lambda.sqrt();
+        ry *= lambda.sqrt();
+    }
+
+    let numerator = (rx * rx * ry * ry - rx * rx * p.y * p.y - ry * ry * p.x * p.x).max(0.0);
+    let denominator = rx * rx * p.y * p.y + ry * ry * p.x * p.x;
+    let mut coefficient = (numerator / denominator).sqrt();
+    if large_arc == sweep {
+        coefficient = -coefficient;
+    }
+
+    let center_prime = Vec2::new(coefficient * rx * p.y / ry, -coefficient * ry * p.x / rx);
+    let center = rotate(center_prime) + (start + end) / 2.0;
+
+    let start_vector = (p - center_prime) / Vec2::new(rx, ry);
+    let end_vector = (-p - center_prime) / Vec2::new(rx, ry);
+    let start_angle = start_vector.y.atan2(start_vector.x);
+    let mut sweep_angle = end_vector.y.atan2(end_vector.x) - start_angle;
+
+    if sweep && sweep_angle < 0.0 {
+        sweep_angle += TAU;
+    } else if !sweep && sweep_angle > 0.0 {
+        sweep_angle -= TAU;
+    }
+
+    let segments = ((sweep_angle.abs() / TAU) * CIRCLE_SEGMENTS as f32)
+        .ceil()
+        .max(1.0) as usize;
+
+    for i in 1..segments {
+        let angle = start_angle + sweep_angle * i as f32 / segments as f32;
+        points.push(center + rotate(Vec2::new(rx * angle.cos(), ry * angle.sin())));
+    }
+
+    points.push(end);
+}
+
+#[derive(PartialEq, Eq)]
+enum TagKind {
+    Open,
+    Close,
+    SelfClosing,
+}
+
+struct Tag<'a> {
+    name: &'a str,
+    kind: TagKind,
+    attributes: Vec<(&'a str, &'a str)>,
+}
+
+impl<'a> Tag<'a> {
+    fn attribute(&self, name: &str) -> Option<&'a str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| *key == name)
+            .map(|&(_, value)| value)
+    }
+
+    fn number(&self, name: &str) -> f32 {
+        self.attribute(name)
+            .and_then(|value| Lexer::new(value).number())
+            .unwrap_or(0.0)
+    }
+}
+
+fn tags(text: &str) -> impl Iterator<Item = Tag<'_>> {
+    let mut rest = text;
+
+    std::iter::from_fn(move || loop {
+        rest = &rest[rest.find('<')? + 1..];
+
+        if let Some(comment) = rest.strip_prefix("!--") {
+            rest = &comment[comment.find("-->")? + 3..];
+            continue;
+        }
+
+        let end = rest.find('>')?;
+        let body = rest[..end].trim();
+        rest = &rest[end + 1..];
+
+        if body.starts_with('?') || body.starts_with('!') {
+            continue;
+        }
+
+        let (kind, body) = if let Some(body) = body.strip_prefix('/') {
+            (TagKind::Close, body)
+        } else if let Some(body) = body.strip_suffix('/') {
+            (TagKind::SelfClosing, body)
+        } else {
+            (TagKind::Open, body)
+        };
+
+        let name_end = body.find(char::is_whitespace).unwrap_or(body.len());
+        let name = &body[..name_end];
+
+        return Some(Tag {
+            name: name.rsplit(':').next().unwrap_or(name),
+            kind,
+            attributes: parse_attributes(&body[name_end..]),
+        });
+    })
+}
+
+fn parse_attributes(mut text: &str) -> Vec<(&str, &str)> {
+    let mut attributes = Vec::new();
+
+    while let Some(equals) = text.find('=') {
+        let name = text[..equals].trim();
+        let value = text[equals + 1..].trim_start();
+
+        let Some(quote) = value.chars().next().filter(|c| *c == '"' || *c == '\'') else {
+            break;
+        };
+        let value = &value[1..];
+        let Some(close) = value.find(quote) else {
+            break;
+        };
+
+        attributes.push((name, &value[..close]));
+        text = &value[close + 1..];
+    }
+
+    attributes
+}
+
+struct Lexer<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(text: &'a str) -> Self {
+        Self {
+            bytes: text.as_bytes(),
+            position: 0,
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.position).copied()
+    }
+
+    fn skip_separators(&mut self) {
+        while self
+            .peek()
+            .is_some_and(|byte| byte.is_ascii_whitespace() || byte == b',')
+        {
+            self.position += 1;
+        }
+    }
+
+    fn at_end(&mut self) -> bool {
+        self.skip_separators();
+        self.position >= self.bytes.len()
+    }
+
+    fn command(&mut self) -> Option<u8> {
+        self.skip_separators();
+        let byte = self.peek()?;
+
+        if b"MmLlHhVvCcSsQqTtAaZz".contains(&byte) {
+            self.position += 1;
+            Some(byte)
+        } else {
+            None
+        }
+    }
+
+    fn number(&mut self) -> Option<f32> {
+        self.skip_separators();
+        let start = self.position;
+
+        if matches!(self.peek(), Some(b'+' | b'-')) {
+            self.position += 1;
+        }
+
+        let mut seen_dot = false;
+        while let Some(byte) = self.peek() {
+            match byte {
+                b'0'..=b'9' => self.position += 1,
+                b'.' if !seen_dot => {
+                    seen_dot = true;
+                    self.position += 1;
+                }
+                _ => break,
+            }
+        }
+
+        if matches!(self.peek(), Some(b'e' | b'E')) {
+            let mantissa_end = self.position;
+            self.position += 1;
+            if matches!(self.peek(), Some(b'+' | b'-')) {
+                self.position += 1;
+            }
+            let exponent_start = self.position;
+            while self.peek().is_some_and(|byte| byte.is_ascii_digit()) {
+                self.position += 1;
+            }
+            if self.position == exponent_start {
+                self.position = mantissa_end;
+            }
+        }
+
+        let number = std::str::from_utf8(&self.bytes[start..self.position])
+            .ok()
+            .and_then(|text| text.parse().ok());
+
+        if number.is_none() {
+            self.position = start;
+        }
+
+        number
+    }
+
+    fn point(&mut self) -> Option<Vec2> {
+        let start = self.position;
+        let point = self
+            .number()
+            .zip(self.number())
+            .map(|(x, y)| Vec2::new(x, y));
+
+        if point.is_none() {
+            self.position = start;
+        }
+
+        point
+    }
+
+    fn flag(&mut self) -> Option<bool> {
+        self.skip_separators();
+
+        let flag = match self.peek()? {
+            b'0' => false,
+            b'1' => true,
+            _ => return None,
+        };
+
+        self.position += 1;
+        Some(flag)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_points(actual: &[Vec2], expected: &[Vec2]) {
+        assert_eq!(actual.len(), expected.len(), "{actual:?} != {expected:?}");
+        for (actual, expected) in actual.iter().zip(expected) {
+            assert!(
+                actual.distance(*expected) < 1e-4,
+                "{actual:?} != {expected:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn arc_bulges_towards_the_sweep() {
+        let document = parse_svg(r#"<svg><path d="M 0 0 A 1 1 0 0 1 2 0"/></svg>"#);
+        let [path] = &document.paths[..] else {
+            panic!("expected one path, got {}", document.paths.len());
+        };
+
+        assert!(!path.closed);
+        assert_points(&path.points[..1], &[Vec2::ZERO]);
+        assert_points(
+            &path.points[path.points.len() - 1..],
+            &[Vec2::new(2.0, 0.0)],
+        );
+
+        for point in &path.points {
+            assert!((point.distance(Vec2::new(1.0, 0.0)) - 1.0).abs() < 1e-4);
+            assert!(point.y <= 1e-4, "{point:?} on the wrong side");
+        }
+        assert!(path.points.iter().any(|point| point.y < -0.99));
+    }
+
+    #[test]
+    fn arc_too_small_for_its_endpoints_is_scaled_up() {
+        let document = parse_svg(r#"<svg><path d="M 0 0 A 0.1 0.1 0 0 0 4 0"/></svg>"#);
+        let path = &document.paths[0];
+
+        for point in &path.points {
+            assert!((point.distance(Vec2::new(2.0, 0.0)) - 2.0).abs() < 1e-3);
+        }
+        assert!(path.points.iter().any(|point| point.y > 1.99));
+    }
+
+    #[test]
+    fn relative_commands_follow_the_current_point() {
+        let document = parse_svg(r#"<svg><path d="m 1 1 2 0 l 0 2 h -2 z m 5 0 v 1"/></svg>"#);
+
+        assert_eq!(document.paths.len(), 2);
+        assert!(document.paths[0].closed);
+        assert_points(
+            &document.paths[0].points,
+            &[
+                Vec2::new(1.0, 1.0),
+                Vec2::new(3.0, 1.0),
+                Vec2::new(3.0, 3.0),
+                Vec2::new(1.0, 3.0),
+            ],
+        );
+        // After closing, relative moves start from the subpath's first point.
+        assert!(!document.paths[1].closed);
+        assert_points(
+            &document.paths[1].points,
+            &[Vec2::new(6.0, 1.0), Vec2::new(6.0, 2.0)],
+        );
+    }
+
+    #[test]
+    fn relative_curves_offset_every_control_point() {
+        let absolute = parse_svg(r#"<svg><path d="M 1 1 C 1 3 3 3 3 1 Q 4 0 5 1"/></svg>"#);
+        let relative = parse_svg(r#"<svg><path d="M 1 1 c 0 2 2 2 2 0 q 1 -1 2 0"/></svg>"#);
+
+        assert_points(&relative.paths[0].points, &absolute.paths[0].points);
+    }
+
+    #[test]
+    fn nested_transforms_compose_and_end_with_their_group() {
+        let document = parse_svg(
+            r#"<svg viewBox="0 0 20 10">
+                <g transform="translate(10 0)">
+                    <g transform="scale(2)">
+                        <rect x="1" y="1" width="1" height="1" transform="translate(1, 0)"/>
+                    </g>
+                    <line x1="0" y1="0" x2="1" y2="0"/>
+                </g>
+                <line x1="0" y1="0" x2="1" y2="0"/>
+            </svg>"#,
+        );
+
+        assert_eq!(document.view_box, Rect::new(0.0, 0.0, 20.0, 10.0));
+        assert_eq!(document.paths.len(), 3);
+        assert_points(
+            &document.paths[0].points,
+            &[
+                Vec2::new(14.0, 2.0),
+                Vec2::new(16.0, 2.0),
+                Vec2::new(16.0, 4.0),
+                Vec2::new(14.0, 4.0),
+            ],
+        );
+        assert_points(
+            &document.paths[1].points,
+            &[Vec2::new(10.0, 0.0), Vec2::new(11.0, 0.0)],
+        );
+        assert_points(
+            &document.paths[2].points,
+            &[Vec2::ZERO, Vec2::new(1.0, 0.0)],
+        );
+    }
+}
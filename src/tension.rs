@@ -0,0 +1,152 @@
+use std::f32::consts::FRAC_PI_2;
+
+use bevy::prelude::*;
+
+use crate::{
+    calculate_spatial_hash,
+    collider::Collider,
+    dilation::TimeScale,
+    domain::Domain,
+    obstacle::Container,
+    params::SimulationParams,
+    sdf::{to_local, SdfBoundary, SignedDistance, WallMaterial},
+    velocity_system, SimulationSet, SpatialGrid, Velocity, SIMULATION_SCHEDULE,
+};
+
+pub struct TensionPlugin;
+
+impl Plugin for TensionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            SIMULATION_SCHEDULE,
+            (cohesion_system, adhesion_system)
+                .in_set(SimulationSet::Forces)
+                .after(velocity_system),
+        );
+    }
+}
+
+// Pairwise attraction profile over the smoothing radius, peaking at 1 halfway out.
+fn attraction(distance: f32, radius: f32) -> f32 {
+    if distance <= 0.0 || distance >= radius {
+        0.0
+    } else {
+        let x = distance / radius;
+        4.0 * x * (1.0 - x)
+    }
+}
+
+fn cohesion_system(
+    time: Res<Time>,
+    params: Res<SimulationParams>,
+    domains: Query<&Domain>,
+    transforms_query: Query<(Entity, &Transform), With<Velocity>>,
+    mut velocities_query: Query<(Entity, &mut Velocity, &TimeScale)>,
+) {
+    if params.surface_tension <= 0.0 {
+        return;
+    }
+
+    let grid = SpatialGrid::new(&params, domains.single());
+    let spatial_hash = calculate_spatial_hash(&transforms_query, &grid);
+
+    for (entity, mut velocity, time_scale) in velocities_query.iter_mut() {
+        let Ok((_, transform)) = transforms_query.get(entity) else {
+            continue;
+        };
+
+        let position = transform.translation;
+        let mut pull = Vec3::ZERO;
+
+        for cell in grid.neighbor_cells(grid.cell(position)) {
+            let Some(neighbors) = spatial_hash.get(&cell) else {
+                continue;
+            };
+
+            for &(_, neighbor_position) in neighbors {
+                let offset = grid.offset(position, neighbor_position);
+                let distance = offset.length();
+                let strength = attraction(distance, params.smoothing_radius);
+
+                if strength > 0.0 {
+                    pull += offset / distance * strength;
+                }
+            }
+        }
+
+        velocity.0 += params.surface_tension * pull * time.delta_secs() * time_scale.0;
+    }
+}
+
+// Walls pull on nearby particles like a half-disc of fluid neighbors would, scaled so the
+// balance against cohesion gives each surface's contact angle.
+fn adhesion_system(
+    time: Res<Time>,
+    params: Res<SimulationParams>,
+    domains: Query<&Domain>,
+    containers: Query<(), With<Container>>,
+    colliders: Query<(&Collider, &Transform, Option<&WallMaterial>)>,
+    boundaries: Query<(&SdfBoundary, &Transform, Option<&WallMaterial>)>,
+    mut particles: Query<(&Transform, &mut Velocity, &TimeScale)>,
+) {
+    if params.surface_tension <= 0.0 {
+        return;
+    }
+
+    let radius = params.smoothing_radius;
+    let wall_neighbors = FRAC_PI_2 * (radius / params.particle_spacing).powi(2);
+    let domain = domains.single();
+    let half_size = domain.half_size();
+    let domain_walls = containers.is_empty();
+    let walls = domain.walls;
+    let adhesion =
+        |material: Option<&WallMaterial>| material.copied().unwrap_or_default().adhesion(&params);
+
+    let shapes: Vec<(&dyn SignedDistance, &Transform, f32)> = colliders
+        .iter()
+        .map(|(collider, transform, material)| {
+            (
+                collider as &dyn SignedDistance,
+                transform,
+                adhesion(material),
+            )
+        })
+        .chain(boundaries.iter().map(|(boundary, transform, material)| {
+            (
+                &boundary.0 as &dyn SignedDistance,
+                transform,
+                adhesion(material),
+            )
+        }))
+        .collect();
+
+    for (transform, mut velocity, time_scale) in particles.iter_mut() {
+        let position = transform.translation.truncate();
+        let mut pull = Vec2::ZERO;
+
+        if domain_walls && !params.periodic_x {
+            pull.x += walls.right.adhesion(&params) * attraction(half_size.x - position.x, radius)
+                - walls.left.adhesion(&params) * attraction(half_size.x + position.x, radius);
+        }
+
+        if domain_walls && !params.periodic_y {
+            pull.y += walls.top.adhesion(&params) * attraction(half_size.y - position.y, radius)
+                - walls.bottom.adhesion(&params) * attraction(half_size.y + position.y, radius);
+        }
+
+        for &(shape, shape_transform, adhesion) in &shapes {
+            let local = to_local(shape_transform, position);
+            let strength = adhesion * attraction(shape.signed_distance(local), radius);
+
+            if strength <= 0.0 {
+                continue;
+            }
+
+            if let Some(normal) = shape.gradient(local).try_normalize() {
+                pull -= strength * (shape_transform.rotation * normal.extend(0.0)).truncate();
+            }
+        }
+
+        velocity.0 += (wall_neighbors * pull * time.delta_secs() * time_scale.0).extend(0.0);
+    }
+}
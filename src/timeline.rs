@@ -0,0 +1,128 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::{
+    flow::{Drain, Emitter},
+    params::SimulationParams,
+    scene::SceneEntity,
+    SimulationSet, SIMULATION_SCHEDULE,
+};
+
+#[derive(Reflect, Deserialize, Clone, Debug)]
+pub struct TimelineEvent {
+    /// Simulated seconds since the scene was spawned
+    pub at: f32,
+    pub action: TimelineAction,
+}
+
+#[derive(Reflect, Deserialize, Clone, Debug)]
+pub enum TimelineAction {
+    /// Despawns every scene entity with this name, e.g. to open a gate
+    Remove(String),
+    /// Downward acceleration from then on
+    SetGravity(f32),
+    /// Gravity direction in degrees counterclockwise from straight down
+    SetGravityAngle(f32),
+    StartEmitter(String),
+    StopEmitter(String),
+    StartDrain(String),
+    StopDrain(String),
+}
+
+// Scene events still to run, in order, and the simulated time since the scene was spawned.
+#[derive(Resource, Reflect, Default)]
+#[reflect(Resource)]
+pub struct Timeline {
+    events: Vec<TimelineEvent>,
+    next: usize,
+    elapsed: f32,
+}
+
+impl Timeline {
+    pub fn new(mut events: Vec<TimelineEvent>) -> Self {
+        events.sort_by(|a, b| a.at.total_cmp(&b.at));
+
+        Self {
+            events,
+            ..default()
+        }
+    }
+}
+
+pub struct TimelinePlugin;
+
+impl Plugin for TimelinePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Timeline>()
+            .register_type::<Timeline>()
+            .add_systems(
+                SIMULATION_SCHEDULE,
+                timeline_system.before(SimulationSet::Density),
+            );
+    }
+}
+
+fn timeline_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut timeline: ResMut<Timeline>,
+    mut params: ResMut<SimulationParams>,
+    scene_entities: Query<(Entity, &Name), With<SceneEntity>>,
+    mut emitters: Query<(&Name, &mut Emitter)>,
+    mut drains: Query<(&Name, &mut Drain)>,
+) {
+    timeline.elapsed += time.delta_secs();
+
+    while let Some(event) = timeline.events.get(timeline.next).cloned() {
+        if event.at > timeline.elapsed {
+            break;
+        }
+
+        timeline.next += 1;
+        info!("t = {:.2}s: {:?}", timeline.elapsed, event.action);
+
+        let found = match &event.action {
+            TimelineAction::Remove(name) => {
+                let mut found = false;
+                for (entity, _) in scene_entities.iter().filter(|(_, n)| n.as_str() == name) {
+                    commands.entity(entity).despawn();
+                    found = true;
+                }
+                found
+            }
+            TimelineAction::SetGravity(gravity) => {
+                params.gravity = *gravity;
+                true
+            }
+            TimelineAction::SetGravityAngle(angle) => {
+                params.gravity_angle = *angle;
+                true
+            }
+            TimelineAction::StartEmitter(name) | TimelineAction::StopEmitter(name) => {
+                let enabled = matches!(event.action, TimelineAction::StartEmitter(_));
+                let mut found = false;
+                for (_, mut emitter) in emitters.iter_mut().filter(|(n, _)| n.as_str() == name) {
+                    emitter.enabled = enabled;
+                    found = true;
+                }
+                found
+            }
+            TimelineAction::StartDrain(name) | TimelineAction::StopDrain(name) => {
+                let enabled = matches!(event.action, TimelineAction::StartDrain(_));
+                let mut found = false;
+                for (_, mut drain) in drains.iter_mut().filter(|(n, _)| n.as_str() == name) {
+                    drain.enabled = enabled;
+                    found = true;
+                }
+                found
+            }
+        };
+
+        if !found {
+            warn!(
+                "Timeline event at {}s matched nothing: {:?}",
+                event.at, event.action
+            );
+        }
+    }
+}
@@ -0,0 +1,363 @@
+use bevy::{
+    input::mouse::{MouseScrollUnit, MouseWheel},
+    prelude::*,
+};
+use bevy_pancam::PanCam;
+use rand::Rng;
+
+use crate::{
+    budget::ParticleBudget,
+    cli::Args,
+    clump::RigidClump,
+    flow::{DespawnCause, ParticleDespawned},
+    fluid_commands::{FluidCommands, FluidMaterial},
+    frozen::FreezeCommands,
+    neighbors::FluidNeighbors,
+    params::SimulationParams,
+    spawn_jitter, SimulationRng, Velocity,
+};
+
+// Scroll distance of one wheel notch for devices that report pixels.
+const PIXELS_PER_LINE: f32 = 20.0;
+// Factor the radius grows by per wheel notch.
+const RADIUS_STEP: f32 = 1.1;
+const MIN_RADIUS: f32 = 2.0;
+const MAX_RADIUS: f32 = 200.0;
+// Inward pull of the whirlpool as a fraction of its swirl, enough for particles to spiral in.
+const WHIRLPOOL_INFLOW: f32 = 0.3;
+// Part of the whirlpool's radius that drains particles.
+const WHIRLPOOL_CORE: f32 = 0.15;
+
+// What the left mouse button does, picked with the number keys.
+#[derive(Resource, Reflect, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[reflect(Resource)]
+pub enum Tool {
+    /// Drag single particles and fling them on release
+    #[default]
+    Drag,
+    /// Pull particles within the radius toward the cursor
+    Attract,
+    /// Push particles within the radius away from the cursor
+    Repel,
+    /// Paint new particles within the radius
+    Brush,
+    /// Delete particles within the radius
+    Erase,
+    /// Swirl particles around the cursor and drain those reaching the center
+    Whirlpool,
+    /// Freeze the particles within the radius into a rigid body, or melt the one clicked
+    Clump,
+    /// Turn particles within the radius into static terrain
+    Freeze,
+    /// Turn static terrain within the radius back into fluid
+    Thaw,
+}
+
+impl Tool {
+    const KEYS: [(KeyCode, Tool); 9] = [
+        (KeyCode::Digit1, Tool::Drag),
+        (KeyCode::Digit2, Tool::Attract),
+        (KeyCode::Digit3, Tool::Repel),
+        (KeyCode::Digit4, Tool::Brush),
+        (KeyCode::Digit5, Tool::Erase),
+        (KeyCode::Digit6, Tool::Whirlpool),
+        (KeyCode::Digit7, Tool::Clump),
+        (KeyCode::Digit8, Tool::Freeze),
+        (KeyCode::Digit9, Tool::Thaw),
+    ];
+
+    fn has_radius(self) -> bool {
+        self != Tool::Drag
+    }
+}
+
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct ToolSettings {
+    /// Reach of the area tools, adjusted with Shift and the mouse wheel
+    pub radius: f32,
+    /// Acceleration toward, away from or around the cursor at its center, fading to zero at
+    /// the radius
+    pub strength: f32,
+    /// Particles painted per second by the brush
+    pub brush_rate: f32,
+    /// Most particles drained per second by the whirlpool
+    pub drain_rate: f32,
+    brush_pending: f32,
+    drain_pending: f32,
+}
+
+impl Default for ToolSettings {
+    fn default() -> Self {
+        Self {
+            radius: 30.0,
+            strength: 400.0,
+            brush_rate: 200.0,
+            drain_rate: 100.0,
+            brush_pending: 0.0,
+            drain_pending: 0.0,
+        }
+    }
+}
+
+pub struct ToolsPlugin;
+
+impl Plugin for ToolsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Tool>()
+            .init_resource::<ToolSettings>()
+            .register_type::<Tool>()
+            .register_type::<ToolSettings>()
+            .add_systems(
+                Update,
+                (
+                    select_tool_system,
+                    tool_radius_system,
+                    apply_tool_system,
+                    draw_tool_system,
+                ),
+            );
+    }
+}
+
+pub fn cursor_world_position(
+    windows: &Query<&Window>,
+    cameras: &Query<(&Camera, &GlobalTransform), With<PanCam>>,
+) -> Option<Vec2> {
+    let cursor_position = windows.get_single().ok()?.cursor_position()?;
+    let (camera, camera_transform) = cameras.get_single().ok()?;
+    camera
+        .viewport_to_world_2d(camera_transform, cursor_position)
+        .ok()
+}
+
+fn select_tool_system(input: Res<ButtonInput<KeyCode>>, mut tool: ResMut<Tool>) {
+    for (key, selected) in Tool::KEYS {
+        if input.just_pressed(key) && *tool != selected {
+            *tool = selected;
+            info!("Selected {selected:?} tool");
+        }
+    }
+}
+
+// The wheel zooms the camera unless Shift is held, in which case it resizes the tool.
+fn tool_radius_system(
+    input: Res<ButtonInput<KeyCode>>,
+    mut wheel: EventReader<MouseWheel>,
+    tool: Res<Tool>,
+    mut settings: ResMut<ToolSettings>,
+    mut cameras: Query<&mut PanCam>,
+) {
+    let adjusting =
+        tool.has_radius() && input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+
+    for mut camera in cameras.iter_mut() {
+        camera.enabled = !adjusting;
+    }
+
+    if !adjusting {
+        wheel.clear();
+        return;
+    }
+
+    let notches: f32 = wheel
+        .read()
+        .map(|event| match event.unit {
+            MouseScrollUnit::Line => event.y,
+            MouseScrollUnit::Pixel => event.y / PIXELS_PER_LINE,
+        })
+        .sum();
+
+    if notches != 0.0 {
+        settings.radius =
+            (settings.radius * RADIUS_STEP.powf(notches)).clamp(MIN_RADIUS, MAX_RADIUS);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn apply_tool_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    args: Res<Args>,
+    budget: Res<ParticleBudget>,
+    params: Res<SimulationParams>,
+    tool: Res<Tool>,
+    mut settings: ResMut<ToolSettings>,
+    mut rng: ResMut<SimulationRng>,
+    windows: Query<&Window>,
+    cameras: Query<(&Camera, &GlobalTransform), With<PanCam>>,
+    neighbors: FluidNeighbors,
+    mut particles: Query<(Entity, &Transform, &mut Velocity)>,
+    mut despawned: EventWriter<ParticleDespawned>,
+    clumps: Query<(Entity, &RigidClump)>,
+) {
+    if !tool.has_radius() || !mouse_input.pressed(MouseButton::Left) {
+        settings.brush_pending = 0.0;
+        settings.drain_pending = 0.0;
+        return;
+    }
+
+    let Some(cursor) = cursor_world_position(&windows, &cameras) else {
+        return;
+    };
+
+    let delta_time = time.delta_secs();
+    let radius = settings.radius;
+
+    match *tool {
+        Tool::Drag => {}
+        Tool::Attract | Tool::Repel => {
+            let sign = if *tool == Tool::Attract { 1.0 } else { -1.0 };
+
+            for (entity, _) in neighbors.within_radius(cursor.extend(0.0), radius) {
+                let Ok((_, transform, mut velocity)) = particles.get_mut(entity) else {
+                    continue;
+                };
+
+                let offset = cursor - transform.translation.truncate();
+                let distance = offset.length();
+
+                if distance < radius {
+                    let falloff = 1.0 - distance / radius;
+                    velocity.0 += (sign * settings.strength * falloff * delta_time)
+                        * offset.normalize_or_zero().extend(0.0);
+                }
+            }
+        }
+        Tool::Brush => {
+            settings.brush_pending += settings.brush_rate.max(0.0) * delta_time;
+            let count = settings.brush_pending.floor();
+            settings.brush_pending -= count;
+
+            let count = (count as usize).min(budget.remaining(particles.iter().count()));
+
+            let positions = (0..count)
+                .map(|_| {
+                    // Uniform over the disc.
+                    let distance = radius * rng.0.gen::<f32>().sqrt();
+                    let angle = rng.0.gen_range(0.0..std::f32::consts::TAU);
+                    cursor
+                        + Vec2::from_angle(angle) * distance
+                        + spawn_jitter(&args, &params, &mut rng)
+                })
+                .collect();
+
+            commands.spawn_fluid(positions, FluidMaterial::default());
+        }
+        Tool::Erase => {
+            for (entity, _) in neighbors.within_radius(cursor.extend(0.0), radius) {
+                let Ok((_, transform, _)) = particles.get(entity) else {
+                    continue;
+                };
+
+                if transform.translation.truncate().distance(cursor) < radius {
+                    commands.entity(entity).despawn();
+                }
+            }
+        }
+        Tool::Whirlpool => {
+            let core = radius * WHIRLPOOL_CORE;
+            let mut draining = Vec::new();
+
+            for (entity, _) in neighbors.within_radius(cursor.extend(0.0), radius) {
+                let Ok((_, transform, mut velocity)) = particles.get_mut(entity) else {
+                    continue;
+                };
+
+                let position = transform.translation.truncate();
+                let offset = cursor - position;
+                let distance = offset.length();
+
+                if distance >= radius {
+                    continue;
+                }
+
+                if distance < core {
+                    draining.push((distance, entity, position));
+                }
+
+                // Counterclockwise swirl, leaning inward so particles spiral to the center.
+                let inward = offset.normalize_or_zero();
+                let direction = -inward.perp() + inward * WHIRLPOOL_INFLOW;
+                let falloff = 1.0 - distance / radius;
+                velocity.0 += (settings.strength * falloff * delta_time * direction).extend(0.0);
+            }
+
+            settings.drain_pending += settings.drain_rate.max(0.0) * delta_time;
+            let count = settings.drain_pending.floor();
+            settings.drain_pending -= count;
+
+            draining.sort_by(|a, b| a.0.total_cmp(&b.0));
+            for (_, entity, position) in draining.into_iter().take(count as usize) {
+                commands.entity(entity).despawn();
+                despawned.send(ParticleDespawned {
+                    entity,
+                    position,
+                    cause: DespawnCause::Drain,
+                });
+            }
+        }
+        Tool::Clump => {
+            if !mouse_input.just_pressed(MouseButton::Left) {
+                return;
+            }
+
+            let selected: Vec<(Entity, Vec2)> = neighbors
+                .within_radius(cursor.extend(0.0), radius)
+                .filter_map(|(entity, _)| {
+                    let position = particles.get(entity).ok()?.1.translation.truncate();
+                    (position.distance(cursor) < radius).then_some((entity, position))
+                })
+                .collect();
+
+            let mut melted = false;
+            for (clump_entity, clump) in clumps.iter() {
+                if selected.iter().any(|&(entity, _)| clump.contains(entity)) {
+                    commands.entity(clump_entity).despawn();
+                    melted = true;
+                }
+            }
+
+            if !melted && selected.len() >= 2 {
+                info!("Froze {} particles into a rigid clump", selected.len());
+                commands.spawn((Name::new("Rigid clump"), RigidClump::new(&selected)));
+            }
+        }
+        Tool::Freeze | Tool::Thaw => {
+            // Frozen particles are left out of the query, having no velocity.
+            let (fluid, frozen): (Vec<Entity>, Vec<Entity>) = neighbors
+                .within_radius(cursor.extend(0.0), radius)
+                .filter(|(_, position)| position.truncate().distance(cursor) < radius)
+                .map(|(entity, _)| entity)
+                .partition(|&entity| particles.contains(entity));
+
+            if *tool == Tool::Freeze {
+                commands.freeze_particles(fluid);
+            } else {
+                commands.thaw_particles(frozen);
+            }
+        }
+    }
+}
+
+fn draw_tool_system(
+    mut gizmos: Gizmos,
+    tool: Res<Tool>,
+    settings: Res<ToolSettings>,
+    windows: Query<&Window>,
+    cameras: Query<(&Camera, &GlobalTransform), With<PanCam>>,
+) {
+    if !tool.has_radius() {
+        return;
+    }
+
+    if let Some(cursor) = cursor_world_position(&windows, &cameras) {
+        gizmos.circle_2d(cursor, settings.radius, Color::srgb(0.9, 0.9, 0.9));
+
+        if *tool == Tool::Whirlpool {
+            let core = settings.radius * WHIRLPOOL_CORE;
+            gizmos.circle_2d(cursor, core, Color::srgb(0.5, 0.6, 0.9));
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use bevy::prelude::*;
+
+use crate::{
+    dilation::TimeScale, params::SimulationParams, velocity_system, SimulationSet, Velocity,
+    SIMULATION_SCHEDULE,
+};
+
+// Step, in noise units, of the finite differences taken for the curl.
+const CURL_STEP: f32 = 1e-2;
+
+pub struct TurbulencePlugin;
+
+impl Plugin for TurbulencePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            SIMULATION_SCHEDULE,
+            turbulence_system
+                .in_set(SimulationSet::Forces)
+                .after(velocity_system),
+        );
+    }
+}
+
+fn turbulence_system(
+    time: Res<Time>,
+    params: Res<SimulationParams>,
+    mut particles: Query<(&Transform, &mut Velocity, &TimeScale)>,
+) {
+    let turbulence = params.turbulence;
+    if turbulence.amplitude == 0.0 {
+        return;
+    }
+
+    let phase = time.elapsed_secs() * turbulence.evolution;
+
+    for (transform, mut velocity, time_scale) in particles.iter_mut() {
+        let point = transform.translation.truncate() * turbulence.frequency;
+        let acceleration = curl(point, phase) * turbulence.amplitude;
+
+        velocity.0 += (acceleration * time.delta_secs() * time_scale.0).extend(0.0);
+    }
+}
+
+// Curl of the noise taken as a stream function, sliced at `phase` along its third axis.
+fn curl(point: Vec2, phase: f32) -> Vec2 {
+    let potential = |offset: Vec2| perlin((point + offset).extend(phase));
+    let dx = potential(Vec2::X * CURL_STEP) - potential(Vec2::NEG_X * CURL_STEP);
+    let dy = potential(Vec2::Y * CURL_STEP) - potential(Vec2::NEG_Y * CURL_STEP);
+
+    Vec2::new(dy, -dx) / (2.0 * CURL_STEP)
+}
+
+// Classic gradient noise in three dimensions, roughly within -1..1, with gradients picked by
+// hashing the lattice corners instead of from a permutation table.
+fn perlin(point: Vec3) -> f32 {
+    let cell = point.floor();
+    let local = point - cell;
+    let corner = cell.as_ivec3();
+    let fade = local * local * local * (local * (local * 6.0 - 15.0) + 10.0);
+
+    let contribution = |x: i32, y: i32, z: i32| {
+        let offset = IVec3::new(x, y, z);
+        gradient(hash(corner + offset), local - offset.as_vec3())
+    };
+
+    let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+    let along_x = |y: i32, z: i32| lerp(contribution(0, y, z), contribution(1, y, z), fade.x);
+    let along_y = |z: i32| lerp(along_x(0, z), along_x(1, z), fade.y);
+
+    lerp(along_y(0), along_y(1), fade.z)
+}
+
+fn hash(corner: IVec3) -> u32 {
+    let mut hash = (corner.x as u32).wrapping_mul(0x8da6_b343)
+        ^ (corner.y as u32).wrapping_mul(0xd816_3841)
+        ^ (corner.z as u32).wrapping_mul(0xcb1a_b31f);
+
+    hash ^= hash >> 13;
+    hash = hash.wrapping_mul(0x5bd1_e995);
+    hash ^ (hash >> 15)
+}
+
+// Dot product with one of the twelve edge directions of a cube.
+fn gradient(hash: u32, offset: Vec3) -> f32 {
+    let Vec3 { x, y, z } = offset;
+
+    match hash % 12 {
+        0 => x + y,
+        1 => -x + y,
+        2 => x - y,
+        3 => -x - y,
+        4 => x + z,
+        5 => -x + z,
+        6 => x - z,
+        7 => -x - z,
+        8 => y + z,
+        9 => -y + z,
+        10 => y - z,
+        _ => -y - z,
+    }
+}
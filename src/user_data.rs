@@ -0,0 +1,272 @@
+use std::{collections::VecDeque, marker::PhantomData};
+
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::checkpoint::ParticleRespawned;
+
+// Removed values kept for each component type, the oldest dropped beyond this.
+const ARCHIVE_CAPACITY: usize = 16384;
+
+/// Game-specific data on a particle, e.g. `ParticleUserData<Team>`. The solver only ever
+/// writes `Transform`, `Position` and `Velocity` on existing particles, so this and any other
+/// component stays put while they move. Rollbacks and rewinds do respawn deleted particles
+/// as new entities, and a `PassthroughPlugin::<ParticleUserData<T>>` carries the data over.
+#[derive(Component, Clone, Debug, Default, Deref, DerefMut)]
+pub struct ParticleUserData<T: Send + Sync + 'static>(pub T);
+
+// Keeps the value of `C` on particles that get deleted, and puts it back on the particle
+// that replaces one when a rollback or rewind respawns it.
+pub struct PassthroughPlugin<C>(PhantomData<C>);
+
+impl<C> Default for PassthroughPlugin<C> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<C: Component + Clone> Plugin for PassthroughPlugin<C> {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Archive<C>>()
+            .add_observer(archive_observer::<C>)
+            .add_observer(respawn_observer::<C>);
+    }
+}
+
+// Values put back on a respawned particle only leave the map, and their entities are
+// cleared out of the order once they make up most of it.
+#[derive(Resource)]
+struct Archive<C> {
+    values: HashMap<Entity, C>,
+    order: VecDeque<Entity>,
+}
+
+impl<C> Default for Archive<C> {
+    fn default() -> Self {
+        Self {
+            values: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+}
+
+fn archive_observer<C: Component + Clone>(
+    trigger: Trigger<OnRemove, C>,
+    components: Query<&C>,
+    mut archive: ResMut<Archive<C>>,
+) {
+    let entity = trigger.entity();
+    let Ok(component) = components.get(entity) else {
+        return;
+    };
+
+    if archive.values.insert(entity, component.clone()).is_none() {
+        archive.order.push_back(entity);
+    }
+
+    while archive.values.len() > ARCHIVE_CAPACITY {
+        if let Some(oldest) = archive.order.pop_front() {
+            archive.values.remove(&oldest);
+        }
+    }
+
+    if archive.order.len() > 2 * ARCHIVE_CAPACITY {
+        let Archive { values, order } = &mut *archive;
+        order.retain(|entity| values.contains_key(entity));
+    }
+}
+
+fn respawn_observer<C: Component + Clone>(
+    trigger: Trigger<ParticleRespawned>,
+    mut commands: Commands,
+    mut archive: ResMut<Archive<C>>,
+) {
+    let respawned = trigger.event();
+
+    if let Some(component) = archive.values.remove(&respawned.previous) {
+        commands.entity(respawned.entity).insert(component);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+    use crate::{
+        checkpoint::{
+            capture, restore, EntityRemap, ParticleState, ParticleStateQuery, RestoreQuery,
+        },
+        particle_bundle,
+    };
+
+    type Tag = ParticleUserData<u32>;
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins(PassthroughPlugin::<Tag>::default())
+            .init_resource::<EntityRemap>();
+        app
+    }
+
+    fn spawn(app: &mut App, tag: u32) -> Entity {
+        app.world_mut()
+            .spawn((
+                particle_bundle(Vec3::ZERO, Color::WHITE),
+                ParticleUserData(tag),
+            ))
+            .id()
+    }
+
+    fn capture_states(app: &mut App) -> Vec<ParticleState> {
+        app.world_mut()
+            .run_system_once(|particles: ParticleStateQuery| capture(&particles))
+            .unwrap()
+    }
+
+    // Restores through the app's remap, like rollbacks and rewinds do, and returns what
+    // `entity` resolves to afterwards.
+    fn restore_states(app: &mut App, states: Vec<ParticleState>, entity: Entity) -> Entity {
+        app.world_mut()
+            .run_system_once(
+                move |mut commands: Commands,
+                      mut remap: ResMut<EntityRemap>,
+                      mut particles: RestoreQuery| {
+                    restore(&mut commands, &states, &mut remap, &mut particles);
+                },
+            )
+            .unwrap();
+        app.world_mut().flush();
+        app.world().resource::<EntityRemap>().resolve(entity)
+    }
+
+    fn tags(app: &mut App) -> Vec<(Entity, u32)> {
+        let mut tags: Vec<_> = app
+            .world_mut()
+            .query::<(Entity, &Tag)>()
+            .iter(app.world())
+            .map(|(entity, tag)| (entity, tag.0))
+            .collect();
+        tags.sort();
+        tags
+    }
+
+    #[test]
+    fn user_data_survives_a_checkpoint_restore() {
+        let mut app = app();
+        let kept = spawn(&mut app, 1);
+        let deleted = spawn(&mut app, 2);
+        let checkpoint = capture_states(&mut app);
+
+        app.world_mut().despawn(deleted);
+        let respawned = restore_states(&mut app, checkpoint, deleted);
+
+        assert_ne!(respawned, deleted);
+        let mut expected = vec![(kept, 1), (respawned, 2)];
+        expected.sort();
+        assert_eq!(tags(&mut app), expected);
+    }
+
+    #[test]
+    fn user_data_survives_rewinding_back_and_forth() {
+        let mut app = app();
+        let original = spawn(&mut app, 7);
+        let with_particle = capture_states(&mut app);
+
+        app.world_mut().despawn(original);
+        let without_particle = capture_states(&mut app);
+
+        // Back to before it was deleted, forward past its deletion, and back once more.
+        let first = restore_states(&mut app, with_particle.clone(), original);
+        assert_eq!(tags(&mut app), vec![(first, 7)]);
+
+        restore_states(&mut app, without_particle, original);
+        assert!(tags(&mut app).is_empty());
+
+        let second = restore_states(&mut app, with_particle, original);
+        assert_ne!(second, first);
+        assert_eq!(tags(&mut app), vec![(second, 7)]);
+    }
+
+    #[test]
+    fn user_data_survives_a_rollback_after_a_rewind() {
+        let mut app = app();
+        let original = spawn(&mut app, 5);
+        let checkpoint = capture_states(&mut app);
+        let frame = capture_states(&mut app);
+
+        app.world_mut().despawn(original);
+        let rewound = restore_states(&mut app, frame, original);
+        assert_eq!(tags(&mut app), vec![(rewound, 5)]);
+
+        // The checkpoint finds the particle the rewind brought back rather than respawning it.
+        let rolled_back = restore_states(&mut app, checkpoint.clone(), original);
+        assert_eq!(rolled_back, rewound);
+        assert_eq!(tags(&mut app), vec![(rewound, 5)]);
+
+        app.world_mut().despawn(rewound);
+        let respawned = restore_states(&mut app, checkpoint, original);
+        assert_ne!(respawned, rewound);
+        assert_eq!(tags(&mut app), vec![(respawned, 5)]);
+    }
+
+    #[test]
+    fn restored_values_leave_the_archive() {
+        let mut app = app();
+        let entity = spawn(&mut app, 3);
+        let checkpoint = capture_states(&mut app);
+
+        app.world_mut().despawn(entity);
+        assert_eq!(app.world().resource::<Archive<Tag>>().order.len(), 1);
+
+        restore_states(&mut app, checkpoint, entity);
+        assert!(app.world().resource::<Archive<Tag>>().values.is_empty());
+    }
+
+    #[test]
+    fn archive_order_sheds_restored_entities() {
+        let mut app = app();
+        let restored: Vec<_> = (0..ARCHIVE_CAPACITY as u32)
+            .map(|tag| spawn(&mut app, tag))
+            .collect();
+        let checkpoint = capture_states(&mut app);
+
+        for &entity in &restored {
+            app.world_mut().despawn(entity);
+        }
+        restore_states(&mut app, checkpoint, restored[0]);
+
+        let deleted: Vec<_> = (0..ARCHIVE_CAPACITY as u32 + 1)
+            .map(|tag| spawn(&mut app, tag))
+            .collect();
+        for &entity in &deleted {
+            app.world_mut().despawn(entity);
+        }
+
+        let archive = app.world().resource::<Archive<Tag>>();
+        assert_eq!(archive.values.len(), ARCHIVE_CAPACITY);
+        assert_eq!(archive.order.len(), ARCHIVE_CAPACITY);
+        assert!(archive
+            .order
+            .iter()
+            .all(|entity| archive.values.contains_key(entity)));
+    }
+
+    #[test]
+    fn archive_drops_the_oldest_values_beyond_its_capacity() {
+        let mut app = app();
+        let entities: Vec<_> = (0..ARCHIVE_CAPACITY as u32 + 2)
+            .map(|tag| spawn(&mut app, tag))
+            .collect();
+
+        for &entity in &entities {
+            app.world_mut().despawn(entity);
+        }
+
+        let archive = app.world().resource::<Archive<Tag>>();
+        assert_eq!(archive.values.len(), ARCHIVE_CAPACITY);
+        assert_eq!(archive.order.len(), ARCHIVE_CAPACITY);
+        assert!(!archive.values.contains_key(&entities[0]));
+        assert!(!archive.values.contains_key(&entities[1]));
+        assert_eq!(archive.values[&entities[2]].0, 2);
+    }
+}
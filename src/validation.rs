@@ -0,0 +1,77 @@
+use bevy::prelude::*;
+
+use crate::{
+    params::SimulationParams,
+    precision::{real, Real, PI},
+    smoothing_kernel, smoothing_kernel_derivative,
+};
+
+const INTEGRATION_STEPS: usize = 1000;
+const NORMALIZATION_TOLERANCE: Real = 0.01;
+// Largest mismatch between the analytic and numeric derivative, relative to the steepest
+// analytic slope.
+const DERIVATIVE_TOLERANCE: Real = 0.01;
+
+// Checks the smoothing kernel against the current smoothing radius whenever the parameters
+// change: it has to integrate to one over its support, and its derivative has to match a
+// central difference of the kernel itself.
+pub struct KernelValidationPlugin;
+
+impl Plugin for KernelValidationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            validate_kernels_system.run_if(resource_changed::<SimulationParams>),
+        );
+    }
+}
+
+fn validate_kernels_system(params: Res<SimulationParams>) {
+    let radius = real(params.smoothing_radius);
+    if radius <= 0.0 || !radius.is_finite() {
+        error!("Smoothing radius {radius} leaves the kernel without support");
+        return;
+    }
+
+    let step = radius / INTEGRATION_STEPS as Real;
+    let samples = (0..INTEGRATION_STEPS).map(|i| (i as Real + 0.5) * step);
+
+    // Midpoint rule over rings of the disc.
+    let integral: Real = samples
+        .clone()
+        .map(|distance| smoothing_kernel(radius, distance) * 2.0 * PI * distance * step)
+        .sum();
+
+    if (integral - 1.0).abs() > NORMALIZATION_TOLERANCE {
+        error!(
+            "Smoothing kernel integrates to {integral} over radius {radius} instead of 1; \
+             densities will be off by the same factor"
+        );
+    }
+
+    let epsilon = step / 2.0;
+    let steepest = smoothing_kernel_derivative(radius, 0.0)
+        .abs()
+        .max(Real::EPSILON);
+    let (worst_distance, worst_error) = samples
+        .map(|distance| {
+            let numeric = (smoothing_kernel(radius, distance + epsilon)
+                - smoothing_kernel(radius, distance - epsilon))
+                / (2.0 * epsilon);
+            let analytic = smoothing_kernel_derivative(radius, distance);
+
+            (distance, (numeric - analytic).abs() / steepest)
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .unwrap_or_default();
+
+    if worst_error > DERIVATIVE_TOLERANCE {
+        error!(
+            "Smoothing kernel derivative is off by {:.1}% of its peak at distance \
+             {worst_distance} (radius {radius}); pressure forces will not match the densities",
+            worst_error * 100.0
+        );
+    } else {
+        debug!("Smoothing kernel validated for radius {radius}: integral {integral}");
+    }
+}
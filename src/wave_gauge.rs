@@ -0,0 +1,276 @@
+#[cfg(not(target_arch = "wasm32"))]
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use bevy::{gizmos::GizmoPlugin, prelude::*};
+use bevy_inspector_egui::bevy_egui::{egui, EguiContexts, EguiPlugin};
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::headless_exit_system;
+use crate::{
+    domain::Domain, shallow_water::ShallowWater, surface::ColorField, SimulationSet,
+    SIMULATION_SCHEDULE,
+};
+
+// Seconds of history shown in the plot.
+const PLOT_SECONDS: f32 = 10.0;
+const PLOT_SIZE: [f32; 2] = [360.0, 160.0];
+const MARKER_SIZE: f32 = 3.0;
+#[cfg(not(target_arch = "wasm32"))]
+const DEFAULT_CSV: &str = "wave_gauges.csv";
+
+// Records the height of the free surface above `x` after every step, for comparing waves
+// against theory. Steps without fluid in reach of the gauge record no height.
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct WaveGauge {
+    pub x: f32,
+    /// Simulated time and surface height of every step since the gauge was spawned
+    samples: Vec<(f32, Option<f32>)>,
+}
+
+impl WaveGauge {
+    pub fn new(x: f32) -> Self {
+        Self {
+            x,
+            samples: Vec::new(),
+        }
+    }
+
+    pub fn samples(&self) -> &[(f32, Option<f32>)] {
+        &self.samples
+    }
+}
+
+pub struct WaveGaugePlugin;
+
+impl Plugin for WaveGaugePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<WaveGauge>().add_systems(
+            SIMULATION_SCHEDULE,
+            record_system.after(SimulationSet::Collision),
+        );
+
+        if app.is_plugin_added::<EguiPlugin>() {
+            app.add_systems(Update, plot_system);
+        }
+
+        if app.is_plugin_added::<GizmoPlugin>() {
+            app.add_systems(Update, draw_gauges_system);
+        }
+    }
+}
+
+// Writes every gauge's series to a CSV file on exit, which the plot's export button also
+// writes to.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct WaveGaugeCsvPlugin(pub PathBuf);
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Plugin for WaveGaugeCsvPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(WaveGaugeCsv(self.0.clone()))
+            .add_systems(Last, write_csv_on_exit_system.after(headless_exit_system));
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Resource)]
+struct WaveGaugeCsv(PathBuf);
+
+fn record_system(
+    time: Res<Time>,
+    field: Option<Res<ColorField>>,
+    lakes: Query<&ShallowWater>,
+    mut gauges: Query<&mut WaveGauge>,
+) {
+    let elapsed = time.elapsed_secs();
+
+    for mut gauge in gauges.iter_mut() {
+        // Over particles and shallow water alike, the topmost surface counts.
+        let height = field
+            .as_deref()
+            .and_then(|field| field.surface_height_at(gauge.x))
+            .into_iter()
+            .chain(
+                lakes
+                    .iter()
+                    .filter_map(|lake| lake.surface_height_at(gauge.x)),
+            )
+            .reduce(f32::max);
+        gauge.samples.push((elapsed, height));
+    }
+}
+
+// Recent history of every gauge on shared axes, the heights scaled to fit. Gaps are steps
+// where a gauge found no fluid.
+fn plot_system(
+    mut contexts: EguiContexts,
+    gauges: Query<(Entity, &WaveGauge, Option<&Name>)>,
+    #[cfg(not(target_arch = "wasm32"))] csv: Option<Res<WaveGaugeCsv>>,
+) {
+    if gauges.is_empty() {
+        return;
+    }
+
+    let end = gauges
+        .iter()
+        .filter_map(|(_, gauge, _)| gauge.samples().last())
+        .map(|&(time, _)| time)
+        .fold(0.0, f32::max);
+    let start = end - PLOT_SECONDS;
+
+    let recent = |gauge: &WaveGauge| {
+        let first = gauge.samples.partition_point(|&(time, _)| time < start);
+        gauge.samples[first..].to_vec()
+    };
+
+    let (low, high) = gauges
+        .iter()
+        .flat_map(|(_, gauge, _)| recent(gauge))
+        .filter_map(|(_, height)| height)
+        .fold((f32::INFINITY, f32::NEG_INFINITY), |(low, high), height| {
+            (low.min(height), high.max(height))
+        });
+
+    egui::Window::new("Wave gauges").show(contexts.ctx_mut(), |ui| {
+        let (response, painter) =
+            ui.allocate_painter(egui::Vec2::from(PLOT_SIZE), egui::Sense::hover());
+        let rect = response.rect;
+        painter.rect_stroke(rect, 0.0, egui::Stroke::new(1.0, egui::Color32::GRAY));
+
+        let span = (high - low).max(f32::EPSILON);
+        let to_screen = |time: f32, height: f32| {
+            egui::pos2(
+                rect.left() + rect.width() * (time - start) / PLOT_SECONDS,
+                rect.bottom() - rect.height() * (height - low) / span,
+            )
+        };
+
+        for (index, (entity, gauge, name)) in gauges.iter().enumerate() {
+            let color = series_color(index);
+            let mut line = Vec::new();
+
+            for (time, height) in recent(gauge) {
+                match height {
+                    Some(height) => line.push(to_screen(time, height)),
+                    None => {
+                        painter.add(egui::Shape::line(
+                            std::mem::take(&mut line),
+                            egui::Stroke::new(1.5, color),
+                        ));
+                    }
+                }
+            }
+            painter.add(egui::Shape::line(line, egui::Stroke::new(1.5, color)));
+
+            let label = name.map_or_else(|| format!("{entity}"), |name| name.to_string());
+            let current = gauge
+                .samples
+                .last()
+                .and_then(|&(_, height)| height)
+                .map_or("dry".to_string(), |height| format!("{height:.2}"));
+            ui.colored_label(color, format!("{label} at x = {}: {current}", gauge.x));
+        }
+
+        if low <= high {
+            ui.label(format!(
+                "Last {PLOT_SECONDS} s, heights {low:.2} to {high:.2}"
+            ));
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if ui.button("Export CSV").clicked() {
+            let path = csv
+                .as_ref()
+                .map_or_else(|| PathBuf::from(DEFAULT_CSV), |csv| csv.0.clone());
+            let series = gauges.iter().map(|(entity, gauge, name)| {
+                let label = name.map_or_else(|| format!("{entity}"), |name| name.to_string());
+                (label, gauge)
+            });
+
+            match write_csv(&path, series) {
+                Ok(()) => info!("Wrote wave gauges to {}", path.display()),
+                Err(error) => error!("Failed to write wave gauges {}: {error}", path.display()),
+            }
+        }
+    });
+}
+
+fn series_color(index: usize) -> egui::Color32 {
+    const COLORS: [egui::Color32; 4] = [
+        egui::Color32::from_rgb(90, 170, 255),
+        egui::Color32::from_rgb(255, 170, 60),
+        egui::Color32::from_rgb(120, 220, 120),
+        egui::Color32::from_rgb(240, 110, 170),
+    ];
+    COLORS[index % COLORS.len()]
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn write_csv_on_exit_system(
+    mut exits: EventReader<AppExit>,
+    csv: Res<WaveGaugeCsv>,
+    gauges: Query<(Entity, &WaveGauge, Option<&Name>)>,
+    mut written: Local<bool>,
+) {
+    if *written || exits.read().next().is_none() {
+        return;
+    }
+
+    *written = true;
+
+    let series = gauges.iter().map(|(entity, gauge, name)| {
+        let label = name.map_or_else(|| format!("{entity}"), |name| name.to_string());
+        (label, gauge)
+    });
+
+    match write_csv(&csv.0, series) {
+        Ok(()) => info!("Wrote wave gauges to {}", csv.0.display()),
+        Err(error) => error!("Failed to write wave gauges {}: {error}", csv.0.display()),
+    }
+}
+
+// One row per gauge and step, the height left empty where the gauge found no fluid.
+#[cfg(not(target_arch = "wasm32"))]
+fn write_csv<'a>(
+    path: &Path,
+    series: impl Iterator<Item = (String, &'a WaveGauge)>,
+) -> io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    writeln!(file, "gauge,x,time,height")?;
+
+    for (label, gauge) in series {
+        let label = format!("\"{}\"", label.replace('"', "\"\""));
+
+        for &(time, height) in gauge.samples() {
+            let height = height.map_or(String::new(), |height| height.to_string());
+            writeln!(file, "{label},{},{time},{height}", gauge.x)?;
+        }
+    }
+
+    file.flush()
+}
+
+fn draw_gauges_system(mut gizmos: Gizmos, domains: Query<&Domain>, gauges: Query<&WaveGauge>) {
+    let Ok(domain) = domains.get_single() else {
+        return;
+    };
+    let half_height = domain.half_size().y;
+    let color = Color::srgb(0.4, 0.7, 1.0);
+
+    for gauge in gauges.iter() {
+        gizmos.line_2d(
+            Vec2::new(gauge.x, -half_height),
+            Vec2::new(gauge.x, half_height),
+            color.with_alpha(0.3),
+        );
+
+        if let Some(&(_, Some(height))) = gauge.samples().last() {
+            gizmos.circle_2d(Vec2::new(gauge.x, height), MARKER_SIZE, color);
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::{collider::Collider, domain::Domain, SimulationSet, SIMULATION_SCHEDULE};
+
+// Paddle spanning the full height of one side of the domain, moved back and forth to make
+// waves. A piston slides the whole paddle, a flap swings it about a hinge on the floor. The
+// paddle is a `Collider` moved before `SimulationSet::Collision` like any other kinematic
+// shape, so it needs a `Kinematic` alongside it to push the fluid along.
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct WaveMaker {
+    pub side: WaveMakerSide,
+    pub kind: WaveMakerKind,
+    /// Greatest travel of the paddle from its mean position, measured at the top for a flap
+    pub amplitude: f32,
+    /// Strokes per second
+    pub frequency: f32,
+    /// Seconds over which the stroke grows to its full amplitude, starting calm water gently
+    pub ramp: f32,
+    pub thickness: f32,
+    elapsed: f32,
+}
+
+#[derive(Reflect, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WaveMakerSide {
+    #[default]
+    Left,
+    Right,
+}
+
+#[derive(Reflect, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WaveMakerKind {
+    /// Paddle sliding horizontally, moving the whole water column alike, suited to shallow
+    /// water waves
+    #[default]
+    Piston,
+    /// Paddle hinged at the floor, moving the water more near the surface, suited to deep
+    /// water waves
+    Flap,
+}
+
+impl WaveMaker {
+    pub fn new(side: WaveMakerSide, kind: WaveMakerKind, amplitude: f32, frequency: f32) -> Self {
+        Self {
+            side,
+            kind,
+            amplitude,
+            frequency,
+            ramp: 0.0,
+            thickness: 6.0,
+            elapsed: 0.0,
+        }
+    }
+
+    /// Travel of the top of the paddle from its mean position into the domain
+    fn stroke(&self) -> f32 {
+        let envelope = if self.ramp > 0.0 {
+            (self.elapsed / self.ramp).min(1.0)
+        } else {
+            1.0
+        };
+
+        self.amplitude * envelope * (TAU * self.frequency * self.elapsed).sin()
+    }
+}
+
+pub struct WaveMakerPlugin;
+
+impl Plugin for WaveMakerPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<WaveMaker>().add_systems(
+            SIMULATION_SCHEDULE,
+            wave_maker_system.in_set(SimulationSet::Integration),
+        );
+    }
+}
+
+fn wave_maker_system(
+    time: Res<Time>,
+    domains: Query<&Domain>,
+    mut makers: Query<(&mut WaveMaker, &mut Collider, &mut Transform)>,
+) {
+    let Ok(domain) = domains.get_single() else {
+        return;
+    };
+    let half_size = domain.half_size();
+
+    for (mut maker, mut collider, mut transform) in makers.iter_mut() {
+        maker.elapsed += time.delta_secs();
+
+        // The paddle follows the domain as it is resized.
+        let paddle = Vec2::new(maker.thickness / 2.0, half_size.y);
+        if !matches!(*collider, Collider::Box { half_size: current } if current == paddle) {
+            *collider = Collider::Box { half_size: paddle };
+        }
+
+        // Its mean position keeps a full stroke of room behind it, so it never passes the
+        // side it stands in front of.
+        let inward = match maker.side {
+            WaveMakerSide::Left => 1.0,
+            WaveMakerSide::Right => -1.0,
+        };
+        let rest = -inward * (half_size.x - maker.amplitude.abs() - paddle.x);
+        let stroke = maker.stroke();
+
+        let (center, angle) = match maker.kind {
+            WaveMakerKind::Piston => (Vec2::new(rest + inward * stroke, 0.0), 0.0),
+            WaveMakerKind::Flap => {
+                let hinge = Vec2::new(rest, -half_size.y);
+                let angle = -inward * (stroke / domain.size.y).atan();
+                (
+                    hinge + Vec2::from_angle(angle).rotate(Vec2::Y * half_size.y),
+                    angle,
+                )
+            }
+        };
+
+        transform.translation = center.extend(transform.translation.z);
+        transform.rotation = Quat::from_rotation_z(angle);
+    }
+}